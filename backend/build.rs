@@ -86,6 +86,7 @@ fn main() {
             build.file("src/cpp/src/linux/native_api.cpp");
             build.file("src/cpp/src/linux/file_api.cpp");
             build.file("src/cpp/src/linux/debugger.cpp");
+            build.file("src/cpp/src/common/util.cpp");
         }
 
         "linux" => {
@@ -94,6 +95,7 @@ fn main() {
             build.file("src/cpp/src/linux/native_api.cpp");
             build.file("src/cpp/src/linux/file_api.cpp");
             build.file("src/cpp/src/linux/debugger.cpp");
+            build.file("src/cpp/src/common/util.cpp");
         }
 
         _ => {
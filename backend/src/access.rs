@@ -0,0 +1,240 @@
+use crate::native_bridge::{self, Access};
+use crate::symbols;
+use crate::util::{self, Arch, AsmSyntax, Instruction};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::collections::hash_map::{Entry, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+// Distinct instructions kept per collection; hits from any more are only counted in total
+const MAX_DISTINCT_HITS: usize = 4096;
+pub const DEFAULT_TOP_HITS: usize = 20;
+
+// Hits on one watched address, keyed by the pc the debugger reported for each
+struct AccessCollection {
+    pid: i32,
+    size: usize,
+    access: Access,
+    started: Instant,
+    hits: HashMap<u64, u64>,
+    total: u64,
+}
+
+impl AccessCollection {
+    fn new(pid: i32, size: usize, access: Access) -> AccessCollection {
+        AccessCollection {
+            pid,
+            size,
+            access,
+            started: Instant::now(),
+            hits: HashMap::new(),
+            total: 0,
+        }
+    }
+
+    fn record(&mut self, pc: u64) {
+        self.total += 1;
+        if let Some(count) = self.hits.get_mut(&pc) {
+            *count += 1;
+        } else if self.hits.len() < MAX_DISTINCT_HITS {
+            self.hits.insert(pc, 1);
+        }
+    }
+
+    // Most hits first; ties go to the lower address so reports are stable
+    fn top(&self, count: usize) -> Vec<(u64, u64)> {
+        let mut hits: Vec<(u64, u64)> = self.hits.iter().map(|(&pc, &n)| (pc, n)).collect();
+        hits.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        hits.truncate(count);
+        hits
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AccessHit {
+    // Where the debugger stopped the thread
+    pub pc: u64,
+    pub count: u64,
+    // The instruction that made the access, which on x86 is the one before pc
+    pub instruction: Option<Instruction>,
+    pub symbol: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AccessReport {
+    pub address: u64,
+    pub size: usize,
+    pub elapsed_ms: u128,
+    pub total_hits: u64,
+    pub distinct_instructions: usize,
+    pub instructions: Vec<AccessHit>,
+}
+
+lazy_static! {
+    static ref ACCESS_COLLECTIONS: Arc<Mutex<HashMap<u64, AccessCollection>>> =
+        Arc::new(Mutex::new(HashMap::new()));
+}
+
+// Sets a hardware watchpoint on address and counts the instructions that trip it until
+// access_stop. x86 debug registers cannot watch reads alone, so there Access::Read also
+// counts writes.
+pub fn access_start(pid: i32, address: u64, size: usize, access: Access) -> Result<(), String> {
+    if access == Access::Write {
+        return Err("Use a write watchpoint to find what writes an address".to_string());
+    }
+    // Registered first so the earliest hits are not queued as plain exceptions. The check
+    // and the insert share one lock so two requests cannot both start collecting; the lock is
+    // released before the watchpoint is set, since the debugger thread records hits through it.
+    match ACCESS_COLLECTIONS.lock().unwrap().entry(address) {
+        Entry::Occupied(_) => {
+            return Err(format!("Accesses to {:#x} are already collected", address));
+        }
+        Entry::Vacant(slot) => {
+            slot.insert(AccessCollection::new(pid, size, access));
+        }
+    }
+    if let Err(e) = native_bridge::set_watchpoint(pid, address as usize, size, access) {
+        ACCESS_COLLECTIONS.lock().unwrap().remove(&address);
+        return Err(format!("Failed to set watchpoint at {:#x}: {}", address, e));
+    }
+    Ok(())
+}
+
+// Called for every watchpoint hit the debugger reports. False when nothing collects hits on
+// watchpoint, so the hit goes to the exception queue instead.
+pub fn record_hit(watchpoint: u64, pc: u64) -> bool {
+    match ACCESS_COLLECTIONS.lock().unwrap().get_mut(&watchpoint) {
+        Some(collection) => {
+            collection.record(pc);
+            true
+        }
+        None => false,
+    }
+}
+
+// The top instructions so far, leaving the collection running
+pub fn access_report(address: u64, top: usize) -> Result<AccessReport, String> {
+    let (pid, report) = {
+        let collections = ACCESS_COLLECTIONS.lock().unwrap();
+        let collection = collections
+            .get(&address)
+            .ok_or_else(|| format!("No accesses to {:#x} are being collected", address))?;
+        (collection.pid, summarize(address, collection, top))
+    };
+    Ok(describe(pid, report))
+}
+
+// Removes the watchpoint and returns the final counts. The watchpoint goes first so no hit
+// arrives after the collection is gone.
+pub fn access_stop(address: u64, top: usize) -> Result<AccessReport, String> {
+    if !ACCESS_COLLECTIONS.lock().unwrap().contains_key(&address) {
+        return Err(format!("No accesses to {:#x} are being collected", address));
+    }
+    if let Err(e) = native_bridge::remove_watchpoint(address as usize) {
+        log::warn!("Failed to remove watchpoint at {:#x}: {}", address, e);
+    }
+    let collection = ACCESS_COLLECTIONS
+        .lock()
+        .unwrap()
+        .remove(&address)
+        .ok_or_else(|| format!("No accesses to {:#x} are being collected", address))?;
+    log::info!(
+        "Collected {} {:?} hits on {:#x} in pid {}",
+        collection.total,
+        collection.access,
+        address,
+        collection.pid
+    );
+    Ok(describe(
+        collection.pid,
+        summarize(address, &collection, top),
+    ))
+}
+
+// Counts only, so the table lock is not held while the target is read
+fn summarize(address: u64, collection: &AccessCollection, top: usize) -> AccessReport {
+    AccessReport {
+        address,
+        size: collection.size,
+        elapsed_ms: collection.started.elapsed().as_millis(),
+        total_hits: collection.total,
+        distinct_instructions: collection.hits.len(),
+        instructions: collection
+            .top(top)
+            .into_iter()
+            .map(|(pc, count)| AccessHit {
+                pc,
+                count,
+                instruction: None,
+                symbol: None,
+            })
+            .collect(),
+    }
+}
+
+fn describe(pid: i32, mut report: AccessReport) -> AccessReport {
+    let arch = Arch::default();
+    let syntax = AsmSyntax::default();
+    let modules = native_bridge::enum_modules(pid).unwrap_or_default();
+    for hit in &mut report.instructions {
+        // arm64 reports the accessing instruction itself, x86 the one after it
+        hit.instruction = match arch {
+            Arch::Arm64 => util::disassemble_at(pid, hit.pc, 1, arch, syntax)
+                .ok()
+                .and_then(|instructions| instructions.into_iter().next()),
+            Arch::X86 | Arch::X86_64 => util::instruction_before(pid, hit.pc, arch, syntax),
+        };
+        let address = hit.instruction.as_ref().map_or(hit.pc, |i| i.address);
+        hit.symbol = symbols::symbolicate(pid, address, &modules);
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hits_are_counted_per_pc_and_ranked() {
+        let address = 0x7000_0000_1000;
+        ACCESS_COLLECTIONS
+            .lock()
+            .unwrap()
+            .insert(address, AccessCollection::new(42, 4, Access::ReadWrite));
+        for pc in [0x401000, 0x401010, 0x401000, 0x402000, 0x401010, 0x401000] {
+            assert!(record_hit(address, pc));
+        }
+        assert!(!record_hit(address + 8, 0x401000));
+
+        let collection = ACCESS_COLLECTIONS.lock().unwrap().remove(&address).unwrap();
+        let report = summarize(address, &collection, 2);
+        assert_eq!(report.total_hits, 6);
+        assert_eq!(report.distinct_instructions, 3);
+        let top: Vec<(u64, u64)> = report
+            .instructions
+            .iter()
+            .map(|h| (h.pc, h.count))
+            .collect();
+        assert_eq!(top, vec![(0x401000, 3), (0x401010, 2)]);
+    }
+
+    #[test]
+    fn ties_rank_the_lower_pc_first() {
+        let mut collection = AccessCollection::new(42, 4, Access::ReadWrite);
+        for pc in [0x30, 0x10, 0x20] {
+            collection.record(pc);
+        }
+        assert_eq!(collection.top(3), vec![(0x10, 1), (0x20, 1), (0x30, 1)]);
+    }
+
+    #[test]
+    fn distinct_pcs_are_capped_but_still_counted() {
+        let mut collection = AccessCollection::new(42, 4, Access::ReadWrite);
+        for pc in 0..(MAX_DISTINCT_HITS as u64 + 10) {
+            collection.record(pc);
+        }
+        assert_eq!(collection.hits.len(), MAX_DISTINCT_HITS);
+        assert_eq!(collection.total, MAX_DISTINCT_HITS as u64 + 10);
+    }
+}
@@ -1,11 +1,11 @@
 use byteorder::{ByteOrder, LittleEndian};
+use futures_util::stream::SplitSink;
+use futures_util::{SinkExt, StreamExt};
 use hex;
 use lazy_static::lazy_static;
-use libc::{self, c_char, c_int, c_void};
-use lz4_flex::block::compress_prepend_size;
+use libc::{self, c_char, c_int};
 
 use memchr::memmem;
-use percent_encoding::percent_decode_str;
 use rayon::prelude::*;
 use regex::bytes::Regex;
 use serde::Serialize;
@@ -27,18 +27,36 @@ use std::mem::size_of;
 use std::panic;
 use std::path::{Path, PathBuf};
 use std::process;
-use std::slice;
 use std::str;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::RwLock;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, TryLockError};
+use std::time::{Duration, Instant};
 use warp::hyper::Body;
+use warp::ws::{Message, WebSocket};
 use warp::{http::Response, http::StatusCode, Filter, Rejection, Reply};
 
+use crate::access;
+use crate::assembler;
+use crate::bookmarks;
+use crate::breakpoint;
+use crate::compression;
+use crate::coredump;
+use crate::deadline;
+use crate::dump;
+use crate::freeze;
+use crate::inspect;
 use crate::native_bridge;
+use crate::pointer;
 use crate::ptrscan;
+use crate::region;
 use crate::request;
+use crate::scan;
+use crate::session;
+use crate::shutdown;
+use crate::symbols;
 use crate::util;
+use crate::watch;
 
 lazy_static! {
     static ref GLOBAL_POSITIONS: RwLock<HashMap<String, Vec<(usize, String)>>> =
@@ -49,6 +67,24 @@ lazy_static! {
         RwLock::new(HashMap::new());
     static ref JSON_QUEUE: Arc<Mutex<VecDeque<String>>> = Arc::new(Mutex::new(VecDeque::new()));
     static ref GLOBAL_PROCESS_STATE: RwLock<bool> = RwLock::new(false);
+    static ref SCAN_TRACKERS: RwLock<HashMap<String, ScanTracker>> = RwLock::new(HashMap::new());
+}
+
+struct ScanTracker {
+    scanned: Arc<AtomicUsize>,
+    total: usize,
+    cancel: Arc<AtomicBool>,
+    // Set when the scan returns; the next progress poll reports it and drops the tracker
+    finished: Option<Instant>,
+}
+
+// How long a finished scan's tracker is kept for a client that never polls it again
+const SCAN_TRACKER_TTL: Duration = Duration::from_secs(300);
+
+fn expire_scan_trackers(trackers: &mut HashMap<String, ScanTracker>, now: Instant) {
+    trackers.retain(|_, tracker| {
+        !matches!(tracker.finished, Some(finished) if now.duration_since(finished) >= SCAN_TRACKER_TTL)
+    });
 }
 
 #[no_mangle]
@@ -78,18 +114,33 @@ pub extern "C" fn send_register_json(register_json: *const c_char, pid: i32) {
         .unwrap();
     let pc_address = u64::from_str_radix(pc_address_hex.trim_start_matches("0x"), 16).unwrap();
 
-    let mut buffer = [0u8; 4];
-    native_bridge::read_process_memory(
-        pid,
-        pc_address as *mut libc::c_void,
-        buffer.len(),
-        &mut buffer as &mut [u8],
-    )
-    .unwrap();
+    // Hits being collected are only counted, so a hot address does not flood the queue
+    let watchpoint = json_value["watchpoint"]
+        .as_str()
+        .and_then(|hex| u64::from_str_radix(hex.trim_start_matches("0x"), 16).ok());
+    if watchpoint.is_some_and(|watchpoint| access::record_hit(watchpoint, pc_address)) {
+        return;
+    }
 
-    let disassembled = util::disassemble(buffer.as_ptr(), buffer.len(), pc_address);
+    // A breakpoint hit shows the instruction its trap replaced
+    let disassembled = match util::disassemble_at(
+        pid,
+        pc_address,
+        1,
+        util::Arch::default(),
+        util::AsmSyntax::default(),
+    ) {
+        Ok(instructions) => instructions
+            .iter()
+            .map(|i| format!("{:#x}: {} {}\n", i.address, i.mnemonic, i.op_str))
+            .collect(),
+        Err(e) => format!("Failed to read instruction at {:#x}: {}", pc_address, e),
+    };
 
     json_value["instruction"] = json!(disassembled);
+    json_value["symbol"] = json!(native_bridge::enum_modules(pid)
+        .ok()
+        .and_then(|modules| symbols::symbolicate(pid, pc_address, &modules)));
 
     let mut queue = JSON_QUEUE.lock().unwrap();
     queue.push_back(json_value.to_string());
@@ -122,11 +173,8 @@ struct ServerInfo {
     mode: String,
 }
 
-pub async fn server_info_handler() -> Result<impl warp::Reply, warp::Rejection> {
-    let git_hash = env!("GIT_HASH");
-    let target_os = env!("TARGET_OS");
-
-    let arch = if cfg!(target_arch = "x86_64") {
+fn server_arch() -> &'static str {
+    if cfg!(target_arch = "x86_64") {
         "x86_64"
     } else if cfg!(target_arch = "aarch64") {
         "aarch64"
@@ -136,14 +184,19 @@ pub async fn server_info_handler() -> Result<impl warp::Reply, warp::Rejection>
         "x86"
     } else {
         "unknown"
-    };
+    }
+}
+
+pub async fn server_info_handler() -> Result<impl warp::Reply, warp::Rejection> {
+    let git_hash = env!("GIT_HASH");
+    let target_os = env!("TARGET_OS");
 
     let pid = process::id();
 
     let server_info = ServerInfo {
         git_hash: git_hash.to_string(),
         target_os: target_os.to_string(),
-        arch: arch.to_string(),
+        arch: server_arch().to_string(),
         pid: pid,
         mode: std::env::var("MEMORY_SERVER_RUNNING_MODE").unwrap_or_else(|_| "unknown".to_string()),
     };
@@ -151,6 +204,101 @@ pub async fn server_info_handler() -> Result<impl warp::Reply, warp::Rejection>
     Ok(warp::reply::json(&server_info))
 }
 
+// What this build can do on the current platform, so the frontend can pick defaults instead
+// of finding out from failed requests. Everything is probed without attaching to a process.
+#[derive(Serialize, Clone)]
+struct Capabilities {
+    target_os: String,
+    memory_read_method: String,
+    default_arch: util::Arch,
+    can_read: bool,
+    can_write: bool,
+    can_suspend: bool,
+    can_enumerate_modules: bool,
+    can_set_watchpoints: bool,
+    can_set_breakpoints: bool,
+    disassemble_archs: Vec<util::Arch>,
+    assemble_archs: Vec<util::Arch>,
+}
+
+lazy_static! {
+    // Fixed for the life of the process, and probing the assemblers is not free
+    static ref CAPABILITIES: Capabilities = probe_capabilities();
+}
+
+fn probe_capabilities() -> Capabilities {
+    Capabilities {
+        target_os: env!("TARGET_OS").to_string(),
+        memory_read_method: native_bridge::memory_read_method().to_string(),
+        default_arch: util::Arch::default(),
+        can_read: true,
+        can_write: native_bridge::can_write_memory(),
+        can_suspend: native_bridge::can_suspend_process(),
+        can_enumerate_modules: native_bridge::can_enumerate_modules(),
+        can_set_watchpoints: native_bridge::has_watchpoints(),
+        can_set_breakpoints: native_bridge::has_breakpoints(),
+        disassemble_archs: util::Arch::ALL
+            .into_iter()
+            .filter(|&arch| util::disassembler_available(arch))
+            .collect(),
+        assemble_archs: util::Arch::ALL
+            .into_iter()
+            .filter(|&arch| assembler::assemble("nop", 0, arch).is_ok())
+            .collect(),
+    }
+}
+
+pub async fn capabilities_handler() -> Result<impl warp::Reply, warp::Rejection> {
+    Ok(warp::reply::json(&*CAPABILITIES))
+}
+
+#[derive(Serialize)]
+struct Health {
+    status: &'static str,
+    uptime_secs: u64,
+    version: &'static str,
+    git_hash: &'static str,
+    target_os: &'static str,
+    arch: &'static str,
+    // None when no process is open, or when busy hides which one
+    attached_pid: Option<i32>,
+    // A long request such as a scan holds the process state; reported instead of waiting
+    busy: bool,
+    capabilities: Capabilities,
+}
+
+// For supervisors and watchdogs: answers from server state alone and never touches the
+// target, so a dead or stuck process cannot hang it. 503 when the native layer failed to
+// initialize.
+pub async fn health_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    started: Instant,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let (attached_pid, busy) = match pid_state.try_lock() {
+        Ok(pid) => (*pid, false),
+        Err(TryLockError::Poisoned(pid)) => (*pid.into_inner(), false),
+        Err(TryLockError::WouldBlock) => (None, true),
+    };
+    let ready = native_bridge::native_ready();
+    let health = Health {
+        status: if ready { "ok" } else { "unavailable" },
+        uptime_secs: started.elapsed().as_secs(),
+        version: env!("CARGO_PKG_VERSION"),
+        git_hash: env!("GIT_HASH"),
+        target_os: env!("TARGET_OS"),
+        arch: server_arch(),
+        attached_pid,
+        busy,
+        capabilities: CAPABILITIES.clone(),
+    };
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    Ok(warp::reply::with_status(warp::reply::json(&health), status))
+}
+
 pub async fn open_process_handler(
     pid_state: Arc<Mutex<Option<i32>>>,
     open_process: request::OpenProcessRequest,
@@ -160,6 +308,94 @@ pub async fn open_process_handler(
     Ok(warp::reply::with_status("OK", warp::http::StatusCode::OK))
 }
 
+// Loads a core dump and points the server at it, so later requests read the dump the way
+// they would a process
+pub async fn open_dump_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    open_request: request::OpenDumpRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let result = util::check_file_path(&open_request.path)
+        .and_then(|path| coredump::open_dump(Path::new(&path)));
+    match result {
+        Ok(info) => {
+            *pid_state.lock().unwrap() = Some(info.handle.pid());
+            let response = Response::builder()
+                .header("Content-Type", "application/json")
+                .body(hyper::Body::from(json!(info).to_string()))
+                .unwrap();
+            Ok(response)
+        }
+        Err(e) => {
+            let response = Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(hyper::Body::from(e))
+                .unwrap();
+            Ok(response)
+        }
+    }
+}
+
+pub async fn close_dump_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    close_request: request::CloseDumpRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if !coredump::close_dump(close_request.handle) {
+        let response = Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(hyper::Body::from("Dump is not open"))
+            .unwrap();
+        return Ok(response);
+    }
+    let mut pid = pid_state.lock().unwrap();
+    if *pid == Some(close_request.handle) {
+        *pid = None;
+    }
+    let response = Response::builder()
+        .header("Content-Type", "text/plain")
+        .body(hyper::Body::from("Dump closed"))
+        .unwrap();
+    Ok(response)
+}
+
+// Sets the pid as POST /process does once the name picks out one process. 409 with the
+// candidates when several match, so the client can ask which was meant.
+pub async fn attach_by_name_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    attach_request: request::AttachByNameRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let matching = attach_request.match_mode.unwrap_or_default();
+    match util::attach_by_name(&attach_request.name, matching) {
+        Ok(process) => {
+            *pid_state.lock().unwrap() = Some(process.pid);
+            let result = json!({
+                "pid": process.pid,
+                "processname": process.name,
+                "path": process.path,
+            });
+            let response = Response::builder()
+                .header("Content-Type", "application/json")
+                .body(hyper::Body::from(result.to_string()))
+                .unwrap();
+            Ok(response)
+        }
+        Err(e) => {
+            let (status, candidates) = match &e {
+                util::ProcessLookupError::NotFound(_) => (StatusCode::NOT_FOUND, Vec::new()),
+                util::ProcessLookupError::Ambiguous(_, candidates) => {
+                    (StatusCode::CONFLICT, candidates.clone())
+                }
+            };
+            let result = json!({ "error": e.to_string(), "candidates": candidates });
+            let response = Response::builder()
+                .status(status)
+                .header("Content-Type", "application/json")
+                .body(hyper::Body::from(result.to_string()))
+                .unwrap();
+            Ok(response)
+        }
+    }
+}
+
 pub async fn resolve_addr_handler(
     pid_state: Arc<Mutex<Option<i32>>>,
     resolve_addr: request::ResolveAddrRequest,
@@ -168,9 +404,9 @@ pub async fn resolve_addr_handler(
 
     if let Some(pid) = *pid {
         let modules = native_bridge::enum_modules(pid).unwrap();
-        match util::resolve_symbolic_address(pid, &resolve_addr.query, &modules) {
-            Ok(resolved_address) => {
-                let result = json!({ "address": resolved_address });
+        match util::resolve_with_module_context(pid, &resolve_addr.query, &modules) {
+            Ok(resolved) => {
+                let result = json!(resolved);
                 let result_string = result.to_string();
                 let response = Response::builder()
                     .header("Content-Type", "application/json")
@@ -186,7 +422,7 @@ pub async fn resolve_addr_handler(
                         e
                     )))
                     .unwrap();
-                return Ok(response);
+                Ok(response)
             }
         }
     } else {
@@ -198,37 +434,34 @@ pub async fn resolve_addr_handler(
     }
 }
 
-pub async fn read_memory_handler(
+pub async fn symbolicate_handler(
     pid_state: Arc<Mutex<Option<i32>>>,
-    read_memory: request::ReadMemoryRequest,
+    symbolicate_request: request::SymbolicateRequest,
 ) -> Result<impl warp::Reply, warp::Rejection> {
     let pid = pid_state.lock().unwrap();
 
     if let Some(pid) = *pid {
-        let mut buffer: Vec<u8> = vec![0; read_memory.size];
-        let nread = native_bridge::read_process_memory(
-            pid,
-            read_memory.address as *mut libc::c_void,
-            read_memory.size,
-            &mut buffer,
-        );
-        match nread {
-            Ok(_) => {
+        match native_bridge::enum_modules(pid) {
+            Ok(modules) => {
+                let address = symbolicate_request.address;
+                let result = json!({
+                    "address": address,
+                    "symbol": symbols::symbolicate(pid, address, &modules),
+                });
                 let response = Response::builder()
-                    .header("Content-Type", "application/octet-stream")
-                    .body(hyper::Body::from(buffer))
+                    .header("Content-Type", "application/json")
+                    .body(hyper::Body::from(result.to_string()))
                     .unwrap();
-                return Ok(response);
+                Ok(response)
             }
-            Err(_) => {
-                let empty_buffer = Vec::new();
+            Err(e) => {
                 let response = Response::builder()
-                    .header("Content-Type", "application/octet-stream")
-                    .body(hyper::Body::from(empty_buffer))
+                    .status(StatusCode::INTERNAL_SERVER_ERROR)
+                    .body(hyper::Body::from(e))
                     .unwrap();
-                return Ok(response);
+                Ok(response)
             }
-        };
+        }
     } else {
         let response = Response::builder()
             .status(StatusCode::BAD_REQUEST)
@@ -238,51 +471,50 @@ pub async fn read_memory_handler(
     }
 }
 
-pub async fn read_memory_multiple_handler(
+// Lists the exports of one module, named by file name, at their addresses in the target
+pub async fn module_exports_handler(
     pid_state: Arc<Mutex<Option<i32>>>,
-    read_memory_requests: Vec<request::ReadMemoryRequest>,
+    exports_request: request::ModuleExportsRequest,
 ) -> Result<impl warp::Reply, warp::Rejection> {
     let pid = pid_state.lock().unwrap();
+
     if let Some(pid) = *pid {
-        let compressed_buffers: Vec<Vec<u8>> = read_memory_requests
-            .par_iter()
-            .map(|request| {
-                let mut buffer: Vec<u8> = vec![0; request.size];
-                let nread = native_bridge::read_process_memory(
-                    pid,
-                    request.address as *mut libc::c_void,
-                    request.size,
-                    &mut buffer,
-                );
-                match nread {
-                    Ok(_) => {
-                        let compressed_buffer = compress_prepend_size(&buffer);
-                        let mut result_buffer = Vec::with_capacity(8 + compressed_buffer.len());
-                        let compresed_buffer_size: u32 = compressed_buffer.len() as u32;
-                        result_buffer.extend_from_slice(&1u32.to_le_bytes());
-                        result_buffer.extend_from_slice(&compresed_buffer_size.to_le_bytes());
-                        result_buffer.extend_from_slice(&compressed_buffer);
-                        result_buffer
-                    }
-                    Err(_) => {
-                        let mut result_buffer = Vec::with_capacity(4);
-                        result_buffer.extend_from_slice(&0u32.to_le_bytes());
-                        result_buffer
-                    }
-                }
+        let module = native_bridge::enum_modules(pid).ok().and_then(|modules| {
+            modules.into_iter().find(|module| {
+                module["modulename"].as_str().is_some_and(|name| {
+                    Path::new(name)
+                        .file_name()
+                        .is_some_and(|file| file.eq_ignore_ascii_case(&*exports_request.module))
+                })
             })
-            .collect();
-
-        let mut concatenated_buffer = Vec::new();
-        for buffer in compressed_buffers {
-            concatenated_buffer.extend(buffer);
+        });
+        match module {
+            Some(module) => {
+                let exports: Vec<Value> = symbols::module_symbols(
+                    pid,
+                    module["base"].as_u64().unwrap_or(0),
+                    module["modulename"].as_str().unwrap_or_default(),
+                )
+                .into_iter()
+                .map(|(name, address)| json!({ "name": name, "address": address }))
+                .collect();
+                let response = Response::builder()
+                    .header("Content-Type", "application/json")
+                    .body(hyper::Body::from(json!(exports).to_string()))
+                    .unwrap();
+                Ok(response)
+            }
+            None => {
+                let response = Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .body(hyper::Body::from(format!(
+                        "Module {} not found",
+                        exports_request.module
+                    )))
+                    .unwrap();
+                Ok(response)
+            }
         }
-
-        let response = Response::builder()
-            .header("Content-Type", "application/octet-stream")
-            .body(hyper::Body::from(concatenated_buffer))
-            .unwrap();
-        Ok(response)
     } else {
         let response = Response::builder()
             .status(StatusCode::BAD_REQUEST)
@@ -292,35 +524,35 @@ pub async fn read_memory_multiple_handler(
     }
 }
 
-pub async fn write_memory_handler(
+pub async fn dissect_handler(
     pid_state: Arc<Mutex<Option<i32>>>,
-    write_memory: request::WriteMemoryRequest,
+    dissect_request: request::DissectRequest,
 ) -> Result<impl warp::Reply, warp::Rejection> {
     let pid = pid_state.lock().unwrap();
 
     if let Some(pid) = *pid {
-        let nwrite = native_bridge::write_process_memory(
-            pid,
-            write_memory.address as *mut libc::c_void,
-            write_memory.buffer.len(),
-            &write_memory.buffer,
-        );
-        match nwrite {
-            Ok(_) => {
+        let result = native_bridge::enum_modules(pid)
+            .map_err(|e| format!("Failed to enumerate modules: {}", e))
+            .and_then(|modules| {
+                util::resolve_symbolic_address(pid, &dissect_request.address, &modules)
+            })
+            .and_then(|address| util::dissect(pid, address as u64, &dissect_request.fields));
+        match result {
+            Ok(value) => {
                 let response = Response::builder()
-                    .header("Content-Type", "text/plain")
-                    .body(hyper::Body::from("Memory successfully written"))
+                    .header("Content-Type", "application/json")
+                    .body(hyper::Body::from(value.to_string()))
                     .unwrap();
-                return Ok(response);
+                Ok(response)
             }
-            Err(_) => {
+            Err(e) => {
                 let response = Response::builder()
                     .status(StatusCode::BAD_REQUEST)
-                    .body(hyper::Body::from("WriteProcessMemory error"))
+                    .body(hyper::Body::from(e))
                     .unwrap();
-                return Ok(response);
+                Ok(response)
             }
-        };
+        }
     } else {
         let response = Response::builder()
             .status(StatusCode::BAD_REQUEST)
@@ -330,322 +562,307 @@ pub async fn write_memory_handler(
     }
 }
 
-pub async fn memory_scan_handler(
+pub async fn guess_type_handler(
     pid_state: Arc<Mutex<Option<i32>>>,
-    scan_request: request::MemoryScanRequest,
+    guess_request: request::GuessTypeRequest,
 ) -> Result<impl warp::Reply, warp::Rejection> {
     let pid = pid_state.lock().unwrap();
 
-    let mut is_suspend_success: bool = false;
-    let do_suspend = scan_request.do_suspend;
     if let Some(pid) = *pid {
-        if do_suspend {
-            unsafe {
-                is_suspend_success = native_bridge::suspend_process(pid);
+        let result = native_bridge::enum_modules(pid)
+            .map_err(|e| format!("Failed to enumerate modules: {}", e))
+            .and_then(|modules| {
+                util::resolve_symbolic_address(pid, &guess_request.address, &modules)
+            })
+            .and_then(|address| {
+                let (bytes, guesses) = inspect::guess_type(pid, address as u64)?;
+                Ok(json!({
+                    "address": address,
+                    "bytes": hex::encode(bytes),
+                    "guesses": guesses,
+                }))
+            });
+        match result {
+            Ok(value) => {
+                let response = Response::builder()
+                    .header("Content-Type", "application/json")
+                    .body(hyper::Body::from(value.to_string()))
+                    .unwrap();
+                Ok(response)
+            }
+            Err(e) => {
+                let response = Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(hyper::Body::from(e))
+                    .unwrap();
+                Ok(response)
             }
         }
-        // Clear global_positions for the given scan_id
-        {
-            let mut global_positions = GLOBAL_POSITIONS.write().unwrap();
-            if let Some(positions) = global_positions.get_mut(&scan_request.scan_id) {
-                positions.clear();
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
+
+pub async fn disassemble_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    disassemble_request: request::DisassembleRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
+    if let Some(pid) = *pid {
+        match util::disassemble_at(
+            pid,
+            disassemble_request.address,
+            disassemble_request.count,
+            disassemble_request.arch,
+            disassemble_request.syntax.unwrap_or_default(),
+        ) {
+            Ok(mut instructions) => {
+                util::annotate_instructions(pid, &mut instructions);
+                let result = json!({ "instructions": instructions });
+                let response = Response::builder()
+                    .header("Content-Type", "application/json")
+                    .body(hyper::Body::from(result.to_string()))
+                    .unwrap();
+                Ok(response)
             }
-            let mut global_memory = GLOBAL_MEMORY.write().unwrap();
-            if let Some(memory) = global_memory.get_mut(&scan_request.scan_id) {
-                memory.clear();
-            } else {
+            Err(e) => {
+                let response = Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(hyper::Body::from(e))
+                    .unwrap();
+                Ok(response)
             }
-            let mut global_scan_option = GLOBAL_SCAN_OPTION.write().unwrap();
-            global_scan_option.insert(scan_request.scan_id.clone(), scan_request.clone());
-        }
-        // memory-server-data-dir/Scan_xxx cleanup and create
-        let mut scan_folder_path = PathBuf::from("");
-        let mode =
-            std::env::var("MEMORY_SERVER_RUNNING_MODE").unwrap_or_else(|_| "unknown".to_string());
-        if mode == "embedded" {
-            let cache_directory = util::get_cache_directory(pid);
-            scan_folder_path = PathBuf::from(&cache_directory);
         }
-        let sanitized_scan_id = scan_request.scan_id.trim().replace(" ", "_");
-        scan_folder_path.push("memory-server-data-dir");
-        scan_folder_path.push(&sanitized_scan_id);
-        let scan_folder = Path::new(&scan_folder_path);
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
 
-        if scan_folder.exists() {
-            fs::remove_dir_all(&scan_folder).expect("Failed to remove directory");
-        }
-        fs::create_dir_all(&scan_folder_path).expect("Failed to create directory");
+// Instructions decoded when the request sets no cap
+const DEFAULT_FUNCTION_INSTRUCTIONS: usize = 2000;
 
-        let is_number = match scan_request.data_type.as_str() {
-            "int16" | "uint16" | "int32" | "uint32" | "float" | "int64" | "uint64" | "double" => {
-                true
+pub async fn disassemble_function_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    function_request: request::DisassembleFunctionRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
+    if let Some(pid) = *pid {
+        match util::disassemble_function(
+            pid,
+            function_request.address,
+            function_request.arch,
+            function_request
+                .max_instructions
+                .unwrap_or(DEFAULT_FUNCTION_INSTRUCTIONS),
+            function_request.syntax.unwrap_or_default(),
+        ) {
+            Ok((mut instructions, end)) => {
+                util::annotate_instructions(pid, &mut instructions);
+                let size: usize = instructions.iter().map(|i| i.size).sum();
+                let result = json!({
+                    "address": function_request.address,
+                    "size": size,
+                    "end": end,
+                    "instructions": instructions
+                });
+                let response = Response::builder()
+                    .header("Content-Type", "application/json")
+                    .body(hyper::Body::from(result.to_string()))
+                    .unwrap();
+                Ok(response)
             }
-            _ => false,
-        };
-        let found_count = Arc::new(AtomicUsize::new(0));
-        let scan_align = scan_request.align;
-        let is_error_occurred = Arc::new(Mutex::new(false));
-        let error_message = Arc::new(Mutex::new(String::new()));
-
-        let thread_results: Vec<Vec<(usize, String)>> = scan_request
-            .address_ranges
-            .par_iter()
-            .enumerate()
-            .flat_map(|(index, &(ref start_address, ref end_address))| {
-                let found_count = Arc::clone(&found_count);
-                let size = end_address - start_address;
-                let chunk_size = 1024 * 1024 * 16; // 16MB
-                let num_chunks = (size + chunk_size - 1) / chunk_size;
-
-                (0..num_chunks)
-                    .map(|i| {
-                        let mut error_occurred = is_error_occurred.lock().unwrap();
-                        let mut error_msg = error_message.lock().unwrap();
-
-                        if *error_occurred == true {
-                            return vec![];
-                        }
-                        let chunk_start = start_address + i * chunk_size;
-                        let chunk_end = std::cmp::min(chunk_start + chunk_size, *end_address);
-                        let chunk_size_actual = chunk_end - chunk_start;
-                        let mut buffer: Vec<u8> = vec![0; chunk_size_actual];
-
-                        let mut local_positions = vec![];
-                        let mut local_values = vec![];
-
-                        let nread = match native_bridge::read_process_memory(
-                            pid,
-                            chunk_start as *mut libc::c_void,
-                            chunk_size_actual,
-                            &mut buffer,
-                        ) {
-                            Ok(nread) => nread,
-                            Err(_) => -1,
-                        };
-
-                        if nread != -1 {
-                            if scan_request.find_type == "exact" {
-                                if scan_request.data_type == "regex" {
-                                    let regex_pattern = &scan_request.pattern;
-                                    let re = match Regex::new(regex_pattern) {
-                                        Ok(re) => re,
-                                        Err(_) => return vec![],
-                                    };
-
-                                    for cap in re.captures_iter(&buffer) {
-                                        let start = cap.get(0).unwrap().start();
-                                        if (chunk_start + start) % scan_align == 0 {
-                                            let end = cap.get(0).unwrap().end();
-                                            let value = hex::encode(&buffer[start..end]);
-                                            local_positions.push(chunk_start + start);
-                                            local_values.push(value);
-                                            found_count.fetch_add(1, Ordering::SeqCst);
-                                        }
-                                    }
-                                } else {
-                                    let search_bytes = match hex::decode(&scan_request.pattern) {
-                                        Ok(bytes) => bytes,
-                                        Err(_) => return vec![],
-                                    };
-
-                                    let mut buffer_offset = 0;
-                                    for pos in memmem::find_iter(&buffer, &search_bytes) {
-                                        let start = chunk_start + buffer_offset + pos;
-                                        if start % scan_align == 0 {
-                                            let value = scan_request.pattern.clone();
-                                            if is_number {
-                                                local_positions.push(start);
-                                                local_values.push(value);
-                                            } else {
-                                                local_positions.push(start);
-                                                local_values.push(value);
-                                            }
-                                            found_count.fetch_add(1, Ordering::SeqCst);
-                                        }
-                                        buffer_offset += pos + 1;
-                                    }
-                                }
-                            } else if scan_request.find_type == "unknown" {
-                                let alignment = match scan_request.data_type.as_str() {
-                                    "int16" | "uint16" => 2,
-                                    "int32" | "uint32" | "float" => 4,
-                                    "int64" | "uint64" | "double" => 8,
-                                    _ => 1,
-                                };
-
-                                let mut file_path = scan_folder_path.clone();
-                                file_path.push(format!("{}.dump", index));
-                                let file_exists = file_path.exists();
-
-                                let file = match OpenOptions::new()
-                                    .create(true)
-                                    .append(true)
-                                    .open(file_path)
-                                {
-                                    Ok(file) => file,
-                                    Err(e) => {
-                                        *error_occurred = true;
-                                        *error_msg = format!("Failed to open file: {}", e);
-                                        return vec![];
-                                    }
-                                };
-
-                                let mut writer = BufWriter::new(file);
-
-                                if !file_exists {
-                                    // status flag
-                                    let zero_bytes = [0x00, 0x00, 0x00, 0x00];
-                                    if let Err(e) = writer.write_all(&zero_bytes) {
-                                        *error_occurred = true;
-                                        *error_msg = format!("Failed to write 4 zero bytes: {}", e);
-                                        return vec![];
-                                    }
-                                }
-
-                                if let Err(e) = writer.write_all(&chunk_start.to_le_bytes()) {
-                                    *error_occurred = true;
-                                    *error_msg = format!("Failed to write chunk_start: {}", e);
-                                    return vec![];
-                                }
-
-                                let compressed_buffer = lz4_flex::block::compress(&buffer);
+            Err(e) => {
+                let response = Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(hyper::Body::from(e))
+                    .unwrap();
+                Ok(response)
+            }
+        }
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
 
-                                if let Err(e) = writer
-                                    .write_all(&(compressed_buffer.len() as u64).to_le_bytes())
-                                {
-                                    *error_occurred = true;
-                                    *error_msg =
-                                        format!("Failed to write compressed buffer length: {}", e);
-                                    return vec![];
-                                }
+pub async fn assemble_handler(
+    assemble_request: request::AssembleRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match assembler::assemble(
+        &assemble_request.source,
+        assemble_request.address,
+        assemble_request.arch,
+    ) {
+        Ok(buffer) => {
+            let result = json!({ "buffer": buffer });
+            let response = Response::builder()
+                .header("Content-Type", "application/json")
+                .body(hyper::Body::from(result.to_string()))
+                .unwrap();
+            Ok(response)
+        }
+        Err(e) => {
+            let response = Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(hyper::Body::from(format!("Failed to assemble: {}", e)))
+                .unwrap();
+            Ok(response)
+        }
+    }
+}
 
-                                if let Err(e) =
-                                    writer.write_all(&(buffer.len() as u64).to_le_bytes())
-                                {
-                                    *error_occurred = true;
-                                    *error_msg = format!(
-                                        "Failed to write uncompressed buffer length: {}",
-                                        e
-                                    );
-                                    return vec![];
-                                }
+pub async fn evaluate_expression_handler(
+    evaluate_request: request::EvaluateExpressionRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match util::evaluate_expression(&evaluate_request.expression) {
+        Ok(value) => {
+            let result = json!({ "value": value });
+            let response = Response::builder()
+                .header("Content-Type", "application/json")
+                .body(hyper::Body::from(result.to_string()))
+                .unwrap();
+            Ok(response)
+        }
+        Err(e) => {
+            let response = Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(hyper::Body::from(format!(
+                    "Failed to evaluate expression: {}",
+                    e
+                )))
+                .unwrap();
+            Ok(response)
+        }
+    }
+}
 
-                                if let Err(e) = writer.write_all(&compressed_buffer) {
-                                    *error_occurred = true;
-                                    *error_msg = format!("Failed to write buffer data: {}", e);
-                                    return vec![];
-                                }
+// A call that overran its deadline may succeed if retried; one that panicked will not
+fn deadline_status(e: &deadline::DeadlineError) -> StatusCode {
+    if e.is_timeout() {
+        StatusCode::GATEWAY_TIMEOUT
+    } else {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
 
-                                if let Err(e) = writer.flush() {
-                                    *error_occurred = true;
-                                    *error_msg = format!("Failed to flush buffer: {}", e);
-                                    return vec![];
-                                }
-                                found_count.fetch_add(buffer.len() / alignment, Ordering::SeqCst);
-                            }
-                            // Check if local_positions exceed MAX_RESULTS and insert into global_positions
-                            if local_positions.len() > MAX_RESULTS {
-                                let mut global_positions = GLOBAL_POSITIONS.write().unwrap();
-                                let combined: Vec<(usize, String)> = local_positions
-                                    .into_iter()
-                                    .zip(local_values.into_iter())
-                                    .collect();
-                                if let Some(positions) =
-                                    global_positions.get_mut(&scan_request.scan_id)
-                                {
-                                    positions.extend(combined);
-                                } else {
-                                    global_positions.insert(scan_request.scan_id.clone(), combined);
-                                }
-                                local_positions = vec![];
-                                local_values = vec![];
-                            }
-                        }
+pub async fn read_memory_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    read_memory: request::ReadMemoryRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
 
-                        let combined: Vec<(usize, String)> = local_positions
-                            .into_iter()
-                            .zip(local_values.into_iter())
-                            .collect();
-                        combined
-                    })
-                    .collect::<Vec<_>>()
-            })
-            .collect();
-        let mut do_play = GLOBAL_PROCESS_STATE.write().unwrap();
-        if do_suspend && is_suspend_success && *do_play {
-            unsafe {
-                native_bridge::resume_process(pid);
+    if let Some(pid) = *pid {
+        let (address, size) = (read_memory.address, read_memory.size);
+        let read = deadline::run_with_deadline(
+            deadline::OperationClass::Read,
+            format!("Reading {} bytes at {:#x} in pid {}", size, address, pid),
+            move || {
+                let mut buffer: Vec<u8> = vec![0; size];
+                native_bridge::read_process_memory(
+                    pid,
+                    address as *mut libc::c_void,
+                    size,
+                    &mut buffer,
+                )
+                .map(|_| buffer)
+            },
+        );
+        match read {
+            Ok(Ok(buffer)) => {
+                if read_memory.hexdump == Some(true) {
+                    let response = Response::builder()
+                        .header("Content-Type", "text/plain")
+                        .body(hyper::Body::from(util::format_hexdump(
+                            &buffer,
+                            address as u64,
+                        )))
+                        .unwrap();
+                    return Ok(response);
+                }
+                if let Some(mode) = read_memory.compression {
+                    let payload = compression::compress_payload(&buffer, mode);
+                    let response = Response::builder()
+                        .header("Content-Type", "application/octet-stream")
+                        .header("X-Compression", "lz4")
+                        .header("X-Original-Length", payload.original_len)
+                        .body(hyper::Body::from(payload.data))
+                        .unwrap();
+                    return Ok(response);
+                }
+                let response = Response::builder()
+                    .header("Content-Type", "application/octet-stream")
+                    .body(hyper::Body::from(buffer))
+                    .unwrap();
+                return Ok(response);
             }
-        }
-        // println!("{}", found_count.load(Ordering::SeqCst));
-
-        let flattened_results: Vec<(usize, String)> =
-            thread_results.into_iter().flatten().collect();
-        {
-            let mut global_positions = GLOBAL_POSITIONS.write().unwrap();
-            if let Some(positions) = global_positions.get_mut(&scan_request.scan_id) {
-                positions.extend(flattened_results);
-            } else {
-                global_positions.insert(scan_request.scan_id.clone(), flattened_results);
+            Ok(Err(_)) => {
+                let empty_buffer = Vec::new();
+                let response = Response::builder()
+                    .header("Content-Type", "application/octet-stream")
+                    .body(hyper::Body::from(empty_buffer))
+                    .unwrap();
+                return Ok(response);
             }
-        }
+            Err(e) => {
+                let response = Response::builder()
+                    .status(deadline_status(&e))
+                    .body(hyper::Body::from(e.to_string()))
+                    .unwrap();
+                return Ok(response);
+            }
+        };
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
 
-        if scan_request.return_as_json {
-            let global_positions = GLOBAL_POSITIONS.read().unwrap();
-            if let Some(positions) = global_positions.get(&scan_request.scan_id) {
-                let limited_positions = &positions[..std::cmp::min(MAX_RESULTS, positions.len())];
-                let count = found_count.load(Ordering::SeqCst);
-                let is_rounded: bool;
-                if scan_request.find_type == "unknown" {
-                    if count > 1_000_000 {
-                        is_rounded = true;
-                    } else {
-                        is_rounded = limited_positions.len() != positions.len();
-                    }
-                } else {
-                    is_rounded = limited_positions.len() != positions.len();
-                }
-                let matched_addresses: Vec<serde_json::Value> = limited_positions
-                    .into_iter()
-                    .map(|(address, value)| {
-                        json!({
-                            "address": address,
-                            "value": value
-                        })
-                    })
-                    .collect();
-                let result = json!({
-                    "matched_addresses": matched_addresses,
-                    "found":count,
-                    "is_rounded":is_rounded
-                });
-                let result_string = result.to_string();
+pub async fn view_memory_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    view_request: request::ViewMemoryRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
+
+    if let Some(pid) = *pid {
+        let (address, length) = (view_request.address, view_request.length);
+        let view = deadline::run_with_deadline(
+            deadline::OperationClass::Read,
+            format!("Viewing {} bytes at {:#x} in pid {}", length, address, pid),
+            move || util::view_memory(pid, address, length),
+        );
+        match view {
+            Ok(Ok(view)) => {
                 let response = Response::builder()
                     .header("Content-Type", "application/json")
-                    .body(hyper::Body::from(result_string))
+                    .body(hyper::Body::from(json!(view).to_string()))
                     .unwrap();
                 Ok(response)
-            } else {
+            }
+            Ok(Err(e)) => {
                 let response = Response::builder()
                     .status(StatusCode::BAD_REQUEST)
-                    .body(hyper::Body::from("Unknown error"))
+                    .body(hyper::Body::from(e))
                     .unwrap();
                 Ok(response)
             }
-        } else {
-            let global_positions = GLOBAL_POSITIONS.read().unwrap();
-            if let Some(_positions) = global_positions.get(&scan_request.scan_id) {
-                let count = found_count.load(Ordering::SeqCst);
-                let result_string = json!({ "found": count }).to_string();
-                let response = Response::builder()
-                    .header("Content-Type", "application/json")
-                    .body(hyper::Body::from(result_string))
-                    .unwrap();
-                Ok(response)
-            } else {
+            Err(e) => {
                 let response = Response::builder()
-                    .status(StatusCode::BAD_REQUEST)
-                    .body(hyper::Body::from("Unknown error"))
+                    .status(deadline_status(&e))
+                    .body(hyper::Body::from(e.to_string()))
                     .unwrap();
                 Ok(response)
             }
@@ -659,35 +876,1847 @@ pub async fn memory_scan_handler(
     }
 }
 
-macro_rules! compare_values {
-    ($val:expr, $old_val:expr, $filter_method:expr) => {
-        match $filter_method {
-            "changed" => $val != $old_val,
-            "unchanged" => $val == $old_val,
-            "increased" => $val > $old_val,
-            "decreased" => $val < $old_val,
-            _ => false,
-        }
-    };
-}
-
-pub async fn memory_filter_handler(
+pub async fn read_value_handler(
     pid_state: Arc<Mutex<Option<i32>>>,
-    filter_request: request::MemoryFilterRequest,
+    read_value: request::ReadValueRequest,
 ) -> Result<impl warp::Reply, warp::Rejection> {
     let pid = pid_state.lock().unwrap();
 
-    let mut is_suspend_success: bool = false;
-    let do_suspend = filter_request.do_suspend;
     if let Some(pid) = *pid {
-        let mut new_positions = Vec::new();
-        let mut global_positions = GLOBAL_POSITIONS.write().unwrap();
-        let mut global_memory = GLOBAL_MEMORY.write().unwrap();
-        let global_scan_option = GLOBAL_SCAN_OPTION.write().unwrap();
-        let scan_option: request::MemoryScanRequest = global_scan_option
-            .get(&filter_request.scan_id)
-            .unwrap()
-            .clone();
+        let value = util::ValueType::parse(&read_value.value_type).and_then(|value_type| {
+            let address = read_value.address as u64;
+            let endian = read_value.endian.unwrap_or(util::Endianness::Little);
+            match read_value.radix {
+                Some(radix) => util::read_value_formatted(
+                    pid,
+                    address,
+                    value_type,
+                    endian,
+                    radix,
+                    read_value.grouped.unwrap_or(false),
+                )
+                .map(|(value, formatted)| (value, Some(formatted))),
+                None => util::read_value_endian(pid, address, value_type, endian)
+                    .map(|value| (value, None)),
+            }
+        });
+        match value {
+            Ok((value, formatted)) => {
+                let mut result = json!({ "address": read_value.address, "value": value });
+                if let Some(formatted) = formatted {
+                    result["formatted"] = json!(formatted);
+                }
+                let response = Response::builder()
+                    .header("Content-Type", "application/json")
+                    .body(hyper::Body::from(result.to_string()))
+                    .unwrap();
+                Ok(response)
+            }
+            Err(e) => {
+                let response = Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(hyper::Body::from(e))
+                    .unwrap();
+                Ok(response)
+            }
+        }
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
+
+pub async fn write_value_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    write_value: request::WriteValueRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
+
+    if let Some(pid) = *pid {
+        let written = write_value
+            .encoding
+            .as_deref()
+            .map(util::StringEncoding::parse)
+            .transpose()
+            .and_then(|encoding| {
+                util::write_typed_value(
+                    pid,
+                    write_value.address,
+                    &write_value.value_type,
+                    &write_value.value,
+                    encoding,
+                )
+            });
+        match written {
+            Ok(bytes) => {
+                let result = json!({
+                    "address": write_value.address,
+                    "bytes": hex::encode(bytes)
+                });
+                let response = Response::builder()
+                    .header("Content-Type", "application/json")
+                    .body(hyper::Body::from(result.to_string()))
+                    .unwrap();
+                Ok(response)
+            }
+            Err(e) => {
+                let response = Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(hyper::Body::from(e))
+                    .unwrap();
+                Ok(response)
+            }
+        }
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
+
+pub async fn read_string_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    read_string: request::ReadStringRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
+
+    if let Some(pid) = *pid {
+        let result = util::StringEncoding::parse(&read_string.encoding).and_then(|encoding| {
+            util::read_string(
+                pid,
+                read_string.address as u64,
+                read_string.max_len,
+                encoding,
+            )
+        });
+        match result {
+            Ok(value) => {
+                let result = json!({ "address": read_string.address, "value": value });
+                let response = Response::builder()
+                    .header("Content-Type", "application/json")
+                    .body(hyper::Body::from(result.to_string()))
+                    .unwrap();
+                Ok(response)
+            }
+            Err(e) => {
+                let response = Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(hyper::Body::from(e))
+                    .unwrap();
+                Ok(response)
+            }
+        }
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
+
+pub async fn write_string_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    write_string: request::WriteStringRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
+
+    if let Some(pid) = *pid {
+        let written = util::StringEncoding::parse(&write_string.encoding).and_then(|encoding| {
+            util::write_string(
+                pid,
+                write_string.address,
+                &write_string.text,
+                encoding,
+                write_string.null_terminate.unwrap_or(true),
+                write_string.length_prefix,
+                write_string.max_len,
+            )
+        });
+        match written {
+            Ok(written) => {
+                let result = json!({ "address": write_string.address, "written": written });
+                let response = Response::builder()
+                    .header("Content-Type", "application/json")
+                    .body(hyper::Body::from(result.to_string()))
+                    .unwrap();
+                Ok(response)
+            }
+            Err(e) => {
+                let response = Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(hyper::Body::from(e))
+                    .unwrap();
+                Ok(response)
+            }
+        }
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
+
+pub async fn read_memory_multiple_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    read_memory_requests: Vec<request::ReadMemoryRequest>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
+    if let Some(pid) = *pid {
+        let reads: Vec<(u64, usize)> = read_memory_requests
+            .iter()
+            .map(|request| (request.address as u64, request.size))
+            .collect();
+        let compressed_buffers: Vec<Vec<u8>> = util::read_memory_batch(pid, &reads)
+            .into_par_iter()
+            .map(
+                |result| match result.and_then(|buffer| compression::compress_framed(&buffer)) {
+                    Ok(compressed_buffer) => {
+                        let mut result_buffer = Vec::with_capacity(8 + compressed_buffer.len());
+                        let compresed_buffer_size: u32 = compressed_buffer.len() as u32;
+                        result_buffer.extend_from_slice(&1u32.to_le_bytes());
+                        result_buffer.extend_from_slice(&compresed_buffer_size.to_le_bytes());
+                        result_buffer.extend_from_slice(&compressed_buffer);
+                        result_buffer
+                    }
+                    Err(_) => {
+                        let mut result_buffer = Vec::with_capacity(4);
+                        result_buffer.extend_from_slice(&0u32.to_le_bytes());
+                        result_buffer
+                    }
+                },
+            )
+            .collect();
+
+        let mut concatenated_buffer = Vec::new();
+        for buffer in compressed_buffers {
+            concatenated_buffer.extend(buffer);
+        }
+
+        let response = Response::builder()
+            .header("Content-Type", "application/octet-stream")
+            .body(hyper::Body::from(concatenated_buffer))
+            .unwrap();
+        Ok(response)
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
+
+pub async fn fill_memory_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    fill_request: request::FillMemoryRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
+
+    if let Some(pid) = *pid {
+        match util::fill_memory(
+            pid,
+            fill_request.address,
+            fill_request.byte,
+            fill_request.length,
+        ) {
+            Ok(()) => {
+                let response = Response::builder()
+                    .header("Content-Type", "text/plain")
+                    .body(hyper::Body::from("Memory successfully filled"))
+                    .unwrap();
+                Ok(response)
+            }
+            Err(e) => {
+                let response = Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(hyper::Body::from(e))
+                    .unwrap();
+                Ok(response)
+            }
+        }
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
+
+pub async fn copy_memory_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    copy_request: request::CopyMemoryRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
+
+    if let Some(pid) = *pid {
+        match util::copy_memory(pid, copy_request.src, copy_request.dst, copy_request.length) {
+            Ok(()) => {
+                let response = Response::builder()
+                    .header("Content-Type", "text/plain")
+                    .body(hyper::Body::from("Memory successfully copied"))
+                    .unwrap();
+                Ok(response)
+            }
+            Err(e) => {
+                let response = Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(hyper::Body::from(e))
+                    .unwrap();
+                Ok(response)
+            }
+        }
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
+
+pub async fn hash_region_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    hash_request: request::HashRegionRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
+
+    if let Some(pid) = *pid {
+        match util::hash_region(pid, hash_request.start, hash_request.end, hash_request.algo) {
+            Ok(hash) => {
+                let result = json!({
+                    "start": hash_request.start,
+                    "end": hash_request.end,
+                    "algo": hash_request.algo,
+                    "hash": hash
+                });
+                let response = Response::builder()
+                    .header("Content-Type", "application/json")
+                    .body(hyper::Body::from(result.to_string()))
+                    .unwrap();
+                Ok(response)
+            }
+            Err(e) => {
+                let response = Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(hyper::Body::from(e))
+                    .unwrap();
+                Ok(response)
+            }
+        }
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
+
+pub async fn write_memory_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    write_memory: request::WriteMemoryRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
+
+    if let Some(pid) = *pid {
+        let nwrite = native_bridge::write_process_memory(
+            pid,
+            write_memory.address as *mut libc::c_void,
+            write_memory.buffer.len(),
+            &write_memory.buffer,
+        );
+        match nwrite {
+            Ok(_) => {
+                let response = Response::builder()
+                    .header("Content-Type", "text/plain")
+                    .body(hyper::Body::from("Memory successfully written"))
+                    .unwrap();
+                return Ok(response);
+            }
+            Err(_) => {
+                let response = Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(hyper::Body::from("WriteProcessMemory error"))
+                    .unwrap();
+                return Ok(response);
+            }
+        };
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
+
+pub async fn memory_scan_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    scan_request: request::MemoryScanRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
+
+    let mut is_suspend_success: bool = false;
+    let do_suspend = scan_request.do_suspend;
+    if let Some(pid) = *pid {
+        if do_suspend {
+            unsafe {
+                is_suspend_success = native_bridge::suspend_process(pid);
+            }
+        }
+        // Clear global_positions for the given scan_id
+        {
+            let mut global_positions = GLOBAL_POSITIONS.write().unwrap();
+            if let Some(positions) = global_positions.get_mut(&scan_request.scan_id) {
+                positions.clear();
+            }
+            let mut global_memory = GLOBAL_MEMORY.write().unwrap();
+            if let Some(memory) = global_memory.get_mut(&scan_request.scan_id) {
+                memory.clear();
+            } else {
+            }
+            let mut global_scan_option = GLOBAL_SCAN_OPTION.write().unwrap();
+            global_scan_option.insert(scan_request.scan_id.clone(), scan_request.clone());
+        }
+        // memory-server-data-dir/Scan_xxx cleanup and create
+        let mut scan_folder_path = PathBuf::from("");
+        let mode =
+            std::env::var("MEMORY_SERVER_RUNNING_MODE").unwrap_or_else(|_| "unknown".to_string());
+        if mode == "embedded" {
+            let cache_directory = util::get_cache_directory(pid);
+            scan_folder_path = PathBuf::from(&cache_directory);
+        }
+        let sanitized_scan_id = scan_request.scan_id.trim().replace(" ", "_");
+        scan_folder_path.push("memory-server-data-dir");
+        scan_folder_path.push(&sanitized_scan_id);
+        let scan_folder = Path::new(&scan_folder_path);
+
+        if scan_folder.exists() {
+            fs::remove_dir_all(&scan_folder).expect("Failed to remove directory");
+        }
+        fs::create_dir_all(&scan_folder_path).expect("Failed to create directory");
+
+        let is_number = match scan_request.data_type.as_str() {
+            "int16" | "uint16" | "int32" | "uint32" | "float" | "int64" | "uint64" | "double" => {
+                true
+            }
+            _ => false,
+        };
+        let found_count = Arc::new(AtomicUsize::new(0));
+        let scan_align = scan_request.align;
+        let is_error_occurred = Arc::new(Mutex::new(false));
+        let error_message = Arc::new(Mutex::new(String::new()));
+
+        let thread_results: Vec<Vec<(usize, String)>> = scan::in_scan_pool(|| {
+            scan_request
+                .address_ranges
+                .par_iter()
+                .enumerate()
+                .flat_map(|(index, &(ref start_address, ref end_address))| {
+                    let found_count = Arc::clone(&found_count);
+                    let size = end_address - start_address;
+                    let chunk_size = 1024 * 1024 * 16; // 16MB
+                    let num_chunks = (size + chunk_size - 1) / chunk_size;
+
+                    (0..num_chunks)
+                        .map(|i| {
+                            let mut error_occurred = is_error_occurred.lock().unwrap();
+                            let mut error_msg = error_message.lock().unwrap();
+
+                            if *error_occurred == true {
+                                return vec![];
+                            }
+                            let chunk_start = start_address + i * chunk_size;
+                            let chunk_end = std::cmp::min(chunk_start + chunk_size, *end_address);
+                            let chunk_size_actual = chunk_end - chunk_start;
+                            let mut buffer: Vec<u8> = vec![0; chunk_size_actual];
+
+                            let mut local_positions = vec![];
+                            let mut local_values = vec![];
+
+                            let nread = match native_bridge::read_process_memory(
+                                pid,
+                                chunk_start as *mut libc::c_void,
+                                chunk_size_actual,
+                                &mut buffer,
+                            ) {
+                                Ok(nread) => nread,
+                                Err(_) => -1,
+                            };
+
+                            if nread != -1 {
+                                if scan_request.find_type == "exact" {
+                                    if scan_request.data_type == "regex" {
+                                        let regex_pattern = &scan_request.pattern;
+                                        let re = match Regex::new(regex_pattern) {
+                                            Ok(re) => re,
+                                            Err(_) => return vec![],
+                                        };
+
+                                        for cap in re.captures_iter(&buffer) {
+                                            let start = cap.get(0).unwrap().start();
+                                            if (chunk_start + start) % scan_align == 0 {
+                                                let end = cap.get(0).unwrap().end();
+                                                let value = hex::encode(&buffer[start..end]);
+                                                local_positions.push(chunk_start + start);
+                                                local_values.push(value);
+                                                found_count.fetch_add(1, Ordering::SeqCst);
+                                            }
+                                        }
+                                    } else {
+                                        let search_bytes = match hex::decode(&scan_request.pattern)
+                                        {
+                                            Ok(bytes) => bytes,
+                                            Err(_) => return vec![],
+                                        };
+
+                                        let mut buffer_offset = 0;
+                                        for pos in memmem::find_iter(&buffer, &search_bytes) {
+                                            let start = chunk_start + buffer_offset + pos;
+                                            if start % scan_align == 0 {
+                                                let value = scan_request.pattern.clone();
+                                                if is_number {
+                                                    local_positions.push(start);
+                                                    local_values.push(value);
+                                                } else {
+                                                    local_positions.push(start);
+                                                    local_values.push(value);
+                                                }
+                                                found_count.fetch_add(1, Ordering::SeqCst);
+                                            }
+                                            buffer_offset += pos + 1;
+                                        }
+                                    }
+                                } else if scan_request.find_type == "unknown" {
+                                    let alignment = match scan_request.data_type.as_str() {
+                                        "int16" | "uint16" => 2,
+                                        "int32" | "uint32" | "float" => 4,
+                                        "int64" | "uint64" | "double" => 8,
+                                        _ => 1,
+                                    };
+
+                                    let mut file_path = scan_folder_path.clone();
+                                    file_path.push(format!("{}.dump", index));
+                                    let file_exists = file_path.exists();
+
+                                    let file = match OpenOptions::new()
+                                        .create(true)
+                                        .append(true)
+                                        .open(file_path)
+                                    {
+                                        Ok(file) => file,
+                                        Err(e) => {
+                                            *error_occurred = true;
+                                            *error_msg = format!("Failed to open file: {}", e);
+                                            return vec![];
+                                        }
+                                    };
+
+                                    let mut writer = BufWriter::new(file);
+
+                                    if !file_exists {
+                                        // status flag
+                                        let zero_bytes = [0x00, 0x00, 0x00, 0x00];
+                                        if let Err(e) = writer.write_all(&zero_bytes) {
+                                            *error_occurred = true;
+                                            *error_msg =
+                                                format!("Failed to write 4 zero bytes: {}", e);
+                                            return vec![];
+                                        }
+                                    }
+
+                                    if let Err(e) = writer.write_all(&chunk_start.to_le_bytes()) {
+                                        *error_occurred = true;
+                                        *error_msg = format!("Failed to write chunk_start: {}", e);
+                                        return vec![];
+                                    }
+
+                                    let compressed_buffer = lz4_flex::block::compress(&buffer);
+
+                                    if let Err(e) = writer
+                                        .write_all(&(compressed_buffer.len() as u64).to_le_bytes())
+                                    {
+                                        *error_occurred = true;
+                                        *error_msg = format!(
+                                            "Failed to write compressed buffer length: {}",
+                                            e
+                                        );
+                                        return vec![];
+                                    }
+
+                                    if let Err(e) =
+                                        writer.write_all(&(buffer.len() as u64).to_le_bytes())
+                                    {
+                                        *error_occurred = true;
+                                        *error_msg = format!(
+                                            "Failed to write uncompressed buffer length: {}",
+                                            e
+                                        );
+                                        return vec![];
+                                    }
+
+                                    if let Err(e) = writer.write_all(&compressed_buffer) {
+                                        *error_occurred = true;
+                                        *error_msg = format!("Failed to write buffer data: {}", e);
+                                        return vec![];
+                                    }
+
+                                    if let Err(e) = writer.flush() {
+                                        *error_occurred = true;
+                                        *error_msg = format!("Failed to flush buffer: {}", e);
+                                        return vec![];
+                                    }
+                                    found_count
+                                        .fetch_add(buffer.len() / alignment, Ordering::SeqCst);
+                                }
+                                // Check if local_positions exceed MAX_RESULTS and insert into global_positions
+                                if local_positions.len() > MAX_RESULTS {
+                                    let mut global_positions = GLOBAL_POSITIONS.write().unwrap();
+                                    let combined: Vec<(usize, String)> = local_positions
+                                        .into_iter()
+                                        .zip(local_values.into_iter())
+                                        .collect();
+                                    if let Some(positions) =
+                                        global_positions.get_mut(&scan_request.scan_id)
+                                    {
+                                        positions.extend(combined);
+                                    } else {
+                                        global_positions
+                                            .insert(scan_request.scan_id.clone(), combined);
+                                    }
+                                    local_positions = vec![];
+                                    local_values = vec![];
+                                }
+                            }
+
+                            let combined: Vec<(usize, String)> = local_positions
+                                .into_iter()
+                                .zip(local_values.into_iter())
+                                .collect();
+                            combined
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect()
+        });
+        let mut do_play = GLOBAL_PROCESS_STATE.write().unwrap();
+        if do_suspend && is_suspend_success && *do_play {
+            unsafe {
+                native_bridge::resume_process(pid);
+            }
+        }
+        // println!("{}", found_count.load(Ordering::SeqCst));
+
+        let flattened_results: Vec<(usize, String)> =
+            thread_results.into_iter().flatten().collect();
+        {
+            let mut global_positions = GLOBAL_POSITIONS.write().unwrap();
+            if let Some(positions) = global_positions.get_mut(&scan_request.scan_id) {
+                positions.extend(flattened_results);
+            } else {
+                global_positions.insert(scan_request.scan_id.clone(), flattened_results);
+            }
+        }
+
+        if scan_request.return_as_json {
+            let global_positions = GLOBAL_POSITIONS.read().unwrap();
+            if let Some(positions) = global_positions.get(&scan_request.scan_id) {
+                let limited_positions = &positions[..std::cmp::min(MAX_RESULTS, positions.len())];
+                let count = found_count.load(Ordering::SeqCst);
+                let is_rounded: bool;
+                if scan_request.find_type == "unknown" {
+                    if count > 1_000_000 {
+                        is_rounded = true;
+                    } else {
+                        is_rounded = limited_positions.len() != positions.len();
+                    }
+                } else {
+                    is_rounded = limited_positions.len() != positions.len();
+                }
+                let matched_addresses: Vec<serde_json::Value> = limited_positions
+                    .into_iter()
+                    .map(|(address, value)| {
+                        json!({
+                            "address": address,
+                            "value": value
+                        })
+                    })
+                    .collect();
+                let result = json!({
+                    "matched_addresses": matched_addresses,
+                    "found":count,
+                    "is_rounded":is_rounded
+                });
+                let result_string = result.to_string();
+                let response = Response::builder()
+                    .header("Content-Type", "application/json")
+                    .body(hyper::Body::from(result_string))
+                    .unwrap();
+                Ok(response)
+            } else {
+                let response = Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(hyper::Body::from("Unknown error"))
+                    .unwrap();
+                Ok(response)
+            }
+        } else {
+            let global_positions = GLOBAL_POSITIONS.read().unwrap();
+            if let Some(_positions) = global_positions.get(&scan_request.scan_id) {
+                let count = found_count.load(Ordering::SeqCst);
+                let result_string = json!({ "found": count }).to_string();
+                let response = Response::builder()
+                    .header("Content-Type", "application/json")
+                    .body(hyper::Body::from(result_string))
+                    .unwrap();
+                Ok(response)
+            } else {
+                let response = Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(hyper::Body::from("Unknown error"))
+                    .unwrap();
+                Ok(response)
+            }
+        }
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
+
+// Explicit ranges are scanned as given unless a protection filter is also supplied
+fn select_scan_ranges(
+    pid: i32,
+    address_ranges: &Option<Vec<(u64, u64)>>,
+    protection: &Option<scan::MemoryProtectionFilter>,
+) -> Result<Vec<(u64, u64)>, String> {
+    match (address_ranges, protection) {
+        (Some(ranges), None) => Ok(ranges.clone()),
+        (ranges, filter) => {
+            let regions = scan::protected_regions(pid, &filter.unwrap_or_default())?;
+            Ok(match ranges {
+                Some(ranges) => scan::clip_ranges(ranges, &regions),
+                None => regions,
+            })
+        }
+    }
+}
+
+struct ScanOutcome {
+    matches: Vec<Value>,
+    cancelled: bool,
+    truncated: bool,
+    total_count: usize,
+    // The alignment actually used, reported for scans where Alignment::Auto can apply
+    alignment: Option<usize>,
+}
+
+impl ScanOutcome {
+    fn new<T>(
+        result: scan::ScanResult<T>,
+        alignment: Option<usize>,
+        to_json: impl FnMut(T) -> Value,
+    ) -> ScanOutcome {
+        ScanOutcome {
+            matches: result.matches.into_iter().map(to_json).collect(),
+            cancelled: result.cancelled,
+            truncated: result.truncated,
+            total_count: result.total_count,
+            alignment,
+        }
+    }
+
+    fn addresses(result: scan::ScanResult<u64>, alignment: Option<usize>) -> ScanOutcome {
+        ScanOutcome::new(result, alignment, |address| json!({ "address": address }))
+    }
+
+    fn to_json(&self, region_count: usize) -> Value {
+        json!({
+            "found": self.matches.len(),
+            "matched_addresses": self.matches,
+            "region_count": region_count,
+            "cancelled": self.cancelled,
+            "truncated": self.truncated,
+            "total_count": self.total_count,
+            "alignment": self.alignment
+        })
+    }
+}
+
+fn run_scan(
+    pid: i32,
+    scan_request: &request::ScanRequest,
+    ranges: &[(u64, u64)],
+    control: &scan::ScanControl,
+) -> Result<ScanOutcome, String> {
+    match scan_request.find_type.as_str() {
+        "exact" => {
+            let needle = hex::decode(&scan_request.pattern)
+                .map_err(|_| "Invalid hex pattern".to_string())?;
+            // data_type is only a hint here; byte and string scans may name types ValueType
+            // does not know
+            let value_type = scan_request
+                .data_type
+                .as_deref()
+                .and_then(|type_name| util::ValueType::parse(type_name).ok());
+            let alignment = scan_request.align.resolve(needle.len(), value_type);
+            Ok(ScanOutcome::addresses(
+                scan::scan_exact(pid, ranges, &needle, alignment, control),
+                Some(alignment),
+            ))
+        }
+        "float" => {
+            let target: f64 = scan_request
+                .pattern
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid float value '{}'", scan_request.pattern))?;
+            let value_type =
+                scan::FloatType::parse(scan_request.data_type.as_deref().unwrap_or("float"))?;
+            Ok(ScanOutcome::addresses(
+                scan::scan_float(
+                    pid,
+                    ranges,
+                    target,
+                    value_type,
+                    scan_request.tolerance.unwrap_or(0.0),
+                    control,
+                ),
+                None,
+            ))
+        }
+        "aob" => {
+            let (bytes, mask) = scan::parse_aob_pattern(&scan_request.pattern)?;
+            let result = scan::scan_aob(pid, ranges, &bytes, &mask, control);
+            if scan_request.module_relative != Some(true) {
+                return Ok(ScanOutcome::addresses(result, None));
+            }
+            // Matches outside every module keep only their absolute address
+            let modules = native_bridge::enum_modules(pid)
+                .map_err(|e| format!("Failed to enumerate modules: {}", e))?;
+            let offsets = symbols::module_offsets(pid, &result.matches, &modules);
+            let mut offsets = offsets.into_iter();
+            Ok(ScanOutcome::new(result, None, |address| {
+                match offsets.next().flatten() {
+                    Some(offset) => json!({
+                        "address": address,
+                        "module": offset.module,
+                        "offset": offset.offset,
+                        "expression": offset.expression()
+                    }),
+                    None => json!({ "address": address }),
+                }
+            }))
+        }
+        "range" => {
+            let value_type = util::ValueType::parse(
+                scan_request
+                    .data_type
+                    .as_deref()
+                    .ok_or("Range scans need a data_type")?,
+            )?;
+            let (low, high) = match (scan_request.low, scan_request.high) {
+                (Some(low), Some(high)) => (low, high),
+                _ => return Err("Range scans need both low and high bounds".to_string()),
+            };
+            let alignment = scan_request
+                .align
+                .resolve(value_type.size(), Some(value_type));
+            Ok(ScanOutcome::addresses(
+                scan::scan_range(pid, ranges, value_type, low, high, alignment, control)?,
+                Some(alignment),
+            ))
+        }
+        "bits" => {
+            let value_type = util::ValueType::parse(
+                scan_request
+                    .data_type
+                    .as_deref()
+                    .ok_or("Bit scans need a data_type")?,
+            )?;
+            let mask = scan_request.mask.ok_or("Bit scans need a mask")?;
+            let alignment = scan_request
+                .align
+                .resolve(value_type.size(), Some(value_type));
+            Ok(ScanOutcome::addresses(
+                scan::scan_bits(
+                    pid,
+                    ranges,
+                    value_type,
+                    mask,
+                    scan_request.expected.unwrap_or(mask),
+                    alignment,
+                    control,
+                )?,
+                Some(alignment),
+            ))
+        }
+        "regex" => {
+            let result = scan::scan_regex(pid, ranges, &scan_request.pattern, control)?;
+            Ok(ScanOutcome::new(
+                result,
+                None,
+                |(address, length)| json!({ "address": address, "length": length }),
+            ))
+        }
+        _ => Err(format!("Unknown find type '{}'", scan_request.find_type)),
+    }
+}
+
+pub async fn scan_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    scan_request: request::ScanRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
+
+    if let Some(pid) = *pid {
+        let progress = Arc::new(AtomicUsize::new(0));
+        let scanned =
+            select_scan_ranges(pid, &scan_request.address_ranges, &scan_request.protection)
+                .and_then(|ranges| {
+                    let scanned = Arc::clone(&progress);
+                    let cancel = Arc::new(AtomicBool::new(false));
+                    if let Some(scan_id) = &scan_request.scan_id {
+                        let mut scan_trackers = SCAN_TRACKERS.write().unwrap();
+                        expire_scan_trackers(&mut scan_trackers, Instant::now());
+                        scan_trackers.insert(
+                            scan_id.clone(),
+                            ScanTracker {
+                                scanned: Arc::clone(&scanned),
+                                total: scan::total_bytes(&ranges),
+                                cancel: Arc::clone(&cancel),
+                                finished: None,
+                            },
+                        );
+                    }
+                    let control = scan::ScanControl {
+                        progress: Some(&scanned),
+                        cancel: Some(&cancel),
+                        max_results: scan_request.max_results,
+                    };
+                    let _suspended = match scan_request.do_suspend {
+                        Some(true) => Some(util::SuspendGuard::new(pid)?),
+                        _ => None,
+                    };
+                    run_scan(pid, &scan_request, &ranges, &control)
+                        .map(|outcome| (outcome, ranges.len()))
+                });
+        if let Some(scan_id) = &scan_request.scan_id {
+            // A later scan may have reused the id, and its tracker is left alone
+            if let Some(tracker) = SCAN_TRACKERS.write().unwrap().get_mut(scan_id) {
+                if Arc::ptr_eq(&tracker.scanned, &progress) {
+                    tracker.finished = Some(Instant::now());
+                }
+            }
+        }
+        let (outcome, region_count) = match scanned {
+            Ok(scanned) => scanned,
+            Err(e) => {
+                let response = Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(hyper::Body::from(e))
+                    .unwrap();
+                return Ok(response);
+            }
+        };
+        let result = outcome.to_json(region_count);
+        let response = Response::builder()
+            .header("Content-Type", "application/json")
+            .body(hyper::Body::from(result.to_string()))
+            .unwrap();
+        Ok(response)
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
+
+// Events the server sends over the scan stream WebSocket. Every scan ends with exactly one
+// done or error event; result batches only carry the matches found since the previous one.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ScanStreamEvent {
+    Progress {
+        scanned: usize,
+        total: usize,
+    },
+    ResultBatch {
+        matches: Vec<Value>,
+    },
+    Done {
+        found: usize,
+        total_count: usize,
+        cancelled: bool,
+        truncated: bool,
+        region_count: usize,
+        alignment: Option<usize>,
+    },
+    Error {
+        message: String,
+    },
+}
+
+// Regions are scanned in batches of roughly this many bytes, each one sent as it finishes
+const STREAM_BATCH_BYTES: usize = 1024 * 1024 * 64; // 64MB batches
+const STREAM_PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+fn stream_batches(ranges: &[(u64, u64)]) -> Vec<&[(u64, u64)]> {
+    let mut batches = Vec::new();
+    let mut batch_start = 0;
+    let mut batch_bytes = 0;
+    for (index, &(start, end)) in ranges.iter().enumerate() {
+        batch_bytes += end.saturating_sub(start) as usize;
+        if batch_bytes >= STREAM_BATCH_BYTES {
+            batches.push(&ranges[batch_start..=index]);
+            batch_start = index + 1;
+            batch_bytes = 0;
+        }
+    }
+    if batch_start < ranges.len() {
+        batches.push(&ranges[batch_start..]);
+    }
+    batches
+}
+
+// Runs on a blocking thread; max_results applies to the whole scan, not to each batch
+fn run_stream_scan(
+    pid: i32,
+    scan_request: &request::ScanRequest,
+    ranges: &[(u64, u64)],
+    scanned: &AtomicUsize,
+    cancel: &AtomicBool,
+    events: &tokio::sync::mpsc::UnboundedSender<ScanStreamEvent>,
+) -> Result<ScanStreamEvent, String> {
+    let _suspended = match scan_request.do_suspend {
+        Some(true) => Some(util::SuspendGuard::new(pid)?),
+        _ => None,
+    };
+    let (mut found, mut total_count) = (0, 0);
+    let (mut cancelled, mut truncated, mut alignment) = (false, false, None);
+    for batch in stream_batches(ranges) {
+        if cancel.load(Ordering::Relaxed) {
+            cancelled = true;
+            break;
+        }
+        let control = scan::ScanControl {
+            progress: Some(scanned),
+            cancel: Some(cancel),
+            max_results: scan_request
+                .max_results
+                .map(|max_results| max_results.saturating_sub(found)),
+        };
+        let outcome = run_scan(pid, scan_request, batch, &control)?;
+        found += outcome.matches.len();
+        total_count += outcome.total_count;
+        cancelled |= outcome.cancelled;
+        truncated |= outcome.truncated;
+        alignment = outcome.alignment;
+        if !outcome.matches.is_empty() {
+            let _ = events.send(ScanStreamEvent::ResultBatch {
+                matches: outcome.matches,
+            });
+        }
+        if cancelled {
+            break;
+        }
+    }
+    Ok(ScanStreamEvent::Done {
+        found,
+        total_count,
+        cancelled,
+        truncated,
+        region_count: ranges.len(),
+        alignment,
+    })
+}
+
+async fn send_event(
+    socket_tx: &mut SplitSink<WebSocket, Message>,
+    event: &ScanStreamEvent,
+) -> Result<(), warp::Error> {
+    socket_tx
+        .send(Message::text(serde_json::to_string(event).unwrap()))
+        .await
+}
+
+// One connection runs one scan at a time and can start another once the previous has sent
+// done or error. Progress is pushed every STREAM_PROGRESS_INTERVAL while a scan runs.
+pub async fn scan_stream_handler(pid_state: Arc<Mutex<Option<i32>>>, socket: WebSocket) {
+    let (mut socket_tx, mut socket_rx) = socket.split();
+
+    while let Some(Ok(message)) = socket_rx.next().await {
+        if message.is_close() {
+            break;
+        }
+        let Ok(text) = message.to_str() else {
+            continue;
+        };
+        let scan_request = match serde_json::from_str::<request::ScanStreamMessage>(text) {
+            Ok(request::ScanStreamMessage::Start(scan_request)) => scan_request,
+            Ok(request::ScanStreamMessage::Cancel) => continue,
+            Err(e) => {
+                let event = ScanStreamEvent::Error {
+                    message: format!("Invalid message: {}", e),
+                };
+                if send_event(&mut socket_tx, &event).await.is_err() {
+                    break;
+                }
+                continue;
+            }
+        };
+
+        let pid = *pid_state.lock().unwrap();
+        let ranges = match pid
+            .ok_or_else(|| "Pid not set".to_string())
+            .and_then(|pid| {
+                select_scan_ranges(pid, &scan_request.address_ranges, &scan_request.protection)
+                    .map(|ranges| (pid, ranges))
+            }) {
+            Ok(ranges) => ranges,
+            Err(message) => {
+                if send_event(&mut socket_tx, &ScanStreamEvent::Error { message })
+                    .await
+                    .is_err()
+                {
+                    break;
+                }
+                continue;
+            }
+        };
+        let (pid, ranges) = ranges;
+
+        let total = scan::total_bytes(&ranges);
+        let scanned = Arc::new(AtomicUsize::new(0));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (events_tx, mut events_rx) = tokio::sync::mpsc::unbounded_channel();
+        let scan_task = {
+            let (scanned, cancel) = (Arc::clone(&scanned), Arc::clone(&cancel));
+            tokio::task::spawn_blocking(move || {
+                let last =
+                    run_stream_scan(pid, &scan_request, &ranges, &scanned, &cancel, &events_tx)
+                        .unwrap_or_else(|message| ScanStreamEvent::Error { message });
+                let _ = events_tx.send(last);
+            })
+        };
+
+        let mut progress = tokio::time::interval(STREAM_PROGRESS_INTERVAL);
+        let mut connected = true;
+        loop {
+            tokio::select! {
+                event = events_rx.recv() => {
+                    let Some(event) = event else {
+                        break;
+                    };
+                    if send_event(&mut socket_tx, &event).await.is_err() {
+                        connected = false;
+                        break;
+                    }
+                }
+                message = socket_rx.next() => match message {
+                    Some(Ok(message)) if message.is_close() => {
+                        connected = false;
+                        break;
+                    }
+                    Some(Ok(message)) => {
+                        let parsed = message
+                            .to_str()
+                            .ok()
+                            .and_then(|text| serde_json::from_str(text).ok());
+                        let event = match parsed {
+                            Some(request::ScanStreamMessage::Cancel) => {
+                                cancel.store(true, Ordering::Relaxed);
+                                continue;
+                            }
+                            Some(request::ScanStreamMessage::Start(_)) => ScanStreamEvent::Error {
+                                message: "A scan is already running on this connection".to_string(),
+                            },
+                            None => ScanStreamEvent::Error {
+                                message: "Invalid message".to_string(),
+                            },
+                        };
+                        if send_event(&mut socket_tx, &event).await.is_err() {
+                            connected = false;
+                            break;
+                        }
+                    }
+                    _ => {
+                        connected = false;
+                        break;
+                    }
+                },
+                _ = progress.tick() => {
+                    let event = ScanStreamEvent::Progress {
+                        scanned: scanned.load(Ordering::Relaxed),
+                        total,
+                    };
+                    if send_event(&mut socket_tx, &event).await.is_err() {
+                        connected = false;
+                        break;
+                    }
+                }
+            }
+        }
+
+        if !connected {
+            // Nobody is left to receive the results, so stop the scan early
+            cancel.store(true, Ordering::Relaxed);
+            let _ = scan_task.await;
+            break;
+        }
+        let _ = scan_task.await;
+    }
+}
+
+pub async fn first_scan_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    first_scan_request: request::FirstScanRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
+
+    if let Some(pid) = *pid {
+        let scanned = hex::decode(&first_scan_request.pattern)
+            .map_err(|_| "Invalid hex pattern".to_string())
+            .and_then(|needle| {
+                let value_type = first_scan_request
+                    .data_type
+                    .as_deref()
+                    .map(util::ValueType::parse)
+                    .transpose()?;
+                let filter = first_scan_request
+                    .protection
+                    .unwrap_or_else(scan::MemoryProtectionFilter::read_write);
+                scan::scan_first(
+                    pid,
+                    &needle,
+                    value_type,
+                    &filter,
+                    &scan::ScanControl {
+                        max_results: first_scan_request.max_results,
+                        ..Default::default()
+                    },
+                )
+            });
+        match scanned {
+            Ok(first_scan) => {
+                let result = ScanOutcome::addresses(first_scan.result, Some(first_scan.alignment))
+                    .to_json(first_scan.region_count);
+                let response = Response::builder()
+                    .header("Content-Type", "application/json")
+                    .body(hyper::Body::from(result.to_string()))
+                    .unwrap();
+                Ok(response)
+            }
+            Err(e) => {
+                let response = Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(hyper::Body::from(e))
+                    .unwrap();
+                Ok(response)
+            }
+        }
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
+
+pub async fn group_scan_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    group_scan_request: request::GroupScanRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
+
+    if let Some(pid) = *pid {
+        let scanned = group_scan_request
+            .elements
+            .iter()
+            .map(|element| {
+                let value_type = util::ValueType::parse(&element.data_type)?;
+                Ok(scan::GroupElement {
+                    offset: element.offset,
+                    value_type,
+                    value: value_type.encode(&element.value)?,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()
+            .and_then(|elements| {
+                let ranges = select_scan_ranges(
+                    pid,
+                    &group_scan_request.address_ranges,
+                    &group_scan_request.protection,
+                )?;
+                let result = scan::scan_group(
+                    pid,
+                    &ranges,
+                    &elements,
+                    &scan::ScanControl {
+                        max_results: group_scan_request.max_results,
+                        ..Default::default()
+                    },
+                )?;
+                Ok((result, ranges.len()))
+            });
+        match scanned {
+            Ok((result, region_count)) => {
+                let result = ScanOutcome::addresses(result, None).to_json(region_count);
+                let response = Response::builder()
+                    .header("Content-Type", "application/json")
+                    .body(hyper::Body::from(result.to_string()))
+                    .unwrap();
+                Ok(response)
+            }
+            Err(e) => {
+                let response = Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(hyper::Body::from(e))
+                    .unwrap();
+                Ok(response)
+            }
+        }
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
+
+pub async fn scan_progress_handler(
+    progress_request: request::ScanProgressRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut scan_trackers = SCAN_TRACKERS.write().unwrap();
+    expire_scan_trackers(&mut scan_trackers, Instant::now());
+    match scan_trackers.get(&progress_request.scan_id) {
+        Some(tracker) => {
+            let finished = tracker.finished.is_some();
+            let result = json!({
+                "scan_id": progress_request.scan_id,
+                "scanned_bytes": tracker.scanned.load(Ordering::Relaxed),
+                "total_bytes": tracker.total,
+                "cancelled": tracker.cancel.load(Ordering::Relaxed),
+                "finished": finished
+            });
+            // The client has now seen the final count
+            if finished {
+                scan_trackers.remove(&progress_request.scan_id);
+            }
+            let response = Response::builder()
+                .header("Content-Type", "application/json")
+                .body(hyper::Body::from(result.to_string()))
+                .unwrap();
+            Ok(response)
+        }
+        None => {
+            let response = Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(hyper::Body::from("Scanid not found"))
+                .unwrap();
+            Ok(response)
+        }
+    }
+}
+
+pub async fn scan_cancel_handler(
+    cancel_request: request::ScanCancelRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let scan_trackers = SCAN_TRACKERS.read().unwrap();
+    match scan_trackers.get(&cancel_request.scan_id) {
+        Some(tracker) => {
+            tracker.cancel.store(true, Ordering::Relaxed);
+            let response = Response::builder()
+                .header("Content-Type", "text/plain")
+                .body(hyper::Body::from("Scan cancelled"))
+                .unwrap();
+            Ok(response)
+        }
+        None => {
+            let response = Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(hyper::Body::from("Scanid not found"))
+                .unwrap();
+            Ok(response)
+        }
+    }
+}
+
+// A saved map is reused while it was built from this same process, otherwise it is rebuilt
+// from process memory and saved over the old file
+fn load_or_build_pointer_map(
+    pid: i32,
+    modules: &[Value],
+    map_path: &str,
+) -> Result<pointer::PointerMap, String> {
+    let map_path = util::check_file_path(map_path)?;
+    let map_path = Path::new(&map_path);
+    if map_path.exists() {
+        let map = pointer::pointer_map_load(map_path)?;
+        if *map.origin() == pointer::MapOrigin::of(pid, modules) {
+            return Ok(map);
+        }
+        log::info!(
+            "Pointer map {} was built from another process; rebuilding it",
+            map_path.display()
+        );
+    }
+    let map = pointer::PointerMap::build(pid, modules)?;
+    pointer::pointer_map_save(&map, map_path)?;
+    Ok(map)
+}
+
+pub async fn pointer_scan_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    pointer_scan_request: request::PointerScanRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
+
+    if let Some(pid) = *pid {
+        let scanned = native_bridge::enum_modules(pid).and_then(|modules| {
+            let chains = match &pointer_scan_request.pointer_map {
+                Some(map_path) => {
+                    let map = load_or_build_pointer_map(pid, &modules, map_path)?;
+                    pointer::pointer_scan_with_map(
+                        &map,
+                        pointer_scan_request.target,
+                        &modules,
+                        pointer_scan_request.max_depth,
+                        pointer_scan_request.max_offset,
+                    )
+                }
+                None => pointer::pointer_scan(
+                    pid,
+                    pointer_scan_request.target,
+                    &modules,
+                    pointer_scan_request.max_depth,
+                    pointer_scan_request.max_offset,
+                )?,
+            };
+            Ok((chains, modules))
+        });
+        match scanned {
+            Ok((chains, modules)) => {
+                let chains: Vec<Value> = chains
+                    .iter()
+                    .map(|chain| {
+                        json!({
+                            "offsets": chain,
+                            "expression": pointer::format_chain(chain, &modules)
+                        })
+                    })
+                    .collect();
+                let result = json!({ "found": chains.len(), "chains": chains });
+                let response = Response::builder()
+                    .header("Content-Type", "application/json")
+                    .body(hyper::Body::from(result.to_string()))
+                    .unwrap();
+                Ok(response)
+            }
+            Err(e) => {
+                let response = Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(hyper::Body::from(e))
+                    .unwrap();
+                Ok(response)
+            }
+        }
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
+
+pub async fn find_references_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    find_request: request::FindReferencesRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
+
+    if let Some(pid) = *pid {
+        let result = native_bridge::enum_modules(pid)
+            .map_err(|e| format!("Failed to enumerate modules: {}", e))
+            .and_then(|modules| util::resolve_symbolic_address(pid, &find_request.target, &modules))
+            .and_then(|target| {
+                let ranges = select_scan_ranges(
+                    pid,
+                    &find_request.address_ranges,
+                    &find_request.protection,
+                )?;
+                let matches = scan::find_pointers_to(
+                    pid,
+                    target as u64,
+                    &ranges,
+                    find_request.align.unwrap_or(scan::Alignment::Auto),
+                );
+                let matched_addresses: Vec<Value> = matches
+                    .iter()
+                    .map(|address| json!({ "address": address }))
+                    .collect();
+                Ok(json!({
+                    "target": target,
+                    "pointer_size": util::pointer_size(pid),
+                    "found": matched_addresses.len(),
+                    "matched_addresses": matched_addresses,
+                }))
+            });
+        match result {
+            Ok(value) => {
+                let response = Response::builder()
+                    .header("Content-Type", "application/json")
+                    .body(hyper::Body::from(value.to_string()))
+                    .unwrap();
+                Ok(response)
+            }
+            Err(e) => {
+                let response = Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(hyper::Body::from(e))
+                    .unwrap();
+                Ok(response)
+            }
+        }
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
+
+pub async fn scan_refine_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    refine_request: request::ScanRefineRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
+
+    if let Some(pid) = *pid {
+        let needle = match hex::decode(&refine_request.pattern) {
+            Ok(bytes) => bytes,
+            Err(_) => {
+                let response = Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(hyper::Body::from("Invalid hex pattern"))
+                    .unwrap();
+                return Ok(response);
+            }
+        };
+        let addresses = scan::scan_refine(pid, &refine_request.addresses, &needle);
+        let result = json!({
+            "matched_addresses": addresses,
+            "found": addresses.len()
+        });
+        let response = Response::builder()
+            .header("Content-Type", "application/json")
+            .body(hyper::Body::from(result.to_string()))
+            .unwrap();
+        Ok(response)
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
+
+const SESSION_PAGE_SIZE: usize = 1000;
+
+pub async fn session_new_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    session_request: request::SessionNewRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
+
+    if let Some(pid) = *pid {
+        let filter = session_request
+            .protection
+            .unwrap_or_else(scan::MemoryProtectionFilter::read_write);
+        let result = util::ValueType::parse(&session_request.data_type).and_then(|value_type| {
+            match &session_request.pattern {
+                Some(pattern) => hex::decode(pattern)
+                    .map_err(|_| "Invalid hex pattern".to_string())
+                    .and_then(|needle| session::session_new(pid, value_type, &needle, &filter)),
+                None => session::session_new_unknown(pid, value_type, &filter),
+            }
+        });
+        match result {
+            Ok((session_id, count)) => {
+                let result = json!({ "session_id": session_id, "found": count });
+                let response = Response::builder()
+                    .header("Content-Type", "application/json")
+                    .body(hyper::Body::from(result.to_string()))
+                    .unwrap();
+                Ok(response)
+            }
+            Err(e) => {
+                let response = Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(hyper::Body::from(e))
+                    .unwrap();
+                Ok(response)
+            }
+        }
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
+
+pub async fn session_refine_handler(
+    refine_request: request::SessionRefineRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let refine = match (&refine_request.pattern, &refine_request.filter_method) {
+        (Some(pattern), None) => hex::decode(pattern)
+            .map(session::SessionRefine::Exact)
+            .map_err(|_| "Invalid hex pattern".to_string()),
+        (None, Some(method)) => {
+            scan::ScanCompare::parse(method, refine_request.amount, refine_request.tolerance)
+                .map(session::SessionRefine::Compare)
+        }
+        _ => Err("Give either a pattern or a filter_method".to_string()),
+    };
+    match refine.and_then(|refine| session::session_refine(refine_request.session_id, refine)) {
+        Ok(count) => {
+            let result = json!({ "session_id": refine_request.session_id, "found": count });
+            let response = Response::builder()
+                .header("Content-Type", "application/json")
+                .body(hyper::Body::from(result.to_string()))
+                .unwrap();
+            Ok(response)
+        }
+        Err(e) => {
+            let response = Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(hyper::Body::from(e))
+                .unwrap();
+            Ok(response)
+        }
+    }
+}
+
+pub async fn session_get_handler(
+    get_request: request::SessionGetRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match session::session_get_page(
+        get_request.session_id,
+        get_request.offset.unwrap_or(0),
+        get_request.limit.unwrap_or(SESSION_PAGE_SIZE),
+    ) {
+        Ok((page, total)) => {
+            let matched_addresses: Vec<Value> = page
+                .into_iter()
+                .map(|(address, value)| json!({ "address": address, "value": value }))
+                .collect();
+            let result = json!({
+                "session_id": get_request.session_id,
+                "total": total,
+                "matched_addresses": matched_addresses
+            });
+            let response = Response::builder()
+                .header("Content-Type", "application/json")
+                .body(hyper::Body::from(result.to_string()))
+                .unwrap();
+            Ok(response)
+        }
+        Err(e) => {
+            let response = Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(hyper::Body::from(e))
+                .unwrap();
+            Ok(response)
+        }
+    }
+}
+
+pub async fn session_close_handler(
+    close_request: request::SessionCloseRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if session::session_close(close_request.session_id) {
+        let response = Response::builder()
+            .header("Content-Type", "text/plain")
+            .body(hyper::Body::from("Session closed"))
+            .unwrap();
+        Ok(response)
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(hyper::Body::from("Session not found"))
+            .unwrap();
+        Ok(response)
+    }
+}
+
+pub async fn diff_region_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    diff_request: request::DiffRegionRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
+
+    if let Some(pid) = *pid {
+        let result = hex::decode(&diff_request.baseline)
+            .map_err(|_| "Invalid hex baseline".to_string())
+            .and_then(|baseline| {
+                scan::diff_region(pid, diff_request.start, diff_request.end, &baseline)
+            });
+        match result {
+            Ok(diffs) => {
+                let diffs: Vec<serde_json::Value> = diffs
+                    .into_iter()
+                    .map(|(address, old, new)| {
+                        json!({
+                            "address": address,
+                            "old": old,
+                            "new": new
+                        })
+                    })
+                    .collect();
+                let result = json!({
+                    "found": diffs.len(),
+                    "diffs": diffs
+                });
+                let response = Response::builder()
+                    .header("Content-Type", "application/json")
+                    .body(hyper::Body::from(result.to_string()))
+                    .unwrap();
+                Ok(response)
+            }
+            Err(e) => {
+                let response = Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(hyper::Body::from(e))
+                    .unwrap();
+                Ok(response)
+            }
+        }
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
+
+pub async fn scan_compare_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    compare_request: request::ScanCompareRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
+
+    if let Some(pid) = *pid {
+        let parsed = util::ValueType::parse(&compare_request.value_type).and_then(|value_type| {
+            let cmp = scan::ScanCompare::parse(
+                &compare_request.filter_method,
+                compare_request.amount,
+                compare_request.tolerance,
+            )?;
+            let previous = compare_request
+                .previous
+                .iter()
+                .map(|entry| {
+                    hex::decode(&entry.value)
+                        .map(|bytes| (entry.address, bytes))
+                        .map_err(|_| format!("Invalid hex value at address {:#x}", entry.address))
+                })
+                .collect::<Result<scan::ScanSnapshot, String>>()?;
+            Ok((value_type, cmp, previous))
+        });
+        let (value_type, cmp, previous) = match parsed {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                let response = Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(hyper::Body::from(e))
+                    .unwrap();
+                return Ok(response);
+            }
+        };
+        let matched_addresses: Vec<serde_json::Value> =
+            scan::scan_compare(pid, &previous, value_type, cmp)
+                .into_iter()
+                .map(|(address, bytes)| {
+                    // value stays hex so the result can be passed back as previous
+                    let typed_value =
+                        util::interpret_bytes(&bytes, value_type, util::Endianness::Little)
+                            .unwrap_or(serde_json::Value::Null);
+                    json!({
+                        "address": address,
+                        "value": hex::encode(bytes),
+                        "typed_value": typed_value
+                    })
+                })
+                .collect();
+        let result = json!({
+            "found": matched_addresses.len(),
+            "matched_addresses": matched_addresses
+        });
+        let response = Response::builder()
+            .header("Content-Type", "application/json")
+            .body(hyper::Body::from(result.to_string()))
+            .unwrap();
+        Ok(response)
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
+
+macro_rules! compare_values {
+    ($val:expr, $old_val:expr, $filter_method:expr) => {
+        match $filter_method {
+            "changed" => $val != $old_val,
+            "unchanged" => $val == $old_val,
+            "increased" => $val > $old_val,
+            "decreased" => $val < $old_val,
+            _ => false,
+        }
+    };
+}
+
+pub async fn memory_filter_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    filter_request: request::MemoryFilterRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
+
+    let mut is_suspend_success: bool = false;
+    let do_suspend = filter_request.do_suspend;
+    if let Some(pid) = *pid {
+        let mut new_positions = Vec::new();
+        let mut global_positions = GLOBAL_POSITIONS.write().unwrap();
+        let mut global_memory = GLOBAL_MEMORY.write().unwrap();
+        let global_scan_option = GLOBAL_SCAN_OPTION.write().unwrap();
+        let scan_option: request::MemoryScanRequest = global_scan_option
+            .get(&filter_request.scan_id)
+            .unwrap()
+            .clone();
         let found_count = Arc::new(AtomicUsize::new(0));
         let size = match filter_request.data_type.as_str() {
             "int16" | "uint16" => 2,
@@ -730,622 +2759,1066 @@ pub async fn memory_filter_handler(
                 }
             };
 
-            let scan_align = scan_option.align;
+            let scan_align = scan_option.align;
+
+            let mut exact_bytes: Vec<u8> = vec![];
+            if filter_request.filter_method.as_str() == "exact" {
+                exact_bytes = match hex::decode(&filter_request.pattern) {
+                    Ok(bytes) => bytes,
+                    Err(_) => vec![],
+                };
+            }
+
+            if !*is_error_occurred.lock().unwrap() {
+                scan::in_scan_pool(|| {
+                    paths.par_iter().for_each(|file_path| {
+                        let mut error_occurred = is_error_occurred.lock().unwrap();
+                        let mut error_msg = error_message.lock().unwrap();
+                        if *error_occurred {
+                            return;
+                        }
+                        let mut serialized_data: Vec<u8> = Vec::new();
+                        if let Ok(file) = File::open(file_path) {
+                            let mut reader = BufReader::new(file);
+                            let mut data_buffer: Vec<u8> = Vec::new();
+                            if let Err(e) = reader.read_to_end(&mut data_buffer) {
+                                *error_occurred = true;
+                                *error_msg = format!("Failed to read file: {}", e);
+                                return;
+                            }
+                            let status_flag: [u8; 4] = match data_buffer[0..4].try_into() {
+                                Ok(flag) => flag,
+                                Err(e) => {
+                                    *error_occurred = true;
+                                    *error_msg = format!("Invalid address format: {}", e);
+                                    return;
+                                }
+                            };
+                            let mut offset = 4;
+                            let usize_size = size_of::<usize>();
+                            if status_flag == [0x00, 0x00, 0x00, 0x00] {
+                                while offset + 3 * usize_size <= data_buffer.len() {
+                                    let address = usize::from_le_bytes(
+                                        data_buffer[offset..offset + usize_size]
+                                            .try_into()
+                                            .expect("Invalid address format"),
+                                    );
+
+                                    offset += usize_size;
+
+                                    let compressed_data_size = usize::from_le_bytes(
+                                        data_buffer[offset..offset + usize_size]
+                                            .try_into()
+                                            .expect("Invalid length format"),
+                                    );
+                                    offset += usize_size;
+
+                                    let uncompressed_data_size = usize::from_le_bytes(
+                                        data_buffer[offset..offset + usize_size]
+                                            .try_into()
+                                            .expect("Invalid length format"),
+                                    );
+                                    offset += usize_size;
+
+                                    if offset + compressed_data_size <= data_buffer.len() {
+                                        let compressed_data =
+                                            &data_buffer[offset..offset + compressed_data_size];
+                                        offset += compressed_data_size;
+                                        let decompressed_data = match lz4_flex::block::decompress(
+                                            &compressed_data,
+                                            uncompressed_data_size,
+                                        ) {
+                                            Ok(data) => data,
+                                            Err(e) => {
+                                                *error_occurred = true;
+                                                *error_msg =
+                                                    format!("Failed to decompress data: {}", e);
+                                                return;
+                                            }
+                                        };
+
+                                        let mut buffer: Vec<u8> =
+                                            vec![0; (decompressed_data.len()) as usize];
+                                        let _nread = match native_bridge::read_process_memory(
+                                            pid,
+                                            address as *mut libc::c_void,
+                                            decompressed_data.len(),
+                                            &mut buffer,
+                                        ) {
+                                            Ok(nread) => nread,
+                                            Err(_err) => -1,
+                                        };
+
+                                        if _nread == -1 {
+                                            return;
+                                        }
+                                        for offset in (0..decompressed_data.len()).step_by(1) {
+                                            if (address + offset) % scan_align != 0 {
+                                                continue;
+                                            }
+                                            if offset + size > decompressed_data.len() {
+                                                break;
+                                            }
+                                            let old_val = &decompressed_data[offset..offset + size];
+                                            let new_val = &buffer[offset..offset + size];
+
+                                            let mut pass_filter: bool = false;
+                                            if filter_request.filter_method.as_str() == "exact" {
+                                                if exact_bytes == new_val {
+                                                    pass_filter = true;
+                                                }
+                                            } else {
+                                                pass_filter =
+                                                    match filter_request.data_type.as_str() {
+                                                        _ => compare_values!(
+                                                            new_val,
+                                                            old_val,
+                                                            filter_request.filter_method.as_str()
+                                                        ),
+                                                    };
+                                            }
+                                            if pass_filter {
+                                                serialized_data.extend_from_slice(
+                                                    &(address + offset).to_le_bytes(),
+                                                );
+                                                serialized_data.extend_from_slice(new_val);
+                                                found_count.fetch_add(1, Ordering::SeqCst);
+                                            }
+                                        }
+                                    } else {
+                                        break;
+                                    }
+                                }
+                            } else {
+                                while offset + usize_size + size <= data_buffer.len() {
+                                    let address = match data_buffer.get(offset..offset + usize_size)
+                                    {
+                                        Some(slice) => usize::from_le_bytes(
+                                            slice.try_into().expect("Invalid address format"),
+                                        ),
+                                        None => break,
+                                    };
+                                    offset += usize_size;
+
+                                    let old_val = &data_buffer[offset..offset + size];
+                                    offset += size;
+
+                                    let mut new_val_vec: Vec<u8> = vec![0; size];
+                                    let nread = match native_bridge::read_process_memory(
+                                        pid,
+                                        address as *mut libc::c_void,
+                                        size,
+                                        &mut new_val_vec,
+                                    ) {
+                                        Ok(nread) => nread,
+                                        Err(_) => {
+                                            continue;
+                                        }
+                                    };
+
+                                    if nread != size as isize {
+                                        println!("Incomplete read at address {:x}", address);
+                                        continue;
+                                    }
+                                    let new_val: &[u8] = &new_val_vec;
+
+                                    let mut pass_filter: bool = false;
+                                    if filter_request.filter_method.as_str() == "exact" {
+                                        if exact_bytes == new_val {
+                                            pass_filter = true;
+                                        }
+                                    } else {
+                                        pass_filter = match filter_request.data_type.as_str() {
+                                            _ => compare_values!(
+                                                new_val,
+                                                old_val,
+                                                filter_request.filter_method.as_str()
+                                            ),
+                                        };
+                                    }
+
+                                    if pass_filter {
+                                        serialized_data.extend_from_slice(&address.to_le_bytes());
+                                        serialized_data.extend_from_slice(&new_val);
+                                        found_count.fetch_add(1, Ordering::SeqCst);
+                                    }
+                                }
+                            }
+                        }
+
+                        // rewrite file
+                        let mut file = match OpenOptions::new()
+                            .write(true)
+                            .truncate(true)
+                            .open(file_path)
+                        {
+                            Ok(file) => file,
+                            Err(e) => {
+                                *error_occurred = true;
+                                *error_msg = format!("Failed to open file for writing: {}", e);
+                                return;
+                            }
+                        };
+
+                        let number: u32 = 0x00000001;
+                        if let Err(e) = file.write_all(&number.to_le_bytes()) {
+                            *error_occurred = true;
+                            *error_msg = format!("Failed to write status flag: {}", e);
+                            return;
+                        }
+
+                        if let Err(e) = file.write_all(&serialized_data) {
+                            *error_occurred = true;
+                            *error_msg = format!("Failed to write data: {}", e);
+                            return;
+                        }
+                    });
+                });
+            }
+
+            new_positions = if found_count.load(Ordering::SeqCst) < 1_000_000 {
+                let results: Vec<(usize, String)> = scan::in_scan_pool(|| {
+                    paths
+                        .par_iter()
+                        .flat_map(|file_path| {
+                            let mut file = match File::open(file_path) {
+                                Ok(file) => file,
+                                Err(e) => {
+                                    eprintln!("Failed to open file {:?}: {}", file_path, e);
+                                    return Vec::new();
+                                }
+                            };
+
+                            let mut flag = [0u8; 4];
+                            if let Err(e) = file.read_exact(&mut flag) {
+                                eprintln!("Failed to read flag from {:?}: {}", file_path, e);
+                                return Vec::new();
+                            }
+
+                            if u32::from_le_bytes(flag) != 0x00000001 {
+                                return Vec::new();
+                            }
+
+                            let mut data = Vec::new();
+                            if let Err(e) = file.read_to_end(&mut data) {
+                                eprintln!("Failed to read data from {:?}: {}", file_path, e);
+                                return Vec::new();
+                            }
+
+                            let mut local_results = Vec::new();
+                            let mut offset = 0;
+                            while offset + std::mem::size_of::<usize>() + size <= data.len() {
+                                let address = usize::from_le_bytes(
+                                    data[offset..offset + std::mem::size_of::<usize>()]
+                                        .try_into()
+                                        .unwrap(),
+                                );
+                                offset += std::mem::size_of::<usize>();
+                                let value = hex::encode(&data[offset..offset + size]);
+                                offset += size;
+                                local_results.push((address, value));
+                            }
+
+                            local_results
+                        })
+                        .collect()
+                });
+                results
+            } else {
+                Vec::new()
+            };
+            scan::in_scan_pool(|| new_positions.par_sort_unstable_by_key(|&(address, _)| address));
+        } else if let Some(positions) = global_positions.get(&filter_request.scan_id) {
+            if do_suspend {
+                unsafe {
+                    is_suspend_success = native_bridge::suspend_process(pid);
+                }
+            }
+            let results: Result<Vec<_>, _> = scan::in_scan_pool(|| {
+                positions
+                    .par_iter()
+                    .map(|(address, value)| {
+                        let mut buffer: Vec<u8> = vec![0; (value.len() / 2) as usize];
+                        let _nread = match native_bridge::read_process_memory(
+                            pid,
+                            *address as *mut libc::c_void,
+                            filter_request.pattern.len(),
+                            &mut buffer,
+                        ) {
+                            Ok(nread) => nread,
+                            Err(_err) => -1,
+                        };
+
+                        if _nread == -1 {
+                            return Ok(None);
+                        }
+
+                        if filter_request.data_type == "regex" {
+                            let regex_pattern = &filter_request.pattern;
+                            let re = match Regex::new(regex_pattern) {
+                                Ok(re) => re,
+                                Err(_) => return Ok(None),
+                            };
+                            if re.is_match(&buffer) {
+                                found_count.fetch_add(1, Ordering::SeqCst);
+                                return Ok(Some((*address, hex::encode(&buffer))));
+                            }
+                        } else {
+                            if filter_request.filter_method == "exact" {
+                                let result = hex::decode(&filter_request.pattern);
+                                let bytes = match result {
+                                    Ok(bytes) => bytes,
+                                    Err(_) => {
+                                        let response = Response::builder()
+                                            .status(StatusCode::BAD_REQUEST)
+                                            .body(hyper::Body::from("Invalid hex pattern"))
+                                            .unwrap();
+                                        return Err(Box::new(response));
+                                    }
+                                };
+                                if buffer == bytes {
+                                    found_count.fetch_add(1, Ordering::SeqCst);
+                                    return Ok(Some((*address, hex::encode(&buffer))));
+                                }
+                            } else {
+                                let result = hex::decode(&value);
+                                let bytes = match result {
+                                    Ok(bytes) => bytes,
+                                    Err(_) => {
+                                        let response = Response::builder()
+                                            .status(StatusCode::BAD_REQUEST)
+                                            .body(hyper::Body::from("Invalid hex pattern"))
+                                            .unwrap();
+                                        return Err(Box::new(response));
+                                    }
+                                };
+                                let pass_filter: bool;
+
+                                pass_filter = match filter_request.data_type.as_str() {
+                                    "int8" => {
+                                        let old_val = i8::from_le_bytes(bytes.try_into().unwrap());
+                                        let val =
+                                            i8::from_le_bytes(buffer.clone().try_into().unwrap());
+                                        compare_values!(
+                                            val,
+                                            old_val,
+                                            filter_request.filter_method.as_str()
+                                        )
+                                    }
+                                    "uint8" => {
+                                        let old_val = u8::from_le_bytes(bytes.try_into().unwrap());
+                                        let val =
+                                            u8::from_le_bytes(buffer.clone().try_into().unwrap());
+                                        compare_values!(
+                                            val,
+                                            old_val,
+                                            filter_request.filter_method.as_str()
+                                        )
+                                    }
+                                    "int16" => {
+                                        let old_val = i16::from_le_bytes(bytes.try_into().unwrap());
+                                        let val =
+                                            i16::from_le_bytes(buffer.clone().try_into().unwrap());
+                                        compare_values!(
+                                            val,
+                                            old_val,
+                                            filter_request.filter_method.as_str()
+                                        )
+                                    }
+                                    "uint16" => {
+                                        let old_val = u16::from_le_bytes(bytes.try_into().unwrap());
+                                        let val =
+                                            u16::from_le_bytes(buffer.clone().try_into().unwrap());
+                                        compare_values!(
+                                            val,
+                                            old_val,
+                                            filter_request.filter_method.as_str()
+                                        )
+                                    }
+                                    "int32" => {
+                                        let old_val = i32::from_le_bytes(bytes.try_into().unwrap());
+                                        let val =
+                                            i32::from_le_bytes(buffer.clone().try_into().unwrap());
+                                        compare_values!(
+                                            val,
+                                            old_val,
+                                            filter_request.filter_method.as_str()
+                                        )
+                                    }
+                                    "uint32" => {
+                                        let old_val = u32::from_le_bytes(bytes.try_into().unwrap());
+                                        let val =
+                                            u32::from_le_bytes(buffer.clone().try_into().unwrap());
+                                        compare_values!(
+                                            val,
+                                            old_val,
+                                            filter_request.filter_method.as_str()
+                                        )
+                                    }
+                                    "int64" => {
+                                        let old_val = i64::from_le_bytes(bytes.try_into().unwrap());
+                                        let val =
+                                            i64::from_le_bytes(buffer.clone().try_into().unwrap());
+                                        compare_values!(
+                                            val,
+                                            old_val,
+                                            filter_request.filter_method.as_str()
+                                        )
+                                    }
+                                    "uint64" => {
+                                        let old_val = u64::from_le_bytes(bytes.try_into().unwrap());
+                                        let val =
+                                            u64::from_le_bytes(buffer.clone().try_into().unwrap());
+                                        compare_values!(
+                                            val,
+                                            old_val,
+                                            filter_request.filter_method.as_str()
+                                        )
+                                    }
+                                    "float" => {
+                                        let old_val = LittleEndian::read_f32(&bytes);
+                                        let val = LittleEndian::read_f32(&buffer.clone());
+                                        compare_values!(
+                                            val,
+                                            old_val,
+                                            filter_request.filter_method.as_str()
+                                        )
+                                    }
+                                    "double" => {
+                                        let old_val = LittleEndian::read_f64(&bytes);
+                                        let val = LittleEndian::read_f64(&buffer.clone());
+                                        compare_values!(
+                                            val,
+                                            old_val,
+                                            filter_request.filter_method.as_str()
+                                        )
+                                    }
+                                    "utf-8" => {
+                                        let old_val = str::from_utf8(&bytes).unwrap_or("");
+                                        let val = str::from_utf8(&buffer).unwrap_or("");
+                                        match filter_request.filter_method.as_str() {
+                                            "changed" => val != old_val,
+                                            "unchanged" => val == old_val,
+                                            _ => false,
+                                        }
+                                    }
+                                    "utf-16" => {
+                                        let buffer_u16: Vec<u16> = buffer
+                                            .clone()
+                                            .chunks_exact(2)
+                                            .map(|b| u16::from_ne_bytes([b[0], b[1]]))
+                                            .collect();
+                                        match filter_request.filter_method.as_str() {
+                                            "changed" => {
+                                                let old_value: Vec<u16> = hex::decode(&value)
+                                                    .unwrap()
+                                                    .chunks_exact(2)
+                                                    .map(|b| u16::from_ne_bytes([b[0], b[1]]))
+                                                    .collect();
+                                                buffer_u16 != old_value
+                                            }
+                                            "unchanged" => {
+                                                let old_value: Vec<u16> = hex::decode(&value)
+                                                    .unwrap()
+                                                    .chunks_exact(2)
+                                                    .map(|b| u16::from_ne_bytes([b[0], b[1]]))
+                                                    .collect();
+                                                buffer_u16 == old_value
+                                            }
+                                            _ => false,
+                                        }
+                                    }
+                                    "aob" => match filter_request.filter_method.as_str() {
+                                        "changed" => buffer != bytes,
+                                        "unchanged" => buffer == bytes,
+                                        _ => false,
+                                    },
+                                    _ => false,
+                                };
+
+                                if pass_filter {
+                                    found_count.fetch_add(1, Ordering::SeqCst);
+                                    return Ok(Some((*address, hex::encode(&buffer))));
+                                }
+                            }
+                        }
+                        Ok(None)
+                    })
+                    .collect()
+            });
+
+            match results {
+                Ok(results) => {
+                    new_positions = results.into_iter().filter_map(|x| x).collect();
+                }
+                Err(response) => {
+                    let mut do_play = GLOBAL_PROCESS_STATE.write().unwrap();
+                    if do_suspend && is_suspend_success && *do_play {
+                        unsafe {
+                            native_bridge::resume_process(pid);
+                        }
+                    }
+                    return Ok(*response);
+                }
+            }
+        } else {
+            let response = Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(Body::from("Scanid not found"))
+                .unwrap();
+            return Ok(response);
+        }
+        let mut do_play = GLOBAL_PROCESS_STATE.write().unwrap();
+        if do_suspend && is_suspend_success && *do_play {
+            unsafe {
+                native_bridge::resume_process(pid);
+            }
+        }
+        global_positions.insert(filter_request.scan_id.clone(), new_positions.clone());
+
+        if filter_request.return_as_json {
+            let limited_positions =
+                &new_positions[..std::cmp::min(MAX_RESULTS, new_positions.len())];
+            let is_rounded: bool;
+            let count = found_count.load(Ordering::SeqCst);
+            if scan_option.find_type == "unknown" {
+                if count > 1_000_000 {
+                    is_rounded = true;
+                } else {
+                    is_rounded = limited_positions.len() != new_positions.len();
+                }
+            } else {
+                is_rounded = limited_positions.len() != new_positions.len();
+            }
+            let matched_addresses: Vec<serde_json::Value> = limited_positions
+                .iter()
+                .map(|(address, value)| {
+                    json!({
+                        "address": address,
+                        "value": value
+                    })
+                })
+                .collect();
+
+            let result = json!({
+                "matched_addresses": matched_addresses,
+                "found":count,
+                "is_rounded":is_rounded
 
-            let mut exact_bytes: Vec<u8> = vec![];
-            if filter_request.filter_method.as_str() == "exact" {
-                exact_bytes = match hex::decode(&filter_request.pattern) {
-                    Ok(bytes) => bytes,
-                    Err(_) => vec![],
-                };
-            }
+            });
+            let result_string = result.to_string();
+            let response = Response::builder()
+                .header("Content-Type", "application/json")
+                .body(Body::from(result_string))
+                .unwrap();
+            Ok(response)
+        } else {
+            let count = found_count.load(Ordering::SeqCst);
+            let result_string = json!({ "found": count }).to_string();
+            let response = Response::builder()
+                .header("Content-Type", "application/json")
+                .body(hyper::Body::from(result_string))
+                .unwrap();
+            Ok(response)
+        }
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
 
-            if !*is_error_occurred.lock().unwrap() {
-                paths.par_iter().for_each(|file_path| {
-                    let mut error_occurred = is_error_occurred.lock().unwrap();
-                    let mut error_msg = error_message.lock().unwrap();
-                    if *error_occurred {
-                        return;
-                    }
-                    let mut serialized_data: Vec<u8> = Vec::new();
-                    if let Ok(file) = File::open(file_path) {
-                        let mut reader = BufReader::new(file);
-                        let mut data_buffer: Vec<u8> = Vec::new();
-                        if let Err(e) = reader.read_to_end(&mut data_buffer) {
-                            *error_occurred = true;
-                            *error_msg = format!("Failed to read file: {}", e);
-                            return;
-                        }
-                        let status_flag: [u8; 4] = match data_buffer[0..4].try_into() {
-                            Ok(flag) => flag,
-                            Err(e) => {
-                                *error_occurred = true;
-                                *error_msg = format!("Invalid address format: {}", e);
-                                return;
-                            }
-                        };
-                        let mut offset = 4;
-                        let usize_size = size_of::<usize>();
-                        if status_flag == [0x00, 0x00, 0x00, 0x00] {
-                            while offset + 3 * usize_size <= data_buffer.len() {
-                                let address = usize::from_le_bytes(
-                                    data_buffer[offset..offset + usize_size]
-                                        .try_into()
-                                        .expect("Invalid address format"),
-                                );
+#[derive(Serialize)]
+struct Region {
+    start_address: String,
+    end_address: String,
+    protection: String,
+    file_path: Option<String>,
+    kind: region::RegionKind,
+}
 
-                                offset += usize_size;
+pub async fn enumerate_regions_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
 
-                                let compressed_data_size = usize::from_le_bytes(
-                                    data_buffer[offset..offset + usize_size]
-                                        .try_into()
-                                        .expect("Invalid length format"),
-                                );
-                                offset += usize_size;
+    if let Some(pid) = *pid {
+        // A core dump lists its segments, in the shape the native listing is parsed into
+        if coredump::is_dump_handle(pid) {
+            let regions: Vec<Region> = region::enumerate_regions(pid)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|region| Region {
+                    start_address: format!("{:x}", region.start),
+                    end_address: format!("{:x}", region.end),
+                    protection: format!("{}p", region.protection),
+                    kind: region.kind,
+                    file_path: region.path,
+                })
+                .collect();
+            let response = Response::builder()
+                .header("Content-Type", "application/json")
+                .body(hyper::Body::from(json!({ "regions": regions }).to_string()))
+                .unwrap();
+            return Ok(response);
+        }
+        let mut buffer = vec![0u8; 1024 * 1024];
 
-                                let uncompressed_data_size = usize::from_le_bytes(
-                                    data_buffer[offset..offset + usize_size]
-                                        .try_into()
-                                        .expect("Invalid length format"),
-                                );
-                                offset += usize_size;
-
-                                if offset + compressed_data_size <= data_buffer.len() {
-                                    let compressed_data =
-                                        &data_buffer[offset..offset + compressed_data_size];
-                                    offset += compressed_data_size;
-                                    let decompressed_data = match lz4_flex::block::decompress(
-                                        &compressed_data,
-                                        uncompressed_data_size,
-                                    ) {
-                                        Ok(data) => data,
-                                        Err(e) => {
-                                            *error_occurred = true;
-                                            *error_msg =
-                                                format!("Failed to decompress data: {}", e);
-                                            return;
-                                        }
-                                    };
+        unsafe {
+            native_bridge::enumerate_regions_to_buffer(pid, buffer.as_mut_ptr(), buffer.len());
+        }
+        let buffer_cstring = unsafe { CString::from_vec_unchecked(buffer) };
+        let buffer_string = buffer_cstring.into_string().unwrap();
+        let buffer_reader = BufReader::new(buffer_string.as_bytes());
 
-                                    let mut buffer: Vec<u8> =
-                                        vec![0; (decompressed_data.len()) as usize];
-                                    let _nread = match native_bridge::read_process_memory(
-                                        pid,
-                                        address as *mut libc::c_void,
-                                        decompressed_data.len(),
-                                        &mut buffer,
-                                    ) {
-                                        Ok(nread) => nread,
-                                        Err(_err) => -1,
-                                    };
+        let mut regions = Vec::new();
 
-                                    if _nread == -1 {
-                                        return;
-                                    }
-                                    for offset in (0..decompressed_data.len()).step_by(1) {
-                                        if (address + offset) % scan_align != 0 {
-                                            continue;
-                                        }
-                                        if offset + size > decompressed_data.len() {
-                                            break;
-                                        }
-                                        let old_val = &decompressed_data[offset..offset + size];
-                                        let new_val = &buffer[offset..offset + size];
+        for line in buffer_reader.lines() {
+            if let Ok(line) = line {
+                let parts: Vec<&str> = line.split_whitespace().collect();
 
-                                        let mut pass_filter: bool = false;
-                                        if filter_request.filter_method.as_str() == "exact" {
-                                            if exact_bytes == new_val {
-                                                pass_filter = true;
-                                            }
-                                        } else {
-                                            pass_filter = match filter_request.data_type.as_str() {
-                                                _ => compare_values!(
-                                                    new_val,
-                                                    old_val,
-                                                    filter_request.filter_method.as_str()
-                                                ),
-                                            };
-                                        }
-                                        if pass_filter {
-                                            serialized_data.extend_from_slice(
-                                                &(address + offset).to_le_bytes(),
-                                            );
-                                            serialized_data.extend_from_slice(new_val);
-                                            found_count.fetch_add(1, Ordering::SeqCst);
-                                        }
-                                    }
-                                } else {
-                                    break;
-                                }
-                            }
+                if parts.len() >= 5 {
+                    let addresses: Vec<&str> = parts[0].split('-').collect();
+                    if addresses.len() == 2 {
+                        let file_path = if parts.len() > 5 {
+                            Some(parts[5..].join(" "))
                         } else {
-                            while offset + usize_size + size <= data_buffer.len() {
-                                let address = match data_buffer.get(offset..offset + usize_size) {
-                                    Some(slice) => usize::from_le_bytes(
-                                        slice.try_into().expect("Invalid address format"),
-                                    ),
-                                    None => break,
-                                };
-                                offset += usize_size;
+                            None
+                        };
+                        let region = Region {
+                            start_address: addresses[0].to_string(),
+                            end_address: addresses[1].to_string(),
+                            protection: parts[1].to_string(),
+                            kind: region::RegionKind::classify(file_path.as_deref()),
+                            file_path,
+                        };
+                        regions.push(region);
+                    }
+                }
+            }
+        }
 
-                                let old_val = &data_buffer[offset..offset + size];
-                                offset += size;
+        let result = json!({ "regions": regions });
+        let result_string = result.to_string();
+        let response = Response::builder()
+            .header("Content-Type", "application/json")
+            .body(hyper::Body::from(result_string))
+            .unwrap();
+        Ok(response)
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
 
-                                let mut new_val_vec: Vec<u8> = vec![0; size];
-                                let nread = match native_bridge::read_process_memory(
-                                    pid,
-                                    address as *mut libc::c_void,
-                                    size,
-                                    &mut new_val_vec,
-                                ) {
-                                    Ok(nread) => nread,
-                                    Err(_) => {
-                                        continue;
-                                    }
-                                };
+pub async fn enumerate_process_handler() -> Result<impl Reply, Rejection> {
+    let mut processes = util::list_processes();
 
-                                if nread != size as isize {
-                                    println!("Incomplete read at address {:x}", address);
-                                    continue;
-                                }
-                                let new_val: &[u8] = &new_val_vec;
+    // for cdylib
+    if processes.is_empty() {
+        let pid = unsafe { native_bridge::get_pid_native() };
+        processes.push(util::ProcessInfo {
+            pid,
+            name: "self".to_string(),
+            path: None,
+        });
+    }
 
-                                let mut pass_filter: bool = false;
-                                if filter_request.filter_method.as_str() == "exact" {
-                                    if exact_bytes == new_val {
-                                        pass_filter = true;
-                                    }
-                                } else {
-                                    pass_filter = match filter_request.data_type.as_str() {
-                                        _ => compare_values!(
-                                            new_val,
-                                            old_val,
-                                            filter_request.filter_method.as_str()
-                                        ),
-                                    };
-                                }
+    let json_response = warp::reply::json(&processes);
+    Ok(json_response)
+}
 
-                                if pass_filter {
-                                    serialized_data.extend_from_slice(&address.to_le_bytes());
-                                    serialized_data.extend_from_slice(&new_val);
-                                    found_count.fetch_add(1, Ordering::SeqCst);
-                                }
-                            }
-                        }
-                    }
+pub async fn enummodule_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
+    if let Some(pid) = *pid {
+        let modules = util::enumerate_modules(pid);
+        let result = json!({ "modules": modules });
+        let result_string = result.to_string();
+        let response = Response::builder()
+            .header("Content-Type", "application/json")
+            .body(hyper::Body::from(result_string))
+            .unwrap();
+        Ok(response)
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
 
-                    // rewrite file
-                    let mut file = match OpenOptions::new()
-                        .write(true)
-                        .truncate(true)
-                        .open(file_path)
-                    {
-                        Ok(file) => file,
-                        Err(e) => {
-                            *error_occurred = true;
-                            *error_msg = format!("Failed to open file for writing: {}", e);
-                            return;
-                        }
-                    };
+pub async fn enumerate_threads_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
+    if let Some(pid) = *pid {
+        match native_bridge::enumerate_threads(pid) {
+            Ok(threads) => {
+                let result = json!({ "threads": threads });
+                let response = Response::builder()
+                    .header("Content-Type", "application/json")
+                    .body(hyper::Body::from(result.to_string()))
+                    .unwrap();
+                Ok(response)
+            }
+            Err(e) => {
+                let response = Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(hyper::Body::from(e))
+                    .unwrap();
+                Ok(response)
+            }
+        }
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
 
-                    let number: u32 = 0x00000001;
-                    if let Err(e) = file.write_all(&number.to_le_bytes()) {
-                        *error_occurred = true;
-                        *error_msg = format!("Failed to write status flag: {}", e);
-                        return;
-                    }
+pub async fn read_registers_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    registers_request: request::ReadRegistersRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
+    if let Some(pid) = *pid {
+        match native_bridge::read_thread_registers(pid, registers_request.tid) {
+            Ok(registers) => {
+                let result = json!(registers);
+                let response = Response::builder()
+                    .header("Content-Type", "application/json")
+                    .body(hyper::Body::from(result.to_string()))
+                    .unwrap();
+                Ok(response)
+            }
+            Err(e) => {
+                let response = Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(hyper::Body::from(e))
+                    .unwrap();
+                Ok(response)
+            }
+        }
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
 
-                    if let Err(e) = file.write_all(&serialized_data) {
-                        *error_occurred = true;
-                        *error_msg = format!("Failed to write data: {}", e);
-                        return;
-                    }
+// Steps a thread halted on a breakpoint and returns its registers together with the
+// instruction it is about to execute
+pub async fn single_step_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    step_request: request::ThreadControlRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
+    if let Some(pid) = *pid {
+        match native_bridge::single_step(pid, step_request.tid) {
+            Ok(registers) => {
+                let next_instruction = util::disassemble_at(
+                    pid,
+                    registers.pc,
+                    1,
+                    util::Arch::default(),
+                    util::AsmSyntax::default(),
+                )
+                .ok()
+                .and_then(|instructions| instructions.into_iter().next());
+                let result = json!({
+                    "registers": registers,
+                    "next_instruction": next_instruction,
                 });
+                let response = Response::builder()
+                    .header("Content-Type", "application/json")
+                    .body(hyper::Body::from(result.to_string()))
+                    .unwrap();
+                Ok(response)
+            }
+            Err(e) => {
+                let response = Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(hyper::Body::from(e))
+                    .unwrap();
+                Ok(response)
             }
+        }
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
 
-            new_positions = if found_count.load(Ordering::SeqCst) < 1_000_000 {
-                let mut results: Vec<(usize, String)> = paths
-                    .par_iter()
-                    .flat_map(|file_path| {
-                        let mut file = match File::open(file_path) {
-                            Ok(file) => file,
-                            Err(e) => {
-                                eprintln!("Failed to open file {:?}: {}", file_path, e);
-                                return Vec::new();
-                            }
-                        };
-
-                        let mut flag = [0u8; 4];
-                        if let Err(e) = file.read_exact(&mut flag) {
-                            eprintln!("Failed to read flag from {:?}: {}", file_path, e);
-                            return Vec::new();
-                        }
-
-                        if u32::from_le_bytes(flag) != 0x00000001 {
-                            return Vec::new();
-                        }
-
-                        let mut data = Vec::new();
-                        if let Err(e) = file.read_to_end(&mut data) {
-                            eprintln!("Failed to read data from {:?}: {}", file_path, e);
-                            return Vec::new();
-                        }
-
-                        let mut local_results = Vec::new();
-                        let mut offset = 0;
-                        while offset + std::mem::size_of::<usize>() + size <= data.len() {
-                            let address = usize::from_le_bytes(
-                                data[offset..offset + std::mem::size_of::<usize>()]
-                                    .try_into()
-                                    .unwrap(),
-                            );
-                            offset += std::mem::size_of::<usize>();
-                            let value = hex::encode(&data[offset..offset + size]);
-                            offset += size;
-                            local_results.push((address, value));
-                        }
-
-                        local_results
-                    })
-                    .collect();
-                results
-            } else {
-                Vec::new()
-            };
-            new_positions.par_sort_unstable_by_key(|&(address, _)| address);
-        } else if let Some(positions) = global_positions.get(&filter_request.scan_id) {
-            if do_suspend {
-                unsafe {
-                    is_suspend_success = native_bridge::suspend_process(pid);
-                }
+pub async fn continue_thread_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    continue_request: request::ThreadControlRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
+    if let Some(pid) = *pid {
+        match native_bridge::resume_thread(pid, continue_request.tid) {
+            Ok(()) => {
+                let response = Response::builder()
+                    .header("Content-Type", "text/plain")
+                    .body(hyper::Body::from("Thread resumed"))
+                    .unwrap();
+                Ok(response)
             }
-            let results: Result<Vec<_>, _> = positions
-                .par_iter()
-                .map(|(address, value)| {
-                    let mut buffer: Vec<u8> = vec![0; (value.len() / 2) as usize];
-                    let _nread = match native_bridge::read_process_memory(
-                        pid,
-                        *address as *mut libc::c_void,
-                        filter_request.pattern.len(),
-                        &mut buffer,
-                    ) {
-                        Ok(nread) => nread,
-                        Err(_err) => -1,
-                    };
+            Err(e) => {
+                let response = Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(hyper::Body::from(e))
+                    .unwrap();
+                Ok(response)
+            }
+        }
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
 
-                    if _nread == -1 {
-                        return Ok(None);
-                    }
+pub async fn freeze_add_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    freeze_request: request::FreezeRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
+    if let Some(pid) = *pid {
+        match freeze::freeze_add(pid, freeze_request.address, freeze_request.buffer) {
+            Ok(()) => {
+                let response = Response::builder()
+                    .header("Content-Type", "text/plain")
+                    .body(hyper::Body::from("Value frozen"))
+                    .unwrap();
+                Ok(response)
+            }
+            Err(e) => {
+                let response = Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(hyper::Body::from(e))
+                    .unwrap();
+                Ok(response)
+            }
+        }
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
 
-                    if filter_request.data_type == "regex" {
-                        let regex_pattern = &filter_request.pattern;
-                        let re = match Regex::new(regex_pattern) {
-                            Ok(re) => re,
-                            Err(_) => return Ok(None),
-                        };
-                        if re.is_match(&buffer) {
-                            found_count.fetch_add(1, Ordering::SeqCst);
-                            return Ok(Some((*address, hex::encode(&buffer))));
-                        }
-                    } else {
-                        if filter_request.filter_method == "exact" {
-                            let result = hex::decode(&filter_request.pattern);
-                            let bytes = match result {
-                                Ok(bytes) => bytes,
-                                Err(_) => {
-                                    let response = Response::builder()
-                                        .status(StatusCode::BAD_REQUEST)
-                                        .body(hyper::Body::from("Invalid hex pattern"))
-                                        .unwrap();
-                                    return Err(response);
-                                }
-                            };
-                            if buffer == bytes {
-                                found_count.fetch_add(1, Ordering::SeqCst);
-                                return Ok(Some((*address, hex::encode(&buffer))));
-                            }
-                        } else {
-                            let result = hex::decode(&value);
-                            let bytes = match result {
-                                Ok(bytes) => bytes,
-                                Err(_) => {
-                                    let response = Response::builder()
-                                        .status(StatusCode::BAD_REQUEST)
-                                        .body(hyper::Body::from("Invalid hex pattern"))
-                                        .unwrap();
-                                    return Err(response);
-                                }
-                            };
-                            let pass_filter: bool;
-
-                            pass_filter = match filter_request.data_type.as_str() {
-                                "int8" => {
-                                    let old_val = i8::from_le_bytes(bytes.try_into().unwrap());
-                                    let val = i8::from_le_bytes(buffer.clone().try_into().unwrap());
-                                    compare_values!(
-                                        val,
-                                        old_val,
-                                        filter_request.filter_method.as_str()
-                                    )
-                                }
-                                "uint8" => {
-                                    let old_val = u8::from_le_bytes(bytes.try_into().unwrap());
-                                    let val = u8::from_le_bytes(buffer.clone().try_into().unwrap());
-                                    compare_values!(
-                                        val,
-                                        old_val,
-                                        filter_request.filter_method.as_str()
-                                    )
-                                }
-                                "int16" => {
-                                    let old_val = i16::from_le_bytes(bytes.try_into().unwrap());
-                                    let val =
-                                        i16::from_le_bytes(buffer.clone().try_into().unwrap());
-                                    compare_values!(
-                                        val,
-                                        old_val,
-                                        filter_request.filter_method.as_str()
-                                    )
-                                }
-                                "uint16" => {
-                                    let old_val = u16::from_le_bytes(bytes.try_into().unwrap());
-                                    let val =
-                                        u16::from_le_bytes(buffer.clone().try_into().unwrap());
-                                    compare_values!(
-                                        val,
-                                        old_val,
-                                        filter_request.filter_method.as_str()
-                                    )
-                                }
-                                "int32" => {
-                                    let old_val = i32::from_le_bytes(bytes.try_into().unwrap());
-                                    let val =
-                                        i32::from_le_bytes(buffer.clone().try_into().unwrap());
-                                    compare_values!(
-                                        val,
-                                        old_val,
-                                        filter_request.filter_method.as_str()
-                                    )
-                                }
-                                "uint32" => {
-                                    let old_val = u32::from_le_bytes(bytes.try_into().unwrap());
-                                    let val =
-                                        u32::from_le_bytes(buffer.clone().try_into().unwrap());
-                                    compare_values!(
-                                        val,
-                                        old_val,
-                                        filter_request.filter_method.as_str()
-                                    )
-                                }
-                                "int64" => {
-                                    let old_val = i64::from_le_bytes(bytes.try_into().unwrap());
-                                    let val =
-                                        i64::from_le_bytes(buffer.clone().try_into().unwrap());
-                                    compare_values!(
-                                        val,
-                                        old_val,
-                                        filter_request.filter_method.as_str()
-                                    )
-                                }
-                                "uint64" => {
-                                    let old_val = u64::from_le_bytes(bytes.try_into().unwrap());
-                                    let val =
-                                        u64::from_le_bytes(buffer.clone().try_into().unwrap());
-                                    compare_values!(
-                                        val,
-                                        old_val,
-                                        filter_request.filter_method.as_str()
-                                    )
-                                }
-                                "float" => {
-                                    let old_val = LittleEndian::read_f32(&bytes);
-                                    let val = LittleEndian::read_f32(&buffer.clone());
-                                    compare_values!(
-                                        val,
-                                        old_val,
-                                        filter_request.filter_method.as_str()
-                                    )
-                                }
-                                "double" => {
-                                    let old_val = LittleEndian::read_f64(&bytes);
-                                    let val = LittleEndian::read_f64(&buffer.clone());
-                                    compare_values!(
-                                        val,
-                                        old_val,
-                                        filter_request.filter_method.as_str()
-                                    )
-                                }
-                                "utf-8" => {
-                                    let old_val = str::from_utf8(&bytes).unwrap_or("");
-                                    let val = str::from_utf8(&buffer).unwrap_or("");
-                                    match filter_request.filter_method.as_str() {
-                                        "changed" => val != old_val,
-                                        "unchanged" => val == old_val,
-                                        _ => false,
-                                    }
-                                }
-                                "utf-16" => {
-                                    let buffer_u16: Vec<u16> = buffer
-                                        .clone()
-                                        .chunks_exact(2)
-                                        .map(|b| u16::from_ne_bytes([b[0], b[1]]))
-                                        .collect();
-                                    match filter_request.filter_method.as_str() {
-                                        "changed" => {
-                                            let old_value: Vec<u16> = hex::decode(&value)
-                                                .unwrap()
-                                                .chunks_exact(2)
-                                                .map(|b| u16::from_ne_bytes([b[0], b[1]]))
-                                                .collect();
-                                            buffer_u16 != old_value
-                                        }
-                                        "unchanged" => {
-                                            let old_value: Vec<u16> = hex::decode(&value)
-                                                .unwrap()
-                                                .chunks_exact(2)
-                                                .map(|b| u16::from_ne_bytes([b[0], b[1]]))
-                                                .collect();
-                                            buffer_u16 == old_value
-                                        }
-                                        _ => false,
-                                    }
-                                }
-                                "aob" => match filter_request.filter_method.as_str() {
-                                    "changed" => buffer != bytes,
-                                    "unchanged" => buffer == bytes,
-                                    _ => false,
-                                },
-                                _ => false,
-                            };
+pub async fn freeze_remove_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    unfreeze_request: request::UnfreezeRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
+    if let Some(pid) = *pid {
+        if freeze::freeze_remove(pid, unfreeze_request.address) {
+            let response = Response::builder()
+                .header("Content-Type", "text/plain")
+                .body(hyper::Body::from("Value unfrozen"))
+                .unwrap();
+            Ok(response)
+        } else {
+            let response = Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(hyper::Body::from("Address is not frozen"))
+                .unwrap();
+            Ok(response)
+        }
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
 
-                            if pass_filter {
-                                found_count.fetch_add(1, Ordering::SeqCst);
-                                return Ok(Some((*address, hex::encode(&buffer))));
-                            }
-                        }
-                    }
-                    Ok(None)
-                })
-                .collect();
+pub async fn freeze_list_handler() -> Result<impl warp::Reply, warp::Rejection> {
+    let result = json!({ "frozen": freeze::freeze_list() });
+    let response = Response::builder()
+        .header("Content-Type", "application/json")
+        .body(hyper::Body::from(result.to_string()))
+        .unwrap();
+    Ok(response)
+}
 
-            match results {
-                Ok(results) => {
-                    new_positions = results.into_iter().filter_map(|x| x).collect();
-                }
-                Err(response) => {
-                    let mut do_play = GLOBAL_PROCESS_STATE.write().unwrap();
-                    if do_suspend && is_suspend_success && *do_play {
-                        unsafe {
-                            native_bridge::resume_process(pid);
-                        }
-                    }
-                    return Ok(response);
-                }
-            }
-        } else {
+pub async fn bookmark_add_handler(
+    bookmark_request: request::BookmarkAddRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match bookmarks::bookmark_add(
+        &bookmark_request.address,
+        &bookmark_request.label,
+        &bookmark_request.value_type,
+    ) {
+        Ok(bookmark) => {
             let response = Response::builder()
-                .status(StatusCode::BAD_REQUEST)
-                .body(Body::from("Scanid not found"))
+                .header("Content-Type", "application/json")
+                .body(hyper::Body::from(json!(bookmark).to_string()))
                 .unwrap();
-            return Ok(response);
+            Ok(response)
         }
-        let mut do_play = GLOBAL_PROCESS_STATE.write().unwrap();
-        if do_suspend && is_suspend_success && *do_play {
-            unsafe {
-                native_bridge::resume_process(pid);
-            }
+        Err(e) => {
+            let response = Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(hyper::Body::from(e))
+                .unwrap();
+            Ok(response)
         }
-        global_positions.insert(filter_request.scan_id.clone(), new_positions.clone());
+    }
+}
 
-        if filter_request.return_as_json {
-            let limited_positions =
-                &new_positions[..std::cmp::min(MAX_RESULTS, new_positions.len())];
-            let is_rounded: bool;
-            let count = found_count.load(Ordering::SeqCst);
-            if scan_option.find_type == "unknown" {
-                if count > 1_000_000 {
-                    is_rounded = true;
-                } else {
-                    is_rounded = limited_positions.len() != new_positions.len();
-                }
-            } else {
-                is_rounded = limited_positions.len() != new_positions.len();
-            }
-            let matched_addresses: Vec<serde_json::Value> = limited_positions
-                .iter()
-                .map(|(address, value)| {
-                    json!({
-                        "address": address,
-                        "value": value
-                    })
-                })
-                .collect();
+pub async fn bookmark_remove_handler(
+    bookmark_request: request::BookmarkRemoveRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let response = match bookmarks::bookmark_remove(bookmark_request.id) {
+        Ok(true) => Response::builder()
+            .header("Content-Type", "text/plain")
+            .body(hyper::Body::from("Bookmark removed")),
+        Ok(false) => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(hyper::Body::from(format!(
+                "No bookmark with id {}",
+                bookmark_request.id
+            ))),
+        Err(e) => Response::builder()
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .body(hyper::Body::from(e)),
+    };
+    Ok(response.unwrap())
+}
 
-            let result = json!({
-                "matched_addresses": matched_addresses,
-                "found":count,
-                "is_rounded":is_rounded
+// Listed even without a process, each one then carrying an error instead of a value
+pub async fn bookmark_list_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = *pid_state.lock().unwrap();
+    let result = json!({ "bookmarks": bookmarks::bookmark_list(pid) });
+    let response = Response::builder()
+        .header("Content-Type", "application/json")
+        .body(hyper::Body::from(result.to_string()))
+        .unwrap();
+    Ok(response)
+}
 
-            });
-            let result_string = result.to_string();
+pub async fn watch_register_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    watch_request: request::WatchRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
+    if let Some(pid) = *pid {
+        match watch::watch_register(pid, watch_request.address, &watch_request.value_type) {
+            Ok(()) => {
+                let response = Response::builder()
+                    .header("Content-Type", "text/plain")
+                    .body(hyper::Body::from("Address watched"))
+                    .unwrap();
+                Ok(response)
+            }
+            Err(e) => {
+                let response = Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(hyper::Body::from(e))
+                    .unwrap();
+                Ok(response)
+            }
+        }
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
+
+pub async fn watch_unregister_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    unwatch_request: request::UnwatchRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
+    if let Some(pid) = *pid {
+        if watch::watch_unregister(pid, unwatch_request.address) {
             let response = Response::builder()
-                .header("Content-Type", "application/json")
-                .body(Body::from(result_string))
+                .header("Content-Type", "text/plain")
+                .body(hyper::Body::from("Address unwatched"))
                 .unwrap();
             Ok(response)
         } else {
-            let count = found_count.load(Ordering::SeqCst);
-            let result_string = json!({ "found": count }).to_string();
-            let response = Response::builder()
-                .header("Content-Type", "application/json")
-                .body(hyper::Body::from(result_string))
+            let response = Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(hyper::Body::from("Address is not watched"))
                 .unwrap();
             Ok(response)
         }
     } else {
         let response = Response::builder()
             .status(StatusCode::BAD_REQUEST)
-            .body(Body::from("Pid not set"))
+            .body(hyper::Body::from("Pid not set"))
             .unwrap();
         Ok(response)
     }
 }
 
-#[derive(Serialize)]
-struct Region {
-    start_address: String,
-    end_address: String,
-    protection: String,
-    file_path: Option<String>,
+pub async fn watch_poll_handler() -> Result<impl warp::Reply, warp::Rejection> {
+    let result = json!({ "updates": watch::watch_poll() });
+    let response = Response::builder()
+        .header("Content-Type", "application/json")
+        .body(hyper::Body::from(result.to_string()))
+        .unwrap();
+    Ok(response)
 }
 
-pub async fn enumerate_regions_handler(
+pub async fn dump_regions_handler(
     pid_state: Arc<Mutex<Option<i32>>>,
+    dump_request: request::DumpRequest,
 ) -> Result<impl warp::Reply, warp::Rejection> {
     let pid = pid_state.lock().unwrap();
-
     if let Some(pid) = *pid {
-        let mut buffer = vec![0u8; 1024 * 1024];
-
-        unsafe {
-            native_bridge::enumerate_regions_to_buffer(pid, buffer.as_mut_ptr(), buffer.len());
-        }
-        let buffer_cstring = unsafe { CString::from_vec_unchecked(buffer) };
-        let buffer_string = buffer_cstring.into_string().unwrap();
-        let buffer_reader = BufReader::new(buffer_string.as_bytes());
-
-        let mut regions = Vec::new();
-
-        for line in buffer_reader.lines() {
-            if let Ok(line) = line {
-                let parts: Vec<&str> = line.split_whitespace().collect();
-
-                if parts.len() >= 5 {
-                    let addresses: Vec<&str> = parts[0].split('-').collect();
-                    if addresses.len() == 2 {
-                        let region = Region {
-                            start_address: addresses[0].to_string(),
-                            end_address: addresses[1].to_string(),
-                            protection: parts[1].to_string(),
-                            file_path: if parts.len() > 5 {
-                                Some(parts[5..].join(" "))
-                            } else {
-                                None
-                            },
-                        };
-                        regions.push(region);
-                    }
-                }
+        let regions = match dump_request.regions {
+            Some(regions) => Ok(regions),
+            None => scan::protected_regions(pid, &scan::MemoryProtectionFilter::default()),
+        };
+        let result = util::check_file_path(&dump_request.path).and_then(|path| {
+            regions.and_then(|regions| dump::dump_regions(pid, &regions, Path::new(&path)))
+        });
+        match result {
+            Ok(summary) => {
+                let result = json!(summary);
+                let response = Response::builder()
+                    .header("Content-Type", "application/json")
+                    .body(hyper::Body::from(result.to_string()))
+                    .unwrap();
+                Ok(response)
+            }
+            Err(e) => {
+                let response = Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(hyper::Body::from(e))
+                    .unwrap();
+                Ok(response)
             }
         }
-
-        let result = json!({ "regions": regions });
-        let result_string = result.to_string();
-        let response = Response::builder()
-            .header("Content-Type", "application/json")
-            .body(hyper::Body::from(result_string))
-            .unwrap();
-        Ok(response)
     } else {
         let response = Response::builder()
             .status(StatusCode::BAD_REQUEST)
@@ -1355,70 +3828,51 @@ pub async fn enumerate_regions_handler(
     }
 }
 
-pub async fn enumerate_process_handler() -> Result<impl Reply, Rejection> {
-    let mut count: usize = 0;
-    let process_info_ptr = unsafe { native_bridge::enumprocess_native(&mut count) };
-    let process_info_slice = unsafe { std::slice::from_raw_parts(process_info_ptr, count) };
-
-    let mut json_array = Vec::new();
-    for i in 0..count {
-        let process_name = unsafe {
-            CStr::from_ptr(process_info_slice[i].processname)
-                .to_string_lossy()
-                .into_owned()
-        };
-        json_array.push(json!({
-            "pid": process_info_slice[i].pid,
-            "processname": process_name
-        }));
-        unsafe { libc::free(process_info_slice[i].processname as *mut libc::c_void) };
-    }
-
-    // for cdylib
-    if count == 0 {
-        let pid = unsafe { native_bridge::get_pid_native() };
-        json_array.push(json!({
-            "pid": pid,
-            "processname": "self".to_string()
-        }));
-    } else {
-        unsafe {
-            libc::free(process_info_ptr as *mut libc::c_void);
-        }
-    }
-
-    let json_response = warp::reply::json(&json_array);
-    Ok(json_response)
-}
-
-pub async fn enummodule_handler(
-    pid_state: Arc<Mutex<Option<i32>>>,
+pub async fn dump_read_handler(
+    dump_read_request: request::DumpReadRequest,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let pid = pid_state.lock().unwrap();
-    if let Some(pid) = *pid {
-        let modules = native_bridge::enum_modules(pid).unwrap();
-        let result = json!({ "modules": modules });
-        let result_string = result.to_string();
-        let response = Response::builder()
-            .header("Content-Type", "application/json")
-            .body(hyper::Body::from(result_string))
-            .unwrap();
-        Ok(response)
-    } else {
-        let response = Response::builder()
-            .status(StatusCode::BAD_REQUEST)
-            .body(hyper::Body::from("Pid not set"))
-            .unwrap();
-        Ok(response)
+    let result = util::check_file_path(&dump_read_request.path).and_then(|path| {
+        dump::dump_read(
+            Path::new(&path),
+            dump_read_request.address,
+            dump_read_request.size,
+        )
+    });
+    match result {
+        Ok(data) => {
+            let response = Response::builder()
+                .header("Content-Type", "application/octet-stream")
+                .body(hyper::Body::from(data))
+                .unwrap();
+            Ok(response)
+        }
+        Err(e) => {
+            let response = Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(hyper::Body::from(e))
+                .unwrap();
+            Ok(response)
+        }
     }
 }
 
 pub async fn explore_directory_handler(
     req: request::ExploreDirectoryRequest,
 ) -> Result<impl Reply, Rejection> {
-    let decoded_path = percent_decode_str(&req.path)
-        .decode_utf8_lossy()
-        .into_owned();
+    let decoded_path =
+        match util::decode_path_param(&req.path).and_then(|path| util::check_file_path(&path)) {
+            Ok(path) => path,
+            Err(e) => {
+                return Ok(warp::reply::with_status(
+                    warp::reply::json(&json!({
+                        "error": e,
+                        "path": req.path,
+                        "max_depth": req.max_depth
+                    })),
+                    warp::http::StatusCode::BAD_REQUEST,
+                ))
+            }
+        };
 
     let c_path = match CString::new(decoded_path.clone()) {
         Ok(path) => path,
@@ -1479,11 +3933,21 @@ pub async fn explore_directory_handler(
         ));
     }
 
-    match panic::catch_unwind(|| util::parse_directory_structure(&result)) {
-        Ok(items) => Ok(warp::reply::with_status(
-            warp::reply::json(&items),
-            warp::http::StatusCode::OK,
-        )),
+    match panic::catch_unwind(|| {
+        util::parse_directory_structure(
+            &result,
+            req.indent_width.unwrap_or(util::DEFAULT_INDENT_WIDTH),
+        )
+    }) {
+        Ok(mut items) => {
+            if let Some(sort_by) = req.sort_by {
+                util::sort_file_items(&mut items, sort_by, req.sort_order.unwrap_or_default());
+            }
+            Ok(warp::reply::with_status(
+                warp::reply::json(&items),
+                warp::http::StatusCode::OK,
+            ))
+        }
         Err(_) => Ok(warp::reply::with_status(
             warp::reply::json(&json!({
                 "error": "Process panicked during parsing of directory structure",
@@ -1496,40 +3960,53 @@ pub async fn explore_directory_handler(
 }
 
 pub async fn read_file_handler(req: request::ReadFileRequest) -> Result<Response<Body>, Rejection> {
-    let decoded_path = percent_decode_str(&req.path)
-        .decode_utf8_lossy()
-        .into_owned();
-
-    let c_path = CString::new(decoded_path.clone()).unwrap();
-    let mut size: usize = 0;
-    let mut error_ptr: *mut c_char = std::ptr::null_mut();
-
-    let data_ptr = unsafe {
-        native_bridge::read_file(
-            c_path.as_ptr(),
-            &mut size as *mut usize,
-            &mut error_ptr as *mut *mut c_char,
-        )
-    };
+    let decoded_path =
+        match util::decode_path_param(&req.path).and_then(|path| util::check_file_path(&path)) {
+            Ok(path) => path,
+            Err(e) => {
+                return Ok(Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(Body::from(e))
+                    .unwrap())
+            }
+        };
 
-    if !error_ptr.is_null() {
-        let error_message = unsafe { CStr::from_ptr(error_ptr).to_string_lossy().into_owned() };
-        unsafe { libc::free(error_ptr as *mut c_void) };
-        return Ok(Response::builder()
-            .status(StatusCode::INTERNAL_SERVER_ERROR)
-            .body(Body::from(error_message))
-            .unwrap());
-    }
+    // A length selects one chunk of the file; without it the whole file is returned
+    let result = match req.length {
+        Some(length) => util::read_file_chunk(&decoded_path, req.offset.unwrap_or(0), length),
+        None => util::read_file(&decoded_path),
+    };
+    let data = match result {
+        Ok(data) => data,
+        Err(e) => {
+            return Ok(Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Body::from(e))
+                .unwrap())
+        }
+    };
 
-    if data_ptr.is_null() || size == 0 {
+    if data.is_empty() && req.length.is_none() {
         return Ok(Response::builder()
             .status(StatusCode::NOT_FOUND)
             .body(Body::from("File not found or empty"))
             .unwrap());
     }
 
-    let data = unsafe { slice::from_raw_parts(data_ptr as *const u8, size) }.to_vec();
-    unsafe { libc::free(data_ptr as *mut c_void) };
+    if req.compress.unwrap_or(false) {
+        return Ok(match compression::compress_framed(&data) {
+            Ok(framed) => Response::builder()
+                .header("Content-Type", "application/octet-stream")
+                .header("X-Compression", "lz4")
+                .body(Body::from(framed))
+                .unwrap(),
+            // Larger files are compressed a chunk at a time
+            Err(e) => Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(Body::from(e))
+                .unwrap(),
+        });
+    }
 
     Ok(Response::builder()
         .header("Content-Type", "application/octet-stream")
@@ -1537,6 +4014,32 @@ pub async fn read_file_handler(req: request::ReadFileRequest) -> Result<Response
         .unwrap())
 }
 
+pub async fn write_file_handler(
+    req: request::WriteFileRequest,
+    data: hyper::body::Bytes,
+) -> Result<Response<Body>, Rejection> {
+    let result = util::decode_path_param(&req.path)
+        .and_then(|path| util::check_file_path(&path))
+        .and_then(|path| {
+            let data = if req.compressed.unwrap_or(false) {
+                compression::decompress_framed(&data)?
+            } else {
+                data.to_vec()
+            };
+            util::write_file(&path, &data, req.append.unwrap_or(false))
+        });
+    match result {
+        Ok(()) => Ok(Response::builder()
+            .header("Content-Type", "text/plain")
+            .body(Body::from("File successfully written"))
+            .unwrap()),
+        Err(e) => Ok(Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(Body::from(e))
+            .unwrap()),
+    }
+}
+
 pub async fn get_app_info_handler(
     pid_state: Arc<Mutex<Option<i32>>>,
 ) -> Result<impl warp::Reply, warp::Rejection> {
@@ -1584,21 +4087,30 @@ pub async fn set_watchpoint_handler(
     let pid = pid_state.lock().unwrap();
 
     if let Some(pid) = *pid {
-        let _type = match watchpoint._type.as_str() {
-            "r" => 1,
-            "w" => 2,
-            "a" => 3,
-            _ => {
+        let on_access = match native_bridge::Access::parse(&watchpoint._type) {
+            Ok(on_access) => on_access,
+            Err(e) => {
                 return Ok(warp::reply::with_status(
                     warp::reply::json(&request::SetWatchPointResponse {
                         success: false,
-                        message: format!("Unknown type"),
+                        message: e,
                     }),
                     StatusCode::BAD_REQUEST,
                 ))
             }
         };
-        let result = native_bridge::set_watchpoint(pid, watchpoint.address, watchpoint.size, _type);
+        let (address, size) = (watchpoint.address, watchpoint.size);
+        let result = deadline::run_with_deadline_or_undo(
+            deadline::OperationClass::Breakpoint,
+            format!("Setting a watchpoint at {:#x} in pid {}", address, pid),
+            move || native_bridge::set_watchpoint(pid, address, size, on_access),
+            move |result| {
+                if result.is_ok() {
+                    let _ = native_bridge::remove_watchpoint(address);
+                }
+            },
+        )
+        .unwrap_or_else(|e| Err(e.into()));
 
         let ret = match result {
             Ok(_) => Ok(warp::reply::with_status(
@@ -1613,7 +4125,13 @@ pub async fn set_watchpoint_handler(
                     success: false,
                     message: format!("Failed to set watchpoint. Error: {}", e),
                 }),
-                StatusCode::INTERNAL_SERVER_ERROR,
+                if e.kind() == std::io::ErrorKind::InvalidInput {
+                    StatusCode::BAD_REQUEST
+                } else if e.kind() == std::io::ErrorKind::TimedOut {
+                    StatusCode::GATEWAY_TIMEOUT
+                } else {
+                    StatusCode::INTERNAL_SERVER_ERROR
+                },
             )),
         };
         return ret;
@@ -1635,7 +4153,13 @@ pub async fn remove_watchpoint_handler(
     let pid = pid_state.lock().unwrap();
 
     if let Some(_pid) = *pid {
-        let result = native_bridge::remove_watchpoint(watchpoint.address);
+        let address = watchpoint.address;
+        let result = deadline::run_with_deadline(
+            deadline::OperationClass::Breakpoint,
+            format!("Removing the watchpoint at {:#x}", address),
+            move || native_bridge::remove_watchpoint(address),
+        )
+        .unwrap_or_else(|e| Err(e.into()));
 
         let ret = match result {
             Ok(_) => Ok(warp::reply::with_status(
@@ -1650,7 +4174,11 @@ pub async fn remove_watchpoint_handler(
                     success: false,
                     message: format!("Failed to remove watchpoint. Error: {}", e),
                 }),
-                StatusCode::INTERNAL_SERVER_ERROR,
+                if e.kind() == std::io::ErrorKind::TimedOut {
+                    StatusCode::GATEWAY_TIMEOUT
+                } else {
+                    StatusCode::INTERNAL_SERVER_ERROR
+                },
             )),
         };
         return ret;
@@ -1665,28 +4193,139 @@ pub async fn remove_watchpoint_handler(
     }
 }
 
+pub async fn access_start_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    access_request: request::AccessWatchRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pid = pid_state.lock().unwrap();
+    if let Some(pid) = *pid {
+        let (address, size) = (access_request.address, access_request.size);
+        let result = native_bridge::Access::parse(access_request.access.as_deref().unwrap_or("r"))
+            .and_then(|on_access| {
+                deadline::run_with_deadline_or_undo(
+                    deadline::OperationClass::Breakpoint,
+                    format!("Setting a watchpoint at {:#x} in pid {}", address, pid),
+                    move || access::access_start(pid, address, size, on_access),
+                    move |result| {
+                        if result.is_ok() {
+                            let _ = access::access_stop(address, 0);
+                        }
+                    },
+                )
+                .unwrap_or_else(|e| Err(e.to_string()))
+            });
+        match result {
+            Ok(()) => {
+                let result = json!({ "address": address, "size": size });
+                let response = Response::builder()
+                    .header("Content-Type", "application/json")
+                    .body(hyper::Body::from(result.to_string()))
+                    .unwrap();
+                Ok(response)
+            }
+            Err(e) => {
+                let response = Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(hyper::Body::from(e))
+                    .unwrap();
+                Ok(response)
+            }
+        }
+    } else {
+        let response = Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(hyper::Body::from("Pid not set"))
+            .unwrap();
+        Ok(response)
+    }
+}
+
+fn access_report_response(report: Result<access::AccessReport, String>) -> Response<hyper::Body> {
+    match report {
+        Ok(report) => Response::builder()
+            .header("Content-Type", "application/json")
+            .body(hyper::Body::from(json!(report).to_string()))
+            .unwrap(),
+        Err(e) => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(hyper::Body::from(e))
+            .unwrap(),
+    }
+}
+
+pub async fn access_report_handler(
+    report_request: request::AccessReportRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let top = report_request.top.unwrap_or(access::DEFAULT_TOP_HITS);
+    Ok(access_report_response(access::access_report(
+        report_request.address,
+        top,
+    )))
+}
+
+pub async fn access_stop_handler(
+    report_request: request::AccessReportRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let top = report_request.top.unwrap_or(access::DEFAULT_TOP_HITS);
+    Ok(access_report_response(access::access_stop(
+        report_request.address,
+        top,
+    )))
+}
+
 pub async fn set_breakpoint_handler(
     pid_state: Arc<Mutex<Option<i32>>>,
     breakpoint: request::SetBreakPointRequest,
 ) -> Result<impl warp::Reply, warp::Rejection> {
     let pid = pid_state.lock().unwrap();
     if let Some(pid) = *pid {
-        let result = native_bridge::set_breakpoint(pid, breakpoint.address, breakpoint.hit_count);
+        let (address, halt, hit_count) =
+            (breakpoint.address, breakpoint.halt, breakpoint.hit_count);
+        let result = deadline::run_with_deadline_or_undo(
+            deadline::OperationClass::Breakpoint,
+            format!("Setting a breakpoint at {:#x} in pid {}", address, pid),
+            move || {
+                if breakpoint::software_breakpoints_supported() {
+                    breakpoint::breakpoint_set(pid, address as u64, halt)
+                } else {
+                    native_bridge::set_breakpoint(pid, address, hit_count)
+                        .map(|_| ())
+                        .map_err(|e| e.to_string())
+                }
+            },
+            move |result| {
+                if result.is_err() {
+                    return;
+                }
+                if breakpoint::software_breakpoints_supported() {
+                    let _ = breakpoint::breakpoint_remove(pid, address as u64);
+                } else {
+                    let _ = native_bridge::remove_breakpoint(address);
+                }
+            },
+        );
         let ret = match result {
-            Ok(_) => Ok(warp::reply::with_status(
+            Ok(Ok(_)) => Ok(warp::reply::with_status(
                 warp::reply::json(&request::SetBreakPointResponse {
                     success: true,
                     message: "Breakpoint set successfully".to_string(),
                 }),
                 StatusCode::OK,
             )),
-            Err(e) => Ok(warp::reply::with_status(
+            Ok(Err(e)) => Ok(warp::reply::with_status(
                 warp::reply::json(&request::SetBreakPointResponse {
                     success: false,
                     message: format!("Failed to set breakpoint. Error: {}", e),
                 }),
                 StatusCode::INTERNAL_SERVER_ERROR,
             )),
+            Err(e) => Ok(warp::reply::with_status(
+                warp::reply::json(&request::SetBreakPointResponse {
+                    success: false,
+                    message: e.to_string(),
+                }),
+                deadline_status(&e),
+            )),
         };
         return ret;
     } else {
@@ -1705,23 +4344,43 @@ pub async fn remove_breakpoint_handler(
     breakpoint: request::RemoveBreakPointRequest,
 ) -> Result<impl warp::Reply, warp::Rejection> {
     let pid = pid_state.lock().unwrap();
-    if let Some(_pid) = *pid {
-        let result = native_bridge::remove_breakpoint(breakpoint.address);
+    if let Some(pid) = *pid {
+        let address = breakpoint.address;
+        let result = deadline::run_with_deadline(
+            deadline::OperationClass::Breakpoint,
+            format!("Removing the breakpoint at {:#x}", address),
+            move || {
+                if breakpoint::software_breakpoints_supported() {
+                    breakpoint::breakpoint_remove(pid, address as u64)
+                } else {
+                    native_bridge::remove_breakpoint(address)
+                        .map(|_| ())
+                        .map_err(|e| e.to_string())
+                }
+            },
+        );
         let ret = match result {
-            Ok(_) => Ok(warp::reply::with_status(
+            Ok(Ok(_)) => Ok(warp::reply::with_status(
                 warp::reply::json(&request::RemoveBreakPointResponse {
                     success: true,
                     message: "Breakpoint removed successfully".to_string(),
                 }),
                 StatusCode::OK,
             )),
-            Err(e) => Ok(warp::reply::with_status(
+            Ok(Err(e)) => Ok(warp::reply::with_status(
                 warp::reply::json(&request::RemoveBreakPointResponse {
                     success: false,
                     message: format!("Failed to remove breakpoint. Error: {}", e),
                 }),
                 StatusCode::INTERNAL_SERVER_ERROR,
             )),
+            Err(e) => Ok(warp::reply::with_status(
+                warp::reply::json(&request::RemoveBreakPointResponse {
+                    success: false,
+                    message: e.to_string(),
+                }),
+                deadline_status(&e),
+            )),
         };
         return ret;
     } else {
@@ -1735,6 +4394,10 @@ pub async fn remove_breakpoint_handler(
     }
 }
 
+pub async fn list_breakpoints_handler() -> Result<impl warp::Reply, warp::Rejection> {
+    Ok(warp::reply::json(&breakpoint::breakpoint_list()))
+}
+
 pub async fn change_process_state_handler(
     pid_state: Arc<Mutex<Option<i32>>>,
     state_request: request::ChangeProcessStateRequest,
@@ -1742,14 +4405,36 @@ pub async fn change_process_state_handler(
     let pid = pid_state.lock().unwrap();
 
     if let Some(_pid) = *pid {
-        let result = if state_request.do_play {
-            unsafe { native_bridge::resume_process(_pid) }
-        } else {
-            unsafe { native_bridge::suspend_process(_pid) }
-        };
+        let (pid, do_play) = (_pid, state_request.do_play);
+        // A suspend that lands after the deadline is resumed again, since the client was told
+        // it failed; a late resume is left alone
+        let result = deadline::run_with_deadline_or_undo(
+            deadline::OperationClass::Suspend,
+            format!(
+                "{} pid {}",
+                if do_play { "Resuming" } else { "Suspending" },
+                pid
+            ),
+            move || {
+                let changed = if do_play {
+                    unsafe { native_bridge::resume_process(pid) }
+                } else {
+                    unsafe { native_bridge::suspend_process(pid) }
+                };
+                if changed {
+                    shutdown::track_suspended(pid, !do_play);
+                }
+                changed
+            },
+            move |changed| {
+                if changed && !do_play && unsafe { native_bridge::resume_process(pid) } {
+                    shutdown::track_suspended(pid, false);
+                }
+            },
+        );
 
         let ret = match result {
-            true => Ok(warp::reply::with_status(
+            Ok(true) => Ok(warp::reply::with_status(
                 warp::reply::json(&request::ChangeProcessStateResponse {
                     success: true,
                     message: format!(
@@ -1763,13 +4448,20 @@ pub async fn change_process_state_handler(
                 }),
                 StatusCode::OK,
             )),
-            false => Ok(warp::reply::with_status(
+            Ok(false) => Ok(warp::reply::with_status(
                 warp::reply::json(&request::ChangeProcessStateResponse {
                     success: false,
                     message: format!("Failed to change process state. Error"),
                 }),
                 StatusCode::INTERNAL_SERVER_ERROR,
             )),
+            Err(e) => Ok(warp::reply::with_status(
+                warp::reply::json(&request::ChangeProcessStateResponse {
+                    success: false,
+                    message: e.to_string(),
+                }),
+                deadline_status(&e),
+            )),
         };
         return ret;
     } else {
@@ -1817,3 +4509,178 @@ pub async fn pointermap_generate_handler(
         Ok(response)
     }
 }
+
+// Calls a batch may make. Only reads are offered, so a batch never gets around the write
+// rate limit.
+const BATCH_METHODS: &[&str] = &[
+    "memory",
+    "value",
+    "string",
+    "resolveaddr",
+    "symbol",
+    "exports",
+    "guesstype",
+    "hashregion",
+    "disassemble",
+    "disassemblefunction",
+    "modules",
+    "regions",
+];
+
+fn batch_params<T: serde::de::DeserializeOwned>(params: Value) -> Result<T, String> {
+    serde_json::from_value(params).map_err(|e| format!("Invalid params: {}", e))
+}
+
+// Runs the handler behind one batch call and returns its response unchanged
+async fn batch_dispatch(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    call: request::BatchCall,
+) -> Result<warp::reply::Response, String> {
+    let params = call.params;
+    let reply = match call.method.as_str() {
+        "memory" => read_memory_handler(pid_state, batch_params(params)?)
+            .await
+            .map(Reply::into_response),
+        "value" => read_value_handler(pid_state, batch_params(params)?)
+            .await
+            .map(Reply::into_response),
+        "string" => read_string_handler(pid_state, batch_params(params)?)
+            .await
+            .map(Reply::into_response),
+        "resolveaddr" => resolve_addr_handler(pid_state, batch_params(params)?)
+            .await
+            .map(Reply::into_response),
+        "symbol" => symbolicate_handler(pid_state, batch_params(params)?)
+            .await
+            .map(Reply::into_response),
+        "exports" => module_exports_handler(pid_state, batch_params(params)?)
+            .await
+            .map(Reply::into_response),
+        "guesstype" => guess_type_handler(pid_state, batch_params(params)?)
+            .await
+            .map(Reply::into_response),
+        "hashregion" => hash_region_handler(pid_state, batch_params(params)?)
+            .await
+            .map(Reply::into_response),
+        "disassemble" => disassemble_handler(pid_state, batch_params(params)?)
+            .await
+            .map(Reply::into_response),
+        "disassemblefunction" => disassemble_function_handler(pid_state, batch_params(params)?)
+            .await
+            .map(Reply::into_response),
+        "modules" => enummodule_handler(pid_state)
+            .await
+            .map(Reply::into_response),
+        "regions" => enumerate_regions_handler(pid_state)
+            .await
+            .map(Reply::into_response),
+        method => {
+            return Err(format!(
+                "Unknown batch method '{}': expected one of {}",
+                method,
+                BATCH_METHODS.join(", ")
+            ))
+        }
+    };
+    reply.map_err(|rejection| format!("Request rejected: {:?}", rejection))
+}
+
+async fn batch_call(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    call: request::BatchCall,
+) -> request::BatchResult {
+    let response = match batch_dispatch(pid_state, call).await {
+        Ok(response) => response,
+        Err(e) => {
+            return request::BatchResult {
+                status: StatusCode::BAD_REQUEST.as_u16(),
+                result: None,
+                error: Some(e),
+            }
+        }
+    };
+    let status = response.status();
+    let content_type = response
+        .headers()
+        .get("Content-Type")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+    let body = match hyper::body::to_bytes(response.into_body()).await {
+        Ok(body) => body,
+        Err(e) => {
+            return request::BatchResult {
+                status: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                result: None,
+                error: Some(format!("Failed to read response: {}", e)),
+            }
+        }
+    };
+    if !status.is_success() {
+        return request::BatchResult {
+            status: status.as_u16(),
+            result: None,
+            error: Some(String::from_utf8_lossy(&body).into_owned()),
+        };
+    }
+    let result = if content_type.starts_with("application/json") {
+        serde_json::from_slice(&body).unwrap_or_else(|_| json!(hex::encode(&body)))
+    } else if content_type.starts_with("text/") {
+        json!(String::from_utf8_lossy(&body))
+    } else {
+        json!(hex::encode(&body))
+    };
+    request::BatchResult {
+        status: status.as_u16(),
+        result: Some(result),
+        error: None,
+    }
+}
+
+// Runs the calls one after another and answers them in order. A failed call fills its own
+// slot with the error and the rest still run.
+pub async fn batch_handler(
+    pid_state: Arc<Mutex<Option<i32>>>,
+    calls: Vec<request::BatchCall>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut results = Vec::with_capacity(calls.len());
+    for call in calls {
+        results.push(batch_call(pid_state.clone(), call).await);
+    }
+    let response = Response::builder()
+        .header("Content-Type", "application/json")
+        .body(hyper::Body::from(json!(results).to_string()))
+        .unwrap();
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker(finished: Option<Instant>) -> ScanTracker {
+        ScanTracker {
+            scanned: Arc::new(AtomicUsize::new(0)),
+            total: 0,
+            cancel: Arc::new(AtomicBool::new(false)),
+            finished,
+        }
+    }
+
+    #[test]
+    fn finished_trackers_expire_after_the_ttl() {
+        let now = Instant::now();
+        let mut trackers = HashMap::new();
+        trackers.insert("running".to_string(), tracker(None));
+        trackers.insert("recent".to_string(), tracker(Some(now)));
+        trackers.insert("stale".to_string(), tracker(Some(now)));
+
+        expire_scan_trackers(&mut trackers, now + SCAN_TRACKER_TTL / 2);
+        assert_eq!(trackers.len(), 3);
+        trackers.get_mut("recent").unwrap().finished = Some(now + SCAN_TRACKER_TTL / 2);
+        expire_scan_trackers(&mut trackers, now + SCAN_TRACKER_TTL);
+        let mut left: Vec<&String> = trackers.keys().collect();
+        left.sort();
+        assert_eq!(left, ["recent", "running"]);
+    }
+}
@@ -0,0 +1,175 @@
+use crate::util::Arch;
+
+// Hand-rolled encoder for the handful of arm64 instructions needed when patching:
+// nop, b, bl, ret and mov with an immediate. One instruction per line or separated by ';'.
+pub fn assemble(source: &str, address: u64, arch: Arch) -> Result<Vec<u8>, String> {
+    if arch != Arch::Arm64 {
+        return Err(format!("Assembling is not supported for {:?}", arch));
+    }
+
+    let mut bytes = Vec::new();
+    let mut pc = address;
+    for line in source.split(['\n', ';']) {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let word = encode_arm64(line, pc).map_err(|e| format!("{} in '{}'", e, line))?;
+        bytes.extend_from_slice(&word.to_le_bytes());
+        pc = pc.wrapping_add(4);
+    }
+    Ok(bytes)
+}
+
+fn encode_arm64(line: &str, pc: u64) -> Result<u32, String> {
+    let (mnemonic, operands) = match line.split_once(char::is_whitespace) {
+        Some((mnemonic, operands)) => (mnemonic, operands.trim()),
+        None => (line, ""),
+    };
+    let operands: Vec<&str> = if operands.is_empty() {
+        Vec::new()
+    } else {
+        operands.split(',').map(str::trim).collect()
+    };
+
+    match (mnemonic.to_lowercase().as_str(), operands.as_slice()) {
+        ("nop", []) => Ok(0xD503_201F),
+        ("ret", []) => Ok(0xD65F_03C0),
+        ("ret", [reg]) => {
+            let (rn, _) = parse_register(reg)?;
+            Ok(0xD65F_0000 | (rn << 5))
+        }
+        ("b", [target]) => Ok(0x1400_0000 | branch_offset(pc, parse_immediate(target)?)?),
+        ("bl", [target]) => Ok(0x9400_0000 | branch_offset(pc, parse_immediate(target)?)?),
+        ("mov", [reg, imm]) => {
+            let (rd, is_64bit) = parse_register(reg)?;
+            encode_mov_immediate(rd, is_64bit, parse_immediate(imm)?)
+        }
+        (mnemonic, _) => Err(format!(
+            "Unsupported instruction '{}' with {} operand(s)",
+            mnemonic,
+            operands.len()
+        )),
+    }
+}
+
+// Returns the register number and whether it is a 64-bit x register
+fn parse_register(reg: &str) -> Result<(u32, bool), String> {
+    let reg = reg.to_lowercase();
+    match reg.as_str() {
+        "xzr" => return Ok((31, true)),
+        "wzr" => return Ok((31, false)),
+        "lr" => return Ok((30, true)),
+        _ => {}
+    }
+    let is_64bit = match reg.chars().next() {
+        Some('x') => true,
+        Some('w') => false,
+        _ => return Err(format!("Invalid register '{}'", reg)),
+    };
+    match reg[1..].parse::<u32>() {
+        Ok(number) if number <= 30 => Ok((number, is_64bit)),
+        _ => Err(format!("Invalid register '{}'", reg)),
+    }
+}
+
+// Accepts "#0x10", "0x10", "#16", "#-1" in the same forms the disassembler prints
+fn parse_immediate(imm: &str) -> Result<i64, String> {
+    let text = imm.trim().trim_start_matches('#');
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let value = match digits.strip_prefix("0x") {
+        Some(hex_digits) => u64::from_str_radix(hex_digits, 16),
+        None => digits.parse::<u64>(),
+    }
+    .map_err(|_| format!("Invalid immediate '{}'", imm))? as i64;
+    Ok(if negative {
+        value.wrapping_neg()
+    } else {
+        value
+    })
+}
+
+fn branch_offset(pc: u64, target: i64) -> Result<u32, String> {
+    let delta = (target as u64).wrapping_sub(pc) as i64;
+    if delta % 4 != 0 {
+        return Err(format!("Branch target {:#x} is not 4-byte aligned", target));
+    }
+    let words = delta / 4;
+    if !(-(1 << 25)..(1 << 25)).contains(&words) {
+        return Err(format!("Branch target {:#x} is out of range", target));
+    }
+    Ok((words as u32) & 0x03FF_FFFF)
+}
+
+// Uses MOVZ when the value is a single 16-bit chunk, otherwise MOVN for its inverse
+fn encode_mov_immediate(rd: u32, is_64bit: bool, value: i64) -> Result<u32, String> {
+    let (width, movz, movn) = if is_64bit {
+        (64, 0xD280_0000u32, 0x9280_0000u32)
+    } else {
+        (32, 0x5280_0000u32, 0x1280_0000u32)
+    };
+    if !is_64bit && !(i32::MIN as i64..=u32::MAX as i64).contains(&value) {
+        return Err(format!(
+            "Immediate {:#x} does not fit in a 32-bit register",
+            value
+        ));
+    }
+    let mask = if is_64bit { u64::MAX } else { u32::MAX as u64 };
+    let value = value as u64 & mask;
+
+    for (base, candidate) in [(movz, value), (movn, !value & mask)] {
+        for hw in 0..width / 16 {
+            let shift = hw * 16;
+            if candidate & !(0xFFFF << shift) == 0 {
+                let imm16 = ((candidate >> shift) & 0xFFFF) as u32;
+                return Ok(base | ((hw as u32) << 21) | (imm16 << 5) | rd);
+            }
+        }
+    }
+    Err(format!(
+        "Immediate {:#x} cannot be encoded in a single mov",
+        value
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{self, AsmSyntax};
+
+    #[test]
+    fn assembled_code_disassembles_to_the_source() {
+        let source =
+            "nop; b #0x1100; bl #0x1008; mov x0, #0x10; mov w1, #-1; mov x2, #0x10000; ret";
+        let bytes = assemble(source, 0x1000, Arch::Arm64).unwrap();
+        assert_eq!(bytes.len(), 7 * 4);
+        let instructions =
+            util::disassemble_structured(&bytes, 0x1000, Arch::Arm64, AsmSyntax::default())
+                .unwrap();
+        let text: Vec<String> = instructions
+            .iter()
+            .map(|i| format!("{} {}", i.mnemonic, i.op_str).trim().to_string())
+            .collect();
+        assert_eq!(text.join("; "), source);
+
+        // Each instruction's text assembles back to the bytes it came from
+        for instruction in &instructions {
+            let line = format!("{} {}", instruction.mnemonic, instruction.op_str);
+            assert_eq!(
+                assemble(&line, instruction.address, Arch::Arm64).unwrap(),
+                instruction.bytes
+            );
+        }
+    }
+
+    #[test]
+    fn unsupported_input_is_an_error() {
+        assert!(assemble("nop", 0, Arch::X86_64).is_err());
+        assert!(assemble("add x0, x1, x2", 0, Arch::Arm64).is_err());
+        assert!(assemble("b #0x1002", 0x1000, Arch::Arm64).is_err());
+        assert!(assemble("mov x0, #0x12345", 0, Arch::Arm64).is_err());
+    }
+}
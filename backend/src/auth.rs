@@ -0,0 +1,136 @@
+use std::sync::Arc;
+use warp::http::StatusCode;
+use warp::reject::Reject;
+use warp::{Filter, Rejection, Reply};
+
+pub const TOKEN_ENV_VAR: &str = "MEMSERVER_TOKEN";
+
+#[derive(Debug)]
+struct Unauthorized;
+
+impl Reject for Unauthorized {}
+
+// Compares every byte so the time taken does not reveal how much of the token matched
+fn token_matches(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected
+            .bytes()
+            .zip(given.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+// Passes requests carrying "Authorization: Bearer <token>" and rejects the rest; without a
+// configured token every request passes
+pub fn with_auth(token: Option<String>) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    let token = token.map(Arc::new);
+    warp::header::optional::<String>("authorization")
+        .and_then(move |header: Option<String>| {
+            let token = token.clone();
+            async move {
+                let Some(token) = token else {
+                    return Ok(());
+                };
+                match header
+                    .as_deref()
+                    .and_then(|value| value.strip_prefix("Bearer "))
+                {
+                    Some(given) if token_matches(&token, given.trim()) => Ok(()),
+                    _ => Err(warp::reject::custom(Unauthorized)),
+                }
+            }
+        })
+        .untuple_one()
+}
+
+// Turns a failed token check into 401; every other rejection keeps warp's default handling
+pub async fn handle_rejection(err: Rejection) -> Result<impl Reply, Rejection> {
+    if err.find::<Unauthorized>().is_some() {
+        Ok(warp::reply::with_header(
+            warp::reply::with_status("Unauthorized", StatusCode::UNAUTHORIZED),
+            "WWW-Authenticate",
+            "Bearer",
+        ))
+    } else {
+        Err(err)
+    }
+}
+
+pub fn warn_if_open(token: &Option<String>) {
+    if token.is_none() {
+        log::warn!(
+            "No auth token configured: anyone who can reach this server can read and write process memory. Set {} or pass --token to require one.",
+            TOKEN_ENV_VAR
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn status_with(token: Option<&str>, authorization: Option<&str>) -> StatusCode {
+        let route = with_auth(token.map(str::to_string))
+            .map(warp::reply)
+            .recover(handle_rejection);
+        let mut request = warp::test::request().path("/api/read");
+        if let Some(value) = authorization {
+            request = request.header("authorization", value);
+        }
+        request.reply(&route).await.status()
+    }
+
+    #[tokio::test]
+    async fn matching_bearer_token_is_allowed() {
+        let token = Some("s3cret");
+        assert_eq!(
+            status_with(token, Some("Bearer s3cret")).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status_with(token, Some("Bearer s3cret ")).await,
+            StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_or_wrong_tokens_get_401() {
+        let token = Some("s3cret");
+        for authorization in [
+            None,
+            Some("Bearer wrong!"),
+            Some("Bearer s3cre"),
+            Some("Bearer "),
+            Some("Basic s3cret"),
+            Some("s3cret"),
+        ] {
+            assert_eq!(
+                status_with(token, authorization).await,
+                StatusCode::UNAUTHORIZED,
+                "{:?}",
+                authorization
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn rejections_carry_the_bearer_challenge() {
+        let reply = handle_rejection(warp::reject::custom(Unauthorized))
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(reply.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(reply.headers()["WWW-Authenticate"], "Bearer");
+        // Rejections from other filters are left to warp
+        assert!(handle_rejection(warp::reject::not_found()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn no_configured_token_lets_everything_through() {
+        assert_eq!(status_with(None, None).await, StatusCode::OK);
+        assert_eq!(
+            status_with(None, Some("Bearer anything")).await,
+            StatusCode::OK
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use crate::native_bridge;
+use crate::util::{self, ValueType};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+// The address is kept as written, e.g. "libgame.so+0x1F00" or "[[base]+0x10]+8", and
+// resolved again on every listing so a bookmark still points at the right place after the
+// target restarts and its modules load somewhere else
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub id: u64,
+    pub address: String,
+    pub label: String,
+    pub value_type: String,
+}
+
+// A bookmark as listed: where its address resolves to now and the value there, or why
+// either could not be read
+#[derive(Debug, Serialize)]
+pub struct BookmarkValue {
+    #[serde(flatten)]
+    pub bookmark: Bookmark,
+    pub resolved: Option<u64>,
+    pub value: Value,
+    pub error: Option<String>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct BookmarkStore {
+    next_id: u64,
+    bookmarks: Vec<Bookmark>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl BookmarkStore {
+    // The whole store is rewritten through a temporary file so a crash mid-write never leaves
+    // a truncated file behind
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let contents = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize bookmarks: {}", e))?;
+        let temporary = path.with_extension("tmp");
+        // The default location is a data directory that may not exist yet
+        let parent = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty());
+        parent
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&temporary, contents))
+            .and_then(|_| fs::rename(&temporary, path))
+            .map_err(|e| format!("Failed to save bookmarks to {}: {}", path.display(), e))
+    }
+}
+
+lazy_static! {
+    static ref BOOKMARKS: Arc<Mutex<BookmarkStore>> =
+        Arc::new(Mutex::new(BookmarkStore::default()));
+}
+
+// Reads the bookmarks saved by a previous run; a missing file starts an empty store there
+pub fn load(path: PathBuf) {
+    let mut store = match fs::read_to_string(&path) {
+        Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+            log::error!(
+                "Ignoring unreadable bookmarks file {}: {}",
+                path.display(),
+                e
+            );
+            BookmarkStore::default()
+        }),
+        Err(_) => BookmarkStore::default(),
+    };
+    log::info!(
+        "Loaded {} bookmarks from {}",
+        store.bookmarks.len(),
+        path.display()
+    );
+    store.path = Some(path);
+    *BOOKMARKS.lock().unwrap() = store;
+}
+
+pub fn bookmark_add(address: &str, label: &str, value_type: &str) -> Result<Bookmark, String> {
+    if address.trim().is_empty() {
+        return Err("Bookmark address must not be empty".to_string());
+    }
+    ValueType::parse(value_type)?;
+
+    let mut store = BOOKMARKS.lock().unwrap();
+    store.next_id = store.next_id.max(1);
+    let bookmark = Bookmark {
+        id: store.next_id,
+        address: address.trim().to_string(),
+        label: label.to_string(),
+        value_type: value_type.to_string(),
+    };
+    store.next_id += 1;
+    store.bookmarks.push(bookmark.clone());
+    if let Err(e) = store.save() {
+        store.bookmarks.pop();
+        return Err(e);
+    }
+    Ok(bookmark)
+}
+
+// False when no bookmark has this id
+pub fn bookmark_remove(id: u64) -> Result<bool, String> {
+    let mut store = BOOKMARKS.lock().unwrap();
+    let Some(index) = store
+        .bookmarks
+        .iter()
+        .position(|bookmark| bookmark.id == id)
+    else {
+        return Ok(false);
+    };
+    let removed = store.bookmarks.remove(index);
+    if let Err(e) = store.save() {
+        store.bookmarks.insert(index, removed);
+        return Err(e);
+    }
+    Ok(true)
+}
+
+// Every bookmark in the order added, with its value read from pid; without a process the
+// addresses are left unresolved
+pub fn bookmark_list(pid: Option<i32>) -> Vec<BookmarkValue> {
+    // Copied out so reading the target never holds the store lock
+    let bookmarks = BOOKMARKS.lock().unwrap().bookmarks.clone();
+    let Some(pid) = pid else {
+        return bookmarks
+            .into_iter()
+            .map(|bookmark| BookmarkValue {
+                bookmark,
+                resolved: None,
+                value: Value::Null,
+                error: Some("Pid not set".to_string()),
+            })
+            .collect();
+    };
+    let modules = native_bridge::enum_modules(pid).unwrap_or_default();
+    bookmarks
+        .into_iter()
+        .map(|bookmark| {
+            let resolved = util::resolve_symbolic_address(pid, &bookmark.address, &modules)
+                .map(|address| address as u64);
+            let value = resolved
+                .clone()
+                .and_then(|address| util::read_typed_value(pid, address, &bookmark.value_type));
+            let (resolved, value, error) = match (resolved, value) {
+                (Ok(address), Ok(value)) => (Some(address), value, None),
+                (Ok(address), Err(e)) => (Some(address), Value::Null, Some(e)),
+                (Err(e), _) => (None, Value::Null, Some(e)),
+            };
+            BookmarkValue {
+                bookmark,
+                resolved,
+                value,
+                error,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,172 @@
+use crate::native_bridge;
+use crate::util::{self, Arch};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::collections::hash_map::{Entry, HashMap};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Breakpoint {
+    pub pid: i32,
+    pub address: u64,
+    // The bytes the trap instruction replaced
+    pub original: Vec<u8>,
+    // Keeps the thread that hits it stopped until it is stepped or continued
+    pub halt: bool,
+}
+
+// Keyed by (pid, address), since the same address means different code in different processes
+lazy_static! {
+    static ref BREAKPOINT_TABLE: Arc<Mutex<HashMap<(i32, u64), Breakpoint>>> =
+        Arc::new(Mutex::new(HashMap::new()));
+}
+
+// Held for the whole of a set or remove, from the table check through the patch, so two
+// requests for one address cannot both pass the check or interleave their writes. The table
+// lock itself is never held over the native calls: reads go through unpatch, and the debugger
+// thread looks the table up when a trap fires.
+static BREAKPOINT_EDITS: Mutex<()> = Mutex::new(());
+
+// int3 on x86, brk #0 on arm64
+pub fn trap_instruction(arch: Arch) -> &'static [u8] {
+    match arch {
+        Arch::X86 | Arch::X86_64 => &[0xCC],
+        Arch::Arm64 => &[0x00, 0x00, 0x20, 0xD4],
+    }
+}
+
+// A trap instruction needs a tracer to catch it, which only the Linux and Android debugger
+// provides; the Darwin debugger uses hardware breakpoints instead
+pub fn software_breakpoints_supported() -> bool {
+    cfg!(all(
+        any(target_os = "linux", target_os = "android"),
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    ))
+}
+
+// Saves the original instruction bytes and patches in a trap. The debugger reports each hit
+// to the exception queue, then steps the original instruction and re-arms the trap, unless
+// halt holds the thread there.
+pub fn breakpoint_set(pid: i32, address: u64, halt: bool) -> Result<(), String> {
+    let trap = trap_instruction(Arch::default());
+    if !address.is_multiple_of(trap.len() as u64) {
+        return Err(format!(
+            "Breakpoint address {:#x} is not aligned to the {} byte instruction size",
+            address,
+            trap.len()
+        ));
+    }
+    let _edit = BREAKPOINT_EDITS.lock().unwrap();
+
+    let mut original = vec![0u8; trap.len()];
+    let nread = native_bridge::read_process_memory(
+        pid,
+        address as *mut libc::c_void,
+        original.len(),
+        &mut original,
+    )
+    .map_err(|e| format!("Failed to read the instruction at {:#x}: {}", address, e))?;
+    if (nread as usize) < original.len() {
+        return Err(format!("Failed to read the instruction at {:#x}", address));
+    }
+
+    // The table entry and the debugger registration both come before the trap is written so
+    // a thread hitting it straight away is handled
+    match BREAKPOINT_TABLE.lock().unwrap().entry((pid, address)) {
+        Entry::Occupied(_) => {
+            return Err(format!("A breakpoint is already set at {:#x}", address));
+        }
+        Entry::Vacant(slot) => {
+            slot.insert(Breakpoint {
+                pid,
+                address,
+                original,
+                halt,
+            });
+        }
+    }
+    if let Err(e) = native_bridge::set_breakpoint(pid, address as usize, 0) {
+        BREAKPOINT_TABLE.lock().unwrap().remove(&(pid, address));
+        return Err(e.to_string());
+    }
+    if let Err(e) = util::write_memory_bytes(pid, address, trap) {
+        let _ = native_bridge::remove_breakpoint(address as usize);
+        BREAKPOINT_TABLE.lock().unwrap().remove(&(pid, address));
+        return Err(e);
+    }
+    Ok(())
+}
+
+// Puts the original bytes back; the debugger detaches once nothing else needs it
+pub fn breakpoint_remove(pid: i32, address: u64) -> Result<(), String> {
+    let _edit = BREAKPOINT_EDITS.lock().unwrap();
+    let breakpoint = BREAKPOINT_TABLE
+        .lock()
+        .unwrap()
+        .get(&(pid, address))
+        .cloned()
+        .ok_or_else(|| format!("No breakpoint at {:#x}", address))?;
+    util::write_memory_bytes(pid, address, &breakpoint.original)?;
+    if let Err(e) = native_bridge::remove_breakpoint(address as usize) {
+        log::warn!("Failed to unregister breakpoint at {:#x}: {}", address, e);
+    }
+    BREAKPOINT_TABLE.lock().unwrap().remove(&(pid, address));
+    Ok(())
+}
+
+// Puts the original bytes back over any trap inside bytes, which were read at address
+pub fn unpatch(pid: i32, address: u64, bytes: &mut [u8]) {
+    let end = address + bytes.len() as u64;
+    let table = BREAKPOINT_TABLE.lock().unwrap();
+    for breakpoint in table.values() {
+        if breakpoint.pid != pid || breakpoint.address < address || breakpoint.address >= end {
+            continue;
+        }
+        let offset = (breakpoint.address - address) as usize;
+        for (slot, &byte) in bytes[offset..].iter_mut().zip(&breakpoint.original) {
+            *slot = byte;
+        }
+    }
+}
+
+// A breakpoint as if breakpoint_set had patched it, without a debugger behind it
+#[cfg(test)]
+pub(crate) fn register_patched(breakpoint: Breakpoint) {
+    BREAKPOINT_TABLE
+        .lock()
+        .unwrap()
+        .insert((breakpoint.pid, breakpoint.address), breakpoint);
+}
+
+pub fn breakpoint_list() -> Vec<Breakpoint> {
+    let mut breakpoints: Vec<Breakpoint> =
+        BREAKPOINT_TABLE.lock().unwrap().values().cloned().collect();
+    breakpoints.sort_by_key(|breakpoint| (breakpoint.pid, breakpoint.address));
+    breakpoints
+}
+
+// Called by the native debugger when a trap fires, to step the original instruction
+#[no_mangle]
+pub extern "C" fn breakpoint_original_bytes(
+    pid: i32,
+    address: usize,
+    buffer: *mut u8,
+    size: usize,
+) -> bool {
+    let table = BREAKPOINT_TABLE.lock().unwrap();
+    match table.get(&(pid, address as u64)) {
+        Some(breakpoint) if breakpoint.original.len() == size => {
+            unsafe { std::ptr::copy_nonoverlapping(breakpoint.original.as_ptr(), buffer, size) };
+            true
+        }
+        _ => false,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn breakpoint_halts(pid: i32, address: usize) -> bool {
+    let table = BREAKPOINT_TABLE.lock().unwrap();
+    table
+        .get(&(pid, address as u64))
+        .is_some_and(|breakpoint| breakpoint.halt)
+}
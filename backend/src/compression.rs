@@ -0,0 +1,251 @@
+use byteorder::{ByteOrder, LittleEndian};
+use lz4_flex::block::{compress, decompress};
+use serde::Deserialize;
+
+// Both modes produce a plain lz4 block that lz4_flex (and any lz4 decoder) reads back. Fast is
+// lz4_flex's single probe per position; High searches a chain of earlier positions for the
+// longest match, which is several times slower but smaller on memory dumps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompressMode {
+    Fast,
+    High,
+}
+
+pub struct CompressedPayload {
+    pub data: Vec<u8>,
+    // Needed by the client to size its buffer before decompressing
+    pub original_len: usize,
+}
+
+pub fn compress_payload(data: &[u8], mode: CompressMode) -> CompressedPayload {
+    let compressed = match mode {
+        CompressMode::Fast => compress(data),
+        CompressMode::High => compress_high(data),
+    };
+    CompressedPayload {
+        data: compressed,
+        original_len: data.len(),
+    }
+}
+
+// lz4 block format limits: matches are at least 4 bytes and reach back at most 64KB, the last
+// 5 bytes are always literals and no match starts in the last 12
+const MIN_MATCH: usize = 4;
+const MAX_OFFSET: usize = 65535;
+const LAST_LITERALS: usize = 5;
+const MATCH_FIND_LIMIT: usize = 12;
+const HASH_BITS: u32 = 16;
+// Earlier positions tried per match; more finds longer matches at the cost of speed
+const MAX_CHAIN_ATTEMPTS: usize = 256;
+
+fn hash4(data: &[u8], position: usize) -> usize {
+    let word = LittleEndian::read_u32(&data[position..]);
+    (word.wrapping_mul(2654435761) >> (32 - HASH_BITS)) as usize
+}
+
+fn push_length(out: &mut Vec<u8>, mut length: usize) {
+    while length >= 255 {
+        out.push(255);
+        length -= 255;
+    }
+    out.push(length as u8);
+}
+
+// One sequence: the literals, then a match of (offset, length) unless it is the last one
+fn push_sequence(out: &mut Vec<u8>, literals: &[u8], found: Option<(usize, usize)>) {
+    let match_code = found.map_or(0, |(_, length)| length - MIN_MATCH);
+    out.push(((literals.len().min(15) as u8) << 4) | match_code.min(15) as u8);
+    if literals.len() >= 15 {
+        push_length(out, literals.len() - 15);
+    }
+    out.extend_from_slice(literals);
+    if let Some((offset, _)) = found {
+        out.extend_from_slice(&(offset as u16).to_le_bytes());
+        if match_code >= 15 {
+            push_length(out, match_code - 15);
+        }
+    }
+}
+
+// Greedy lz4 with hash chains, like lz4's own HC mode: every position is linked to the last
+// one with the same 4-byte hash, and the chain is walked for the longest match in the window.
+// The chain is indexed by position modulo the window, so memory stays fixed for any input.
+fn compress_high(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() / 2 + 16);
+    let mut anchor = 0;
+    if data.len() > MATCH_FIND_LIMIT {
+        let mut head = vec![usize::MAX; 1 << HASH_BITS];
+        let mut chain = vec![0u16; MAX_OFFSET + 1];
+        let match_limit = data.len() - LAST_LITERALS;
+        let mut inserted = 0;
+        let mut position = 0;
+        while position < data.len() - MATCH_FIND_LIMIT {
+            while inserted < position {
+                let hash = hash4(data, inserted);
+                // 0 ends the chain, for a first occurrence or one out of the window
+                let delta = match head[hash] {
+                    usize::MAX => 0,
+                    previous if inserted - previous > MAX_OFFSET => 0,
+                    previous => inserted - previous,
+                };
+                chain[inserted & MAX_OFFSET] = delta as u16;
+                head[hash] = inserted;
+                inserted += 1;
+            }
+
+            let mut best: Option<(usize, usize)> = None;
+            let mut candidate = head[hash4(data, position)];
+            for _ in 0..MAX_CHAIN_ATTEMPTS {
+                if candidate == usize::MAX || position - candidate > MAX_OFFSET {
+                    break;
+                }
+                let length = data[candidate..match_limit]
+                    .iter()
+                    .zip(&data[position..match_limit])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if length >= MIN_MATCH && best.is_none_or(|(_, best_length)| length > best_length) {
+                    best = Some((position - candidate, length));
+                }
+                let delta = chain[candidate & MAX_OFFSET] as usize;
+                if delta == 0 {
+                    break;
+                }
+                candidate -= delta;
+            }
+
+            match best {
+                Some((offset, length)) => {
+                    push_sequence(&mut out, &data[anchor..position], Some((offset, length)));
+                    position += length;
+                    anchor = position;
+                }
+                None => position += 1,
+            }
+        }
+    }
+    push_sequence(&mut out, &data[anchor..], None);
+    out
+}
+
+const FRAME_HEADER_SIZE: usize = 4;
+// Largest payload one frame holds. Anything claiming to be larger is treated as corrupt
+// rather than allocated, so bigger transfers are split into several frames.
+pub const MAX_FRAME_LEN: usize = 1024 * 1024 * 8; // 8MB
+
+// A u32 little-endian original length followed by the lz4 block, the same layout the
+// frontend already decodes for batched memory reads
+pub fn compress_framed(data: &[u8]) -> Result<Vec<u8>, String> {
+    let original_len = u32::try_from(data.len())
+        .ok()
+        .filter(|&len| len as usize <= MAX_FRAME_LEN)
+        .ok_or_else(|| {
+            format!(
+                "Cannot compress {} bytes into one frame, above the {} byte limit",
+                data.len(),
+                MAX_FRAME_LEN
+            )
+        })?;
+    let compressed = compress(data);
+    let mut framed = vec![0u8; FRAME_HEADER_SIZE];
+    LittleEndian::write_u32(&mut framed, original_len);
+    framed.extend_from_slice(&compressed);
+    Ok(framed)
+}
+
+pub fn decompress_framed(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    if bytes.len() < FRAME_HEADER_SIZE {
+        return Err("Compressed frame is missing its length header".to_string());
+    }
+    let original_len = LittleEndian::read_u32(&bytes[..FRAME_HEADER_SIZE]) as usize;
+    if original_len > MAX_FRAME_LEN {
+        return Err(format!(
+            "Compressed frame claims {} bytes, above the {} byte limit",
+            original_len, MAX_FRAME_LEN
+        ));
+    }
+    decompress(&bytes[FRAME_HEADER_SIZE..], original_len)
+        .map_err(|e| format!("Failed to decompress frame: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Repeats of a few records with small differences, like arrays of game objects
+    fn compressible_buffer() -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut seed = 0x2545_f491u32;
+        let records: Vec<Vec<u8>> = (0..8)
+            .map(|_| {
+                (0..48)
+                    .map(|_| {
+                        seed ^= seed << 13;
+                        seed ^= seed >> 17;
+                        seed ^= seed << 5;
+                        seed as u8
+                    })
+                    .collect()
+            })
+            .collect();
+        for i in 0..4096u32 {
+            let mut record = records[(i.wrapping_mul(7) % 8) as usize].clone();
+            record[(i % 48) as usize] = i as u8;
+            data.extend_from_slice(&record);
+        }
+        data
+    }
+
+    #[test]
+    fn high_is_smaller_than_fast_and_both_round_trip() {
+        let data = compressible_buffer();
+        let fast = compress_payload(&data, CompressMode::Fast);
+        let high = compress_payload(&data, CompressMode::High);
+        assert!(high.data.len() < fast.data.len());
+        for payload in [fast, high] {
+            assert_eq!(payload.original_len, data.len());
+            assert_eq!(
+                decompress(&payload.data, payload.original_len).unwrap(),
+                data
+            );
+        }
+    }
+
+    #[test]
+    fn high_round_trips_edge_inputs() {
+        let long_run = vec![7u8; 100_000];
+        let mut literals_only: Vec<u8> = (0..=255u8).collect();
+        literals_only.extend((0..=255u8).rev());
+        for data in [&b""[..], b"abc", &[1u8; 13], &long_run, &literals_only] {
+            let high = compress_high(data);
+            assert_eq!(decompress(&high, data.len()).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+        let framed = compress_framed(&data).unwrap();
+        assert_eq!(LittleEndian::read_u32(&framed), data.len() as u32);
+        assert_eq!(decompress_framed(&framed).unwrap(), data);
+    }
+
+    #[test]
+    fn empty_frame_round_trips() {
+        let framed = compress_framed(&[]).unwrap();
+        assert_eq!(&framed[..FRAME_HEADER_SIZE], &[0, 0, 0, 0]);
+        assert!(decompress_framed(&framed).unwrap().is_empty());
+    }
+
+    #[test]
+    fn oversized_frames_are_refused() {
+        assert!(compress_framed(&vec![0u8; MAX_FRAME_LEN + 1]).is_err());
+
+        // A header claiming 4GB is rejected before anything is allocated for it
+        let mut bomb = u32::MAX.to_le_bytes().to_vec();
+        bomb.extend_from_slice(&[0x10, 0x00]);
+        assert!(decompress_framed(&bomb).unwrap_err().contains("limit"));
+        assert!(decompress_framed(&[1, 0]).is_err());
+    }
+}
@@ -0,0 +1,481 @@
+use crate::region::{MemoryRegion, Protection, RegionKind};
+use crate::symbols::ElfReader;
+use lazy_static::lazy_static;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Error, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{Arc, Mutex};
+
+// Dump handles stand in for a pid wherever one is taken. They start above the largest pid
+// Linux hands out (2^22), so a handle that reaches a native call names no process.
+const DUMP_HANDLE_BASE: i32 = 0x4000_0000;
+
+const ET_CORE: u16 = 4;
+const PT_LOAD: u32 = 1;
+const PT_NOTE: u32 = 4;
+// "FILE": the files mapped into the process, written by the Linux kernel and gdb
+const NT_FILE: u32 = 0x4649_4c45;
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+const PF_R: u32 = 4;
+// Notes beyond this are not parsed; a core holds a few hundred KB of them at most
+const MAX_NOTE_SIZE: u64 = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct DumpHandle(i32);
+
+impl DumpHandle {
+    // What to use as the pid for reads, scans and disassembly against the dump
+    pub fn pid(self) -> i32 {
+        self.0
+    }
+}
+
+// A PT_LOAD segment. Only file_size bytes from start were saved; the kernel leaves out
+// mappings it can read back from their files, which then have a file_size of 0.
+struct Segment {
+    start: u64,
+    end: u64,
+    file_offset: u64,
+    file_size: u64,
+    protection: Protection,
+}
+
+struct FileMapping {
+    start: u64,
+    end: u64,
+    path: String,
+}
+
+struct CoreDump {
+    file: Mutex<File>,
+    is_64bit: bool,
+    // Sorted by start
+    segments: Vec<Segment>,
+    files: Vec<FileMapping>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DumpInfo {
+    pub handle: DumpHandle,
+    pub path: PathBuf,
+    pub is_64bit: bool,
+    pub segments: usize,
+    pub dumped_bytes: u64,
+    pub mapped_files: usize,
+}
+
+lazy_static! {
+    static ref DUMPS: Arc<Mutex<HashMap<i32, Arc<CoreDump>>>> =
+        Arc::new(Mutex::new(HashMap::new()));
+}
+
+static NEXT_HANDLE: AtomicI32 = AtomicI32::new(DUMP_HANDLE_BASE);
+
+pub fn is_dump_handle(pid: i32) -> bool {
+    pid >= DUMP_HANDLE_BASE
+}
+
+fn open_handle(pid: i32) -> Result<Arc<CoreDump>, String> {
+    DUMPS
+        .lock()
+        .unwrap()
+        .get(&pid)
+        .cloned()
+        .ok_or_else(|| format!("Dump handle {} is not open", pid))
+}
+
+// Loads the segment table of an ELF core file. The memory itself stays in the file and is
+// read on demand, so a dump of any size opens quickly.
+pub fn open_dump(path: &Path) -> Result<DumpInfo, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut elf = ElfReader {
+        file,
+        is_64bit: true,
+        big_endian: false,
+    };
+    let header = elf.read_at(0, 64)?;
+    if &header[..4] != b"\x7fELF" {
+        return Err(format!("{} is not an ELF file", path.display()));
+    }
+    elf.is_64bit = match header[4] {
+        1 => false,
+        2 => true,
+        class => {
+            return Err(format!(
+                "{} has unknown ELF class {}",
+                path.display(),
+                class
+            ))
+        }
+    };
+    elf.big_endian = header[5] == 2;
+    if elf.u16(&header, 0x10) != ET_CORE {
+        return Err(format!(
+            "{} is an ELF file but not a core dump",
+            path.display()
+        ));
+    }
+
+    let (program_size, program_count) = if elf.is_64bit {
+        (
+            elf.u16(&header, 0x36) as usize,
+            elf.u16(&header, 0x38) as usize,
+        )
+    } else {
+        (
+            elf.u16(&header, 0x2A) as usize,
+            elf.u16(&header, 0x2C) as usize,
+        )
+    };
+    let programs = elf.read_table(
+        elf.word(&header, 0x20, 0x1C),
+        program_size,
+        program_count,
+        if elf.is_64bit { 0x38 } else { 0x20 },
+    )?;
+
+    let mut segments = Vec::new();
+    let mut notes = Vec::new();
+    for program in programs.chunks_exact(program_size) {
+        let file_offset = elf.word(program, 0x08, 0x04);
+        let start = elf.word(program, 0x10, 0x08);
+        let file_size = elf.word(program, 0x20, 0x10);
+        let memory_size = elf.word(program, 0x28, 0x14);
+        match elf.u32(program, 0) {
+            PT_LOAD if memory_size > 0 => {
+                // A segment whose bounds overflow cannot be read back, so it is left out
+                let (Some(end), Some(_)) = (
+                    start.checked_add(memory_size),
+                    file_offset.checked_add(file_size),
+                ) else {
+                    log::warn!(
+                        "Skipping a malformed segment at {:#x} in {}",
+                        start,
+                        path.display()
+                    );
+                    continue;
+                };
+                let flags = elf.u32(program, if elf.is_64bit { 0x04 } else { 0x18 });
+                let flag = |bit: u32, protection: Protection| {
+                    if flags & bit != 0 {
+                        protection
+                    } else {
+                        Protection::NONE
+                    }
+                };
+                segments.push(Segment {
+                    start,
+                    end,
+                    file_offset,
+                    file_size: file_size.min(memory_size),
+                    protection: flag(PF_R, Protection::READ)
+                        | flag(PF_W, Protection::WRITE)
+                        | flag(PF_X, Protection::EXEC),
+                });
+            }
+            PT_NOTE if file_size <= MAX_NOTE_SIZE => {
+                notes.push(elf.read_at(file_offset, file_size as usize)?);
+            }
+            _ => {}
+        }
+    }
+    if segments.is_empty() {
+        return Err(format!("{} holds no memory segments", path.display()));
+    }
+    segments.sort_by_key(|segment| segment.start);
+    let files = notes
+        .iter()
+        .flat_map(|note| file_mappings(&elf, note))
+        .collect::<Vec<_>>();
+
+    let handle = DumpHandle(NEXT_HANDLE.fetch_add(1, Ordering::Relaxed));
+    let info = DumpInfo {
+        handle,
+        path: path.to_path_buf(),
+        is_64bit: elf.is_64bit,
+        segments: segments.len(),
+        dumped_bytes: segments.iter().map(|segment| segment.file_size).sum(),
+        mapped_files: files.len(),
+    };
+    DUMPS.lock().unwrap().insert(
+        handle.pid(),
+        Arc::new(CoreDump {
+            file: Mutex::new(elf.file),
+            is_64bit: elf.is_64bit,
+            segments,
+            files,
+        }),
+    );
+    log::info!(
+        "Opened core dump {} as handle {} with {} segments",
+        path.display(),
+        handle.pid(),
+        info.segments
+    );
+    Ok(info)
+}
+
+// False when no dump is open under pid
+pub fn close_dump(pid: i32) -> bool {
+    DUMPS.lock().unwrap().remove(&pid).is_some()
+}
+
+// The NT_FILE entries of a PT_NOTE segment: a count and page size, count (start, end, page
+// offset) triples, then count NUL terminated paths. Notes are 4-byte aligned in both classes.
+fn file_mappings(elf: &ElfReader, notes: &[u8]) -> Vec<FileMapping> {
+    let align = |size: usize| size.next_multiple_of(4);
+    let word_size = if elf.is_64bit { 8 } else { 4 };
+    let mut mappings = Vec::new();
+    let mut offset = 0;
+    while offset + 12 <= notes.len() {
+        let name_size = elf.u32(notes, offset) as usize;
+        let desc_size = elf.u32(notes, offset + 4) as usize;
+        let note_type = elf.u32(notes, offset + 8);
+        let Some(desc_start) = (offset + 12).checked_add(align(name_size)) else {
+            break;
+        };
+        let Some(desc) = desc_start
+            .checked_add(desc_size)
+            .and_then(|desc_end| notes.get(desc_start..desc_end))
+        else {
+            break;
+        };
+        offset = desc_start + align(desc_size);
+        if note_type != NT_FILE || desc.len() < 2 * word_size {
+            continue;
+        }
+        // A count the note cannot hold marks it as malformed, and it is skipped
+        let count = elf.word(desc, 0, 0) as usize;
+        let Some(paths) = count
+            .checked_mul(3)
+            .and_then(|words| words.checked_add(2))
+            .and_then(|words| words.checked_mul(word_size))
+            .and_then(|paths_start| desc.get(paths_start..))
+        else {
+            continue;
+        };
+        let entries = (0..count).map(|index| {
+            let entry = (2 + 3 * index) * word_size;
+            (
+                elf.word(desc, entry, entry),
+                elf.word(desc, entry + word_size, entry + word_size),
+            )
+        });
+        let names = paths
+            .split(|&b| b == 0)
+            .map(|name| String::from_utf8_lossy(name).into_owned());
+        mappings.extend(
+            entries
+                .zip(names)
+                .filter(|((start, end), _)| start < end)
+                .map(|((start, end), path)| FileMapping { start, end, path }),
+        );
+    }
+    mappings
+}
+
+// Copies the saved bytes at address into buffer, running on into adjacent segments, and
+// returns how many were copied. Like a partial process read it stops at the first byte that
+// was not saved, and fails with EFAULT when that is the first one.
+pub fn read_dump(pid: i32, address: u64, buffer: &mut [u8]) -> Result<isize, Error> {
+    let dump = open_handle(pid).map_err(|e| Error::new(std::io::ErrorKind::NotFound, e))?;
+    let mut copied = 0;
+    while copied < buffer.len() {
+        let current = address.saturating_add(copied as u64);
+        let index = dump
+            .segments
+            .partition_point(|segment| segment.start <= current);
+        let Some(segment) = index.checked_sub(1).map(|index| &dump.segments[index]) else {
+            break;
+        };
+        // open_dump only keeps segments whose memory and file ranges do not overflow
+        let saved_end = segment.start + segment.file_size;
+        if current >= saved_end {
+            break;
+        }
+        let count = ((saved_end - current) as usize).min(buffer.len() - copied);
+        let mut file = dump.file.lock().unwrap();
+        file.seek(SeekFrom::Start(
+            segment.file_offset + (current - segment.start),
+        ))
+        .and_then(|_| file.read_exact(&mut buffer[copied..copied + count]))?;
+        copied += count;
+    }
+    if copied == 0 && !buffer.is_empty() {
+        return Err(Error::from_raw_os_error(libc::EFAULT));
+    }
+    Ok(copied as isize)
+}
+
+// Every segment as a region, named after the file mapped there when the dump records one
+pub fn dump_regions(pid: i32) -> Result<Vec<MemoryRegion>, String> {
+    let dump = open_handle(pid)?;
+    Ok(dump
+        .segments
+        .iter()
+        .map(|segment| {
+            let path = dump
+                .files
+                .iter()
+                .find(|file| file.start <= segment.start && segment.start < file.end)
+                .map(|file| file.path.clone());
+            MemoryRegion {
+                start: segment.start,
+                end: segment.end,
+                protection: segment.protection,
+                kind: RegionKind::classify(path.as_deref()),
+                path,
+            }
+        })
+        .collect())
+}
+
+// The mapped files in the shape enum_modules returns, each based at its lowest mapping
+pub fn dump_modules(pid: i32) -> Result<Vec<Value>, String> {
+    let dump = open_handle(pid)?;
+    let mut bases: Vec<&FileMapping> = Vec::new();
+    for file in &dump.files {
+        match bases.iter_mut().find(|base| base.path == file.path) {
+            Some(base) if file.start < base.start => *base = file,
+            Some(_) => {}
+            None => bases.push(file),
+        }
+    }
+    bases.sort_by_key(|file| file.start);
+    Ok(bases
+        .into_iter()
+        .map(|file| {
+            json!({
+                "base": file.start,
+                "size": file.end.saturating_sub(file.start),
+                "is_64bit": dump.is_64bit,
+                "modulename": file.path,
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use byteorder::{ByteOrder, LittleEndian};
+
+    const NOTE_HEADER: usize = 12 + 8;
+
+    // An NT_FILE note for files given as (start, end, path), with count as its entry count
+    fn file_note(count: u64, files: &[(u64, u64, &str)]) -> Vec<u8> {
+        let mut desc = Vec::new();
+        for word in [count, 4096] {
+            desc.extend_from_slice(&word.to_le_bytes());
+        }
+        for &(start, end, _) in files {
+            for word in [start, end, 0] {
+                desc.extend_from_slice(&word.to_le_bytes());
+            }
+        }
+        for &(_, _, path) in files {
+            desc.extend_from_slice(path.as_bytes());
+            desc.push(0);
+        }
+        let mut note = vec![0u8; NOTE_HEADER];
+        LittleEndian::write_u32(&mut note[0..], 5);
+        LittleEndian::write_u32(&mut note[4..], desc.len() as u32);
+        LittleEndian::write_u32(&mut note[8..], NT_FILE);
+        note[12..17].copy_from_slice(b"CORE\0");
+        note.extend_from_slice(&desc);
+        note.resize(note.len().next_multiple_of(4), 0);
+        note
+    }
+
+    // A little-endian ELF64 core: one PT_NOTE, then a PT_LOAD per (address, bytes, memory size)
+    fn core_file(notes: &[u8], loads: &[(u64, &[u8], u64)]) -> Vec<u8> {
+        let program_count = 1 + loads.len();
+        let mut data_offset = 64 + 56 * program_count;
+        let mut core = vec![0u8; 64];
+        core[..7].copy_from_slice(b"\x7fELF\x02\x01\x01");
+        LittleEndian::write_u16(&mut core[0x10..], ET_CORE);
+        LittleEndian::write_u64(&mut core[0x20..], 64);
+        LittleEndian::write_u16(&mut core[0x36..], 56);
+        LittleEndian::write_u16(&mut core[0x38..], program_count as u16);
+
+        let mut contents = Vec::new();
+        let mut program = |kind: u32, flags: u32, address: u64, bytes: &[u8], memory_size: u64| {
+            let mut header = [0u8; 56];
+            LittleEndian::write_u32(&mut header[0..], kind);
+            LittleEndian::write_u32(&mut header[4..], flags);
+            LittleEndian::write_u64(&mut header[0x08..], data_offset as u64);
+            LittleEndian::write_u64(&mut header[0x10..], address);
+            LittleEndian::write_u64(&mut header[0x20..], bytes.len() as u64);
+            LittleEndian::write_u64(&mut header[0x28..], memory_size);
+            data_offset += bytes.len();
+            contents.extend_from_slice(bytes);
+            header
+        };
+        let mut headers = vec![program(PT_NOTE, 0, 0, notes, 0)];
+        for &(address, bytes, memory_size) in loads {
+            headers.push(program(PT_LOAD, PF_R | PF_W, address, bytes, memory_size));
+        }
+        for header in headers {
+            core.extend_from_slice(&header);
+        }
+        core.extend_from_slice(&contents);
+        core
+    }
+
+    fn open_fixture(name: &str, core: &[u8]) -> DumpInfo {
+        let path = std::env::temp_dir().join(format!(
+            "memory-server-{}-{}.core",
+            name,
+            std::process::id()
+        ));
+        std::fs::write(&path, core).unwrap();
+        let info = open_dump(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        info
+    }
+
+    #[test]
+    fn value_is_read_from_a_core_segment() {
+        let mut memory = vec![0u8; 64];
+        memory[16..20].copy_from_slice(&1337u32.to_le_bytes());
+        let notes = file_note(1, &[(0x400000, 0x401000, "/usr/bin/game")]);
+        let core = core_file(&notes, &[(0x400000, &memory, 0x1000)]);
+        let pid = open_fixture("read", &core).handle.pid();
+
+        let mut value = [0u8; 4];
+        assert_eq!(read_dump(pid, 0x400010, &mut value).unwrap(), 4);
+        assert_eq!(u32::from_le_bytes(value), 1337);
+        // Past the saved bytes the segment reads like an unreadable page
+        assert!(read_dump(pid, 0x400040, &mut value).is_err());
+        let modules = dump_modules(pid).unwrap();
+        assert_eq!(modules[0]["modulename"], "/usr/bin/game");
+        assert_eq!(modules[0]["size"], 0x1000);
+        assert!(close_dump(pid));
+    }
+
+    #[test]
+    fn malformed_notes_and_segments_are_skipped() {
+        let mut notes = file_note(u64::MAX / 2, &[(0x400000, 0x401000, "/bogus")]);
+        notes.extend(file_note(
+            2,
+            &[
+                (0x500000, 0x400000, "/backwards"),
+                (0x600000, 0x601000, "/lib.so"),
+            ],
+        ));
+        let core = core_file(
+            &notes,
+            &[(u64::MAX - 8, &[0; 8], 0x1000), (0x600000, &[7; 8], 0x1000)],
+        );
+        let info = open_fixture("malformed", &core);
+        assert_eq!(info.segments, 1);
+        assert_eq!(info.mapped_files, 1);
+        assert!(close_dump(info.handle.pid()));
+    }
+}
@@ -311,6 +311,32 @@ const void *read_file(const char *path, size_t *size, char **error_message)
     }
 }
 
+int write_file(const char *path, const void *data, size_t size, bool append,
+               char **error_message)
+{
+    FILE *file = fopen(path, append ? "ab" : "wb");
+    if (!file)
+    {
+        NSString *errorString =
+            [NSString stringWithFormat:@"Error: Could not open file %s. Error: %s", path,
+                                       strerror(errno)];
+        *error_message = strdup([errorString UTF8String]);
+        return -1;
+    }
+
+    size_t written = fwrite(data, 1, size, file);
+    fclose(file);
+    if (written != size)
+    {
+        NSString *errorString =
+            [NSString stringWithFormat:@"Error: Failed to write file %s", path];
+        *error_message = strdup([errorString UTF8String]);
+        return -1;
+    }
+
+    return 0;
+}
+
 const char *get_application_info_native(pid_t pid)
 {
     @autoreleasepool
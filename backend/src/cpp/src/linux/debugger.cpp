@@ -1,29 +1,1096 @@
 #include "debugger.h"
 
+#if defined(__aarch64__)
+#include <asm/ptrace.h>
+#ifndef NT_ARM_HW_WATCH
+#define NT_ARM_HW_WATCH 0x403
+#endif
+#endif
+
+#ifndef NT_PRSTATUS
+#define NT_PRSTATUS 1
+#endif
+
+#ifndef TRAP_HWBKPT
+#define TRAP_HWBKPT 4
+#endif
+
+// Every wait is limited to this thread's own tracees with __WNOTHREAD. Other server threads
+// attach to processes for one-off reads and writes and wait for those stops themselves; a
+// wait on any child would take them away.
+static constexpr int TRACEE_WAIT = __WALL | __WNOTHREAD;
+
+// Guards g_debugger itself. Writer threads reach it through write_memory_native while a
+// request or the shutdown handler may be replacing or deleting it.
+static std::mutex g_debugger_mutex;
+static Debugger *g_debugger = nullptr;
+
+Debugger::Debugger(pid_t pid)
+    : pid_(pid),
+      attached_(false),
+      watchpoints_(),
+      command_result_(0),
+      command_done_(false),
+      finished_(false)
+{
+}
+
+Debugger::~Debugger()
+{
+    run_command([this]() {
+        detach_all();
+        return 0;
+    });
+    if (thread_.joinable())
+    {
+        thread_.join();
+    }
+}
+
+// Attaches on the tracer thread; returns 0 or the errno of the failed attach
+int Debugger::initialize()
+{
+    std::promise<int> attached;
+    std::future<int> result = attached.get_future();
+    thread_ = std::thread(&Debugger::run, this, &attached);
+    return result.get();
+}
+
+bool Debugger::is_attached(pid_t pid)
+{
+    std::lock_guard<std::mutex> lock(mutex_);
+    return pid_ == pid && !finished_;
+}
+
+// Whether a watchpoint or breakpoint is still set; a detached debugger has neither
+bool Debugger::in_use()
+{
+    return run_command([=]() { return has_watchpoints() || !breakpoints_.empty() ? 1 : 0; }) == 1;
+}
+
+void Debugger::run(std::promise<int> *attached)
+{
+    int error = attach_all();
+    attached->set_value(error);
+
+    while (attached_)
+    {
+        std::function<int()> command;
+        {
+            std::lock_guard<std::mutex> lock(mutex_);
+            command.swap(command_);
+        }
+        if (command)
+        {
+            int result = stop_all() ? command() : ESRCH;
+            resume_all();
+            {
+                std::lock_guard<std::mutex> lock(mutex_);
+                command_result_ = result;
+                command_done_ = true;
+                // Set here rather than after the loop so a detach is visible to is_attached
+                // as soon as the command returns
+                finished_ = !attached_;
+            }
+            command_cv_.notify_all();
+            continue;
+        }
+
+        // Polling keeps the loop free to pick up commands; ptrace gives no way to wake a
+        // blocking waitpid from another thread
+        int status = 0;
+        pid_t tid = waitpid(-1, &status, TRACEE_WAIT | WNOHANG);
+        if (tid > 0)
+        {
+            handle_event(tid, status);
+            resume_all();
+        }
+        else if (tid == 0)
+        {
+            usleep(1000);
+        }
+        else if (errno == ECHILD)
+        {
+            attached_ = false;
+        }
+    }
+
+    std::lock_guard<std::mutex> lock(mutex_);
+    finished_ = true;
+    command_cv_.notify_all();
+}
+
+// Seizes every thread of the target. New threads are picked up through PTRACE_O_TRACECLONE;
+// the listing is repeated until it is stable to catch threads created while attaching.
+int Debugger::attach_all()
+{
+    char path[64];
+    snprintf(path, sizeof(path), "/proc/%d/task", pid_);
+    bool added = true;
+    while (added)
+    {
+        added = false;
+        DIR *dir = opendir(path);
+        if (!dir)
+        {
+            int error = errno;
+            detach_all();
+            return error;
+        }
+        while (struct dirent *entry = readdir(dir))
+        {
+            pid_t tid = atoi(entry->d_name);
+            if (tid <= 0 || threads_.count(tid))
+            {
+                continue;
+            }
+            if (ptrace(PTRACE_SEIZE, tid, NULL, PTRACE_O_TRACECLONE) == -1)
+            {
+                int error = errno;
+                if (tid == pid_)
+                {
+                    debug_log(LOG_ERROR, "Failed to attach to process %d. Error: %d (%s)\n", pid_,
+                              error, strerror(error));
+                    closedir(dir);
+                    detach_all();
+                    return error;
+                }
+                // The thread may have exited in the meantime
+                continue;
+            }
+            threads_.insert(tid);
+            added = true;
+        }
+        closedir(dir);
+    }
+    attached_ = !threads_.empty();
+    return attached_ ? 0 : ESRCH;
+}
+
+// Clears the debug registers before detaching: a watchpoint left behind would deliver a
+// SIGTRAP nobody handles and kill the target
+void Debugger::detach_all()
+{
+    // Also reached from a failed attach, where some threads may be seized already
+    attached_ = !threads_.empty();
+    if (attached_ && stop_all())
+    {
+        for (pid_t tid : threads_)
+        {
+            apply_debug_registers(tid, false);
+            int sig = stopped_.count(tid) && stopped_[tid] > 0 ? stopped_[tid] : 0;
+            sig = halted_.count(tid) ? halted_[tid] : sig;
+            ptrace(PTRACE_DETACH, tid, NULL, sig);
+        }
+    }
+    threads_.clear();
+    new_threads_.clear();
+    interrupted_.clear();
+    stopped_.clear();
+    halted_.clear();
+    attached_ = false;
+}
+
+// Runs command on the tracer thread with every target thread stopped and returns its result
+int Debugger::run_command(std::function<int()> command)
+{
+    std::lock_guard<std::mutex> serialized(command_mutex_);
+    std::unique_lock<std::mutex> lock(mutex_);
+    if (finished_)
+    {
+        return ESRCH;
+    }
+    command_ = std::move(command);
+    command_done_ = false;
+    command_cv_.wait(lock, [this]() { return command_done_ || finished_; });
+    return command_done_ ? command_result_ : ESRCH;
+}
+
+bool Debugger::stop_all()
+{
+    while (attached_)
+    {
+        bool all_stopped = true;
+        for (pid_t tid : threads_)
+        {
+            if (stopped_.count(tid) || halted_.count(tid))
+            {
+                continue;
+            }
+            all_stopped = false;
+            // New threads report a stop of their own once they start
+            if (!new_threads_.count(tid) && interrupted_.insert(tid).second)
+            {
+                ptrace(PTRACE_INTERRUPT, tid, NULL, NULL);
+            }
+        }
+        if (all_stopped)
+        {
+            return true;
+        }
+
+        int status = 0;
+        pid_t tid = waitpid(-1, &status, TRACEE_WAIT);
+        if (tid < 0)
+        {
+            if (errno == EINTR)
+            {
+                continue;
+            }
+            attached_ = false;
+            return false;
+        }
+        handle_event(tid, status);
+    }
+    return false;
+}
+
+void Debugger::resume_all()
+{
+    for (const auto &[tid, sig] : stopped_)
+    {
+        if (sig == RESUME_LISTEN)
+        {
+            ptrace(PTRACE_LISTEN, tid, NULL, NULL);
+        }
+        else
+        {
+            ptrace(PTRACE_CONT, tid, NULL, sig);
+        }
+    }
+    stopped_.clear();
+}
+
+// Records a stop in stopped_ with the signal to pass on, or drops an exited thread
+void Debugger::handle_event(pid_t tid, int status)
+{
+    if (WIFEXITED(status) || WIFSIGNALED(status))
+    {
+        threads_.erase(tid);
+        new_threads_.erase(tid);
+        interrupted_.erase(tid);
+        stopped_.erase(tid);
+        halted_.erase(tid);
+        if (tid == pid_ || threads_.empty())
+        {
+            attached_ = false;
+        }
+        return;
+    }
+    if (!WIFSTOPPED(status))
+    {
+        return;
+    }
+
+    int sig = WSTOPSIG(status);
+    int event = status >> 16;
+    if (!threads_.count(tid))
+    {
+        // A new thread's first stop can arrive before its parent's clone event
+        threads_.insert(tid);
+        new_threads_.insert(tid);
+    }
+
+    if (event == PTRACE_EVENT_CLONE)
+    {
+        unsigned long new_tid = 0;
+        if (ptrace(PTRACE_GETEVENTMSG, tid, NULL, &new_tid) == 0 &&
+            threads_.insert(static_cast<pid_t>(new_tid)).second)
+        {
+            new_threads_.insert(static_cast<pid_t>(new_tid));
+        }
+        stopped_[tid] = 0;
+        return;
+    }
+    if (event == PTRACE_EVENT_STOP)
+    {
+        interrupted_.erase(tid);
+        if (new_threads_.erase(tid))
+        {
+            apply_debug_registers(tid, true);
+        }
+        // Group-stops (e.g. the SIGSTOP sent by suspend_process) have to stay stopped
+        bool group_stop = sig == SIGSTOP || sig == SIGTSTP || sig == SIGTTIN || sig == SIGTTOU;
+        stopped_[tid] = group_stop ? RESUME_LISTEN : 0;
+        return;
+    }
+
+    if (sig == SIGTRAP)
+    {
+        siginfo_t info;
+        if (ptrace(PTRACE_GETSIGINFO, tid, NULL, &info) == 0 && info.si_code == TRAP_HWBKPT)
+        {
+            handle_watchpoint_hit(tid, reinterpret_cast<uintptr_t>(info.si_addr));
+            stopped_[tid] = 0;
+            return;
+        }
+        if (handle_breakpoint_trap(tid))
+        {
+            return;
+        }
+    }
+    // Any other signal belongs to the target and is passed on when the thread resumes
+    stopped_[tid] = sig;
+}
+
+bool Debugger::has_watchpoints() const
+{
+    for (const Watchpoint &watchpoint : watchpoints_)
+    {
+        if (watchpoint.used)
+        {
+            return true;
+        }
+    }
+    return false;
+}
+
+#if defined(__x86_64__)
+
+// int3; the trap leaves pc just past it
+static const unsigned char TRAP_INSTRUCTION[] = {0xCC};
+static const uintptr_t TRAP_PC_OFFSET = 1;
+
+static bool poke_debug_register(pid_t tid, int index, unsigned long value)
+{
+    return ptrace(PTRACE_POKEUSER, tid, offsetof(struct user, u_debugreg) + index * sizeof(long),
+                  value) == 0;
+}
+
+// DR7 is cleared first because the kernel validates every address write against it
+bool Debugger::apply_debug_registers(pid_t tid, bool enabled)
+{
+    if (!poke_debug_register(tid, 7, 0))
+    {
+        return false;
+    }
+    if (!enabled)
+    {
+        return true;
+    }
+
+    unsigned long dr7 = 0;
+    for (int i = 0; i < MAX_WATCHPOINTS; i++)
+    {
+        const Watchpoint &watchpoint = watchpoints_[i];
+        if (!watchpoint.used)
+        {
+            continue;
+        }
+        if (!poke_debug_register(tid, i, watchpoint.address))
+        {
+            return false;
+        }
+        // x86 cannot trap reads alone, so read watchpoints trigger on any access
+        unsigned long rw = watchpoint.type == WatchpointType::WRITE ? 0b01 : 0b11;
+        unsigned long len = watchpoint.size == 1   ? 0b00
+                            : watchpoint.size == 2 ? 0b01
+                            : watchpoint.size == 8 ? 0b10
+                                                   : 0b11;
+        dr7 |= (1UL << (i * 2)) | (rw << (16 + i * 4)) | (len << (18 + i * 4));
+    }
+    return poke_debug_register(tid, 7, dr7);
+}
+
+// Data breakpoints trap after the access, so pc is the instruction following the one that
+// touched the memory. si_addr only repeats the pc here; DR6 tells which slot fired.
+void Debugger::handle_watchpoint_hit(pid_t tid, uintptr_t)
+{
+    errno = 0;
+    unsigned long dr6 = ptrace(PTRACE_PEEKUSER, tid, offsetof(struct user, u_debugreg[6]), NULL);
+    if (errno != 0)
+    {
+        return;
+    }
+    poke_debug_register(tid, 6, 0);
+    for (int i = 0; i < MAX_WATCHPOINTS; i++)
+    {
+        if (watchpoints_[i].used && (dr6 & (1UL << i)))
+        {
+            report_registers(tid, {{{"memory", watchpoints_[i].address}},
+                                   {{"watchpoint", watchpoints_[i].address}}});
+            return;
+        }
+    }
+}
+
+bool Debugger::read_registers(pid_t tid, RegisterList &registers, uintptr_t &pc)
+{
+    struct user_regs_struct regs;
+    if (ptrace(PTRACE_GETREGS, tid, NULL, &regs) == -1)
+    {
+        return false;
+    }
+    registers = {
+        {{"rax", regs.rax}}, {{"rbx", regs.rbx}}, {{"rcx", regs.rcx}}, {{"rdx", regs.rdx}},
+        {{"rsi", regs.rsi}}, {{"rdi", regs.rdi}}, {{"r8", regs.r8}},   {{"r9", regs.r9}},
+        {{"r10", regs.r10}}, {{"r11", regs.r11}}, {{"r12", regs.r12}}, {{"r13", regs.r13}},
+        {{"r14", regs.r14}}, {{"r15", regs.r15}}, {{"fp", regs.rbp}},  {{"sp", regs.rsp}},
+        {{"pc", regs.rip}},  {{"eflags", regs.eflags}},
+    };
+    pc = regs.rip;
+    return true;
+}
+
+bool Debugger::write_pc(pid_t tid, uintptr_t pc)
+{
+    struct user_regs_struct regs;
+    if (ptrace(PTRACE_GETREGS, tid, NULL, &regs) == -1)
+    {
+        return false;
+    }
+    regs.rip = pc;
+    return ptrace(PTRACE_SETREGS, tid, NULL, &regs) == 0;
+}
+
+#elif defined(__aarch64__)
+
+// brk #0; the trap leaves pc on it
+static const unsigned char TRAP_INSTRUCTION[] = {0x00, 0x00, 0x20, 0xD4};
+static const uintptr_t TRAP_PC_OFFSET = 0;
+
+// Writes every watchpoint slot; a slot is 8 byte aligned and BAS selects the watched bytes
+// within that doubleword
+bool Debugger::apply_debug_registers(pid_t tid, bool enabled)
+{
+    struct user_hwdebug_state state;
+    memset(&state, 0, sizeof(state));
+    struct iovec iov = {&state, sizeof(state)};
+    if (ptrace(PTRACE_GETREGSET, tid, NT_ARM_HW_WATCH, &iov) == -1)
+    {
+        return false;
+    }
+    int slots = std::min(static_cast<int>(state.dbg_info & 0xff), MAX_WATCHPOINTS);
+
+    for (int i = 0; i < slots; i++)
+    {
+        const Watchpoint &watchpoint = watchpoints_[i];
+        state.dbg_regs[i].addr = 0;
+        state.dbg_regs[i].ctrl = 0;
+        if (!enabled || !watchpoint.used)
+        {
+            continue;
+        }
+        uint32_t bas = ((1u << watchpoint.size) - 1) << (watchpoint.address & 7);
+        uint32_t lsc = static_cast<uint32_t>(watchpoint.type);
+        // Enabled, EL0 only (PAC 0b10), load/store control, byte address select
+        state.dbg_regs[i].addr = watchpoint.address & ~static_cast<uintptr_t>(7);
+        state.dbg_regs[i].ctrl = (bas << 5) | (lsc << 3) | (0b10 << 1) | 1;
+    }
+    iov.iov_len = offsetof(struct user_hwdebug_state, dbg_regs) + slots * sizeof(state.dbg_regs[0]);
+    return ptrace(PTRACE_SETREGSET, tid, NT_ARM_HW_WATCH, &iov) == 0;
+}
+
+// Watchpoints fire before the access completes, so pc is the accessing instruction. The
+// thread is stepped past it with the watchpoints off, or it would trap again on resume.
+void Debugger::handle_watchpoint_hit(pid_t tid, uintptr_t accessed)
+{
+    for (int i = 0; i < MAX_WATCHPOINTS; i++)
+    {
+        const Watchpoint &watchpoint = watchpoints_[i];
+        uintptr_t block = watchpoint.address & ~static_cast<uintptr_t>(7);
+        if (watchpoint.used && accessed >= block && accessed < block + 8)
+        {
+            report_registers(tid, {{{"memory", accessed}}, {{"watchpoint", watchpoint.address}}});
+            break;
+        }
+    }
+
+    apply_debug_registers(tid, false);
+    int status = 0;
+    if (ptrace(PTRACE_SINGLESTEP, tid, NULL, NULL) == 0 &&
+        waitpid(tid, &status, TRACEE_WAIT) == tid && (WIFEXITED(status) || WIFSIGNALED(status)))
+    {
+        handle_event(tid, status);
+        return;
+    }
+    apply_debug_registers(tid, true);
+}
+
+bool Debugger::read_registers(pid_t tid, RegisterList &registers, uintptr_t &pc)
+{
+    struct user_pt_regs regs;
+    struct iovec iov = {&regs, sizeof(regs)};
+    if (ptrace(PTRACE_GETREGSET, tid, NT_PRSTATUS, &iov) == -1)
+    {
+        return false;
+    }
+    registers.clear();
+    for (int i = 0; i < 30; ++i)
+    {
+        registers.push_back({{"x" + std::to_string(i), regs.regs[i]}});
+    }
+    registers.push_back({{"lr", regs.regs[30]}});
+    registers.push_back({{"fp", regs.regs[29]}});
+    registers.push_back({{"sp", regs.sp}});
+    registers.push_back({{"pc", regs.pc}});
+    registers.push_back({{"cpsr", regs.pstate}});
+    pc = regs.pc;
+    return true;
+}
+
+bool Debugger::write_pc(pid_t tid, uintptr_t pc)
+{
+    struct user_pt_regs regs;
+    struct iovec iov = {&regs, sizeof(regs)};
+    if (ptrace(PTRACE_GETREGSET, tid, NT_PRSTATUS, &iov) == -1)
+    {
+        return false;
+    }
+    regs.pc = pc;
+    return ptrace(PTRACE_SETREGSET, tid, NT_PRSTATUS, &iov) == 0;
+}
+
+#else
+
+static const unsigned char TRAP_INSTRUCTION[] = {0};
+static const uintptr_t TRAP_PC_OFFSET = 0;
+
+bool Debugger::apply_debug_registers(pid_t tid, bool enabled)
+{
+    errno = ENOTSUP;
+    return !enabled;
+}
+
+void Debugger::handle_watchpoint_hit(pid_t tid, uintptr_t accessed) {}
+
+bool Debugger::read_registers(pid_t tid, RegisterList &registers, uintptr_t &pc)
+{
+    return false;
+}
+
+bool Debugger::write_pc(pid_t tid, uintptr_t pc)
+{
+    return false;
+}
+
+#endif
+
+void Debugger::report_registers(pid_t tid, const RegisterList &extra)
+{
+    RegisterList registers;
+    uintptr_t pc = 0;
+    if (!read_registers(tid, registers, pc))
+    {
+        debug_log(LOG_ERROR, "Failed to get registers of thread %d. Error: %d (%s)\n", tid, errno,
+                  strerror(errno));
+        return;
+    }
+    registers.insert(registers.end(), extra.begin(), extra.end());
+    registers.push_back({{"thread", static_cast<uint64_t>(tid)}});
+    std::string register_json = map_vector_to_json_string(registers);
+    send_register_json(register_json.c_str(), pid_);
+}
+
+// Word-wise PEEKDATA/POKEDATA through a stopped thread. Unlike process_vm_writev this also
+// writes read-only pages, which is what code patching needs.
+bool Debugger::peek_bytes(pid_t tid, uintptr_t address, unsigned char *buffer, size_t size)
+{
+    uintptr_t word_address = address & ~static_cast<uintptr_t>(sizeof(long) - 1);
+    size_t copied = 0;
+    while (copied < size)
+    {
+        errno = 0;
+        long word = ptrace(PTRACE_PEEKDATA, tid, reinterpret_cast<void *>(word_address), NULL);
+        if (errno != 0)
+        {
+            return false;
+        }
+        size_t skip = word_address < address ? address - word_address : 0;
+        size_t count = std::min(sizeof(long) - skip, size - copied);
+        std::memcpy(buffer + copied, reinterpret_cast<unsigned char *>(&word) + skip, count);
+        copied += count;
+        word_address += sizeof(long);
+    }
+    return true;
+}
+
+bool Debugger::poke_bytes(pid_t tid, uintptr_t address, const unsigned char *buffer, size_t size)
+{
+    uintptr_t word_address = address & ~static_cast<uintptr_t>(sizeof(long) - 1);
+    size_t copied = 0;
+    while (copied < size)
+    {
+        size_t skip = word_address < address ? address - word_address : 0;
+        size_t count = std::min(sizeof(long) - skip, size - copied);
+        long word = 0;
+        if (skip != 0 || count != sizeof(long))
+        {
+            errno = 0;
+            word = ptrace(PTRACE_PEEKDATA, tid, reinterpret_cast<void *>(word_address), NULL);
+            if (errno != 0)
+            {
+                return false;
+            }
+        }
+        std::memcpy(reinterpret_cast<unsigned char *>(&word) + skip, buffer + copied, count);
+        if (ptrace(PTRACE_POKEDATA, tid, reinterpret_cast<void *>(word_address), word) == -1)
+        {
+            return false;
+        }
+        copied += count;
+        word_address += sizeof(long);
+    }
+    return true;
+}
+
+// Handles a SIGTRAP that may come from one of our trap instructions. A hit is reported with
+// pc on the breakpoint, then the original instruction is put back for a single step and the
+// trap re-armed. Returns false for traps that belong to the target.
+bool Debugger::handle_breakpoint_trap(pid_t tid)
+{
+    RegisterList registers;
+    uintptr_t pc = 0;
+    if (!read_registers(tid, registers, pc))
+    {
+        return false;
+    }
+    uintptr_t address = pc - TRAP_PC_OFFSET;
+    unsigned char original[sizeof(TRAP_INSTRUCTION)];
+
+    if (!breakpoints_.count(address))
+    {
+        // A thread that hit the trap just before the breakpoint was removed reruns the
+        // restored instruction; a trap still in memory is the target's own
+        unsigned char current[sizeof(TRAP_INSTRUCTION)];
+        if (removed_breakpoints_.count(address) && peek_bytes(tid, address, current, sizeof(current)) &&
+            memcmp(current, TRAP_INSTRUCTION, sizeof(current)) != 0)
+        {
+            write_pc(tid, address);
+            stopped_[tid] = 0;
+            return true;
+        }
+        return false;
+    }
+    if (!breakpoint_original_bytes(pid_, address, original, sizeof(original)))
+    {
+        return false;
+    }
+
+    write_pc(tid, address);
+    report_registers(tid, {{{"breakpoint", address}}});
+    if (breakpoint_halts(pid_, address))
+    {
+        // The trap stays in place; stepping or continuing the thread takes it past it
+        halted_[tid] = 0;
+        return true;
+    }
+    stopped_[tid] = 0;
+
+    if (!poke_bytes(tid, address, original, sizeof(original)))
+    {
+        debug_log(LOG_ERROR, "Failed to restore the instruction at 0x%lx\n", address);
+        return true;
+    }
+    int status = 0;
+    if (ptrace(PTRACE_SINGLESTEP, tid, NULL, NULL) == 0 &&
+        waitpid(tid, &status, TRACEE_WAIT) == tid)
+    {
+        if (WIFEXITED(status) || WIFSIGNALED(status))
+        {
+            handle_event(tid, status);
+        }
+        else if (WIFSTOPPED(status) && WSTOPSIG(status) != SIGTRAP)
+        {
+            stopped_[tid] = WSTOPSIG(status);
+        }
+    }
+    // Any stopped thread will do for the write; memory is shared
+    pid_t writer = stopped_.count(tid) ? tid : threads_.empty() ? 0 : *threads_.begin();
+    if (writer && !poke_bytes(writer, address, TRAP_INSTRUCTION, sizeof(TRAP_INSTRUCTION)))
+    {
+        debug_log(LOG_ERROR, "Failed to re-arm the breakpoint at 0x%lx\n", address);
+    }
+    return true;
+}
+
+// Runs one instruction of a halted thread. A breakpoint under pc gets its original
+// instruction back for the step and is re-armed afterwards, also when the thread steps
+// from one breakpoint onto another.
+int Debugger::step_thread(pid_t tid)
+{
+    RegisterList registers;
+    uintptr_t pc = 0;
+    if (!read_registers(tid, registers, pc))
+    {
+        return errno ? errno : ESRCH;
+    }
+    unsigned char original[sizeof(TRAP_INSTRUCTION)];
+    bool on_breakpoint =
+        breakpoints_.count(pc) && breakpoint_original_bytes(pid_, pc, original, sizeof(original));
+    if (on_breakpoint && !poke_bytes(tid, pc, original, sizeof(original)))
+    {
+        return errno ? errno : EIO;
+    }
+
+    int error = 0;
+    int status = 0;
+    if (ptrace(PTRACE_SINGLESTEP, tid, NULL, NULL) == -1 ||
+        waitpid(tid, &status, TRACEE_WAIT) != tid)
+    {
+        error = errno ? errno : ESRCH;
+    }
+    else if (WIFEXITED(status) || WIFSIGNALED(status))
+    {
+        handle_event(tid, status);
+        error = ESRCH;
+    }
+    else if (WIFSTOPPED(status) && WSTOPSIG(status) != SIGTRAP)
+    {
+        // A signal arrived before the instruction ran; it is delivered on continue
+        halted_[tid] = WSTOPSIG(status);
+    }
+
+    // Every thread is stopped while a command runs, so any of them can do the write
+    if (on_breakpoint && !threads_.empty() &&
+        !poke_bytes(threads_.count(tid) ? tid : *threads_.begin(), pc, TRAP_INSTRUCTION,
+                    sizeof(TRAP_INSTRUCTION)))
+    {
+        debug_log(LOG_ERROR, "Failed to re-arm the breakpoint at 0x%lx\n", pc);
+    }
+    return error;
+}
+
+// The NT_PRSTATUS register set, the layout read_thread_registers uses on the Rust side
+bool Debugger::read_register_set(pid_t tid, uint64_t *registers, size_t count)
+{
+    struct iovec iov = {registers, count * sizeof(uint64_t)};
+    return ptrace(PTRACE_GETREGSET, tid, NT_PRSTATUS, &iov) == 0;
+}
+
+// Setting an address that is already watched replaces its size and type
+int Debugger::set_watchpoint(uintptr_t address, int size, WatchpointType type)
+{
+    return run_command([=]() {
+        int index = -1;
+        for (int i = 0; i < MAX_WATCHPOINTS; i++)
+        {
+            if (watchpoints_[i].used && watchpoints_[i].address == address)
+            {
+                index = i;
+                break;
+            }
+            if (!watchpoints_[i].used && index == -1)
+            {
+                index = i;
+            }
+        }
+        if (index == -1)
+        {
+            return ENOSPC;
+        }
+
+        Watchpoint previous = watchpoints_[index];
+        watchpoints_[index] = {true, address, size, type};
+        for (pid_t tid : threads_)
+        {
+            if (!apply_debug_registers(tid, true))
+            {
+                int error = errno ? errno : EINVAL;
+                debug_log(LOG_ERROR, "Failed to set watchpoint on thread %d. Error: %d (%s)\n",
+                          tid, error, strerror(error));
+                watchpoints_[index] = previous;
+                for (pid_t restored : threads_)
+                {
+                    apply_debug_registers(restored, true);
+                }
+                return error;
+            }
+        }
+        return 0;
+    });
+}
+
+// Detaches once the last watchpoint or breakpoint is gone so the target is no longer traced
+int Debugger::remove_watchpoint(uintptr_t address)
+{
+    return run_command([=]() {
+        for (Watchpoint &watchpoint : watchpoints_)
+        {
+            if (watchpoint.used && watchpoint.address == address)
+            {
+                watchpoint.used = false;
+                if (!has_watchpoints() && breakpoints_.empty())
+                {
+                    detach_all();
+                    return 0;
+                }
+                for (pid_t tid : threads_)
+                {
+                    apply_debug_registers(tid, true);
+                }
+                return 0;
+            }
+        }
+        return ENOENT;
+    });
+}
+
+// Only records the address; the trap itself is written through write_memory_native, which
+// ends up in write_memory below while the target is traced
+int Debugger::set_breakpoint(uintptr_t address)
+{
+    return run_command([=]() {
+        breakpoints_.insert(address);
+        removed_breakpoints_.erase(address);
+        return 0;
+    });
+}
+
+int Debugger::remove_breakpoint(uintptr_t address)
+{
+    return run_command([=]() {
+        if (!breakpoints_.erase(address))
+        {
+            return ENOENT;
+        }
+        removed_breakpoints_.insert(address);
+        if (!has_watchpoints() && breakpoints_.empty())
+        {
+            detach_all();
+        }
+        return 0;
+    });
+}
+
+int Debugger::write_memory(uintptr_t address, const unsigned char *buffer, size_t size)
+{
+    return run_command([=]() {
+        if (threads_.empty())
+        {
+            return ESRCH;
+        }
+        return poke_bytes(*threads_.begin(), address, buffer, size) ? 0 : (errno ? errno : EIO);
+    });
+}
+
+// EBUSY when tid is not halted on a breakpoint, since only a held thread can be stepped
+int Debugger::single_step(pid_t tid, uint64_t *registers, size_t count)
+{
+    return run_command([=]() {
+        if (!halted_.count(tid))
+        {
+            return threads_.count(tid) ? EBUSY : ESRCH;
+        }
+        int error = step_thread(tid);
+        if (error != 0)
+        {
+            return error;
+        }
+        return read_register_set(tid, registers, count) ? 0 : (errno ? errno : EIO);
+    });
+}
+
+int Debugger::resume_thread(pid_t tid)
+{
+    return run_command([=]() {
+        if (!halted_.count(tid))
+        {
+            return threads_.count(tid) ? EBUSY : ESRCH;
+        }
+        // Step off a breakpoint first, or the thread would trap on it again straight away
+        RegisterList registers;
+        uintptr_t pc = 0;
+        if (read_registers(tid, registers, pc) && breakpoints_.count(pc) && halted_[tid] == 0)
+        {
+            int error = step_thread(tid);
+            if (error != 0)
+            {
+                return error;
+            }
+        }
+        // resume_all continues it once the command returns
+        stopped_[tid] = halted_[tid];
+        halted_.erase(tid);
+        return 0;
+    });
+}
+
+int Debugger::thread_registers(pid_t tid, uint64_t *registers, size_t count)
+{
+    return run_command([=]() {
+        if (!threads_.count(tid))
+        {
+            return ESRCH;
+        }
+        return read_register_set(tid, registers, count) ? 0 : (errno ? errno : EIO);
+    });
+}
+
 extern "C"
 {
     bool debugger_new(int pid)
     {
+        std::lock_guard<std::mutex> lock(g_debugger_mutex);
+        if (pid == getpid())
+        {
+            debug_log(LOG_ERROR, "Cannot debug the server's own process\n");
+            errno = EPERM;
+            return false;
+        }
+        if (g_debugger && g_debugger->is_attached(pid))
+        {
+            return true;
+        }
+        // Detaching would leave the current target's traps in its code with nothing to
+        // handle them, so it has to give its breakpoints and watchpoints up first
+        if (g_debugger && g_debugger->in_use())
+        {
+            debug_log(LOG_ERROR, "Cannot debug process %d while another has breakpoints or watchpoints\n",
+                      pid);
+            errno = EBUSY;
+            return false;
+        }
+        delete g_debugger;
+        g_debugger = new Debugger(pid);
+        int error = g_debugger->initialize();
+        if (error != 0)
+        {
+            delete g_debugger;
+            g_debugger = nullptr;
+            errno = error;
+            return false;
+        }
         return true;
     }
 
+    // Detaches from every thread, leaving them running with the debug registers cleared
+    void debugger_detach()
+    {
+        std::lock_guard<std::mutex> lock(g_debugger_mutex);
+        delete g_debugger;
+        g_debugger = nullptr;
+    }
+
     int set_watchpoint_native(uint64_t address, int size, WatchpointType type)
     {
+        std::lock_guard<std::mutex> lock(g_debugger_mutex);
+        if (!g_debugger)
+        {
+            errno = ESRCH;
+            return -1;
+        }
+        int error = g_debugger->set_watchpoint(address, size, type);
+        if (error != 0)
+        {
+            errno = error;
+            return -1;
+        }
         return 0;
     }
 
     int remove_watchpoint_native(uint64_t address)
     {
+        std::lock_guard<std::mutex> lock(g_debugger_mutex);
+        int error = g_debugger ? g_debugger->remove_watchpoint(address) : ENOENT;
+        if (error != 0)
+        {
+            errno = error;
+            return -1;
+        }
         return 0;
     }
 
-    int set_breakpoint_native(uint64_t address, int hit_count)
+    // hit_count is only used by the Darwin hardware breakpoints
+    int set_breakpoint_native(uint64_t address, int)
     {
+        std::lock_guard<std::mutex> lock(g_debugger_mutex);
+        if (!g_debugger)
+        {
+            errno = ESRCH;
+            return -1;
+        }
+        int error = g_debugger->set_breakpoint(address);
+        if (error != 0)
+        {
+            errno = error;
+            return -1;
+        }
         return 0;
     }
 
     int remove_breakpoint_native(uint64_t address)
     {
+        std::lock_guard<std::mutex> lock(g_debugger_mutex);
+        int error = g_debugger ? g_debugger->remove_breakpoint(address) : ENOENT;
+        if (error != 0)
+        {
+            errno = error;
+            return -1;
+        }
+        return 0;
+    }
+
+    // Writes through the tracer when the debugger holds pid, since nothing else can attach
+    // to it then. Returns the bytes written, or -1 with errno ESRCH when it does not.
+    ssize_t debugger_write_memory(int pid, uintptr_t address, size_t size,
+                                  const unsigned char *buffer)
+    {
+        std::lock_guard<std::mutex> lock(g_debugger_mutex);
+        if (!g_debugger || !g_debugger->is_attached(pid))
+        {
+            errno = ESRCH;
+            return -1;
+        }
+        int error = g_debugger->write_memory(address, buffer, size);
+        if (error != 0)
+        {
+            errno = error;
+            return -1;
+        }
+        return size;
+    }
+
+    // The calls below fail with ESRCH when the debugger does not hold pid, so callers can
+    // tell that apart from an error of the request itself
+    int debugger_single_step(int pid, int tid, uint64_t *registers, size_t count)
+    {
+        std::lock_guard<std::mutex> lock(g_debugger_mutex);
+        if (!g_debugger || !g_debugger->is_attached(pid))
+        {
+            errno = ESRCH;
+            return -1;
+        }
+        int error = g_debugger->single_step(tid, registers, count);
+        if (error != 0)
+        {
+            errno = error;
+            return -1;
+        }
+        return 0;
+    }
+
+    int debugger_resume_thread(int pid, int tid)
+    {
+        std::lock_guard<std::mutex> lock(g_debugger_mutex);
+        if (!g_debugger || !g_debugger->is_attached(pid))
+        {
+            errno = ESRCH;
+            return -1;
+        }
+        int error = g_debugger->resume_thread(tid);
+        if (error != 0)
+        {
+            errno = error;
+            return -1;
+        }
+        return 0;
+    }
+
+    int debugger_thread_registers(int pid, int tid, uint64_t *registers, size_t count)
+    {
+        std::lock_guard<std::mutex> lock(g_debugger_mutex);
+        if (!g_debugger || !g_debugger->is_attached(pid))
+        {
+            errno = ESRCH;
+            return -1;
+        }
+        int error = g_debugger->thread_registers(tid, registers, count);
+        if (error != 0)
+        {
+            errno = error;
+            return -1;
+        }
         return 0;
     }
-}
\ No newline at end of file
+}
@@ -1,6 +1,20 @@
 #ifndef DEBUGGER_H
 #define DEBUGGER_H
 
+#include <signal.h>
+#include <stddef.h>
+#include <sys/user.h>
+
+#include <condition_variable>
+#include <cstdint>
+#include <functional>
+#include <future>
+#include <map>
+#include <mutex>
+#include <set>
+#include <thread>
+
+#include "../common/util.h"
 #include "native_api.h"
 
 enum class WatchpointType
@@ -10,4 +24,85 @@ enum class WatchpointType
     READWRITE = 3
 };
 
-#endif
\ No newline at end of file
+// ptrace based debugger. Every ptrace request has to come from the thread that attached, so a
+// single tracer thread owns the target and the public calls hand their work to it.
+class Debugger
+{
+public:
+    Debugger(pid_t pid);
+    ~Debugger();
+    int initialize();
+    bool is_attached(pid_t pid);
+    bool in_use();
+    int set_watchpoint(uintptr_t address, int size, WatchpointType type);
+    int remove_watchpoint(uintptr_t address);
+    int set_breakpoint(uintptr_t address);
+    int remove_breakpoint(uintptr_t address);
+    int write_memory(uintptr_t address, const unsigned char *buffer, size_t size);
+    int single_step(pid_t tid, uint64_t *registers, size_t count);
+    int resume_thread(pid_t tid);
+    int thread_registers(pid_t tid, uint64_t *registers, size_t count);
+
+private:
+    static constexpr int MAX_WATCHPOINTS = 4;  // x86 has four debug address registers
+    // Marks a thread in group-stop, which is resumed with PTRACE_LISTEN so it stays stopped
+    static constexpr int RESUME_LISTEN = -1;
+
+    typedef std::vector<std::map<std::string, uint64_t>> RegisterList;
+
+    struct Watchpoint
+    {
+        bool used;
+        uintptr_t address;
+        int size;
+        WatchpointType type;
+    };
+
+    void run(std::promise<int> *attached);
+    int attach_all();
+    void detach_all();
+    int run_command(std::function<int()> command);
+    bool stop_all();
+    void resume_all();
+    void handle_event(pid_t tid, int status);
+    void handle_watchpoint_hit(pid_t tid, uintptr_t accessed);
+    bool handle_breakpoint_trap(pid_t tid);
+    int step_thread(pid_t tid);
+    bool read_register_set(pid_t tid, uint64_t *registers, size_t count);
+    bool apply_debug_registers(pid_t tid, bool enabled);
+    bool has_watchpoints() const;
+    bool read_registers(pid_t tid, RegisterList &registers, uintptr_t &pc);
+    bool write_pc(pid_t tid, uintptr_t pc);
+    void report_registers(pid_t tid, const RegisterList &extra);
+    bool peek_bytes(pid_t tid, uintptr_t address, unsigned char *buffer, size_t size);
+    bool poke_bytes(pid_t tid, uintptr_t address, const unsigned char *buffer, size_t size);
+
+    pid_t pid_;
+    std::thread thread_;
+    bool attached_;
+    Watchpoint watchpoints_[MAX_WATCHPOINTS];
+    // Addresses holding a trap instruction; the original bytes live on the Rust side
+    std::set<uintptr_t> breakpoints_;
+    // Removed breakpoints, to tell a late hit from a trap that belongs to the target
+    std::set<uintptr_t> removed_breakpoints_;
+    std::set<pid_t> threads_;
+    // Threads seen through a clone event that still need the debug registers written
+    std::set<pid_t> new_threads_;
+    // Threads sent PTRACE_INTERRUPT whose interrupt stop has not been seen yet
+    std::set<pid_t> interrupted_;
+    // Stopped threads and the signal each one is resumed with
+    std::map<pid_t, int> stopped_;
+    // Threads held on a halting breakpoint, left out of resume_all until continued, and the
+    // signal each one is resumed with
+    std::map<pid_t, int> halted_;
+
+    std::mutex command_mutex_;
+    std::mutex mutex_;
+    std::condition_variable command_cv_;
+    std::function<int()> command_;
+    int command_result_;
+    bool command_done_;
+    bool finished_;
+};
+
+#endif
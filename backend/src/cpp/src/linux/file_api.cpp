@@ -104,6 +104,29 @@ const void *read_file(const char *path, size_t *size, char **error_message)
     return buffer;
 }
 
+int write_file(const char *path, const void *data, size_t size, bool append,
+               char **error_message)
+{
+    std::ofstream file(path, std::ios::binary | (append ? std::ios::app : std::ios::trunc));
+    if (!file.is_open())
+    {
+        std::ostringstream error;
+        error << "Error: Could not open file " << path << ". Error: " << strerror(errno);
+        *error_message = strdup(error.str().c_str());
+        return -1;
+    }
+
+    if (!file.write((const char *)data, size))
+    {
+        std::ostringstream error;
+        error << "Error: Failed to write file " << path << ". Error: " << strerror(errno);
+        *error_message = strdup(error.str().c_str());
+        return -1;
+    }
+
+    return 0;
+}
+
 const char *get_application_info_native(pid_t pid)
 {
     char exe_path[64];
@@ -20,6 +20,8 @@
 
 extern "C" const char *explore_directory(const char *path, int maxDepth);
 extern "C" const void *read_file(const char *path, size_t *size, char **error_message);
+extern "C" int write_file(const char *path, const void *data, size_t size, bool append,
+                          char **error_message);
 extern "C" const char *get_application_info_native(pid_t pid);
 
 #endif
\ No newline at end of file
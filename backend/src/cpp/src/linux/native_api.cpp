@@ -42,7 +42,30 @@ pid_t get_pid_native()
     return getpid();
 }
 
-ssize_t read_memory_native(int pid, uintptr_t address, size_t size, unsigned char *buffer)
+// Reads through /proc/<pid>/mem. Used when process_vm_readv is missing from an older Android
+// libc or the kernel refuses it (ENOSYS without CONFIG_CROSS_MEMORY_ATTACH, ENOSYS or EPERM
+// under a seccomp filter).
+static ssize_t read_proc_mem(int pid, uintptr_t address, size_t size, unsigned char *buffer)
+{
+    char path[64];
+    snprintf(path, sizeof(path), "/proc/%d/mem", pid);
+    int fd = open(path, O_RDONLY | O_CLOEXEC);
+    if (fd < 0)
+    {
+        return -errno;
+    }
+    ssize_t nread = pread(fd, buffer, size, static_cast<off_t>(address));
+    int error = errno;
+    close(fd);
+    if (nread < 0)
+    {
+        errno = error;
+        return -error;
+    }
+    return nread;
+}
+
+static ssize_t read_vm(int pid, uintptr_t address, size_t size, unsigned char *buffer)
 {
     struct iovec local_iov;
     struct iovec remote_iov;
@@ -53,17 +76,47 @@ ssize_t read_memory_native(int pid, uintptr_t address, size_t size, unsigned cha
     remote_iov.iov_len = size;
 
 #ifdef TARGET_IS_ANDROID
-    ssize_t nread = PROCESS_VM_READV(pid, &local_iov, 1, &remote_iov, 1, 0);
+    if (!PROCESS_VM_READV)
+    {
+        errno = ENOSYS;
+        return -1;
+    }
+    return PROCESS_VM_READV(pid, &local_iov, 1, &remote_iov, 1, 0);
 #else
-    ssize_t nread = process_vm_readv(pid, &local_iov, 1, &remote_iov, 1, 0);
+    return process_vm_readv(pid, &local_iov, 1, &remote_iov, 1, 0);
 #endif
+}
+
+bool process_vm_readv_usable()
+{
+    unsigned char source = 0x5a;
+    unsigned char target = 0;
+    return read_vm(getpid(), reinterpret_cast<uintptr_t>(&source), 1, &target) == 1 &&
+           target == source;
+}
+
+ssize_t read_memory_native(int pid, uintptr_t address, size_t size, unsigned char *buffer)
+{
+    ssize_t nread = read_vm(pid, address, size, buffer);
+    if (nread < 0 && (errno == ENOSYS || errno == EPERM))
+    {
+        int vm_error = errno;
+        nread = read_proc_mem(pid, address, size, buffer);
+        // EPERM may also mean the target is simply not ours to read, and the caller's ptrace
+        // fallback keys off it, so a failed /proc read must not replace it with EACCES
+        if (nread < 0 && vm_error == EPERM)
+        {
+            errno = EPERM;
+        }
+    }
 
     if (nread < 0)
     {
+        int error = errno;
         debug_log(LOG_DEBUG,
                   "Failed to read memory from process %d at address 0x%lx. Error: %d (%s)\n", pid,
-                  address, errno, strerror(errno));
-        return -errno;
+                  address, error, strerror(error));
+        return -error;
     }
 
     if (static_cast<size_t>(nread) < size)
@@ -75,6 +128,63 @@ ssize_t read_memory_native(int pid, uintptr_t address, size_t size, unsigned cha
     return nread;
 }
 
+// Waits for the stop of a PTRACE_ATTACH made on this thread. __WNOTHREAD keeps this wait off
+// the debugger thread's tracees, and the debugger's waits off this one.
+static void wait_for_attach_stop(int pid)
+{
+    while (waitpid(pid, NULL, __WALL | __WNOTHREAD) == -1 && errno == EINTR)
+    {
+    }
+}
+
+// Reads word by word with PTRACE_PEEKDATA. ptrace can read pages process_vm_readv rejects
+// (e.g. PROT_NONE guard pages), but the target is stopped for the whole read, so this is only
+// used for regions the fast path failed on. Returns the bytes read before the first failing word.
+ssize_t read_memory_ptrace_native(int pid, uintptr_t address, size_t size, unsigned char *buffer)
+{
+    if (ptrace(PTRACE_ATTACH, pid, NULL, NULL) == -1)
+    {
+        int error = errno;
+        debug_log(LOG_DEBUG, "Failed to attach to process %d for reading. Error: %d (%s)\n", pid,
+                  error, strerror(error));
+        return -error;
+    }
+    wait_for_attach_stop(pid);
+
+    // Aligned words never straddle a page boundary, so a readable range is read in full
+    uintptr_t word_address = address & ~static_cast<uintptr_t>(sizeof(long) - 1);
+    size_t copied = 0;
+    int error = 0;
+    while (copied < size)
+    {
+        errno = 0;
+        long word = ptrace(PTRACE_PEEKDATA, pid, reinterpret_cast<void *>(word_address), NULL);
+        if (errno != 0)
+        {
+            error = errno;
+            break;
+        }
+        size_t skip = word_address < address ? address - word_address : 0;
+        size_t count = std::min(sizeof(long) - skip, size - copied);
+        std::memcpy(buffer + copied, reinterpret_cast<unsigned char *>(&word) + skip, count);
+        copied += count;
+        word_address += sizeof(long);
+    }
+
+    if (ptrace(PTRACE_DETACH, pid, NULL, NULL) == -1)
+    {
+        debug_log(LOG_WARN, "Failed to detach from process %d. Error: %d (%s)\n", pid, errno,
+                  strerror(errno));
+    }
+
+    if (copied == 0 && error != 0)
+    {
+        errno = error;
+        return -error;
+    }
+    return copied;
+}
+
 ssize_t write_memory_native(int pid, void *address, size_t size, unsigned char *buffer)
 {
     if (pid == get_pid_native())
@@ -118,11 +228,36 @@ ssize_t write_memory_native(int pid, void *address, size_t size, unsigned char *
         // Writing to another process
         if (ptrace(PTRACE_ATTACH, pid, NULL, NULL) == -1)
         {
-            debug_log(LOG_ERROR, "Failed to attach to process %d. Error: %d (%s)\n", pid, errno,
-                      strerror(errno));
+            int error = errno;
+            // The debugger may already be tracing the target, in which case it writes for us;
+            // otherwise writable pages can still be written without attaching
+            if (error == EPERM)
+            {
+                ssize_t patched = debugger_write_memory(pid, reinterpret_cast<uintptr_t>(address),
+                                                        size, buffer);
+                if (patched >= 0)
+                {
+                    return patched;
+                }
+                iovec local_iov = {buffer, size};
+                iovec remote_iov = {address, size};
+#ifdef TARGET_IS_ANDROID
+                ssize_t written = PROCESS_VM_WRITEV
+                                      ? PROCESS_VM_WRITEV(pid, &local_iov, 1, &remote_iov, 1, 0)
+                                      : -1;
+#else
+                ssize_t written = process_vm_writev(pid, &local_iov, 1, &remote_iov, 1, 0);
+#endif
+                if (written >= 0)
+                {
+                    return written;
+                }
+            }
+            debug_log(LOG_ERROR, "Failed to attach to process %d. Error: %d (%s)\n", pid, error,
+                      strerror(error));
             return -1;
         }
-        waitpid(pid, NULL, 0);
+        wait_for_attach_stop(pid);
 
         ssize_t total_written = 0;
         for (size_t i = 0; i < size; i += sizeof(long))
@@ -450,12 +585,13 @@ int native_init(int mode)
         return -1;
     }
 
+    // Reads fall back to /proc/<pid>/mem without it, so a missing symbol is not fatal
     PROCESS_VM_READV = (process_vm_readv_func)dlsym(handle, "process_vm_readv");
     if (!PROCESS_VM_READV)
     {
-        debug_log(LOG_ERROR, "Failed to find process_vm_readv symbol. Error: %s\n", dlerror());
-        dlclose(handle);
-        return -1;
+        debug_log(LOG_WARN,
+                  "process_vm_readv is not available, reading through /proc/pid/mem. Error: %s\n",
+                  dlerror());
     }
 
     PROCESS_VM_WRITEV = (process_vm_writev_func)dlsym(handle, "process_vm_writev");
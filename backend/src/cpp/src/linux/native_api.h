@@ -17,6 +17,7 @@
 #include <sys/wait.h>
 #include <unistd.h>
 
+#include <algorithm>
 #include <cstdarg>
 #include <cstdio>
 #include <cstring>
@@ -50,10 +51,19 @@ typedef struct
 } ModuleInfo;
 
 extern "C" void native_log(int level, const char *message);
+extern "C" void send_register_json(const char *register_json, pid_t pid);
+extern "C" bool breakpoint_original_bytes(pid_t pid, uintptr_t address, unsigned char *buffer,
+                                          size_t size);
+extern "C" bool breakpoint_halts(pid_t pid, uintptr_t address);
+extern "C" ssize_t debugger_write_memory(int pid, uintptr_t address, size_t size,
+                                         const unsigned char *buffer);
 int debug_log(LogLevel level, const char *format, ...);
 extern "C" pid_t get_pid_native();
 extern "C" ssize_t read_memory_native(int pid, uintptr_t address, size_t size,
                                       unsigned char *buffer);
+extern "C" bool process_vm_readv_usable();
+extern "C" ssize_t read_memory_ptrace_native(int pid, uintptr_t address, size_t size,
+                                             unsigned char *buffer);
 extern "C" ssize_t write_memory_native(int pid, void *address, size_t size, unsigned char *buffer);
 extern "C" void enumerate_regions_to_buffer(pid_t pid, char *buffer, size_t buffer_size);
 extern "C" ProcessInfo *enumprocess_native(size_t *count);
@@ -163,6 +163,36 @@ const void *read_file(const char *path, size_t *size, char **error_message)
     return data;
 }
 
+int write_file(const char *path, const void *data, size_t size, bool append,
+               char **error_message)
+{
+    HANDLE hFile = CreateFile(path, append ? FILE_APPEND_DATA : GENERIC_WRITE, 0, NULL,
+                              append ? OPEN_ALWAYS : CREATE_ALWAYS, FILE_ATTRIBUTE_NORMAL, NULL);
+    if (hFile == INVALID_HANDLE_VALUE)
+    {
+        std::ostringstream error;
+        error << "Error: Could not open file " << path;
+        *error_message = strdup(error.str().c_str());
+        debug_log(LOG_ERROR, "Failed to open file %s. Error code: %lu", path, GetLastError());
+        return -1;
+    }
+
+    DWORD bytesWritten;
+    if (!WriteFile(hFile, data, static_cast<DWORD>(size), &bytesWritten, NULL) ||
+        bytesWritten != size)
+    {
+        std::ostringstream error;
+        error << "Error: Could not write file " << path;
+        *error_message = strdup(error.str().c_str());
+        debug_log(LOG_ERROR, "Failed to write file %s. Error code: %lu", path, GetLastError());
+        CloseHandle(hFile);
+        return -1;
+    }
+
+    CloseHandle(hFile);
+    return 0;
+}
+
 const char *get_application_info_native(DWORD pid)
 {
     HANDLE hProcess = OpenProcess(PROCESS_QUERY_INFORMATION | PROCESS_VM_READ, FALSE, pid);
@@ -0,0 +1,296 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Condvar, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+// Native calls that can block on the target, each with its own deadline since a suspend or a
+// debugger round trip is expected to take longer than a read
+#[derive(Debug, Clone, Copy)]
+pub enum OperationClass {
+    Read,
+    Suspend,
+    Breakpoint,
+}
+
+pub const DEFAULT_READ_TIMEOUT_MS: u64 = 5_000;
+pub const DEFAULT_SUSPEND_TIMEOUT_MS: u64 = 5_000;
+pub const DEFAULT_BREAKPOINT_TIMEOUT_MS: u64 = 10_000;
+
+static READ_TIMEOUT_MS: AtomicU64 = AtomicU64::new(DEFAULT_READ_TIMEOUT_MS);
+static SUSPEND_TIMEOUT_MS: AtomicU64 = AtomicU64::new(DEFAULT_SUSPEND_TIMEOUT_MS);
+static BREAKPOINT_TIMEOUT_MS: AtomicU64 = AtomicU64::new(DEFAULT_BREAKPOINT_TIMEOUT_MS);
+
+impl OperationClass {
+    fn setting(self) -> &'static AtomicU64 {
+        match self {
+            OperationClass::Read => &READ_TIMEOUT_MS,
+            OperationClass::Suspend => &SUSPEND_TIMEOUT_MS,
+            OperationClass::Breakpoint => &BREAKPOINT_TIMEOUT_MS,
+        }
+    }
+}
+
+// 0 turns the deadline off for that class
+pub fn set_timeout(class: OperationClass, millis: u64) {
+    class.setting().store(millis, Ordering::Relaxed);
+}
+
+// Why a call gave no result: it overran its deadline, or it panicked, which retrying won't fix
+#[derive(Debug)]
+pub enum DeadlineError {
+    TimedOut { operation: String, after: Duration },
+    Panicked { operation: String, message: String },
+}
+
+impl DeadlineError {
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, DeadlineError::TimedOut { .. })
+    }
+}
+
+impl std::fmt::Display for DeadlineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeadlineError::TimedOut { operation, after } => write!(
+                f,
+                "Timeout: {} did not finish within {}ms",
+                operation,
+                after.as_millis()
+            ),
+            DeadlineError::Panicked { operation, message } => {
+                write!(f, "{} failed: {}", operation, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DeadlineError {}
+
+impl From<DeadlineError> for std::io::Error {
+    fn from(e: DeadlineError) -> Self {
+        let kind = if e.is_timeout() {
+            std::io::ErrorKind::TimedOut
+        } else {
+            std::io::ErrorKind::Other
+        };
+        std::io::Error::new(kind, e)
+    }
+}
+
+// The text a panic was raised with, for the payloads panic! and friends produce
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "the call panicked".to_string())
+}
+
+// Workers per class. Reads are independent and run side by side; suspends and debugger calls
+// each go through a single thread, which keeps every ptrace request for a class on the thread
+// that attached (ptrace only answers the tracer thread) and serializes changes to the
+// breakpoint table and the suspension tracking.
+const READ_WORKERS: usize = 4;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+fn worker_pool(class: OperationClass) -> &'static Mutex<mpsc::Sender<Job>> {
+    static READ: OnceLock<Mutex<mpsc::Sender<Job>>> = OnceLock::new();
+    static SUSPEND: OnceLock<Mutex<mpsc::Sender<Job>>> = OnceLock::new();
+    static BREAKPOINT: OnceLock<Mutex<mpsc::Sender<Job>>> = OnceLock::new();
+    let (pool, name, workers) = match class {
+        OperationClass::Read => (&READ, "native-read", READ_WORKERS),
+        OperationClass::Suspend => (&SUSPEND, "native-suspend", 1),
+        OperationClass::Breakpoint => (&BREAKPOINT, "native-debug", 1),
+    };
+    pool.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for index in 0..workers {
+            let receiver = receiver.clone();
+            let spawned = thread::Builder::new()
+                .name(format!("{}-{}", name, index))
+                .spawn(move || loop {
+                    let job = match receiver.lock().unwrap().recv() {
+                        Ok(job) => job,
+                        Err(_) => return,
+                    };
+                    // A panicking call is reported by the panic hook; the worker carries on
+                    let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                });
+            if let Err(e) = spawned {
+                log::error!("Failed to start the {} worker: {}", name, e);
+            }
+        }
+        Mutex::new(sender)
+    })
+}
+
+// Where a call's result goes. The caller and the worker both settle it under the lock, so a
+// result is either taken by the caller or handed to undo, never both and never neither.
+enum Delivery<T> {
+    Pending,
+    Ready(T),
+    Panicked(String),
+    Abandoned,
+}
+
+// Runs call on the class's worker pool and waits at most the class's deadline for it
+pub fn run_with_deadline<T, F>(
+    class: OperationClass,
+    operation: impl Into<String>,
+    call: F,
+) -> Result<T, DeadlineError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    run_with_deadline_or_undo(class, operation, call, |_| {})
+}
+
+// A native call cannot be interrupted, so one that overruns keeps its worker until it
+// returns. What happens to a call the caller gave up on:
+// - still queued behind a blocked one: it is dropped without running, changing nothing
+// - finished after the deadline: undo gets its result on the same worker, so whatever it
+//   changed (a breakpoint set, a process stopped) can be put back before the next call runs
+pub fn run_with_deadline_or_undo<T, F, U>(
+    class: OperationClass,
+    operation: impl Into<String>,
+    call: F,
+    undo: U,
+) -> Result<T, DeadlineError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+    U: FnOnce(T) + Send + 'static,
+{
+    let millis = class.setting().load(Ordering::Relaxed);
+    let operation = operation.into();
+    let after = Duration::from_millis(millis);
+    let delivery = Arc::new((Mutex::new(Delivery::Pending), Condvar::new()));
+
+    let job_delivery = delivery.clone();
+    let job_operation = operation.clone();
+    let job: Job = Box::new(move || {
+        let (slot, ready) = &*job_delivery;
+        if matches!(*slot.lock().unwrap(), Delivery::Abandoned) {
+            log::debug!(
+                "Skipping {}, which timed out before it started",
+                job_operation
+            );
+            return;
+        }
+        let result = panic::catch_unwind(AssertUnwindSafe(call));
+        let mut slot = slot.lock().unwrap();
+        match (&*slot, result) {
+            (Delivery::Abandoned, Ok(result)) => {
+                drop(slot);
+                log::warn!("{} finished after its deadline; undoing it", job_operation);
+                undo(result);
+            }
+            (Delivery::Abandoned, Err(_)) => {}
+            (_, Ok(result)) => *slot = Delivery::Ready(result),
+            (_, Err(payload)) => *slot = Delivery::Panicked(panic_message(&*payload)),
+        }
+        ready.notify_one();
+    });
+    if worker_pool(class).lock().unwrap().send(job).is_err() {
+        log::error!("No worker is left to run {}", operation);
+        return Err(DeadlineError::TimedOut { operation, after });
+    }
+
+    let (slot, ready) = &*delivery;
+    let mut slot = slot.lock().unwrap();
+    // 0 turns the deadline off for that class
+    if millis == 0 {
+        slot = ready
+            .wait_while(slot, |slot| matches!(slot, Delivery::Pending))
+            .unwrap();
+    } else {
+        slot = ready
+            .wait_timeout_while(slot, after, |slot| matches!(slot, Delivery::Pending))
+            .unwrap()
+            .0;
+    }
+    match std::mem::replace(&mut *slot, Delivery::Abandoned) {
+        Delivery::Ready(result) => Ok(result),
+        Delivery::Pending => {
+            log::warn!(
+                "{} is still blocked after {}ms; abandoning it",
+                operation,
+                millis
+            );
+            Err(DeadlineError::TimedOut { operation, after })
+        }
+        // The panic itself has already been reported by the panic hook
+        Delivery::Panicked(message) => Err(DeadlineError::Panicked { operation, message }),
+        Delivery::Abandoned => Err(DeadlineError::TimedOut { operation, after }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, AtomicUsize};
+
+    // The only test touching the Suspend class, whose deadline it changes
+    #[test]
+    fn late_calls_are_undone_and_queued_ones_skipped() {
+        set_timeout(OperationClass::Suspend, 50);
+        let (release, blocked) = mpsc::channel::<()>();
+        let undone = Arc::new(AtomicUsize::new(0));
+        let undo_count = undone.clone();
+        let stuck = run_with_deadline_or_undo(
+            OperationClass::Suspend,
+            "stuck call",
+            move || {
+                blocked.recv().unwrap();
+                7
+            },
+            move |result| {
+                undo_count.fetch_add(result, Ordering::SeqCst);
+            },
+        );
+        assert!(stuck.is_err());
+
+        // Queued behind the stuck call, this one times out before it ever starts
+        let ran = Arc::new(AtomicBool::new(false));
+        let ran_flag = ran.clone();
+        let queued = run_with_deadline(OperationClass::Suspend, "queued call", move || {
+            ran_flag.store(true, Ordering::SeqCst)
+        });
+        assert!(queued.is_err());
+
+        release.send(()).unwrap();
+        // Runs after both, on the same worker, so they have settled by the time it returns
+        assert_eq!(
+            run_with_deadline(OperationClass::Suspend, "fast call", || 1).unwrap(),
+            1
+        );
+        assert_eq!(undone.load(Ordering::SeqCst), 7);
+        assert!(!ran.load(Ordering::SeqCst));
+
+        set_timeout(OperationClass::Suspend, DEFAULT_SUSPEND_TIMEOUT_MS);
+    }
+
+    #[test]
+    fn panics_are_reported_as_failures_not_timeouts() {
+        let error = run_with_deadline(OperationClass::Read, "panicking call", || -> u8 {
+            panic!("call failed")
+        })
+        .unwrap_err();
+        assert!(!error.is_timeout());
+        assert_eq!(error.to_string(), "panicking call failed: call failed");
+        assert_eq!(
+            std::io::Error::from(error).kind(),
+            std::io::ErrorKind::Other
+        );
+
+        // The worker that caught the panic carries on
+        assert_eq!(
+            run_with_deadline(OperationClass::Read, "next call", || 1).unwrap(),
+            1
+        );
+    }
+}
@@ -0,0 +1,272 @@
+use crate::compression;
+use crate::native_bridge;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+const DUMP_MAGIC: &[u8; 4] = b"MDMP";
+const DUMP_VERSION: u32 = 1;
+// magic, u32 version, u64 index offset
+const DUMP_HEADER_SIZE: u64 = 16;
+const INDEX_ENTRY_SIZE: usize = 32;
+// Regions are stored in pieces of this size so a lookup only decompresses a small blob
+const DUMP_CHUNK_SIZE: usize = 1024 * 1024 * 4; // 4MB chunks
+
+// Bytes one dump_read returns; larger ranges are read in several calls
+pub const MAX_DUMP_READ: usize = 1024 * 1024 * 16; // 16MB
+
+// One stored piece of memory: covers start..end and its compressed blob sits at offset
+struct DumpEntry {
+    start: u64,
+    end: u64,
+    offset: u64,
+    compressed_len: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DumpSummary {
+    pub chunk_count: usize,
+    pub dumped_bytes: u64,
+    pub file_size: u64,
+}
+
+// File layout: header, then compress_framed blobs back to back, then the index of
+// (start, end, blob offset, blob length) u64 entries that the header points to.
+// Unreadable parts of a region are left out, so lookups there fail instead of returning zeros.
+pub fn dump_regions(pid: i32, regions: &[(u64, u64)], path: &Path) -> Result<DumpSummary, String> {
+    let write_error = |e: std::io::Error| format!("Failed to write dump {}: {}", path.display(), e);
+    let file = File::create(path).map_err(write_error)?;
+    let mut writer = BufWriter::new(file);
+    writer
+        .write_all(&[0u8; DUMP_HEADER_SIZE as usize])
+        .map_err(write_error)?;
+
+    let mut entries = Vec::new();
+    let mut offset = DUMP_HEADER_SIZE;
+    let mut dumped_bytes = 0u64;
+    for &(start, end) in regions {
+        let mut current = start;
+        while current < end {
+            let size = ((end - current) as usize).min(DUMP_CHUNK_SIZE);
+            let mut buffer = vec![0u8; size];
+            let nread = native_bridge::read_process_memory(
+                pid,
+                current as *mut libc::c_void,
+                size,
+                &mut buffer,
+            )
+            .map(|nread| nread.max(0) as usize)
+            .unwrap_or(0);
+
+            if nread > 0 {
+                let blob = compression::compress_framed(&buffer[..nread])?;
+                writer.write_all(&blob).map_err(write_error)?;
+                entries.push(DumpEntry {
+                    start: current,
+                    end: current + nread as u64,
+                    offset,
+                    compressed_len: blob.len() as u64,
+                });
+                offset += blob.len() as u64;
+                dumped_bytes += nread as u64;
+            }
+            current += size as u64;
+        }
+    }
+
+    for entry in &entries {
+        for field in [entry.start, entry.end, entry.offset, entry.compressed_len] {
+            writer
+                .write_all(&field.to_le_bytes())
+                .map_err(write_error)?;
+        }
+    }
+    let file_size = offset + (entries.len() * INDEX_ENTRY_SIZE) as u64;
+
+    let mut header = Vec::with_capacity(DUMP_HEADER_SIZE as usize);
+    header.extend_from_slice(DUMP_MAGIC);
+    header.extend_from_slice(&DUMP_VERSION.to_le_bytes());
+    header.extend_from_slice(&offset.to_le_bytes());
+    writer.seek(SeekFrom::Start(0)).map_err(write_error)?;
+    writer.write_all(&header).map_err(write_error)?;
+    writer.flush().map_err(write_error)?;
+
+    Ok(DumpSummary {
+        chunk_count: entries.len(),
+        dumped_bytes,
+        file_size,
+    })
+}
+
+fn read_index(reader: &mut BufReader<File>, path: &Path) -> Result<Vec<DumpEntry>, String> {
+    let read_error = |e: std::io::Error| format!("Failed to read dump {}: {}", path.display(), e);
+    let mut header = [0u8; DUMP_HEADER_SIZE as usize];
+    reader.read_exact(&mut header).map_err(read_error)?;
+    if &header[..4] != DUMP_MAGIC {
+        return Err(format!("{} is not a memory dump file", path.display()));
+    }
+    let version = u32::from_le_bytes(header[4..8].try_into().unwrap());
+    if version != DUMP_VERSION {
+        return Err(format!(
+            "Unsupported memory dump version {} (expected {})",
+            version, DUMP_VERSION
+        ));
+    }
+    let index_offset = u64::from_le_bytes(header[8..16].try_into().unwrap());
+
+    reader
+        .seek(SeekFrom::Start(index_offset))
+        .map_err(read_error)?;
+    let mut index = Vec::new();
+    reader.read_to_end(&mut index).map_err(read_error)?;
+    if index.len() % INDEX_ENTRY_SIZE != 0 {
+        return Err(format!(
+            "Memory dump {} has a corrupt index",
+            path.display()
+        ));
+    }
+
+    let entries: Vec<DumpEntry> = index
+        .chunks_exact(INDEX_ENTRY_SIZE)
+        .map(|entry| {
+            let field = |i: usize| u64::from_le_bytes(entry[i * 8..i * 8 + 8].try_into().unwrap());
+            DumpEntry {
+                start: field(0),
+                end: field(1),
+                offset: field(2),
+                compressed_len: field(3),
+            }
+        })
+        .collect();
+    // Every blob sits between the header and the index, and no chunk is larger than written
+    let corrupt = entries.iter().any(|entry| {
+        entry.start >= entry.end
+            || entry.end - entry.start > DUMP_CHUNK_SIZE as u64
+            || entry.offset < DUMP_HEADER_SIZE
+            || entry
+                .offset
+                .checked_add(entry.compressed_len)
+                .is_none_or(|blob_end| blob_end > index_offset)
+    });
+    if corrupt {
+        return Err(format!(
+            "Memory dump {} has a corrupt index",
+            path.display()
+        ));
+    }
+    Ok(entries)
+}
+
+// The entries that hold start..end in order, or the first address no entry covers
+fn covering_entries(entries: &[DumpEntry], start: u64, end: u64) -> Result<Vec<&DumpEntry>, u64> {
+    let mut covering = Vec::new();
+    let mut current = start;
+    while current < end {
+        let entry = entries
+            .iter()
+            .find(|entry| entry.start <= current && current < entry.end)
+            .ok_or(current)?;
+        covering.push(entry);
+        current = entry.end;
+    }
+    Ok(covering)
+}
+
+// Reads size bytes at address from a saved dump; the range may span several stored chunks
+// but every byte of it has to have been dumped
+pub fn dump_read(path: &Path, address: u64, size: usize) -> Result<Vec<u8>, String> {
+    let read_error = |e: std::io::Error| format!("Failed to read dump {}: {}", path.display(), e);
+    let file = File::open(path).map_err(read_error)?;
+    let mut reader = BufReader::new(file);
+    let entries = read_index(&mut reader, path)?;
+
+    if size > MAX_DUMP_READ {
+        return Err(format!(
+            "Cannot read {} bytes from a dump at once; the limit is {}",
+            size, MAX_DUMP_READ
+        ));
+    }
+    let end = address
+        .checked_add(size as u64)
+        .ok_or_else(|| format!("Range {:#x}+{:#x} overflows", address, size))?;
+    // The whole range has to be in the dump before anything is allocated for it
+    let covering = covering_entries(&entries, address, end)
+        .map_err(|missing| format!("Address {:#x} is not in the dump", missing))?;
+
+    let mut result = Vec::with_capacity(size);
+    let mut current = address;
+    for entry in covering {
+        let mut blob = vec![0u8; entry.compressed_len as usize];
+        reader
+            .seek(SeekFrom::Start(entry.offset))
+            .and_then(|_| reader.read_exact(&mut blob))
+            .map_err(read_error)?;
+        let data = compression::decompress_framed(&blob)?;
+
+        let from = (current - entry.start) as usize;
+        let to = (end.min(entry.end) - entry.start) as usize;
+        if data.len() < to {
+            return Err(format!(
+                "Memory dump {} has a truncated chunk",
+                path.display()
+            ));
+        }
+        result.extend_from_slice(&data[from..to]);
+        current = entry.start + to as u64;
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dump_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!(
+            "memory-server-{}-{}.mdmp",
+            name,
+            std::process::id()
+        ))
+    }
+
+    #[test]
+    fn value_round_trips_through_a_dump() {
+        let buffer: Vec<u64> = (0..1024u64).map(|i| i * 0x0101_0101).collect();
+        let start = buffer.as_ptr() as u64;
+        let end = start + (buffer.len() * 8) as u64;
+        let pid = unsafe { native_bridge::get_pid_native() };
+        let path = dump_path("roundtrip");
+
+        let summary = dump_regions(pid, &[(start, end)], &path).unwrap();
+        assert_eq!(summary.dumped_bytes, end - start);
+        let value = dump_read(&path, start + 100 * 8, 8).unwrap();
+        assert_eq!(
+            u64::from_le_bytes(value.try_into().unwrap()),
+            100 * 0x0101_0101
+        );
+
+        // Ranges leaving the dump fail without reading, as do oversized ones
+        assert!(dump_read(&path, end - 4, 8).is_err());
+        assert!(dump_read(&path, start, MAX_DUMP_READ + 1).is_err());
+        assert!(dump_read(&path, u64::MAX - 2, 8).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn index_pointing_past_the_blobs_is_rejected() {
+        let path = dump_path("corrupt");
+        let mut file = Vec::new();
+        file.extend_from_slice(DUMP_MAGIC);
+        file.extend_from_slice(&DUMP_VERSION.to_le_bytes());
+        file.extend_from_slice(&DUMP_HEADER_SIZE.to_le_bytes());
+        // One entry whose blob would need an exabyte of file
+        for field in [0x1000u64, 0x2000, DUMP_HEADER_SIZE, u64::MAX / 16] {
+            file.extend_from_slice(&field.to_le_bytes());
+        }
+        std::fs::write(&path, &file).unwrap();
+        let error = dump_read(&path, 0x1000, 4).unwrap_err();
+        assert!(error.contains("corrupt index"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
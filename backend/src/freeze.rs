@@ -0,0 +1,75 @@
+use crate::util;
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Once};
+use std::thread;
+use std::time::Duration;
+
+const FREEZE_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FrozenValue {
+    pub pid: i32,
+    pub address: u64,
+    pub bytes: Vec<u8>,
+}
+
+lazy_static! {
+    // Keyed by (pid, address) so the same address frozen in two processes stays two entries
+    static ref FREEZE_TABLE: Arc<Mutex<HashMap<(i32, u64), FrozenValue>>> =
+        Arc::new(Mutex::new(HashMap::new()));
+}
+
+static FREEZE_WORKER: Once = Once::new();
+
+// Locks the value at address in pid; freezing an address that is already frozen in pid replaces
+// its value
+pub fn freeze_add(pid: i32, address: u64, value_bytes: Vec<u8>) -> Result<(), String> {
+    if value_bytes.is_empty() {
+        return Err("Freeze value must not be empty".to_string());
+    }
+    // Write once up front so an unwritable address is reported instead of silently retried
+    util::write_memory_bytes(pid, address, &value_bytes)?;
+
+    FREEZE_TABLE.lock().unwrap().insert(
+        (pid, address),
+        FrozenValue {
+            pid,
+            address,
+            bytes: value_bytes,
+        },
+    );
+    FREEZE_WORKER.call_once(|| {
+        thread::spawn(freeze_worker);
+    });
+    Ok(())
+}
+
+pub fn freeze_remove(pid: i32, address: u64) -> bool {
+    FREEZE_TABLE
+        .lock()
+        .unwrap()
+        .remove(&(pid, address))
+        .is_some()
+}
+
+pub fn freeze_list() -> Vec<FrozenValue> {
+    let mut frozen: Vec<FrozenValue> = FREEZE_TABLE.lock().unwrap().values().cloned().collect();
+    frozen.sort_by_key(|value| (value.pid, value.address));
+    frozen
+}
+
+fn freeze_worker() {
+    loop {
+        thread::sleep(FREEZE_INTERVAL);
+        // Writes happen on a copy so a slow target never blocks freeze_add/freeze_remove
+        let frozen: Vec<FrozenValue> = FREEZE_TABLE.lock().unwrap().values().cloned().collect();
+        for value in frozen {
+            // A failed write (e.g. the page was unmapped for a moment) only skips this round
+            if let Err(e) = util::write_memory_bytes(value.pid, value.address, &value.bytes) {
+                log::debug!("Freeze write failed: {}", e);
+            }
+        }
+    }
+}
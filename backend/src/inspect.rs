@@ -0,0 +1,215 @@
+use crate::native_bridge;
+use crate::region::{self, MemoryRegion, Protection};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+// One way to read the bytes at an address. Confidence is a hint between 0 and 1 for ranking
+// the guesses against each other, not a probability.
+#[derive(Debug, Clone, Serialize)]
+pub struct TypeGuess {
+    pub value_type: &'static str,
+    pub value: Value,
+    pub confidence: f64,
+    pub reason: String,
+}
+
+// Bytes read for a guess; enough for the widest value type
+const GUESS_SIZE: usize = 8;
+// Shortest printable run that is offered as a string
+const MIN_STRING_LENGTH: usize = 4;
+
+// Reads up to 8 bytes at address and returns every plausible interpretation, most likely
+// first. Only the bytes at the start of the read are used for the narrower types, so each
+// guess describes a value that starts at address.
+pub fn guess_type(pid: i32, address: u64) -> Result<(Vec<u8>, Vec<TypeGuess>), String> {
+    let mut bytes = vec![0u8; GUESS_SIZE];
+    let nread = native_bridge::read_process_memory(
+        pid,
+        address as *mut libc::c_void,
+        GUESS_SIZE,
+        &mut bytes,
+    )
+    .map_err(|e| format!("Failed to read memory at {:#x}: {}", address, e))?;
+    if nread <= 0 {
+        return Err(format!("Failed to read memory at {:#x}", address));
+    }
+    bytes.truncate(nread as usize);
+    let regions = region::enumerate_regions(pid).unwrap_or_default();
+
+    let mut guesses = Vec::new();
+    if bytes.iter().all(|&b| b == 0) {
+        guesses.push(TypeGuess {
+            value_type: "u64",
+            value: json!(0),
+            confidence: 0.3,
+            reason: "All bytes are zero, which any numeric type or a null pointer reads as"
+                .to_string(),
+        });
+    } else {
+        guesses.extend(guess_pointer(&bytes, &regions));
+        guesses.extend(guess_string(&bytes));
+        guesses.extend(guess_floats(&bytes));
+        guesses.extend(guess_integers(&bytes));
+    }
+    // Raw bytes always fit, so there is an answer even when nothing else looks plausible
+    guesses.push(TypeGuess {
+        value_type: "bytes",
+        value: json!(hex::encode(&bytes)),
+        confidence: 0.05,
+        reason: "Raw bytes".to_string(),
+    });
+    guesses.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    Ok((bytes, guesses))
+}
+
+fn guess_pointer(bytes: &[u8], regions: &[MemoryRegion]) -> Option<TypeGuess> {
+    let value = u64::from_le_bytes(bytes.get(..8)?.try_into().ok()?);
+    let target = regions
+        .iter()
+        .find(|region| region.start <= value && value < region.end)?;
+    let name = target.path.as_deref().unwrap_or("anonymous memory");
+    let (confidence, what) = if target.protection.contains(Protection::EXEC) {
+        (0.9, "code in")
+    } else if target.protection.contains(Protection::READ) {
+        (0.85, "data in")
+    } else {
+        (0.4, "an inaccessible mapping of")
+    };
+    // Pointers to heap objects are normally aligned, arbitrary data landing in a mapping
+    // often is not
+    let confidence = if value % 8 == 0 || target.protection.contains(Protection::EXEC) {
+        confidence
+    } else {
+        confidence - 0.25
+    };
+    Some(TypeGuess {
+        value_type: "pointer",
+        value: json!(value),
+        confidence,
+        reason: format!(
+            "Points to {} {} ({}, +{:#x})",
+            what,
+            name,
+            target.protection,
+            value - target.start
+        ),
+    })
+}
+
+fn guess_string(bytes: &[u8]) -> Option<TypeGuess> {
+    let printable = |b: u8| b.is_ascii_graphic() || b == b' ';
+    let ascii_length = bytes.iter().take_while(|&&b| printable(b)).count();
+    if ascii_length >= MIN_STRING_LENGTH {
+        // A run that fills the read probably goes on past it
+        let terminated = ascii_length == bytes.len() || bytes[ascii_length] == 0;
+        return Some(TypeGuess {
+            value_type: "string",
+            value: json!(String::from_utf8_lossy(&bytes[..ascii_length])),
+            confidence: if terminated { 0.75 } else { 0.5 },
+            reason: format!("{} printable ASCII characters", ascii_length),
+        });
+    }
+    let utf16_length = bytes
+        .chunks_exact(2)
+        .take_while(|pair| printable(pair[0]) && pair[1] == 0)
+        .count();
+    // Eight bytes hold only four UTF-16 characters, so three of them are enough
+    if utf16_length >= 3 {
+        let text: String = bytes
+            .chunks_exact(2)
+            .take(utf16_length)
+            .map(|pair| pair[0] as char)
+            .collect();
+        return Some(TypeGuess {
+            value_type: "utf16le",
+            value: json!(text),
+            confidence: 0.55,
+            reason: format!("{} printable UTF-16 characters", utf16_length),
+        });
+    }
+    None
+}
+
+// How close value is to a number a program would plausibly store: a whole number, or one
+// with at most two decimals
+fn roundness(value: f64) -> Option<&'static str> {
+    if value == value.trunc() {
+        Some("a whole number")
+    } else if ((value * 100.0).round() - value * 100.0).abs() < 1e-3 {
+        Some("a round number")
+    } else {
+        None
+    }
+}
+
+fn float_guess(value_type: &'static str, value: f64, range: (f64, f64)) -> Option<TypeGuess> {
+    let magnitude = value.abs();
+    if !value.is_finite() || magnitude < range.0 || magnitude > range.1 {
+        return None;
+    }
+    let (confidence, reason) = match roundness(value) {
+        Some(round) => (0.7, format!("Finite {} that is {}", value_type, round)),
+        None => (0.45, format!("Finite {} of ordinary magnitude", value_type)),
+    };
+    Some(TypeGuess {
+        value_type,
+        value: json!(value),
+        confidence,
+        reason,
+    })
+}
+
+fn guess_floats(bytes: &[u8]) -> Vec<TypeGuess> {
+    let mut guesses = Vec::new();
+    if let Some(word) = bytes.get(..4) {
+        let value = f32::from_le_bytes(word.try_into().unwrap());
+        // Random words mostly decode to huge or tiny floats, so the range does the filtering
+        guesses.extend(float_guess("f32", value as f64, (1e-3, 1e7)));
+    }
+    if let Some(word) = bytes.get(..8) {
+        let value = f64::from_le_bytes(word.try_into().unwrap());
+        guesses.extend(float_guess("f64", value, (1e-6, 1e12)));
+    }
+    guesses
+}
+
+fn guess_integers(bytes: &[u8]) -> Vec<TypeGuess> {
+    let mut guesses = Vec::new();
+    if let Some(word) = bytes.get(..4) {
+        let value = i32::from_le_bytes(word.try_into().unwrap());
+        let confidence = match value.unsigned_abs() {
+            0..=100_000 => 0.6,
+            100_001..=10_000_000 => 0.35,
+            _ => 0.15,
+        };
+        guesses.push(TypeGuess {
+            value_type: "i32",
+            value: json!(value),
+            confidence,
+            reason: if confidence > 0.5 {
+                "Small 32-bit integer".to_string()
+            } else {
+                "32-bit integer".to_string()
+            },
+        });
+    }
+    if let Some(word) = bytes.get(..8) {
+        let value = i64::from_le_bytes(word.try_into().unwrap());
+        // A 64-bit value whose upper half only extends the sign is as likely an i32 followed
+        // by padding, so it ranks just below the i32 reading
+        let (confidence, reason) = if value == (value as i32) as i64 {
+            (0.5, "Small 64-bit integer")
+        } else if value.unsigned_abs() < 1 << 48 {
+            (0.2, "64-bit integer")
+        } else {
+            (0.1, "Large 64-bit value, possibly a hash or packed data")
+        };
+        guesses.push(TypeGuess {
+            value_type: "i64",
+            value: json!(value),
+            confidence,
+            reason: reason.to_string(),
+        });
+    }
+    guesses
+}
@@ -1,34 +1,62 @@
 #![recursion_limit = "256"]
 
 use ctor::ctor;
-use std::net::IpAddr;
 use std::thread;
 
+mod access;
 mod allocator;
 mod api;
+mod assembler;
+mod auth;
+mod bookmarks;
+mod breakpoint;
+mod compression;
+mod coredump;
+mod deadline;
+mod dump;
+mod freeze;
+mod inspect;
+mod limit;
 mod logger;
 mod native_bridge;
+mod pointer;
 mod ptrscan;
+mod region;
 mod request;
+mod scan;
 mod serve;
+mod session;
+mod settings;
+mod shutdown;
+mod symbols;
 mod util;
+mod watch;
 
 #[ctor]
 fn main() {
+    // The test harness loads the library too, and must not start a server
+    if cfg!(test) {
+        return;
+    }
     thread::spawn(|| {
         let runtime = tokio::runtime::Runtime::new().unwrap();
 
         runtime.block_on(async {
             std::env::set_var("MEMORY_SERVER_RUNNING_MODE", "embedded");
 
-            let host: IpAddr = "0.0.0.0".parse().unwrap();
-            let port: u16 = 3030;
+            logger::init_log();
+            // No command line when injected, so only the config file and environment apply.
+            // A bad setting must not take the host process down with it.
+            let settings =
+                settings::load(None, settings::SettingsLayer::default()).unwrap_or_else(|e| {
+                    log::error!("{}; starting with the default settings", e);
+                    settings::Settings::default()
+                });
             println!(
                 "memory_spy has started listening on host {} and port {}.",
-                host, port
+                settings.host, settings.port
             );
-            logger::init_log();
-            serve::serve(1, host, port).await;
+            serve::serve(1, settings).await;
         });
     });
 }
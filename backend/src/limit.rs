@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use warp::http::StatusCode;
+use warp::reject::Reject;
+use warp::{Filter, Rejection, Reply};
+
+pub const WRITE_RATE_ENV_VAR: &str = "MEMSERVER_WRITE_RATE";
+pub const DEFAULT_WRITE_RATE: f64 = 500.0;
+// Idle buckets are dropped once this many clients have been seen
+const MAX_TRACKED_CLIENTS: usize = 1024;
+
+#[derive(Debug)]
+struct RateLimited {
+    retry_after: Duration,
+}
+
+impl Reject for RateLimited {}
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+// A token bucket per client address: each one refills at rate tokens per second and holds at
+// most one second's worth, so short bursts pass and sustained floods are turned away. Keying by
+// IP rather than by connection keeps a client from getting a fresh bucket per new socket.
+pub struct RateLimiter {
+    rate: f64,
+    capacity: f64,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(rate: f64) -> Self {
+        RateLimiter {
+            rate,
+            capacity: rate.max(1.0),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Takes one token, or returns how long until the next one is available
+    pub fn acquire(&self, client: IpAddr) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_TRACKED_CLIENTS && !buckets.contains_key(&client) {
+            let (rate, capacity) = (self.rate, self.capacity);
+            buckets.retain(|_, bucket| {
+                let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+                bucket.tokens + elapsed * rate < capacity
+            });
+        }
+        let bucket = buckets.entry(client).or_insert(Bucket {
+            tokens: self.capacity,
+            last_refill: now,
+        });
+
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.capacity);
+        bucket.last_refill = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate))
+        }
+    }
+}
+
+// Writes per second; 0 turns the limit off
+pub fn validate_write_rate(rate: f64) -> Result<Option<f64>, String> {
+    if rate == 0.0 {
+        Ok(None)
+    } else if rate.is_finite() && rate > 0.0 {
+        Ok(Some(rate))
+    } else {
+        Err(format!(
+            "Invalid write rate {}: expected a positive number of writes per second or 0",
+            rate
+        ))
+    }
+}
+
+// Rejects with 429 once the client address runs out of tokens; passes everything when no
+// limiter is configured
+pub fn with_rate_limit(
+    limiter: Option<Arc<RateLimiter>>,
+) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::addr::remote()
+        .and_then(move |remote: Option<SocketAddr>| {
+            let limiter = limiter.clone();
+            async move {
+                let Some(limiter) = limiter else {
+                    return Ok(());
+                };
+                let client = remote
+                    .map(|remote| remote.ip())
+                    .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+                limiter
+                    .acquire(client)
+                    .map_err(|retry_after| warp::reject::custom(RateLimited { retry_after }))
+            }
+        })
+        .untuple_one()
+}
+
+pub async fn handle_rejection(err: Rejection) -> Result<impl Reply, Rejection> {
+    if let Some(limited) = err.find::<RateLimited>() {
+        // Retry-After only takes whole seconds, so the body carries the precise delay
+        let retry_after_secs = limited.retry_after.as_secs_f64().ceil().max(1.0) as u64;
+        Ok(warp::reply::with_header(
+            warp::reply::with_status(
+                format!(
+                    "Too many writes, retry in {}ms",
+                    limited.retry_after.as_millis().max(1)
+                ),
+                StatusCode::TOO_MANY_REQUESTS,
+            ),
+            "Retry-After",
+            retry_after_secs.to_string(),
+        ))
+    } else {
+        Err(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(ip: [u8; 4], port: u16) -> SocketAddr {
+        SocketAddr::new(IpAddr::from(ip), port)
+    }
+
+    async fn write_from(
+        filter: &(impl Filter<Extract = (), Error = Rejection> + Clone + 'static),
+        remote: SocketAddr,
+    ) -> bool {
+        warp::test::request()
+            .remote_addr(remote)
+            .filter(filter)
+            .await
+            .is_ok()
+    }
+
+    #[tokio::test]
+    async fn writes_past_the_rate_are_refused_per_address() {
+        // One token that takes 100s to come back, so a slow run can't refill the bucket
+        let filter = with_rate_limit(Some(Arc::new(RateLimiter::new(0.01))));
+        assert!(write_from(&filter, client([10, 0, 0, 1], 40000)).await);
+        // A new port is a new connection, and it must not get a fresh bucket
+        assert!(!write_from(&filter, client([10, 0, 0, 1], 41000)).await);
+        assert!(write_from(&filter, client([10, 0, 0, 2], 41000)).await);
+    }
+
+    #[tokio::test]
+    async fn refused_writes_get_429_with_a_retry_hint() {
+        let limiter = RateLimiter::new(1.0);
+        limiter.acquire(IpAddr::from([10, 0, 0, 1])).unwrap();
+        let retry_after = limiter.acquire(IpAddr::from([10, 0, 0, 1])).unwrap_err();
+        assert!(retry_after > Duration::ZERO && retry_after <= Duration::from_secs(1));
+
+        let reply = handle_rejection(warp::reject::custom(RateLimited { retry_after }))
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(reply.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(reply.headers()["Retry-After"], "1");
+    }
+
+    #[tokio::test]
+    async fn no_limiter_means_no_limit() {
+        let filter = with_rate_limit(None);
+        for port in 0..100 {
+            assert!(write_from(&filter, client([10, 0, 0, 1], port)).await);
+        }
+    }
+}
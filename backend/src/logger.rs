@@ -6,7 +6,14 @@ use log::LevelFilter;
 use std::io::Write;
 use warp::log::Info;
 
-static EXCLUDED_PATHS: &[&str] = &["/_next", "/exceptioninfo", "/resolveaddr"];
+static EXCLUDED_PATHS: &[&str] = &[
+    "/_next",
+    "/exceptioninfo",
+    "/health",
+    "/resolveaddr",
+    "/scanprogress",
+    "/watch",
+];
 static EXCLUDED_EXTENSIONS: &[&str] = &[
     ".png", ".jpg", ".jpeg", ".gif", ".ico", ".svg", ".webp", ".bmp", ".tiff",
 ];
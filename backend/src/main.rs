@@ -4,16 +4,35 @@ use ctor::ctor;
 
 use clap::{Arg, Command};
 use std::env;
-use std::net::IpAddr;
 
+mod access;
 mod allocator;
 mod api;
+mod assembler;
+mod auth;
+mod bookmarks;
+mod breakpoint;
+mod compression;
+mod coredump;
+mod deadline;
+mod dump;
+mod freeze;
+mod inspect;
+mod limit;
 mod logger;
 mod native_bridge;
+mod pointer;
 mod ptrscan;
+mod region;
 mod request;
+mod scan;
 mod serve;
+mod session;
+mod settings;
+mod shutdown;
+mod symbols;
 mod util;
+mod watch;
 
 #[ctor]
 fn init() {
@@ -27,39 +46,129 @@ async fn main() {
     let matches = Command::new("memory_server")
         .version("1.0")
         .about("Dynamic analysis tool")
+        .arg(
+            Arg::new("config")
+                .short('c')
+                .long("config")
+                .num_args(1)
+                .value_name("FILE")
+                .help("Reads settings from this JSON file (also read from MEMSERVER_CONFIG); the environment and flags override it"),
+        )
         .arg(
             Arg::new("port")
                 .short('p')
                 .long("port")
                 .num_args(1)
                 .value_name("PORT")
-                .help("Sets the port number to listen on"),
+                .help("Sets the port number to listen on (default 3030, also read from MEMSERVER_PORT)"),
         )
         .arg(
             Arg::new("host")
                 .short('H')
                 .long("host")
+                .visible_alias("bind")
                 .num_args(1)
                 .value_name("HOST")
-                .help("Sets the host to listen on"),
+                .help("Sets the address to listen on (default 127.0.0.1, also read from MEMSERVER_BIND)"),
+        )
+        .arg(
+            Arg::new("token")
+                .long("token")
+                .num_args(1)
+                .value_name("TOKEN")
+                .help("Requires this bearer token on API requests (also read from MEMSERVER_TOKEN)"),
+        )
+        .arg(
+            Arg::new("write-rate")
+                .long("write-rate")
+                .num_args(1)
+                .value_name("WRITES_PER_SECOND")
+                .help("Limits memory writes per client address, 0 for no limit (default 500, also read from MEMSERVER_WRITE_RATE)"),
+        )
+        .arg(
+            Arg::new("scan-threads")
+                .long("scan-threads")
+                .num_args(1)
+                .value_name("THREADS")
+                .help("Sets how many threads scans run on (default one per physical core, also read from MEMSERVER_SCAN_THREADS)"),
+        )
+        .arg(
+            Arg::new("bookmarks-file")
+                .long("bookmarks-file")
+                .num_args(1)
+                .value_name("FILE")
+                .help("Keeps bookmarks in this JSON file (default in the user's data directory, also read from MEMSERVER_BOOKMARKS)"),
+        )
+        .arg(
+            Arg::new("file-root")
+                .long("file-root")
+                .num_args(1)
+                .value_name("DIR")
+                .help("Only lets file requests reach paths inside this directory (default /, where paths with .. are refused unless --allow-parent-paths is set; also read from MEMSERVER_FILE_ROOT)"),
+        )
+        .arg(
+            Arg::new("allow-parent-paths")
+                .long("allow-parent-paths")
+                .num_args(1)
+                .value_name("BOOL")
+                .help("Lets file request paths contain .. as long as they stay inside the file root (default false, also read from MEMSERVER_ALLOW_PARENT_PATHS)"),
+        )
+        .arg(
+            Arg::new("read-retries")
+                .long("read-retries")
+                .num_args(1)
+                .value_name("COUNT")
+                .help("Retries target reads that fail transiently this many times (default 2, also read from MEMSERVER_READ_RETRIES)"),
+        )
+        .arg(
+            Arg::new("scan-chunk-size")
+                .long("scan-chunk-size")
+                .num_args(1)
+                .value_name("BYTES")
+                .help("Scans large regions this many bytes at a time (default 16MB, also read from MEMSERVER_SCAN_CHUNK_SIZE)"),
+        )
+        .arg(
+            Arg::new("read-timeout-ms")
+                .long("read-timeout-ms")
+                .num_args(1)
+                .value_name("MS")
+                .help("Fails a memory read request still blocked after this long, 0 to wait forever (default 5000, also read from MEMSERVER_READ_TIMEOUT_MS)"),
+        )
+        .arg(
+            Arg::new("suspend-timeout-ms")
+                .long("suspend-timeout-ms")
+                .num_args(1)
+                .value_name("MS")
+                .help("Fails a suspend or resume request still blocked after this long, 0 to wait forever (default 5000, also read from MEMSERVER_SUSPEND_TIMEOUT_MS)"),
+        )
+        .arg(
+            Arg::new("breakpoint-timeout-ms")
+                .long("breakpoint-timeout-ms")
+                .num_args(1)
+                .value_name("MS")
+                .help("Fails a breakpoint or watchpoint request still blocked after this long, 0 to wait forever (default 10000, also read from MEMSERVER_BREAKPOINT_TIMEOUT_MS)"),
         )
         .get_matches();
 
-    let port: u16 = matches
-        .get_one("port")
-        .map(|s: &String| s.parse().expect("Valid port number"))
-        .unwrap_or(3030);
-
-    let host: IpAddr = matches
-        .get_one("host")
-        .map(|s: &String| s.parse().expect("Valid IP address"))
-        .unwrap_or_else(|| "0.0.0.0".parse().unwrap());
+    // Every setting has a flag named after it, with dashes for underscores
+    let flag = |name: &str| matches.get_one::<String>(&name.replace('_', "-")).cloned();
+    let settings = settings::SettingsLayer::from_strings(flag, "the command line")
+        .and_then(|command_line| {
+            settings::load(
+                matches.get_one::<String>("config").map(String::as_str),
+                command_line,
+            )
+        })
+        .unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
 
     println!(
         "memory_server has started listening on host {} and port {}.",
-        host, port
+        settings.host, settings.port
     );
 
     logger::init_log();
-    serve::serve(0, host, port).await;
+    serve::serve(0, settings).await;
 }
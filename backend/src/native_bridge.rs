@@ -1,7 +1,17 @@
+use crate::coredump;
+use crate::shutdown;
 use libc::{self, c_char, c_int, c_void};
+use serde::Serialize;
 use serde_json::json;
 use std::ffi::{CStr, CString};
 use std::io::{BufRead, BufReader, Error};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+};
 
 #[cfg_attr(target_os = "android", link(name = "c++_static", kind = "static"))]
 #[cfg_attr(target_os = "android", link(name = "c++abi", kind = "static"))]
@@ -32,6 +42,13 @@ extern "C" {
         size: *mut usize,
         error_message: *mut *mut c_char,
     ) -> *const c_void;
+    pub fn write_file(
+        path: *const c_char,
+        data: *const c_void,
+        size: usize,
+        append: bool,
+        error_message: *mut *mut c_char,
+    ) -> c_int;
     pub fn get_application_info_native(pid: c_int) -> *const c_char;
     pub fn debugger_new(pid: c_int) -> bool;
     pub fn set_watchpoint_native(
@@ -58,18 +75,119 @@ pub struct ModuleInfo {
     pub modulename: *mut c_char,
 }
 
+// A failed read of target memory. Transient failures (an interrupted or contended call) were
+// already retried and may still succeed later; the others will not.
+#[derive(Debug)]
+pub struct ReadError {
+    error: Error,
+    transient: bool,
+}
+
+impl ReadError {
+    pub fn is_transient(&self) -> bool {
+        self.transient
+    }
+
+    pub fn raw_os_error(&self) -> Option<i32> {
+        self.error.raw_os_error()
+    }
+}
+
+impl std::fmt::Display for ReadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl std::error::Error for ReadError {}
+
+impl From<ReadError> for Error {
+    fn from(error: ReadError) -> Self {
+        error.error
+    }
+}
+
+pub const DEFAULT_READ_RETRIES: u32 = 2;
+pub const MAX_READ_RETRIES: u32 = 10;
+// Doubled after every attempt
+const READ_RETRY_BACKOFF: Duration = Duration::from_millis(1);
+
+static READ_RETRIES: AtomicU32 = AtomicU32::new(DEFAULT_READ_RETRIES);
+
+pub fn set_read_retries(retries: u32) {
+    READ_RETRIES.store(retries.min(MAX_READ_RETRIES), Ordering::Relaxed);
+}
+
+// Only an interrupted or contended call is worth repeating; EFAULT means the address cannot be
+// read, and retrying it would only slow a scan over unmapped pages
+fn is_transient_read_error(error: &Error) -> bool {
+    matches!(error.raw_os_error(), Some(libc::EINTR) | Some(libc::EAGAIN))
+}
+
+// Runs read until it succeeds, fails permanently, or the retries run out, doubling the pause
+// between attempts
+fn retry_read(
+    mut retries: u32,
+    mut read: impl FnMut() -> Result<isize, Error>,
+) -> Result<isize, ReadError> {
+    let mut backoff = READ_RETRY_BACKOFF;
+    loop {
+        let error = match read() {
+            Ok(nread) => return Ok(nread),
+            Err(error) => error,
+        };
+        let transient = is_transient_read_error(&error);
+        if !transient || retries == 0 {
+            return Err(ReadError { error, transient });
+        }
+        log::trace!("Retrying read after: {}", error);
+        std::thread::sleep(backoff);
+        backoff *= 2;
+        retries -= 1;
+    }
+}
+
 pub fn read_process_memory(
     pid: i32,
     address: *mut libc::c_void,
     size: usize,
     buffer: &mut [u8],
+) -> Result<isize, ReadError> {
+    // A loaded core dump answers from its file; nothing there is transient
+    if coredump::is_dump_handle(pid) {
+        let size = size.min(buffer.len());
+        return coredump::read_dump(pid, address as u64, &mut buffer[..size]).map_err(|error| {
+            ReadError {
+                error,
+                transient: false,
+            }
+        });
+    }
+    retry_read(READ_RETRIES.load(Ordering::Relaxed), || {
+        read_process_memory_once(pid, address, size, buffer)
+    })
+}
+
+fn read_process_memory_once(
+    pid: i32,
+    address: *mut libc::c_void,
+    size: usize,
+    buffer: &mut [u8],
 ) -> Result<isize, Error> {
-    let result =
-        unsafe { read_memory_native(pid, address as libc::uintptr_t, size, buffer.as_mut_ptr()) };
-    if result >= 0 {
-        Ok(result as isize)
-    } else {
-        Err(Error::last_os_error())
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        read_with_ptrace_fallback(pid, address as u64, size, buffer)
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    {
+        let result = unsafe {
+            read_memory_native(pid, address as libc::uintptr_t, size, buffer.as_mut_ptr())
+        };
+        if result >= 0 {
+            Ok(result as isize)
+        } else {
+            Err(Error::last_os_error())
+        }
     }
 }
 
@@ -79,6 +197,12 @@ pub fn write_process_memory(
     size: usize,
     buffer: &[u8],
 ) -> Result<isize, Error> {
+    if coredump::is_dump_handle(pid) {
+        return Err(Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            "Core dumps are read-only",
+        ));
+    }
     let result =
         unsafe { write_memory_native(pid, address as libc::uintptr_t, size, buffer.as_ptr()) };
     if result >= 0 {
@@ -88,17 +212,57 @@ pub fn write_process_memory(
     }
 }
 
-pub fn set_watchpoint(pid: i32, address: usize, size: usize, type_: i32) -> Result<i32, Error> {
+// Which accesses trigger a watchpoint; the values match the native WatchpointType
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read = 1,
+    Write = 2,
+    ReadWrite = 3,
+}
+
+impl Access {
+    // The "r", "w" and "a" the frontend sends
+    pub fn parse(kind: &str) -> Result<Access, String> {
+        match kind {
+            "r" => Ok(Access::Read),
+            "w" => Ok(Access::Write),
+            "a" => Ok(Access::ReadWrite),
+            _ => Err(format!("Unknown watchpoint type '{}'", kind)),
+        }
+    }
+}
+
+// Hardware watchpoints cover 1, 2, 4 or 8 naturally aligned bytes. Hits are queued with the
+// registers of the thread that made the access and read back through the exception info.
+pub fn set_watchpoint(
+    pid: i32,
+    address: usize,
+    size: usize,
+    on_access: Access,
+) -> Result<i32, Error> {
+    if ![1, 2, 4, 8].contains(&size) || !address.is_multiple_of(size) {
+        return Err(Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!(
+                "Watchpoints cover 1, 2, 4 or 8 bytes aligned to their size, not {} bytes at {:#x}",
+                size, address
+            ),
+        ));
+    }
     let result: bool = unsafe { debugger_new(pid) };
 
     if !result {
         return Err(Error::new(
             std::io::ErrorKind::Other,
-            "Failed to create debugger instance",
+            format!(
+                "Failed to create debugger instance: {}",
+                Error::last_os_error()
+            ),
         ));
     }
-    let result = unsafe { set_watchpoint_native(address, size, type_) };
+    let result = unsafe { set_watchpoint_native(address, size, on_access as i32) };
     if result == 0 {
+        shutdown::track_watchpoint(address, true);
         Ok(result as i32)
     } else {
         Err(Error::last_os_error())
@@ -108,6 +272,7 @@ pub fn set_watchpoint(pid: i32, address: usize, size: usize, type_: i32) -> Resu
 pub fn remove_watchpoint(address: usize) -> Result<i32, Error> {
     let result = unsafe { remove_watchpoint_native(address) };
     if result == 0 {
+        shutdown::track_watchpoint(address, false);
         Ok(result as i32)
     } else {
         Err(Error::last_os_error())
@@ -119,11 +284,15 @@ pub fn set_breakpoint(pid: i32, address: usize, hit_count: i32) -> Result<i32, E
     if !result {
         return Err(Error::new(
             std::io::ErrorKind::Other,
-            "Failed to create debugger instance",
+            format!(
+                "Failed to create debugger instance: {}",
+                Error::last_os_error()
+            ),
         ));
     }
     let result = unsafe { set_breakpoint_native(address, hit_count) };
     if result == 0 {
+        shutdown::track_native_breakpoint(address, true);
         Ok(result)
     } else {
         Err(Error::last_os_error())
@@ -133,19 +302,486 @@ pub fn set_breakpoint(pid: i32, address: usize, hit_count: i32) -> Result<i32, E
 pub fn remove_breakpoint(address: usize) -> Result<i32, Error> {
     let result = unsafe { remove_breakpoint_native(address) };
     if result == 0 {
+        shutdown::track_native_breakpoint(address, false);
         Ok(result)
     } else {
         Err(Error::last_os_error())
     }
 }
 
-pub fn native_api_init(mode: i32) {
-    unsafe {
-        native_init(mode);
+// Detaching clears the debug registers and resumes every thread the debugger stopped
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn detach_debugger() {
+    unsafe { debugger_detach() };
+}
+
+// The Darwin debugger only holds exception ports, which go away with the server
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn detach_debugger() {}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+extern "C" {
+    fn debugger_detach();
+    fn debugger_single_step(pid: c_int, tid: c_int, registers: *mut u64, count: usize) -> c_int;
+    fn debugger_resume_thread(pid: c_int, tid: c_int) -> c_int;
+    fn debugger_thread_registers(
+        pid: c_int,
+        tid: c_int,
+        registers: *mut u64,
+        count: usize,
+    ) -> c_int;
+    fn process_vm_readv_usable() -> bool;
+    fn read_memory_ptrace_native(
+        pid: libc::c_int,
+        address: libc::uintptr_t,
+        size: libc::size_t,
+        buffer: *mut u8,
+    ) -> libc::ssize_t;
+}
+
+// What a region the fast path rejected turned out to need: Ptrace regions are read with ptrace
+// straight away, Unreadable ones failed under ptrace too and are never attached to again.
+// Unmapped ranges are the gaps between regions, remembered so stale addresses don't list the
+// maps again.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FallbackState {
+    Ptrace,
+    Unreadable,
+    Unmapped,
+}
+
+// Where an address falls in a process' maps: inside a region, or in the gap between two
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mapping {
+    Mapped(u64, u64),
+    Unmapped(u64, u64),
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReadPath {
+    Fast,
+    Ptrace,
+}
+
+// Regions are cached as (pid, start) -> (end, state); the cache is dropped once it grows past
+// this many entries since stale regions only cost one extra fast path attempt
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const MAX_FALLBACK_REGIONS: usize = 4096;
+
+// A process that refused an attach (another tracer holds it, or it isn't ours to trace) is
+// left alone for this long before the fallback tries attaching again
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const ATTACH_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+// The cached outcomes, plus a fingerprint of the maps listing they were recorded against. A
+// process whose listing changed may have remapped any region, so its entries are dropped.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[derive(Default)]
+struct FallbackCache {
+    regions: BTreeMap<(i32, u64), (u64, FallbackState)>,
+    listings: HashMap<i32, u64>,
+    failed_attaches: HashMap<i32, Instant>,
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl FallbackCache {
+    fn state(&self, pid: i32, address: u64) -> Option<FallbackState> {
+        self.regions
+            .range((pid, 0)..=(pid, address))
+            .next_back()
+            .filter(|(_, (end, _))| address < *end)
+            .map(|(_, (_, state))| *state)
+    }
+
+    fn insert(&mut self, pid: i32, start: u64, end: u64, state: FallbackState) {
+        if self.regions.len() >= MAX_FALLBACK_REGIONS {
+            self.regions.clear();
+        }
+        self.regions.insert((pid, start), (end, state));
+    }
+
+    fn attach_refused_recently(&self, pid: i32, now: Instant) -> bool {
+        self.failed_attaches
+            .get(&pid)
+            .is_some_and(|failed| now.duration_since(*failed) < ATTACH_RETRY_INTERVAL)
+    }
+
+    fn note_attach(&mut self, pid: i32, refused_at: Option<Instant>) {
+        match refused_at {
+            Some(now) => {
+                if self.failed_attaches.len() >= MAX_FALLBACK_REGIONS {
+                    self.failed_attaches.clear();
+                }
+                self.failed_attaches.insert(pid, now);
+            }
+            None => {
+                self.failed_attaches.remove(&pid);
+            }
+        }
+    }
+
+    fn note_listing(&mut self, pid: i32, fingerprint: u64) {
+        if self.listings.insert(pid, fingerprint) != Some(fingerprint) {
+            self.regions.retain(|(region_pid, _), _| *region_pid != pid);
+        }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+lazy_static::lazy_static! {
+    static ref PTRACE_FALLBACK: Mutex<FallbackCache> = Mutex::new(FallbackCache::default());
+}
+
+// Called with every maps listing read for a process, so cached fallback outcomes never outlive
+// the mappings they were recorded for
+pub fn note_region_listing(pid: i32, listing: &str) {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        listing.hash(&mut hasher);
+        PTRACE_FALLBACK
+            .lock()
+            .unwrap()
+            .note_listing(pid, hasher.finish());
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    let _ = (pid, listing);
+}
+
+// Which path to take first for an address, given what is cached for its region
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn first_read_path(cached: Option<FallbackState>) -> ReadPath {
+    match cached {
+        Some(FallbackState::Ptrace) => ReadPath::Ptrace,
+        Some(FallbackState::Unreadable | FallbackState::Unmapped) | None => ReadPath::Fast,
+    }
+}
+
+// Whether a fast path failure is worth a ptrace attempt. Only EFAULT and EPERM are, and only
+// for a region nothing is known about yet, inside another process (a process cannot trace
+// itself)
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn should_fall_back(errno: i32, cached: Option<FallbackState>, is_self: bool) -> bool {
+    matches!(errno, libc::EFAULT | libc::EPERM) && cached.is_none() && !is_self
+}
+
+// Whether a failed ptrace read says something lasting about the region. EIO and EFAULT come
+// from PTRACE_PEEKDATA refusing the address; EPERM, ESRCH and EBUSY come from an attach that
+// lost out to another tracer (the debugger, or a concurrent read) and are worth trying again.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn is_permanent_ptrace_error(errno: i32) -> bool {
+    matches!(errno, libc::EIO | libc::EFAULT)
+}
+
+// The reads and lookups the fallback decision needs, split out so the decision can be driven
+// without a target process
+#[cfg(any(target_os = "linux", target_os = "android"))]
+trait FallbackReader {
+    fn read_fast(
+        &self,
+        pid: i32,
+        address: u64,
+        size: usize,
+        buffer: &mut [u8],
+    ) -> Result<isize, Error>;
+    fn read_ptrace(
+        &self,
+        pid: i32,
+        address: u64,
+        size: usize,
+        buffer: &mut [u8],
+    ) -> Result<isize, Error>;
+    // The region or gap holding address, or None when the maps could not be listed
+    fn region_of(&self, pid: i32, address: u64) -> Option<Mapping>;
+    fn is_self(&self, pid: i32) -> bool;
+    fn now(&self) -> Instant;
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+struct NativeReader;
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl FallbackReader for NativeReader {
+    fn read_fast(
+        &self,
+        pid: i32,
+        address: u64,
+        size: usize,
+        buffer: &mut [u8],
+    ) -> Result<isize, Error> {
+        // Both native reads save errno before logging and return it negated, so the logger
+        // can't change which error the fallback sees
+        let result = unsafe {
+            read_memory_native(pid, address as libc::uintptr_t, size, buffer.as_mut_ptr())
+        };
+        if result >= 0 {
+            Ok(result as isize)
+        } else {
+            Err(Error::from_raw_os_error(-result as i32))
+        }
+    }
+
+    fn read_ptrace(
+        &self,
+        pid: i32,
+        address: u64,
+        size: usize,
+        buffer: &mut [u8],
+    ) -> Result<isize, Error> {
+        let result = unsafe {
+            read_memory_ptrace_native(pid, address as libc::uintptr_t, size, buffer.as_mut_ptr())
+        };
+        if result >= 0 {
+            Ok(result as isize)
+        } else {
+            Err(Error::from_raw_os_error(-result as i32))
+        }
+    }
+
+    fn region_of(&self, pid: i32, address: u64) -> Option<Mapping> {
+        let regions = crate::region::enumerate_regions(pid).ok()?;
+        if let Some(r) = regions
+            .iter()
+            .find(|r| r.start <= address && address < r.end)
+        {
+            return Some(Mapping::Mapped(r.start, r.end));
+        }
+        let gap_start = regions
+            .iter()
+            .map(|r| r.end)
+            .filter(|end| *end <= address)
+            .max()
+            .unwrap_or(0);
+        let gap_end = regions
+            .iter()
+            .map(|r| r.start)
+            .filter(|start| *start > address)
+            .min()
+            .unwrap_or(u64::MAX);
+        Some(Mapping::Unmapped(gap_start, gap_end))
+    }
+
+    fn is_self(&self, pid: i32) -> bool {
+        pid == unsafe { get_pid_native() }
+    }
+
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+// process_vm_readv refuses some mapped pages that PTRACE_PEEKDATA can still read (ptrace
+// forces access, e.g. to PROT_NONE guard pages), so a rejected read inside a mapped region is
+// retried through ptrace. The outcome is cached per region so the slow attach/detach happens
+// once for unreadable regions and the failing fast path is skipped for the others.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn read_with_ptrace_fallback(
+    pid: i32,
+    address: u64,
+    size: usize,
+    buffer: &mut [u8],
+) -> Result<isize, Error> {
+    read_with_fallback(&NativeReader, &PTRACE_FALLBACK, pid, address, size, buffer)
+}
+
+// The cache lock is never held across a read: looking up a region lists the maps, which
+// reports back through note_region_listing
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn read_with_fallback(
+    reader: &impl FallbackReader,
+    cache: &Mutex<FallbackCache>,
+    pid: i32,
+    address: u64,
+    size: usize,
+    buffer: &mut [u8],
+) -> Result<isize, Error> {
+    let cached = cache.lock().unwrap().state(pid, address);
+    if first_read_path(cached) == ReadPath::Ptrace {
+        // A failure here usually means the region was remapped, so try the fast path after all
+        if let Ok(nread) = reader.read_ptrace(pid, address, size, buffer) {
+            return Ok(nread);
+        }
+    }
+
+    let error = match reader.read_fast(pid, address, size, buffer) {
+        Ok(nread) => return Ok(nread),
+        Err(error) => error,
+    };
+    if !should_fall_back(
+        error.raw_os_error().unwrap_or(0),
+        cached,
+        reader.is_self(pid),
+    ) {
+        return Err(error);
+    }
+
+    // A process that just refused an attach would refuse it again, so neither the maps nor
+    // an attach are tried for it until the retry interval has passed
+    if cache
+        .lock()
+        .unwrap()
+        .attach_refused_recently(pid, reader.now())
+    {
+        return Err(error);
+    }
+
+    // Addresses outside every mapping (e.g. a stale pointer) fail for good without attaching,
+    // and the gap is cached so other addresses in it skip listing the maps
+    let (start, end) = match reader.region_of(pid, address) {
+        Some(Mapping::Mapped(start, end)) => (start, end),
+        Some(Mapping::Unmapped(start, end)) => {
+            cache
+                .lock()
+                .unwrap()
+                .insert(pid, start, end, FallbackState::Unmapped);
+            return Err(error);
+        }
+        None => return Err(error),
+    };
+    match reader.read_ptrace(pid, address, size, buffer) {
+        Ok(nread) => {
+            log::debug!(
+                "Reading {:#x}-{:#x} of process {} through ptrace",
+                start,
+                end,
+                pid
+            );
+            let mut cache = cache.lock().unwrap();
+            cache.insert(pid, start, end, FallbackState::Ptrace);
+            cache.note_attach(pid, None);
+            Ok(nread)
+        }
+        Err(ptrace_error) => {
+            log::debug!(
+                "ptrace read of {:#x} in process {} failed: {}",
+                address,
+                pid,
+                ptrace_error
+            );
+            let mut cache = cache.lock().unwrap();
+            if is_permanent_ptrace_error(ptrace_error.raw_os_error().unwrap_or(0)) {
+                cache.insert(pid, start, end, FallbackState::Unreadable);
+            } else {
+                cache.note_attach(pid, Some(reader.now()));
+            }
+            Err(error)
+        }
+    }
+}
+
+// How target memory is read on this platform; Linux and Android fall back to /proc/<pid>/mem
+// when process_vm_readv is unavailable
+pub fn memory_read_method() -> &'static str {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        if unsafe { process_vm_readv_usable() } {
+            "process_vm_readv"
+        } else {
+            "proc_mem"
+        }
+    }
+    #[cfg(target_os = "windows")]
+    {
+        "read_process_memory"
+    }
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    {
+        "mach_vm_read"
+    }
+}
+
+// Writes to another process go through ptrace on Linux and Android; Yama's ptrace_scope 3
+// turns attaching off for everyone, root included
+pub fn can_write_memory() -> bool {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        std::fs::read_to_string("/proc/sys/kernel/yama/ptrace_scope")
+            .map_or(true, |scope| scope.trim() != "3")
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    {
+        true
+    }
+}
+
+// Suspending is a SIGSTOP on Linux and Android; signal 0 checks that signals can be sent at all
+pub fn can_suspend_process() -> bool {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        unsafe { libc::kill(get_pid_native(), 0) == 0 }
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    {
+        true
     }
 }
 
+// Enumerating our own modules exercises the same native path without touching a target
+pub fn can_enumerate_modules() -> bool {
+    enum_modules(unsafe { get_pid_native() }).is_ok_and(|modules| !modules.is_empty())
+}
+
+// Darwin sets watchpoints through the arm64 debug state, Linux and Android through ptrace on
+// x86_64 and arm64; the Windows debugger is still a stub
+pub fn has_watchpoints() -> bool {
+    cfg!(any(target_os = "macos", target_os = "ios"))
+        || cfg!(all(
+            any(target_os = "linux", target_os = "android"),
+            any(target_arch = "x86_64", target_arch = "aarch64")
+        ))
+}
+
+// Darwin uses hardware breakpoints, Linux and Android patch in trap instructions
+pub fn has_breakpoints() -> bool {
+    cfg!(any(target_os = "macos", target_os = "ios"))
+        || crate::breakpoint::software_breakpoints_supported()
+}
+
+// False until native_api_init has run, and after it when the native layer failed to start
+static NATIVE_READY: AtomicBool = AtomicBool::new(false);
+
+pub fn native_api_init(mode: i32) -> bool {
+    let ready = unsafe { native_init(mode) } > 0;
+    NATIVE_READY.store(ready, Ordering::Relaxed);
+    ready
+}
+
+pub fn native_ready() -> bool {
+    NATIVE_READY.load(Ordering::Relaxed)
+}
+
+// Returns (pid, name) pairs; an empty list means the native layer could not enumerate
+pub fn enumerate_processes() -> Vec<(i32, String)> {
+    let mut count: usize = 0;
+    let process_info_ptr = unsafe { enumprocess_native(&mut count) };
+    if process_info_ptr.is_null() {
+        return Vec::new();
+    }
+
+    let process_info_slice = unsafe { std::slice::from_raw_parts(process_info_ptr, count) };
+    let mut processes = Vec::with_capacity(count);
+    for info in process_info_slice {
+        let process_name = unsafe {
+            CStr::from_ptr(info.processname)
+                .to_string_lossy()
+                .into_owned()
+        };
+        processes.push((info.pid, process_name));
+        unsafe { libc::free(info.processname as *mut libc::c_void) };
+    }
+
+    unsafe { libc::free(process_info_ptr as *mut libc::c_void) };
+    processes
+}
+
 pub fn enum_modules(pid: i32) -> Result<Vec<serde_json::Value>, String> {
+    if coredump::is_dump_handle(pid) {
+        return coredump::dump_modules(pid);
+    }
     let mut count: usize = 0;
     let module_info_ptr = unsafe { enummodule_native(pid, &mut count) };
 
@@ -225,6 +861,52 @@ pub fn enum_regions(pid: i32) -> Result<Vec<serde_json::Value>, String> {
     }
 }
 
+fn take_error_message(error_ptr: *mut c_char) -> String {
+    let message = unsafe { CStr::from_ptr(error_ptr).to_string_lossy().into_owned() };
+    unsafe { libc::free(error_ptr as *mut c_void) };
+    message
+}
+
+pub fn read_file_data(path: &str) -> Result<Vec<u8>, String> {
+    let c_path = CString::new(path).map_err(|_| "Invalid path: contains null byte".to_string())?;
+    let mut size: usize = 0;
+    let mut error_ptr: *mut c_char = std::ptr::null_mut();
+
+    let data_ptr = unsafe { read_file(c_path.as_ptr(), &mut size, &mut error_ptr) };
+    if !error_ptr.is_null() {
+        return Err(take_error_message(error_ptr));
+    }
+    if data_ptr.is_null() {
+        return Ok(Vec::new());
+    }
+
+    let data = unsafe { std::slice::from_raw_parts(data_ptr as *const u8, size) }.to_vec();
+    unsafe { libc::free(data_ptr as *mut c_void) };
+    Ok(data)
+}
+
+pub fn write_file_data(path: &str, data: &[u8], append: bool) -> Result<(), String> {
+    let c_path = CString::new(path).map_err(|_| "Invalid path: contains null byte".to_string())?;
+    let mut error_ptr: *mut c_char = std::ptr::null_mut();
+
+    let result = unsafe {
+        write_file(
+            c_path.as_ptr(),
+            data.as_ptr() as *const c_void,
+            data.len(),
+            append,
+            &mut error_ptr,
+        )
+    };
+    if !error_ptr.is_null() {
+        return Err(take_error_message(error_ptr));
+    }
+    if result != 0 {
+        return Err(format!("Failed to write file {}", path));
+    }
+    Ok(())
+}
+
 pub fn get_application_info(pid: i32) -> Result<String, Error> {
     let result = unsafe {
         let raw_ptr = get_application_info_native(pid as c_int);
@@ -244,3 +926,451 @@ pub fn get_application_info(pid: i32) -> Result<String, Error> {
 
     Ok(result)
 }
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ThreadInfo {
+    pub tid: i32,
+    pub name: String,
+    pub state: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Registers {
+    pub pc: u64,
+    pub sp: u64,
+    pub general: Vec<(String, u64)>,
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn enumerate_threads(pid: i32) -> Result<Vec<ThreadInfo>, String> {
+    let entries = std::fs::read_dir(format!("/proc/{}/task", pid))
+        .map_err(|e| format!("Failed to list threads of {}: {}", pid, e))?;
+
+    let mut threads: Vec<ThreadInfo> = entries
+        .filter_map(|entry| {
+            let tid: i32 = entry.ok()?.file_name().to_str()?.parse().ok()?;
+            // stat is "tid (comm) state ...", and comm itself may contain spaces or parens
+            let stat = std::fs::read_to_string(format!("/proc/{}/task/{}/stat", pid, tid)).ok()?;
+            let name = &stat[stat.find('(')? + 1..stat.rfind(')')?];
+            let state = stat[stat.rfind(')')? + 1..].split_whitespace().next()?;
+            Some(ThreadInfo {
+                tid,
+                name: name.to_string(),
+                state: state.to_string(),
+            })
+        })
+        .collect();
+    threads.sort_by_key(|thread| thread.tid);
+    Ok(threads)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn enumerate_threads(_pid: i32) -> Result<Vec<ThreadInfo>, String> {
+    Err("Thread enumeration is not supported on this platform".to_string())
+}
+
+#[cfg(target_arch = "aarch64")]
+const REGISTER_NAMES: &[&str] = &[
+    "x0", "x1", "x2", "x3", "x4", "x5", "x6", "x7", "x8", "x9", "x10", "x11", "x12", "x13", "x14",
+    "x15", "x16", "x17", "x18", "x19", "x20", "x21", "x22", "x23", "x24", "x25", "x26", "x27",
+    "x28", "x29", "x30", "sp", "pc", "pstate",
+];
+#[cfg(target_arch = "aarch64")]
+const SP_INDEX: usize = 31;
+#[cfg(target_arch = "aarch64")]
+const PC_INDEX: usize = 32;
+
+#[cfg(target_arch = "x86_64")]
+const REGISTER_NAMES: &[&str] = &[
+    "r15", "r14", "r13", "r12", "rbp", "rbx", "r11", "r10", "r9", "r8", "rax", "rcx", "rdx", "rsi",
+    "rdi", "orig_rax", "rip", "cs", "eflags", "rsp", "ss", "fs_base", "gs_base", "ds", "es", "fs",
+    "gs",
+];
+#[cfg(target_arch = "x86_64")]
+const SP_INDEX: usize = 19;
+#[cfg(target_arch = "x86_64")]
+const PC_INDEX: usize = 16;
+
+// Not exported by libc for every target, but the value is fixed by the ELF core format
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const NT_PRSTATUS: usize = 1;
+
+#[cfg(all(
+    any(target_os = "linux", target_os = "android"),
+    any(target_arch = "aarch64", target_arch = "x86_64")
+))]
+fn registers_from_prstatus(values: &[u64]) -> Registers {
+    Registers {
+        pc: values[PC_INDEX],
+        sp: values[SP_INDEX],
+        general: REGISTER_NAMES
+            .iter()
+            .zip(values.iter())
+            .map(|(name, value)| (name.to_string(), *value))
+            .collect(),
+    }
+}
+
+// Reads NT_PRSTATUS. While the debugger traces the process it reads the registers for us;
+// otherwise this attaches to the single thread and detaches again.
+#[cfg(all(
+    any(target_os = "linux", target_os = "android"),
+    any(target_arch = "aarch64", target_arch = "x86_64")
+))]
+pub fn read_thread_registers(pid: i32, tid: i32) -> Result<Registers, String> {
+    if !std::path::Path::new(&format!("/proc/{}/task/{}", pid, tid)).exists() {
+        return Err(format!("Thread {} does not belong to process {}", tid, pid));
+    }
+
+    let mut values = [0u64; 34];
+    let result =
+        unsafe { debugger_thread_registers(pid, tid, values.as_mut_ptr(), REGISTER_NAMES.len()) };
+    if result == 0 {
+        return Ok(registers_from_prstatus(&values));
+    }
+    let error = Error::last_os_error();
+    if error.raw_os_error() != Some(libc::ESRCH) {
+        return Err(format!(
+            "Failed to read registers of thread {}: {}",
+            tid, error
+        ));
+    }
+
+    let null = std::ptr::null_mut::<c_void>();
+    if unsafe { libc::ptrace(libc::PTRACE_ATTACH, tid, null, null) } == -1 {
+        return Err(format!(
+            "Failed to attach to thread {}: {}",
+            tid,
+            Error::last_os_error()
+        ));
+    }
+
+    let mut status: c_int = 0;
+    let mut iov = libc::iovec {
+        iov_base: values.as_mut_ptr() as *mut c_void,
+        iov_len: REGISTER_NAMES.len() * std::mem::size_of::<u64>(),
+    };
+    let result = unsafe {
+        if libc::waitpid(tid, &mut status, libc::__WALL) == -1 {
+            -1
+        } else {
+            libc::ptrace(
+                libc::PTRACE_GETREGSET,
+                tid,
+                NT_PRSTATUS as *mut c_void,
+                &mut iov as *mut libc::iovec as *mut c_void,
+            )
+        }
+    };
+    let error = Error::last_os_error();
+    unsafe { libc::ptrace(libc::PTRACE_DETACH, tid, null, null) };
+
+    if result == -1 {
+        return Err(format!(
+            "Failed to read registers of thread {}: {}",
+            tid, error
+        ));
+    }
+    Ok(registers_from_prstatus(&values))
+}
+
+#[cfg(all(
+    any(target_os = "linux", target_os = "android"),
+    any(target_arch = "aarch64", target_arch = "x86_64")
+))]
+fn thread_control_error(pid: i32, tid: i32, error: Error) -> String {
+    match error.raw_os_error() {
+        Some(libc::ESRCH) => format!(
+            "Thread {} of process {} is not held by the debugger",
+            tid, pid
+        ),
+        Some(libc::EBUSY) => format!("Thread {} is not stopped at a breakpoint", tid),
+        _ => format!("Failed to control thread {}: {}", tid, error),
+    }
+}
+
+// Runs one instruction of a thread halted on a breakpoint and returns its registers after
+#[cfg(all(
+    any(target_os = "linux", target_os = "android"),
+    any(target_arch = "aarch64", target_arch = "x86_64")
+))]
+pub fn single_step(pid: i32, tid: i32) -> Result<Registers, String> {
+    let mut values = [0u64; 34];
+    let result =
+        unsafe { debugger_single_step(pid, tid, values.as_mut_ptr(), REGISTER_NAMES.len()) };
+    if result != 0 {
+        return Err(thread_control_error(pid, tid, Error::last_os_error()));
+    }
+    Ok(registers_from_prstatus(&values))
+}
+
+#[cfg(all(
+    any(target_os = "linux", target_os = "android"),
+    any(target_arch = "aarch64", target_arch = "x86_64")
+))]
+pub fn resume_thread(pid: i32, tid: i32) -> Result<(), String> {
+    if unsafe { debugger_resume_thread(pid, tid) } != 0 {
+        return Err(thread_control_error(pid, tid, Error::last_os_error()));
+    }
+    Ok(())
+}
+
+#[cfg(not(all(
+    any(target_os = "linux", target_os = "android"),
+    any(target_arch = "aarch64", target_arch = "x86_64")
+)))]
+pub fn read_thread_registers(_pid: i32, _tid: i32) -> Result<Registers, String> {
+    Err("Reading thread registers is not supported on this platform".to_string())
+}
+
+#[cfg(not(all(
+    any(target_os = "linux", target_os = "android"),
+    any(target_arch = "aarch64", target_arch = "x86_64")
+)))]
+pub fn single_step(_pid: i32, _tid: i32) -> Result<Registers, String> {
+    Err("Single-stepping is not supported on this platform".to_string())
+}
+
+#[cfg(not(all(
+    any(target_os = "linux", target_os = "android"),
+    any(target_arch = "aarch64", target_arch = "x86_64")
+)))]
+pub fn resume_thread(_pid: i32, _tid: i32) -> Result<(), String> {
+    Err("Resuming a halted thread is not supported on this platform".to_string())
+}
+
+#[cfg(all(test, any(target_os = "linux", target_os = "android")))]
+mod tests {
+    use super::*;
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+
+    // Hands out canned results and counts what the fallback asked for
+    struct MockReader {
+        fast: RefCell<Vec<Result<isize, i32>>>,
+        ptrace: RefCell<Vec<Result<isize, i32>>>,
+        region: Option<(u64, u64)>,
+        now: Cell<Instant>,
+        fast_calls: Cell<usize>,
+        ptrace_calls: Cell<usize>,
+        region_calls: Cell<usize>,
+    }
+
+    impl MockReader {
+        fn new(fast: Vec<Result<isize, i32>>, ptrace: Vec<Result<isize, i32>>) -> MockReader {
+            MockReader {
+                fast: RefCell::new(fast),
+                ptrace: RefCell::new(ptrace),
+                region: Some((0x1000, 0x3000)),
+                now: Cell::new(Instant::now()),
+                fast_calls: Cell::new(0),
+                ptrace_calls: Cell::new(0),
+                region_calls: Cell::new(0),
+            }
+        }
+
+        fn next(results: &RefCell<Vec<Result<isize, i32>>>) -> Result<isize, Error> {
+            results
+                .borrow_mut()
+                .remove(0)
+                .map_err(Error::from_raw_os_error)
+        }
+    }
+
+    impl FallbackReader for MockReader {
+        fn read_fast(&self, _: i32, _: u64, _: usize, _: &mut [u8]) -> Result<isize, Error> {
+            self.fast_calls.set(self.fast_calls.get() + 1);
+            MockReader::next(&self.fast)
+        }
+
+        fn read_ptrace(&self, _: i32, _: u64, _: usize, _: &mut [u8]) -> Result<isize, Error> {
+            self.ptrace_calls.set(self.ptrace_calls.get() + 1);
+            MockReader::next(&self.ptrace)
+        }
+
+        fn region_of(&self, _: i32, address: u64) -> Option<Mapping> {
+            self.region_calls.set(self.region_calls.get() + 1);
+            Some(match self.region {
+                Some((start, end)) if start <= address && address < end => {
+                    Mapping::Mapped(start, end)
+                }
+                Some((start, _)) if address < start => Mapping::Unmapped(0, start),
+                Some((_, end)) => Mapping::Unmapped(end, u64::MAX),
+                None => Mapping::Unmapped(0, u64::MAX),
+            })
+        }
+
+        fn is_self(&self, pid: i32) -> bool {
+            pid == 1
+        }
+
+        fn now(&self) -> Instant {
+            self.now.get()
+        }
+    }
+
+    fn read(reader: &MockReader, cache: &Mutex<FallbackCache>, pid: i32) -> Result<isize, Error> {
+        read_with_fallback(reader, cache, pid, 0x2000, 8, &mut [0u8; 8])
+    }
+
+    #[test]
+    fn fast_path_success_never_attaches() {
+        let reader = MockReader::new(vec![Ok(8)], vec![]);
+        let cache = Mutex::new(FallbackCache::default());
+        assert_eq!(read(&reader, &cache, 42).unwrap(), 8);
+        assert_eq!(reader.ptrace_calls.get(), 0);
+        assert_eq!(cache.lock().unwrap().state(42, 0x2000), None);
+    }
+
+    #[test]
+    fn region_read_through_ptrace_skips_fast_path_afterwards() {
+        let reader = MockReader::new(vec![Err(libc::EFAULT)], vec![Ok(8), Ok(8)]);
+        let cache = Mutex::new(FallbackCache::default());
+        assert_eq!(read(&reader, &cache, 42).unwrap(), 8);
+        assert_eq!(
+            cache.lock().unwrap().state(42, 0x2000),
+            Some(FallbackState::Ptrace)
+        );
+
+        assert_eq!(read(&reader, &cache, 42).unwrap(), 8);
+        assert_eq!(reader.fast_calls.get(), 1);
+        assert_eq!(reader.ptrace_calls.get(), 2);
+    }
+
+    #[test]
+    fn busy_tracee_is_retried_after_the_interval() {
+        let reader = MockReader::new(vec![Err(libc::EFAULT); 3], vec![Err(libc::EPERM), Ok(8)]);
+        let cache = Mutex::new(FallbackCache::default());
+        // The attach lost out to another tracer, which says nothing lasting about the region
+        assert_eq!(
+            read(&reader, &cache, 42).unwrap_err().raw_os_error(),
+            Some(libc::EFAULT)
+        );
+        assert_eq!(cache.lock().unwrap().state(42, 0x2000), None);
+
+        // Until the interval passes the process is neither listed nor attached to again
+        assert!(read(&reader, &cache, 42).is_err());
+        assert_eq!(reader.ptrace_calls.get(), 1);
+        assert_eq!(reader.region_calls.get(), 1);
+
+        reader.now.set(reader.now.get() + ATTACH_RETRY_INTERVAL);
+        assert_eq!(read(&reader, &cache, 42).unwrap(), 8);
+        assert_eq!(reader.ptrace_calls.get(), 2);
+        assert!(!cache
+            .lock()
+            .unwrap()
+            .attach_refused_recently(42, reader.now.get()));
+    }
+
+    #[test]
+    fn repeated_misses_list_the_maps_once() {
+        let mut reader = MockReader::new(vec![Err(libc::EFAULT); 4], vec![]);
+        reader.region = Some((0x10_000, 0x20_000));
+        let cache = Mutex::new(FallbackCache::default());
+        // Stale addresses anywhere in the gap below the only region
+        for address in [0x2000, 0x3000, 0x8000, 0xf000] {
+            assert!(read_with_fallback(&reader, &cache, 42, address, 8, &mut [0u8; 8]).is_err());
+        }
+        assert_eq!(reader.region_calls.get(), 1);
+        assert_eq!(reader.ptrace_calls.get(), 0);
+        assert_eq!(
+            cache.lock().unwrap().state(42, 0x8000),
+            Some(FallbackState::Unmapped)
+        );
+
+        // A new listing may have mapped the gap, so it is looked up again
+        cache.lock().unwrap().note_listing(42, 1);
+        assert_eq!(cache.lock().unwrap().state(42, 0x8000), None);
+    }
+
+    #[test]
+    fn refused_peek_is_cached_as_unreadable() {
+        let reader = MockReader::new(
+            vec![Err(libc::EFAULT), Err(libc::EFAULT)],
+            vec![Err(libc::EIO)],
+        );
+        let cache = Mutex::new(FallbackCache::default());
+        assert!(read(&reader, &cache, 42).is_err());
+        assert_eq!(
+            cache.lock().unwrap().state(42, 0x2000),
+            Some(FallbackState::Unreadable)
+        );
+        assert!(read(&reader, &cache, 42).is_err());
+        assert_eq!(reader.ptrace_calls.get(), 1);
+    }
+
+    #[test]
+    fn unmapped_and_own_addresses_never_attach() {
+        let mut reader = MockReader::new(vec![Err(libc::EFAULT), Err(libc::EFAULT)], vec![]);
+        let cache = Mutex::new(FallbackCache::default());
+        assert!(read(&reader, &cache, 1).is_err());
+        reader.region = None;
+        assert!(read(&reader, &cache, 42).is_err());
+        assert_eq!(reader.ptrace_calls.get(), 0);
+    }
+
+    #[test]
+    fn other_errors_do_not_fall_back() {
+        let reader = MockReader::new(vec![Err(libc::ESRCH)], vec![]);
+        let cache = Mutex::new(FallbackCache::default());
+        assert!(read(&reader, &cache, 42).is_err());
+        assert_eq!(reader.ptrace_calls.get(), 0);
+    }
+
+    #[test]
+    fn changed_listing_drops_only_that_process() {
+        let mut cache = FallbackCache::default();
+        cache.note_listing(42, 1);
+        cache.insert(42, 0x1000, 0x3000, FallbackState::Unreadable);
+        cache.insert(43, 0x1000, 0x3000, FallbackState::Ptrace);
+
+        cache.note_listing(42, 1);
+        assert_eq!(cache.state(42, 0x2000), Some(FallbackState::Unreadable));
+        cache.note_listing(42, 2);
+        assert_eq!(cache.state(42, 0x2000), None);
+        assert_eq!(cache.state(43, 0x2000), Some(FallbackState::Ptrace));
+    }
+
+    // Fails with each errno in turn, then reads n bytes
+    fn failing_then(
+        errors: Vec<i32>,
+        n: isize,
+    ) -> (impl FnMut() -> Result<isize, Error>, Rc<Cell<usize>>) {
+        let calls = Rc::new(Cell::new(0));
+        let counted = calls.clone();
+        let read = move || {
+            let call = counted.get();
+            counted.set(call + 1);
+            match errors.get(call) {
+                Some(&errno) => Err(Error::from_raw_os_error(errno)),
+                None => Ok(n),
+            }
+        };
+        (read, calls)
+    }
+
+    #[test]
+    fn interrupted_read_succeeds_on_retry() {
+        let (read, calls) = failing_then(vec![libc::EINTR], 8);
+        assert_eq!(retry_read(2, read).unwrap(), 8);
+        assert_eq!(calls.get(), 2);
+
+        let (read, calls) = failing_then(vec![libc::EAGAIN, libc::EINTR], 8);
+        assert_eq!(retry_read(2, read).unwrap(), 8);
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[test]
+    fn faults_are_permanent_and_retries_run_out() {
+        let (read, calls) = failing_then(vec![libc::EFAULT], 8);
+        let error = retry_read(2, read).unwrap_err();
+        assert!(!error.is_transient());
+        assert_eq!(error.raw_os_error(), Some(libc::EFAULT));
+        assert_eq!(calls.get(), 1);
+
+        let (read, calls) = failing_then(vec![libc::EINTR; 3], 8);
+        let error = retry_read(2, read).unwrap_err();
+        assert!(error.is_transient());
+        assert_eq!(calls.get(), 3);
+    }
+}
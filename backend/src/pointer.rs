@@ -0,0 +1,384 @@
+use crate::native_bridge;
+use crate::scan::{self, MemoryProtectionFilter};
+use lz4_flex::block::{compress_prepend_size, decompress};
+use rayon::prelude::*;
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+const POINTER_SIZE: usize = 8;
+const CHUNK_SIZE: usize = 1024 * 1024 * 16; // 16MB chunks
+
+const POINTER_MAP_MAGIC: &[u8; 4] = b"PMAP";
+// Bump whenever the entry layout changes so stale files are rejected instead of misread
+const POINTER_MAP_VERSION: u32 = 2;
+// An lz4 block never decompresses to more than about 255 times its size
+const LZ4_MAX_EXPANSION: usize = 256;
+
+// Upper bound on the addresses carried from one level to the next and on the chains returned.
+// Without it a deep scan over a large process grows exponentially and exhausts memory.
+pub const MAX_CANDIDATES: usize = 1_000_000;
+
+// Every 8-byte aligned value in writable memory that points into a readable region, stored as
+// (pointer value, source address) and sorted by value. Each entry costs 16 bytes, so a process
+// with 50M such pointers needs around 800MB for the map alone.
+pub struct PointerMap {
+    entries: Vec<(u64, u64)>,
+    origin: MapOrigin,
+}
+
+// The process a map was built from. Module bases move with every launch, and pids get reused,
+// so a map whose origin differs from the process being scanned describes another address space.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapOrigin {
+    pid: i32,
+    module_bases: Vec<u64>,
+}
+
+impl MapOrigin {
+    pub fn of(pid: i32, modules: &[Value]) -> MapOrigin {
+        let mut module_bases: Vec<u64> = modules
+            .iter()
+            .filter_map(|module| module["base"].as_u64())
+            .collect();
+        module_bases.sort_unstable();
+        MapOrigin { pid, module_bases }
+    }
+}
+
+impl PointerMap {
+    pub fn build(pid: i32, modules: &[Value]) -> Result<PointerMap, String> {
+        let mut targets = scan::protected_regions(pid, &MemoryProtectionFilter::default())?;
+        targets.sort_unstable();
+        let sources = scan::protected_regions(pid, &MemoryProtectionFilter::read_write())?;
+
+        let entries = scan::in_scan_pool(|| {
+            let mut entries: Vec<(u64, u64)> = sources
+                .par_iter()
+                .flat_map_iter(|&(start, end)| collect_pointers(pid, start, end, &targets))
+                .collect();
+            entries.par_sort_unstable();
+            entries
+        });
+        Ok(PointerMap {
+            entries,
+            origin: MapOrigin::of(pid, modules),
+        })
+    }
+
+    pub fn origin(&self) -> &MapOrigin {
+        &self.origin
+    }
+
+    // All pointers whose value lies in low..=high
+    fn pointing_into(&self, low: u64, high: u64) -> &[(u64, u64)] {
+        let first = self.entries.partition_point(|&(value, _)| value < low);
+        let last = self.entries.partition_point(|&(value, _)| value <= high);
+        &self.entries[first..last]
+    }
+}
+
+// File layout: magic, u32 version, i32 pid, u32 module count, the module bases, u64 entry
+// count, then the lz4 compressed (address, pointer value) pairs. Every number is little-endian.
+pub fn pointer_map_save(map: &PointerMap, path: &Path) -> Result<(), String> {
+    let mut raw = Vec::with_capacity(map.entries.len() * 16);
+    for &(value, address) in &map.entries {
+        raw.extend_from_slice(&address.to_le_bytes());
+        raw.extend_from_slice(&value.to_le_bytes());
+    }
+
+    let bases = &map.origin.module_bases;
+    let mut data = Vec::with_capacity(24 + bases.len() * 8);
+    data.extend_from_slice(POINTER_MAP_MAGIC);
+    data.extend_from_slice(&POINTER_MAP_VERSION.to_le_bytes());
+    data.extend_from_slice(&map.origin.pid.to_le_bytes());
+    data.extend_from_slice(&(bases.len() as u32).to_le_bytes());
+    for base in bases {
+        data.extend_from_slice(&base.to_le_bytes());
+    }
+    data.extend_from_slice(&(map.entries.len() as u64).to_le_bytes());
+    data.extend_from_slice(&compress_prepend_size(&raw));
+
+    fs::write(path, data)
+        .map_err(|e| format!("Failed to write pointer map {}: {}", path.display(), e))
+}
+
+// Takes the next len bytes of a map file, failing on a file cut short
+fn take<'a>(data: &mut &'a [u8], len: usize, path: &Path) -> Result<&'a [u8], String> {
+    if data.len() < len {
+        return Err(format!("Pointer map {} is truncated", path.display()));
+    }
+    let (head, rest) = data.split_at(len);
+    *data = rest;
+    Ok(head)
+}
+
+pub fn pointer_map_load(path: &Path) -> Result<PointerMap, String> {
+    let data = fs::read(path)
+        .map_err(|e| format!("Failed to read pointer map {}: {}", path.display(), e))?;
+    let mut rest = data.as_slice();
+    if take(&mut rest, 4, path).ok() != Some(POINTER_MAP_MAGIC.as_slice()) {
+        return Err(format!("{} is not a pointer map file", path.display()));
+    }
+    let version = u32::from_le_bytes(take(&mut rest, 4, path)?.try_into().unwrap());
+    if version != POINTER_MAP_VERSION {
+        return Err(format!(
+            "Unsupported pointer map version {} (expected {})",
+            version, POINTER_MAP_VERSION
+        ));
+    }
+    let pid = i32::from_le_bytes(take(&mut rest, 4, path)?.try_into().unwrap());
+    let module_count = u32::from_le_bytes(take(&mut rest, 4, path)?.try_into().unwrap()) as usize;
+    let module_bases = take(&mut rest, module_count.saturating_mul(8), path)?
+        .chunks_exact(8)
+        .map(|base| u64::from_le_bytes(base.try_into().unwrap()))
+        .collect();
+    let count = u64::from_le_bytes(take(&mut rest, 8, path)?.try_into().unwrap()) as usize;
+
+    // Both sizes come from the file, so they are checked against each other and against what
+    // the compressed bytes could hold before anything is allocated for the entries
+    let raw_len = u32::from_le_bytes(take(&mut rest, 4, path)?.try_into().unwrap()) as usize;
+    if Some(raw_len) != count.checked_mul(16) {
+        return Err(format!(
+            "Pointer map is corrupt: expected {} entries but found {} bytes",
+            count, raw_len
+        ));
+    }
+    if raw_len > rest.len().saturating_mul(LZ4_MAX_EXPANSION) {
+        return Err(format!(
+            "Pointer map is corrupt: {} entries cannot fit in {} compressed bytes",
+            count,
+            rest.len()
+        ));
+    }
+    let raw = decompress(rest, raw_len)
+        .map_err(|e| format!("Failed to decompress pointer map: {}", e))?;
+
+    let mut entries: Vec<(u64, u64)> = raw
+        .chunks_exact(16)
+        .map(|entry| {
+            let address = u64::from_le_bytes(entry[..8].try_into().unwrap());
+            let value = u64::from_le_bytes(entry[8..].try_into().unwrap());
+            (value, address)
+        })
+        .collect();
+    entries.par_sort_unstable();
+    Ok(PointerMap {
+        entries,
+        origin: MapOrigin { pid, module_bases },
+    })
+}
+
+fn collect_pointers(pid: i32, start: u64, end: u64, targets: &[(u64, u64)]) -> Vec<(u64, u64)> {
+    let mut pointers = Vec::new();
+    let mut current = (start + 7) & !7;
+
+    while current < end {
+        let chunk_end = (current + CHUNK_SIZE as u64).min(end);
+        let size = (chunk_end - current) as usize;
+        let mut buffer = vec![0u8; size];
+        if let Ok(nread) =
+            native_bridge::read_process_memory(pid, current as *mut libc::c_void, size, &mut buffer)
+        {
+            let nread = nread.max(0) as usize;
+            for (index, window) in buffer[..nread].chunks_exact(POINTER_SIZE).enumerate() {
+                let value = u64::from_le_bytes(window.try_into().unwrap());
+                if points_into(value, targets) {
+                    pointers.push((value, current + (index * POINTER_SIZE) as u64));
+                }
+            }
+        }
+        current = chunk_end;
+    }
+    pointers
+}
+
+fn points_into(value: u64, regions: &[(u64, u64)]) -> bool {
+    let index = regions.partition_point(|&(start, _)| start <= value);
+    index > 0 && value < regions[index - 1].1
+}
+
+// Modules are matched by base and size the same way resolve_single_level_address maps a
+// module name to its base, so a chain can be written back as "module+offset"
+fn static_base(address: u64, modules: &[Value]) -> Option<(usize, u64)> {
+    modules.iter().enumerate().find_map(|(index, module)| {
+        let base = module["base"].as_u64()?;
+        let size = module["size"].as_u64()?;
+        (base <= address && address < base.saturating_add(size)).then_some((index, address - base))
+    })
+}
+
+// Each chain is [module index, offset from module base, offset after each dereference...],
+// i.e. the chain [m, a, b, c] stands for "[[module+a]+b]+c"
+pub fn pointer_scan(
+    pid: i32,
+    target: u64,
+    modules: &[Value],
+    max_depth: usize,
+    max_offset: u64,
+) -> Result<Vec<Vec<u64>>, String> {
+    let map = PointerMap::build(pid, modules)?;
+    Ok(pointer_scan_with_map(
+        &map, target, modules, max_depth, max_offset,
+    ))
+}
+
+pub fn pointer_scan_with_map(
+    map: &PointerMap,
+    target: u64,
+    modules: &[Value],
+    max_depth: usize,
+    max_offset: u64,
+) -> Vec<Vec<u64>> {
+    let mut chains = Vec::new();
+    // Offsets are kept in reverse order while walking backwards from the target
+    let mut frontier: Vec<(u64, Vec<u64>)> = vec![(target, Vec::new())];
+
+    for _ in 0..max_depth {
+        let mut next = Vec::new();
+        for (address, offsets) in &frontier {
+            for &(value, source) in map.pointing_into(address.saturating_sub(max_offset), *address)
+            {
+                let mut offsets = offsets.clone();
+                offsets.push(address - value);
+                if let Some((module_index, base_offset)) = static_base(source, modules) {
+                    let mut chain = vec![module_index as u64, base_offset];
+                    chain.extend(offsets.iter().rev());
+                    chains.push(chain);
+                    if chains.len() >= MAX_CANDIDATES {
+                        return chains;
+                    }
+                }
+                if next.len() < MAX_CANDIDATES {
+                    next.push((source, offsets));
+                }
+            }
+        }
+        if next.is_empty() {
+            break;
+        }
+        frontier = next;
+    }
+    chains
+}
+
+pub fn format_chain(chain: &[u64], modules: &[Value]) -> Option<String> {
+    let (module_index, offsets) = chain.split_first()?;
+    let module_name = modules.get(*module_index as usize)?["modulename"].as_str()?;
+    let file_name = Path::new(module_name).file_name()?.to_string_lossy();
+
+    let (last, inner) = offsets.split_last()?;
+    let mut expression = file_name.to_string();
+    for offset in inner {
+        expression = format!("[{}+0x{:X}]", expression, offset);
+    }
+    Some(format!("{}+0x{:X}", expression, last))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!(
+            "memory-server-{}-{}.pmap",
+            name,
+            std::process::id()
+        ))
+    }
+
+    fn modules(base: u64) -> Vec<Value> {
+        vec![json!({ "base": base, "size": 0x100, "modulename": "/system/lib64/libgame.so" })]
+    }
+
+    // The module holds a pointer at base+8 to 0x5000, and the target sits 0x10 past that
+    fn sample_map(base: u64) -> PointerMap {
+        PointerMap {
+            entries: vec![(0x5000, base + 8), (0x9000, 0x7000)],
+            origin: MapOrigin::of(42, &modules(base)),
+        }
+    }
+
+    #[test]
+    fn saved_map_scans_like_the_built_one() {
+        let path = temp_path("round-trip");
+        pointer_map_save(&sample_map(0x1000), &path).unwrap();
+        let loaded = pointer_map_load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.entries, sample_map(0x1000).entries);
+        assert_eq!(*loaded.origin(), MapOrigin::of(42, &modules(0x1000)));
+        let chains = pointer_scan_with_map(&loaded, 0x5010, &modules(0x1000), 1, 0x20);
+        assert_eq!(chains, vec![vec![0, 8, 0x10]]);
+        assert_eq!(
+            format_chain(&chains[0], &modules(0x1000)).unwrap(),
+            "[libgame.so+0x8]+0x10"
+        );
+    }
+
+    #[test]
+    fn map_from_a_relaunched_process_does_not_match() {
+        let origin = sample_map(0x1000).origin;
+        assert_ne!(origin, MapOrigin::of(42, &modules(0x2000)));
+        assert_ne!(origin, MapOrigin::of(43, &modules(0x1000)));
+        // Module order from the enumeration does not matter
+        let mut both = modules(0x1000);
+        both.extend(modules(0x3000));
+        let reversed: Vec<Value> = both.iter().rev().cloned().collect();
+        assert_eq!(MapOrigin::of(42, &both), MapOrigin::of(42, &reversed));
+    }
+
+    #[test]
+    fn old_and_truncated_files_are_rejected() {
+        let path = temp_path("rejected");
+        pointer_map_save(&sample_map(0x1000), &path).unwrap();
+        let data = fs::read(&path).unwrap();
+
+        let mut old = data.clone();
+        old[4..8].copy_from_slice(&1u32.to_le_bytes());
+        fs::write(&path, &old).unwrap();
+        assert!(pointer_map_load(&path).err().unwrap().contains("version 1"));
+
+        fs::write(&path, &data[..20]).unwrap();
+        assert!(pointer_map_load(&path).err().unwrap().contains("truncated"));
+
+        fs::write(&path, b"not a map").unwrap();
+        assert!(pointer_map_load(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn oversized_entry_counts_are_rejected_before_decompressing() {
+        let path = temp_path("oversized");
+        pointer_map_save(&sample_map(0x1000), &path).unwrap();
+        let data = fs::read(&path).unwrap();
+        // Magic, version, pid, one module base, then the entry count and the lz4 size prefix
+        let (count_at, size_at) = (24, 32);
+
+        // 4GB claimed by both the count and the size prefix, backed by a few bytes
+        let mut bomb = data.clone();
+        bomb[count_at..size_at].copy_from_slice(&0x1000_0000u64.to_le_bytes());
+        bomb[size_at..size_at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        fs::write(&path, &bomb).unwrap();
+        let error = pointer_map_load(&path).err().unwrap();
+        assert!(error.contains("corrupt"), "{}", error);
+
+        // Sizes that agree, but 256MB of entries from a few compressed bytes
+        bomb[count_at..size_at].copy_from_slice(&0x0100_0000u64.to_le_bytes());
+        bomb[size_at..size_at + 4].copy_from_slice(&0x1000_0000u32.to_le_bytes());
+        fs::write(&path, &bomb).unwrap();
+        let error = pointer_map_load(&path).err().unwrap();
+        assert!(error.contains("cannot fit"), "{}", error);
+
+        // A size prefix that disagrees with the entry count
+        let mut mismatched = data;
+        mismatched[size_at..size_at + 4].copy_from_slice(&48u32.to_le_bytes());
+        fs::write(&path, &mismatched).unwrap();
+        assert!(pointer_map_load(&path)
+            .err()
+            .unwrap()
+            .contains("expected 2 entries"));
+        fs::remove_file(&path).unwrap();
+    }
+}
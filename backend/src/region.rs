@@ -0,0 +1,176 @@
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::ops::BitOr;
+
+// r/w/x bits of a mapping; combine with | and test with contains()
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Protection(u8);
+
+impl Protection {
+    pub const NONE: Protection = Protection(0);
+    pub const READ: Protection = Protection(1);
+    pub const WRITE: Protection = Protection(2);
+    pub const EXEC: Protection = Protection(4);
+
+    pub fn contains(self, other: Protection) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    // Flags are positional ("rw-p", "r-x"), the same layout every native backend emits
+    pub fn parse(flags: &str) -> Protection {
+        let flags = flags.as_bytes();
+        let mut protection = Protection::NONE;
+        for (index, flag, bit) in [
+            (0, b'r', Protection::READ),
+            (1, b'w', Protection::WRITE),
+            (2, b'x', Protection::EXEC),
+        ] {
+            if flags.get(index) == Some(&flag) {
+                protection = protection | bit;
+            }
+        }
+        protection
+    }
+}
+
+impl BitOr for Protection {
+    type Output = Protection;
+
+    fn bitor(self, other: Protection) -> Protection {
+        Protection(self.0 | other.0)
+    }
+}
+
+impl fmt::Display for Protection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let flag = |bit: Protection, c: char| if self.contains(bit) { c } else { '-' };
+        write!(
+            f,
+            "{}{}{}",
+            flag(Protection::READ, 'r'),
+            flag(Protection::WRITE, 'w'),
+            flag(Protection::EXEC, 'x')
+        )
+    }
+}
+
+impl Serialize for Protection {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+// What a mapping holds, judged from its path. The names follow the Linux and Android
+// conventions: ART labels its spaces "[anon:dalvik-...]", bionic's allocators label theirs
+// "[anon:libc_malloc]" or "[anon:scudo:...]", and installed apps live under /data/app.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RegionKind {
+    JavaHeap,
+    NativeHeap,
+    Stack,
+    AppCode,
+    SystemCode,
+    Anonymous,
+    Other,
+}
+
+impl RegionKind {
+    pub fn classify(path: Option<&str>) -> RegionKind {
+        let Some(path) = path else {
+            return RegionKind::Anonymous;
+        };
+        if path.starts_with("[anon:dalvik-") || path.starts_with("/dev/ashmem/dalvik-") {
+            RegionKind::JavaHeap
+        } else if path == "[heap]"
+            || path.starts_with("[anon:libc_malloc")
+            || path.starts_with("[anon:scudo:")
+            || path.starts_with("[anon:jemalloc")
+        {
+            RegionKind::NativeHeap
+        } else if path.starts_with("[stack")
+            || path.starts_with("[anon:stack_and_tls:")
+            || path.starts_with("[anon:thread stack")
+        {
+            RegionKind::Stack
+        } else if path.starts_with("/data/app/") || path.starts_with("/data/data/") {
+            RegionKind::AppCode
+        } else if [
+            "/system/",
+            "/apex/",
+            "/vendor/",
+            "/product/",
+            "/usr/",
+            "/lib",
+        ]
+        .iter()
+        .any(|prefix| path.starts_with(prefix))
+        {
+            RegionKind::SystemCode
+        } else if path.starts_with("[anon:") {
+            RegionKind::Anonymous
+        } else {
+            RegionKind::Other
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryRegion {
+    pub start: u64,
+    pub end: u64,
+    pub protection: Protection,
+    pub path: Option<String>,
+    pub kind: RegionKind,
+}
+
+// Parses one "start-end perms offset dev inode [path]" line as found in /proc/<pid>/maps
+fn parse_region_line(line: &str) -> Option<MemoryRegion> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    if parts.len() < 5 {
+        return None;
+    }
+    let (start, end) = parts[0].split_once('-')?;
+    let path = parts[5..].join(" ");
+    let path = if path.is_empty() { None } else { Some(path) };
+    Some(MemoryRegion {
+        start: u64::from_str_radix(start, 16).ok()?,
+        end: u64::from_str_radix(end, 16).ok()?,
+        protection: Protection::parse(parts[1]),
+        kind: RegionKind::classify(path.as_deref()),
+        path,
+    })
+}
+
+// Linux and Android read the maps file directly, which also avoids the fixed buffer size of
+// the native call; other platforms emit the same line format through native_bridge
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn region_listing(pid: i32) -> Result<String, String> {
+    std::fs::read_to_string(format!("/proc/{}/maps", pid))
+        .map_err(|e| format!("Failed to read /proc/{}/maps: {}", pid, e))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn region_listing(pid: i32) -> Result<String, String> {
+    let mut buffer = vec![0u8; 1024 * 1024]; // 1MB buffer
+    unsafe {
+        crate::native_bridge::enumerate_regions_to_buffer(pid, buffer.as_mut_ptr(), buffer.len());
+    }
+    let len = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
+    buffer.truncate(len);
+    String::from_utf8(buffer).map_err(|_| "Failed to convert buffer to string".to_string())
+}
+
+pub fn enumerate_regions(pid: i32) -> Result<Vec<MemoryRegion>, String> {
+    if crate::coredump::is_dump_handle(pid) {
+        return crate::coredump::dump_regions(pid);
+    }
+    let listing = region_listing(pid)?;
+    crate::native_bridge::note_region_listing(pid, &listing);
+    let regions: Vec<MemoryRegion> = listing.lines().filter_map(parse_region_line).collect();
+    if regions.is_empty() {
+        Err("No regions found".to_string())
+    } else {
+        Ok(regions)
+    }
+}
@@ -1,14 +1,91 @@
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::compression::CompressMode;
+use crate::scan::{Alignment, MemoryProtectionFilter};
+use crate::util::{
+    Arch, AsmSyntax, Endianness, FileSortKey, HashAlgo, LengthPrefix, NameMatch, Radix, SortOrder,
+    StructField,
+};
 
 #[derive(Deserialize)]
 pub struct OpenProcessRequest {
     pub pid: i32,
 }
 
+// Opens an ELF core file and reads from it in place of a live process
+#[derive(Deserialize)]
+pub struct OpenDumpRequest {
+    pub path: String,
+}
+
+#[derive(Deserialize)]
+pub struct CloseDumpRequest {
+    pub handle: i32,
+}
+
+#[derive(Deserialize)]
+pub struct AttachByNameRequest {
+    pub name: String,
+    // Exact when not given
+    #[serde(rename = "match")]
+    pub match_mode: Option<NameMatch>,
+}
+
 #[derive(Deserialize)]
 pub struct ReadMemoryRequest {
     pub address: usize,
     pub size: usize,
+    pub compression: Option<CompressMode>,
+    // Returns the bytes as a text hexdump instead, e.g. for a look from curl
+    pub hexdump: Option<bool>,
+}
+
+// length is capped at util::MAX_VIEW_LENGTH
+#[derive(Deserialize)]
+pub struct ViewMemoryRequest {
+    pub address: u64,
+    pub length: usize,
+}
+
+#[derive(Deserialize)]
+pub struct ReadValueRequest {
+    pub address: usize,
+    pub value_type: String,
+    // Little when not given
+    pub endian: Option<Endianness>,
+    // Also returns the value as text in this radix, grouped into digit groups when asked
+    pub radix: Option<Radix>,
+    pub grouped: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct WriteValueRequest {
+    pub address: u64,
+    // A value type, or "string"
+    pub value_type: String,
+    pub value: Value,
+    // Only for strings
+    pub encoding: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ReadStringRequest {
+    pub address: usize,
+    pub max_len: usize,
+    pub encoding: String,
+}
+
+#[derive(Deserialize)]
+pub struct WriteStringRequest {
+    pub address: u64,
+    pub text: String,
+    pub encoding: String,
+    // True when not given
+    pub null_terminate: Option<bool>,
+    pub length_prefix: Option<LengthPrefix>,
+    // Room for the string including its prefix and terminator
+    pub max_len: usize,
 }
 
 #[derive(Deserialize)]
@@ -16,16 +93,85 @@ pub struct ResolveAddrRequest {
     pub query: String,
 }
 
+#[derive(Deserialize)]
+pub struct SymbolicateRequest {
+    pub address: u64,
+}
+
+#[derive(Deserialize)]
+pub struct ModuleExportsRequest {
+    pub module: String,
+}
+
+#[derive(Deserialize)]
+pub struct DisassembleRequest {
+    pub address: u64,
+    pub count: usize,
+    #[serde(default)]
+    pub arch: Arch,
+    pub syntax: Option<AsmSyntax>,
+}
+
+#[derive(Deserialize)]
+pub struct DisassembleFunctionRequest {
+    pub address: u64,
+    // Safety cap for code that never reaches a return
+    pub max_instructions: Option<usize>,
+    #[serde(default)]
+    pub arch: Arch,
+    pub syntax: Option<AsmSyntax>,
+}
+
+#[derive(Deserialize)]
+pub struct AssembleRequest {
+    pub source: String,
+    pub address: u64,
+    #[serde(default)]
+    pub arch: Arch,
+}
+
+#[derive(Deserialize)]
+pub struct EvaluateExpressionRequest {
+    pub expression: String,
+}
+
 #[derive(Deserialize)]
 pub struct WriteMemoryRequest {
     pub address: usize,
     pub buffer: Vec<u8>,
 }
 
+#[derive(Deserialize)]
+pub struct FillMemoryRequest {
+    pub address: u64,
+    pub byte: u8,
+    pub length: usize,
+}
+
+// The ranges may overlap; the copy behaves like memmove
+#[derive(Deserialize)]
+pub struct CopyMemoryRequest {
+    pub src: u64,
+    pub dst: u64,
+    pub length: usize,
+}
+
+#[derive(Deserialize)]
+pub struct HashRegionRequest {
+    pub start: u64,
+    pub end: u64,
+    pub algo: HashAlgo,
+}
+
+fn default_find_type() -> String {
+    "exact".to_string()
+}
+
 #[derive(Deserialize, Clone)]
 pub struct MemoryScanRequest {
     pub pattern: String,
     pub address_ranges: Vec<(usize, usize)>,
+    #[serde(default = "default_find_type")]
     pub find_type: String,
     pub data_type: String,
     pub scan_id: String,
@@ -34,6 +180,224 @@ pub struct MemoryScanRequest {
     pub do_suspend: bool,
 }
 
+#[derive(Deserialize)]
+pub struct ScanRequest {
+    pub scan_id: Option<String>,
+    pub pattern: String,
+    pub address_ranges: Option<Vec<(u64, u64)>>,
+    pub protection: Option<MemoryProtectionFilter>,
+    pub align: Alignment,
+    // Clients from before find_type existed only ever ran exact scans
+    #[serde(default = "default_find_type")]
+    pub find_type: String,
+    pub data_type: Option<String>,
+    pub tolerance: Option<f64>,
+    pub low: Option<f64>,
+    pub high: Option<f64>,
+    pub do_suspend: Option<bool>,
+    pub max_results: Option<usize>,
+    // aob only: also report each match as an offset into its module
+    pub module_relative: Option<bool>,
+    // bits only: keep the values where (value & mask) == expected; expected defaults to mask,
+    // i.e. every masked bit set
+    pub mask: Option<u64>,
+    pub expected: Option<u64>,
+}
+
+// Messages a client sends over the scan stream WebSocket
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ScanStreamMessage {
+    // Starts a scan with the same fields as POST /scan; scan_id is not used
+    Start(Box<ScanRequest>),
+    Cancel,
+}
+
+#[derive(Deserialize)]
+pub struct FirstScanRequest {
+    pub pattern: String,
+    pub data_type: Option<String>,
+    pub protection: Option<MemoryProtectionFilter>,
+    pub max_results: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct GroupElementRequest {
+    pub offset: u64,
+    pub data_type: String,
+    pub value: Value,
+}
+
+#[derive(Deserialize)]
+pub struct GroupScanRequest {
+    pub elements: Vec<GroupElementRequest>,
+    pub address_ranges: Option<Vec<(u64, u64)>>,
+    pub protection: Option<MemoryProtectionFilter>,
+    pub max_results: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct ScanProgressRequest {
+    pub scan_id: String,
+}
+
+#[derive(Deserialize)]
+pub struct ScanCancelRequest {
+    pub scan_id: String,
+}
+
+#[derive(Deserialize)]
+pub struct ScanRefineRequest {
+    pub pattern: String,
+    pub addresses: Vec<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct SessionNewRequest {
+    // Without a pattern the session starts from an unknown initial value snapshot
+    pub pattern: Option<String>,
+    pub data_type: String,
+    pub protection: Option<MemoryProtectionFilter>,
+}
+
+// Either pattern for an exact refine or filter_method (with amount where needed)
+#[derive(Deserialize)]
+pub struct SessionRefineRequest {
+    pub session_id: u64,
+    pub pattern: Option<String>,
+    pub filter_method: Option<String>,
+    pub amount: Option<f64>,
+    // Percentage points either side of amount for changed_by_percent
+    pub tolerance: Option<f64>,
+}
+
+#[derive(Deserialize)]
+pub struct SessionGetRequest {
+    pub session_id: u64,
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct SessionCloseRequest {
+    pub session_id: u64,
+}
+
+#[derive(Deserialize)]
+pub struct ScanCompareEntry {
+    pub address: u64,
+    pub value: String,
+}
+
+#[derive(Deserialize)]
+pub struct ScanCompareRequest {
+    pub previous: Vec<ScanCompareEntry>,
+    pub value_type: String,
+    pub filter_method: String,
+    pub amount: Option<f64>,
+    // Percentage points either side of amount for changed_by_percent
+    pub tolerance: Option<f64>,
+}
+
+#[derive(Deserialize)]
+pub struct DiffRegionRequest {
+    pub start: u64,
+    pub end: u64,
+    // Hex encoded bytes of the earlier read, starting at start
+    pub baseline: String,
+}
+
+#[derive(Deserialize)]
+pub struct DissectRequest {
+    // Any expression resolve_addr accepts, e.g. "[libgame.so+0x1234]+0x10"
+    pub address: String,
+    pub fields: Vec<StructField>,
+}
+
+#[derive(Deserialize)]
+pub struct GuessTypeRequest {
+    // Any expression resolve_addr accepts
+    pub address: String,
+}
+
+#[derive(Deserialize)]
+pub struct FreezeRequest {
+    pub address: u64,
+    pub buffer: Vec<u8>,
+}
+
+#[derive(Deserialize)]
+pub struct UnfreezeRequest {
+    pub address: u64,
+}
+
+#[derive(Deserialize)]
+pub struct BookmarkAddRequest {
+    // Any expression resolve_addr accepts, resolved again each time bookmarks are listed
+    pub address: String,
+    #[serde(default)]
+    pub label: String,
+    pub value_type: String,
+}
+
+#[derive(Deserialize)]
+pub struct BookmarkRemoveRequest {
+    pub id: u64,
+}
+
+#[derive(Deserialize)]
+pub struct WatchRequest {
+    pub address: u64,
+    pub value_type: String,
+}
+
+#[derive(Deserialize)]
+pub struct UnwatchRequest {
+    pub address: u64,
+}
+
+#[derive(Deserialize)]
+pub struct DumpRequest {
+    pub path: String,
+    // (start, end) pairs; every readable region when omitted
+    pub regions: Option<Vec<(u64, u64)>>,
+}
+
+#[derive(Deserialize)]
+pub struct DumpReadRequest {
+    pub path: String,
+    pub address: u64,
+    pub size: usize,
+}
+
+#[derive(Deserialize)]
+pub struct ReadRegistersRequest {
+    pub tid: i32,
+}
+
+#[derive(Deserialize)]
+pub struct ThreadControlRequest {
+    pub tid: i32,
+}
+
+#[derive(Deserialize)]
+pub struct PointerScanRequest {
+    pub target: u64,
+    pub max_depth: usize,
+    pub max_offset: u64,
+    pub pointer_map: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct FindReferencesRequest {
+    // Any expression resolve_addr accepts
+    pub target: String,
+    pub address_ranges: Option<Vec<(u64, u64)>>,
+    pub protection: Option<MemoryProtectionFilter>,
+    // Auto aligns to the pointer width
+    pub align: Option<Alignment>,
+}
+
 #[derive(Deserialize)]
 pub struct MemoryFilterRequest {
     pub pattern: String,
@@ -48,11 +412,25 @@ pub struct MemoryFilterRequest {
 pub struct ExploreDirectoryRequest {
     pub path: String,
     pub max_depth: i32,
+    pub indent_width: Option<usize>,
+    pub sort_by: Option<FileSortKey>,
+    pub sort_order: Option<SortOrder>,
 }
 
 #[derive(Deserialize)]
 pub struct ReadFileRequest {
     pub path: String,
+    pub offset: Option<u64>,
+    pub length: Option<usize>,
+    pub compress: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct WriteFileRequest {
+    pub path: String,
+    pub append: Option<bool>,
+    // Body is a compress_framed lz4 frame
+    pub compressed: Option<bool>,
 }
 
 #[derive(Deserialize)]
@@ -73,6 +451,21 @@ pub struct RemoveWatchPointRequest {
     pub address: usize,
 }
 
+// Collects the instructions that access address; "r" (the default) or "a" as for watchpoints
+#[derive(Deserialize)]
+pub struct AccessWatchRequest {
+    pub address: u64,
+    pub size: usize,
+    pub access: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct AccessReportRequest {
+    pub address: u64,
+    // How many instructions to list, most hits first
+    pub top: Option<usize>,
+}
+
 #[derive(Serialize)]
 pub struct RemoveWatchPointResponse {
     pub success: bool,
@@ -83,6 +476,8 @@ pub struct RemoveWatchPointResponse {
 pub struct SetBreakPointRequest {
     pub address: usize,
     pub hit_count: i32,
+    #[serde(default)]
+    pub halt: bool,
 }
 
 #[derive(Serialize)]
@@ -117,3 +512,37 @@ pub struct ChangeProcessStateResponse {
 pub struct PointerMapGenerateRequest {
     pub address: u64,
 }
+
+// One operation of a /batch request: method is the path of the GET route it stands for, e.g.
+// "value" or "resolveaddr", and params are that route's query parameters as a JSON object
+#[derive(Deserialize)]
+pub struct BatchCall {
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+// The outcome of one call, in the slot of the call it answers. result is the route's JSON
+// body, its text for plain text responses, or hex for binary ones.
+#[derive(Serialize)]
+pub struct BatchResult {
+    pub status: u16,
+    pub result: Option<Value>,
+    pub error: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scan_without_a_find_type_is_exact() {
+        let request: ScanRequest =
+            serde_json::from_str(r#"{"pattern": "01000000", "align": 4}"#).unwrap();
+        assert_eq!(request.find_type, "exact");
+        let request: ScanRequest =
+            serde_json::from_str(r#"{"pattern": "1.0", "align": "auto", "find_type": "float"}"#)
+                .unwrap();
+        assert_eq!(request.find_type, "float");
+    }
+}
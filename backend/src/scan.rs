@@ -0,0 +1,2295 @@
+use crate::compression;
+use crate::native_bridge;
+use crate::region::{self, Protection};
+use crate::util::{self, Endianness, Number, ValueType};
+use memchr::{memchr_iter, memmem};
+use rayon::prelude::*;
+use regex::bytes::Regex;
+use serde::Deserialize;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::mem;
+use std::ops::Deref;
+use std::slice;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use std::time::Instant;
+
+// Scans run here rather than on rayon's global pool so their parallelism can be tuned apart
+// from everything else that uses rayon
+static SCAN_POOL: OnceLock<rayon::ThreadPool> = OnceLock::new();
+
+// Builds the scan pool with the given number of threads, or one per physical core, and
+// returns its size
+pub fn init_scan_pool(threads: Option<usize>) -> Result<usize, String> {
+    let threads = threads.unwrap_or_else(util::physical_core_count);
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(|index| format!("scan-{}", index))
+        .build()
+        .map_err(|e| format!("Failed to start {} scan threads: {}", threads, e))?;
+    SCAN_POOL
+        .set(pool)
+        .map_err(|_| "The scan pool is already running".to_string())?;
+    Ok(threads)
+}
+
+// Runs f on the scan pool; before init_scan_pool it runs on rayon's global pool. Work that is
+// already on a rayon pool (the scan pool itself, or one a caller installed) stays there.
+pub fn in_scan_pool<R: Send>(f: impl FnOnce() -> R + Send) -> R {
+    match SCAN_POOL.get() {
+        Some(pool) if rayon::current_thread_index().is_none() => pool.install(f),
+        _ => f(),
+    }
+}
+
+// Each flag is either required (Some(true)), forbidden (Some(false)) or ignored (None)
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct MemoryProtectionFilter {
+    pub readable: Option<bool>,
+    pub writable: Option<bool>,
+    pub executable: Option<bool>,
+}
+
+impl Default for MemoryProtectionFilter {
+    fn default() -> Self {
+        MemoryProtectionFilter {
+            readable: Some(true),
+            writable: None,
+            executable: None,
+        }
+    }
+}
+
+impl MemoryProtectionFilter {
+    // Writable and readable memory, where the values a first scan looks for live
+    pub fn read_write() -> Self {
+        MemoryProtectionFilter {
+            readable: Some(true),
+            writable: Some(true),
+            executable: None,
+        }
+    }
+
+    pub fn matches(&self, protection: Protection) -> bool {
+        [
+            (self.readable, Protection::READ),
+            (self.writable, Protection::WRITE),
+            (self.executable, Protection::EXEC),
+        ]
+        .iter()
+        .all(|&(wanted, flag)| wanted.is_none_or(|wanted| wanted == protection.contains(flag)))
+    }
+}
+
+pub fn protected_regions(
+    pid: i32,
+    filter: &MemoryProtectionFilter,
+) -> Result<Vec<(u64, u64)>, String> {
+    let regions = region::enumerate_regions(pid)?;
+    Ok(regions
+        .iter()
+        .filter(|region| filter.matches(region.protection))
+        .map(|region| (region.start, region.end))
+        .collect())
+}
+
+// Restricts the requested ranges to the parts that fall inside the given regions
+pub fn clip_ranges(ranges: &[(u64, u64)], regions: &[(u64, u64)]) -> Vec<(u64, u64)> {
+    ranges
+        .iter()
+        .flat_map(|&(start, end)| {
+            regions
+                .iter()
+                .filter_map(move |&(region_start, region_end)| {
+                    let clipped_start = start.max(region_start);
+                    let clipped_end = end.min(region_end);
+                    (clipped_start < clipped_end).then_some((clipped_start, clipped_end))
+                })
+        })
+        .collect()
+}
+
+// Either a fixed byte alignment or "auto", which aligns to the natural size of the value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "AlignmentValue")]
+pub enum Alignment {
+    Fixed(usize),
+    Auto,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AlignmentValue {
+    Fixed(usize),
+    Named(String),
+}
+
+impl TryFrom<AlignmentValue> for Alignment {
+    type Error = String;
+
+    fn try_from(value: AlignmentValue) -> Result<Self, Self::Error> {
+        match value {
+            AlignmentValue::Fixed(alignment) => Ok(Alignment::Fixed(alignment)),
+            AlignmentValue::Named(name) if name == "auto" => Ok(Alignment::Auto),
+            AlignmentValue::Named(name) => Err(format!(
+                "Unknown alignment '{}': expected a number or \"auto\"",
+                name
+            )),
+        }
+    }
+}
+
+impl Alignment {
+    // Auto uses the value type's size when one is known, otherwise the needle length if it
+    // looks like a 2, 4 or 8 byte value; anything else is a byte array or string and gets 1
+    pub fn resolve(self, needle_len: usize, value_type: Option<ValueType>) -> usize {
+        match self {
+            Alignment::Fixed(alignment) => alignment.max(1),
+            Alignment::Auto => match value_type {
+                Some(value_type) => value_type.size(),
+                None if matches!(needle_len, 2 | 4 | 8) => needle_len,
+                None => 1,
+            },
+        }
+    }
+}
+
+// Progress, cancellation and the result cap are handled once per region so they stay off the
+// per-byte hot path
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ScanControl<'a> {
+    pub progress: Option<&'a AtomicUsize>,
+    pub cancel: Option<&'a AtomicBool>,
+    pub max_results: Option<usize>,
+}
+
+impl ScanControl<'_> {
+    fn region_done(&self, start: u64, end: u64) {
+        if let Some(progress) = self.progress {
+            progress.fetch_add(end.saturating_sub(start) as usize, Ordering::Relaxed);
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel
+            .is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+    }
+}
+
+// A cancelled scan still carries every match found before the stop flag was seen.
+// total_count covers every match in the scanned regions, including those dropped by the cap.
+// Regions are no longer scanned once the cap is passed, so a truncated total is a lower bound.
+#[derive(Debug, Default, Clone)]
+pub struct ScanResult<T> {
+    pub matches: Vec<T>,
+    pub cancelled: bool,
+    pub truncated: bool,
+    pub total_count: usize,
+}
+
+// One info line per finished scan, as key=value fields so slow scans can be picked out of
+// the log and compared
+fn log_scan_summary(
+    kind: &str,
+    pid: i32,
+    regions: usize,
+    bytes: usize,
+    matches: usize,
+    started: Instant,
+    outcome: &str,
+) {
+    log::info!(
+        "{} scan finished: pid={} regions={} bytes={} matches={} elapsed_ms={}{}",
+        kind,
+        pid,
+        regions,
+        bytes,
+        matches,
+        started.elapsed().as_millis(),
+        outcome
+    );
+}
+
+fn log_region_scanned(
+    kind: &str,
+    pid: i32,
+    start: u64,
+    end: u64,
+    matches: usize,
+    started: Instant,
+) {
+    log::trace!(
+        "{} scan region: pid={} start={:#x} end={:#x} bytes={} matches={} elapsed_ms={}",
+        kind,
+        pid,
+        start,
+        end,
+        end.saturating_sub(start),
+        matches,
+        started.elapsed().as_millis()
+    );
+}
+
+// Refines read back a list of addresses instead of walking regions
+fn log_refine_summary(kind: &str, pid: i32, addresses: usize, matches: usize, started: Instant) {
+    log::info!(
+        "{} scan finished: pid={} addresses={} matches={} elapsed_ms={}",
+        kind,
+        pid,
+        addresses,
+        matches,
+        started.elapsed().as_millis()
+    );
+}
+
+pub const DEFAULT_SCAN_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+// Below a page the overlap between windows would dominate the reads
+pub const MIN_SCAN_CHUNK_SIZE: usize = 4096;
+
+static SCAN_CHUNK_SIZE: AtomicUsize = AtomicUsize::new(DEFAULT_SCAN_CHUNK_SIZE);
+
+// Regions larger than this are scanned a window at a time, so a multi-GB mapped file never
+// has to fit in memory at once. Rounded down to whole pages so windows start page aligned.
+pub fn set_scan_chunk_size(size: usize) {
+    let size = size.max(MIN_SCAN_CHUNK_SIZE) / MIN_SCAN_CHUNK_SIZE * MIN_SCAN_CHUNK_SIZE;
+    SCAN_CHUNK_SIZE.store(size, Ordering::Relaxed);
+}
+
+fn scan_chunk_size() -> usize {
+    SCAN_CHUNK_SIZE.load(Ordering::Relaxed)
+}
+
+// Splits start..end into windows every chunk bytes, each running overlap bytes into the next
+// so a match of up to overlap + 1 bytes across a boundary lies whole in one window. A match
+// that long starting in the overlap does not fit in the earlier window, so none is found twice.
+fn region_windows(start: u64, end: u64, chunk: usize, overlap: usize) -> Vec<(u64, u64)> {
+    (start..end)
+        .step_by(chunk)
+        .map(|window_start| {
+            let window_end = window_start.saturating_add((chunk + overlap) as u64);
+            (window_start, window_end.min(end))
+        })
+        .collect()
+}
+
+// Every rayon task checks the stop flag before reading its window, so windows that have not
+// started yet are skipped instead of being scanned and thrown away. overlap is the longest
+// match minus one; scan_region is handed windows no larger than the chunk size plus that.
+fn scan_regions<T, F>(
+    kind: &str,
+    pid: i32,
+    regions: &[(u64, u64)],
+    overlap: usize,
+    control: &ScanControl,
+    scan_region: F,
+) -> ScanResult<T>
+where
+    T: Send,
+    F: Fn(u64, u64) -> Vec<T> + Sync,
+{
+    let started = Instant::now();
+    let skipped = AtomicBool::new(false);
+    let capped = AtomicBool::new(false);
+    let found = AtomicUsize::new(0);
+    let scanned = AtomicUsize::new(0);
+    let chunk = scan_chunk_size();
+    let windows: Vec<(u64, u64)> = regions
+        .iter()
+        .flat_map(|&(start, end)| region_windows(start, end, chunk, overlap))
+        .collect();
+    let matches: Vec<T> = in_scan_pool(|| {
+        windows
+            .par_iter()
+            .flat_map_iter(|&(start, end)| {
+                if control.is_cancelled() {
+                    skipped.store(true, Ordering::Relaxed);
+                    return Vec::new();
+                }
+                // Only past the cap, not at it, so a scan with exactly max_results matches
+                // still reads every region and is not reported truncated
+                if control
+                    .max_results
+                    .is_some_and(|max_results| found.load(Ordering::Relaxed) > max_results)
+                {
+                    capped.store(true, Ordering::Relaxed);
+                    return Vec::new();
+                }
+                let region_started = Instant::now();
+                let mut matches = scan_region(start, end);
+                // Progress counts each byte once, leaving out the overlap into the next window
+                let owned_end = start.saturating_add(chunk as u64).min(end);
+                control.region_done(start, owned_end);
+                scanned.fetch_add((owned_end - start) as usize, Ordering::Relaxed);
+                log_region_scanned(kind, pid, start, end, matches.len(), region_started);
+                // Only the slots left when a region finishes are kept; which matches survive
+                // therefore depends on the order regions complete in
+                let found_before = found.fetch_add(matches.len(), Ordering::Relaxed);
+                if let Some(max_results) = control.max_results {
+                    matches.truncate(max_results.saturating_sub(found_before));
+                }
+                matches
+            })
+            .collect()
+    });
+    let total_count = found.into_inner();
+    let result = ScanResult {
+        truncated: capped.into_inner() || total_count > matches.len(),
+        matches,
+        cancelled: skipped.into_inner(),
+        total_count,
+    };
+    let outcome = match (result.cancelled, result.truncated) {
+        (true, _) => " cancelled=true",
+        (false, true) => " truncated=true",
+        (false, false) => "",
+    };
+    log_scan_summary(
+        kind,
+        pid,
+        regions.len(),
+        scanned.into_inner(),
+        total_count,
+        started,
+        outcome,
+    );
+    result
+}
+
+pub fn total_bytes(regions: &[(u64, u64)]) -> usize {
+    regions
+        .iter()
+        .map(|&(start, end)| end.saturating_sub(start) as usize)
+        .sum()
+}
+
+// Buffers larger than this are freed after use instead of being kept by the worker thread. A
+// window is the chunk plus its overlap, which a group scan holds to the chunk size, so every
+// window read fits.
+fn pooled_buffer_limit() -> usize {
+    scan_chunk_size().saturating_mul(2)
+}
+
+thread_local! {
+    // One spare read buffer per thread. It is taken out while in use, so a task that rayon
+    // runs on the same thread meanwhile (work stealing inside a region scan) finds the slot
+    // empty and allocates its own instead of sharing it.
+    static REGION_BUFFER: Cell<Vec<u8>> = const { Cell::new(Vec::new()) };
+}
+
+// The bytes of one region read; derefs to the slice and hands its allocation back to the
+// thread's pool when dropped
+#[derive(Default)]
+struct RegionBuffer(Vec<u8>);
+
+impl Deref for RegionBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Drop for RegionBuffer {
+    fn drop(&mut self) {
+        let buffer = mem::take(&mut self.0);
+        if buffer.capacity() > pooled_buffer_limit() {
+            return;
+        }
+        // Keep whichever of the two buffers is larger
+        let _ = REGION_BUFFER.try_with(|slot| {
+            let pooled = slot.take();
+            slot.set(if pooled.capacity() >= buffer.capacity() {
+                pooled
+            } else {
+                buffer
+            });
+        });
+    }
+}
+
+// Scanners read thousands of small regions, so the buffer is reused rather than allocated
+// per region. Stale bytes past the read length are never exposed: the buffer is truncated to
+// what was read.
+fn read_region(pid: i32, start: u64, end: u64) -> Option<RegionBuffer> {
+    let size = end.saturating_sub(start) as usize;
+    if size == 0 {
+        return None;
+    }
+    let mut buffer = RegionBuffer(REGION_BUFFER.with(Cell::take));
+    if buffer.0.capacity() < size {
+        // A fresh zeroed allocation is cheaper than growing, which would copy and memset
+        buffer.0 = vec![0u8; size];
+    } else {
+        buffer.0.resize(size, 0);
+    }
+    match native_bridge::read_process_memory(pid, start as *mut libc::c_void, size, &mut buffer.0) {
+        Ok(nread) if nread > 0 => {
+            buffer.0.truncate(nread as usize);
+            Some(buffer)
+        }
+        _ => None,
+    }
+}
+
+// Unreadable regions are skipped rather than failing the whole scan
+pub fn scan_exact(
+    pid: i32,
+    regions: &[(u64, u64)],
+    needle: &[u8],
+    alignment: usize,
+    control: &ScanControl,
+) -> ScanResult<u64> {
+    if needle.is_empty() {
+        return ScanResult::default();
+    }
+    if let (Ok(word), 4) = (<[u8; 4]>::try_from(needle), alignment) {
+        return scan_u32_aligned(pid, regions, u32::from_le_bytes(word), control);
+    }
+    let alignment = alignment.max(1) as u64;
+    let finder = memmem::Finder::new(needle);
+
+    scan_regions(
+        "exact",
+        pid,
+        regions,
+        needle.len().saturating_sub(1),
+        control,
+        |start, end| {
+            let mut matches = Vec::new();
+            if let Some(buffer) = read_region(pid, start, end) {
+                // Step one byte past each hit so overlapping matches are still found
+                let mut offset = 0;
+                while let Some(pos) = finder.find(&buffer[offset..]) {
+                    let address = start + (offset + pos) as u64;
+                    if address.is_multiple_of(alignment) {
+                        matches.push(address);
+                    }
+                    offset += pos + 1;
+                }
+            }
+            matches
+        },
+    )
+}
+
+// Words handed to one rayon task when a single region is split up
+const WORD_CHUNK_SIZE: usize = 1024 * 64;
+const WORD_BLOCK_SIZE: usize = 64;
+
+// Reads start..end from its first 4-byte aligned address straight into a u32 buffer; a
+// trailing partial word is dropped since no aligned value can start there
+fn read_region_words(pid: i32, start: u64, end: u64) -> Option<Vec<u32>> {
+    let aligned_start = start.next_multiple_of(4);
+    let count = (end.saturating_sub(aligned_start) / 4) as usize;
+    if count == 0 {
+        return None;
+    }
+    let mut words = vec![0u32; count];
+    let bytes = unsafe { slice::from_raw_parts_mut(words.as_mut_ptr() as *mut u8, count * 4) };
+    match native_bridge::read_process_memory(
+        pid,
+        aligned_start as *mut libc::c_void,
+        count * 4,
+        bytes,
+    ) {
+        Ok(nread) if nread >= 4 => {
+            words.truncate(nread as usize / 4);
+            Some(words)
+        }
+        _ => None,
+    }
+}
+
+// The fast path for the most common scan, a 4-byte little-endian value at 4-byte alignment.
+// Comparing whole words lets the compiler vectorize the loop, and large regions are split
+// across rayon tasks instead of being walked by one thread.
+pub fn scan_u32_aligned(
+    pid: i32,
+    regions: &[(u64, u64)],
+    value: u32,
+    control: &ScanControl,
+) -> ScanResult<u64> {
+    // The buffer holds memory as-is, so compare against the value's in-memory representation
+    let word = u32::from_ne_bytes(value.to_le_bytes());
+
+    scan_regions("exact", pid, regions, 3, control, |start, end| {
+        let Some(words) = read_region_words(pid, start, end) else {
+            return Vec::new();
+        };
+        let aligned_start = start.next_multiple_of(4);
+        words
+            .par_chunks(WORD_CHUNK_SIZE)
+            .enumerate()
+            .flat_map_iter(|(chunk_index, chunk)| {
+                let chunk_start = aligned_start + (chunk_index * WORD_CHUNK_SIZE * 4) as u64;
+                let mut matches = Vec::new();
+                // A branch-free "any match" over a block vectorizes; only the rare blocks
+                // with a hit are walked word by word
+                for (block_index, block) in chunk.chunks(WORD_BLOCK_SIZE).enumerate() {
+                    if block
+                        .iter()
+                        .fold(0u32, |hit, &candidate| hit | (candidate == word) as u32)
+                        == 0
+                    {
+                        continue;
+                    }
+                    let block_start = chunk_start + (block_index * WORD_BLOCK_SIZE * 4) as u64;
+                    matches.extend(
+                        block
+                            .iter()
+                            .enumerate()
+                            .filter(|&(_, &candidate)| candidate == word)
+                            .map(|(index, _)| block_start + (index * 4) as u64),
+                    );
+                }
+                matches
+            })
+            .collect()
+    })
+}
+
+#[derive(Debug)]
+pub struct FirstScan {
+    pub result: ScanResult<u64>,
+    pub region_count: usize,
+    pub alignment: usize,
+}
+
+// Scans every region passing the filter with an automatically chosen alignment; callers that
+// already have a region list use scan_exact directly
+pub fn scan_first(
+    pid: i32,
+    needle: &[u8],
+    value_type: Option<ValueType>,
+    filter: &MemoryProtectionFilter,
+    control: &ScanControl,
+) -> Result<FirstScan, String> {
+    let regions = protected_regions(pid, filter)?;
+    let alignment = Alignment::Auto.resolve(needle.len(), value_type);
+    Ok(FirstScan {
+        result: scan_exact(pid, &regions, needle, alignment, control),
+        region_count: regions.len(),
+        alignment,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatType {
+    F32,
+    F64,
+}
+
+impl FloatType {
+    pub fn parse(type_name: &str) -> Result<FloatType, String> {
+        match type_name {
+            "f32" | "float" => Ok(FloatType::F32),
+            "f64" | "double" => Ok(FloatType::F64),
+            _ => Err(format!(
+                "Unknown float type '{}': expected f32 or f64",
+                type_name
+            )),
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            FloatType::F32 => 4,
+            FloatType::F64 => 8,
+        }
+    }
+}
+
+// Addresses holding a pointer to target, i.e. everything that points at it. This is an
+// exact scan for target's little-endian encoding at the target's pointer width, aligned to
+// that width unless alignment says otherwise.
+pub fn find_pointers_to(
+    pid: i32,
+    target: u64,
+    regions: &[(u64, u64)],
+    alignment: Alignment,
+) -> Vec<u64> {
+    let width = util::pointer_size(pid);
+    let needle = if width == 4 {
+        // A 32-bit process cannot hold a pointer above 4GB
+        match u32::try_from(target) {
+            Ok(target) => target.to_le_bytes().to_vec(),
+            Err(_) => return Vec::new(),
+        }
+    } else {
+        target.to_le_bytes().to_vec()
+    };
+    let alignment = alignment.resolve(width, None);
+    scan_exact(pid, regions, &needle, alignment, &ScanControl::default()).matches
+}
+
+pub fn scan_float(
+    pid: i32,
+    regions: &[(u64, u64)],
+    target: f64,
+    value_type: FloatType,
+    tolerance: f64,
+    control: &ScanControl,
+) -> ScanResult<u64> {
+    let size = value_type.size();
+
+    scan_regions("float", pid, regions, size - 1, control, |start, end| {
+        let mut matches = Vec::new();
+        if let Some(buffer) = read_region(pid, start, end) {
+            for offset in aligned_offsets(start, buffer.len(), size, size) {
+                let window = &buffer[offset..offset + size];
+                let value = match value_type {
+                    FloatType::F32 => f32::from_le_bytes(window.try_into().unwrap()) as f64,
+                    FloatType::F64 => f64::from_le_bytes(window.try_into().unwrap()),
+                };
+                if !value.is_nan() && (value - target).abs() <= tolerance {
+                    matches.push(start + offset as u64);
+                }
+            }
+        }
+        matches
+    })
+}
+
+// Accepts space separated tokens such as "48 8B ?? ?? ?? C3"; mask[i] is false for wildcards
+pub fn parse_aob_pattern(pattern: &str) -> Result<(Vec<u8>, Vec<bool>), String> {
+    let mut bytes = Vec::new();
+    let mut mask = Vec::new();
+
+    for token in pattern.split_whitespace() {
+        if token == "?" || token == "??" {
+            bytes.push(0);
+            mask.push(false);
+            continue;
+        }
+        if token.len() % 2 != 0 {
+            return Err(format!("Odd number of hex digits in token '{}'", token));
+        }
+        let decoded =
+            hex::decode(token).map_err(|_| format!("Invalid hex token '{}' in pattern", token))?;
+        mask.extend(std::iter::repeat_n(true, decoded.len()));
+        bytes.extend(decoded);
+    }
+
+    if !mask.contains(&true) {
+        return Err("Pattern must contain at least one non-wildcard byte".to_string());
+    }
+    Ok((bytes, mask))
+}
+
+pub fn scan_aob(
+    pid: i32,
+    regions: &[(u64, u64)],
+    bytes: &[u8],
+    mask: &[bool],
+    control: &ScanControl,
+) -> ScanResult<u64> {
+    // Candidates come from a fast search for the first fixed byte, then the whole pattern is verified
+    let anchor = match mask.iter().position(|&fixed| fixed) {
+        Some(anchor) => anchor,
+        None => return ScanResult::default(),
+    };
+
+    scan_regions(
+        "aob",
+        pid,
+        regions,
+        bytes.len().saturating_sub(1),
+        control,
+        |start, end| {
+            let mut matches = Vec::new();
+            if let Some(buffer) = read_region(pid, start, end) {
+                for pos in memchr_iter(bytes[anchor], &buffer) {
+                    if pos < anchor {
+                        continue;
+                    }
+                    let candidate = pos - anchor;
+                    let window = match buffer.get(candidate..candidate + bytes.len()) {
+                        Some(window) => window,
+                        None => break,
+                    };
+                    if window
+                        .iter()
+                        .zip(bytes.iter().zip(mask))
+                        .all(|(&actual, (&expected, &fixed))| !fixed || actual == expected)
+                    {
+                        matches.push(start + candidate as u64);
+                    }
+                }
+            }
+            matches
+        },
+    )
+}
+
+// Bytes each window of a regex scan reads past its end
+const REGEX_OVERLAP: usize = 4096;
+
+// (address, length) of every match. Matches are confined to a single region read: a region is
+// matched on its own, so nothing spans two regions, and a large region is read in windows
+// overlapping by REGEX_OVERLAP bytes, so a match that crosses a window boundary is cut short
+// at the window's end once it runs further than that. Each match is still reported once.
+pub fn scan_regex(
+    pid: i32,
+    regions: &[(u64, u64)],
+    pattern: &str,
+    control: &ScanControl,
+) -> Result<ScanResult<(u64, usize)>, String> {
+    let re = Regex::new(pattern).map_err(|e| format!("Invalid regex pattern: {}", e))?;
+
+    let chunk = scan_chunk_size();
+    let mut result = scan_regions(
+        "regex",
+        pid,
+        regions,
+        REGEX_OVERLAP,
+        control,
+        |start, end| match read_region(pid, start, end) {
+            // Matches starting in the overlap belong to the next window, which sees them whole
+            Some(buffer) => re
+                .find_iter(&buffer)
+                .take_while(|m| m.start() < chunk)
+                .map(|m| (start + m.start() as u64, m.len()))
+                .collect(),
+            None => Vec::new(),
+        },
+    );
+    // A match running into the overlap can also match from where the next window starts, e.g.
+    // "a+" over a run of a's. One window never yields overlapping matches, so a match starting
+    // inside the one before it is such a tail and is dropped.
+    let found = result.matches.len();
+    let mut previous = 0..0;
+    result.matches.retain(|&(address, len)| {
+        if previous.contains(&address) {
+            return false;
+        }
+        previous = address..address + len as u64;
+        true
+    });
+    result.total_count -= found - result.matches.len();
+    Ok(result)
+}
+
+// One constraint of a group scan: the value's encoded bytes must sit at address + offset
+#[derive(Debug, Clone)]
+pub struct GroupElement {
+    pub offset: u64,
+    pub value_type: ValueType,
+    pub value: Vec<u8>,
+}
+
+// Addresses where every element matches. The first element is searched for and the rest are
+// checked around each hit, so a group never matches across the end of a region.
+pub fn scan_group(
+    pid: i32,
+    regions: &[(u64, u64)],
+    elements: &[GroupElement],
+    control: &ScanControl,
+) -> Result<ScanResult<u64>, String> {
+    let (anchor, rest) = elements
+        .split_first()
+        .ok_or_else(|| "A group scan needs at least one element".to_string())?;
+    if let Some(element) = elements
+        .iter()
+        .find(|element| element.value.len() != element.value_type.size())
+    {
+        return Err(format!(
+            "Element at offset {:#x} has {} bytes but {:?} needs {}",
+            element.offset,
+            element.value.len(),
+            element.value_type,
+            element.value_type.size()
+        ));
+    }
+    let finder = memmem::Finder::new(&anchor.value);
+    // Bytes from the lowest to the highest byte any element covers. The span becomes the
+    // window overlap, so it is held to the chunk size rather than sizing reads by itself.
+    let first = elements.iter().map(|e| e.offset).min().unwrap_or(0);
+    let mut last = 0;
+    for element in elements {
+        let end = element
+            .offset
+            .checked_add(element.value.len() as u64)
+            .ok_or_else(|| format!("Element offset {:#x} is out of range", element.offset))?;
+        last = last.max(end);
+    }
+    let span = last - first;
+    if span > scan_chunk_size() as u64 {
+        return Err(format!(
+            "Group elements span {} bytes, more than the {} byte scan chunk",
+            span,
+            scan_chunk_size()
+        ));
+    }
+
+    Ok(scan_regions(
+        "group",
+        pid,
+        regions,
+        span as usize - 1,
+        control,
+        |start, end| {
+            let mut matches = Vec::new();
+            if let Some(buffer) = read_region(pid, start, end) {
+                let element_at = |address: u64, element: &GroupElement| {
+                    let from = address
+                        .checked_add(element.offset)
+                        .and_then(|position| position.checked_sub(start))?
+                        as usize;
+                    buffer.get(from..from.checked_add(element.value.len())?)
+                };
+                // Overlapping anchor hits are kept, as in scan_exact
+                let mut offset = 0;
+                while let Some(pos) = finder.find(&buffer[offset..]) {
+                    let hit = start + (offset + pos) as u64;
+                    offset += pos + 1;
+                    let Some(address) = hit.checked_sub(anchor.offset) else {
+                        continue;
+                    };
+                    if rest
+                        .iter()
+                        .all(|element| element_at(address, element) == Some(&element.value[..]))
+                    {
+                        matches.push(address);
+                    }
+                }
+            }
+            matches
+        },
+    ))
+}
+
+// Addresses that can no longer be read are dropped from the result set
+pub fn scan_refine(pid: i32, previous: &[u64], needle: &[u8]) -> Vec<u64> {
+    let started = Instant::now();
+    let requests: Vec<(u64, usize)> = previous
+        .iter()
+        .map(|&address| (address, needle.len()))
+        .collect();
+    let matches: Vec<u64> = util::read_memory_batch(pid, &requests)
+        .into_iter()
+        .zip(previous)
+        .filter_map(|(result, &address)| match result {
+            Ok(bytes) if bytes == needle => Some(address),
+            _ => None,
+        })
+        .collect();
+    log_refine_summary("refine", pid, previous.len(), matches.len(), started);
+    matches
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScanCompare {
+    Increased,
+    Decreased,
+    Changed,
+    Unchanged,
+    IncreasedBy(f64),
+    DecreasedBy(f64),
+    // (new - old) / |old| as a percentage within tolerance points of percent; a negative
+    // percent matches a proportional decrease and an old value of zero never matches
+    ChangedByPercent { percent: f64, tolerance: f64 },
+}
+
+// Percentage points either side of the target when a request leaves tolerance out
+const DEFAULT_PERCENT_TOLERANCE: f64 = 1.0;
+
+impl ScanCompare {
+    pub fn parse(
+        method: &str,
+        amount: Option<f64>,
+        tolerance: Option<f64>,
+    ) -> Result<ScanCompare, String> {
+        let amount = || amount.ok_or_else(|| format!("Filter method '{}' needs an amount", method));
+        match method {
+            "increased" => Ok(ScanCompare::Increased),
+            "decreased" => Ok(ScanCompare::Decreased),
+            "changed" => Ok(ScanCompare::Changed),
+            "unchanged" => Ok(ScanCompare::Unchanged),
+            "increased_by" => Ok(ScanCompare::IncreasedBy(amount()?)),
+            "decreased_by" => Ok(ScanCompare::DecreasedBy(amount()?)),
+            "changed_by_percent" => {
+                let percent = amount()?;
+                let tolerance = tolerance.unwrap_or(DEFAULT_PERCENT_TOLERANCE);
+                if !percent.is_finite() || !tolerance.is_finite() || tolerance < 0.0 {
+                    return Err(format!(
+                        "Invalid percent {} or tolerance {} for changed_by_percent",
+                        percent, tolerance
+                    ));
+                }
+                Ok(ScanCompare::ChangedByPercent { percent, tolerance })
+            }
+            _ => Err(format!("Unknown filter method '{}'", method)),
+        }
+    }
+}
+
+// Each entry of a comparative result set is an address with the bytes last seen there
+pub type ScanSnapshot = Vec<(u64, Vec<u8>)>;
+
+// NaN decodes as itself, so it fails every ordering and delta comparison as IEEE says
+fn decode_number(bytes: &[u8], value_type: ValueType) -> Option<Number> {
+    util::decode_number(bytes, value_type, Endianness::Little)
+}
+
+fn float_delta_matches(delta: f64, expected: f64, scale: f64, value_type: ValueType) -> bool {
+    let epsilon = match value_type {
+        ValueType::F32 => f32::EPSILON as f64,
+        _ => f64::EPSILON,
+    };
+    (delta - expected).abs() <= epsilon * scale.max(1.0)
+}
+
+// Relative change in percent, None when old is zero and there is nothing to be relative to
+fn percent_change(new: f64, old: f64) -> Option<f64> {
+    (old != 0.0).then(|| (new - old) / old.abs() * 100.0)
+}
+
+fn compare_bytes(new: &[u8], old: &[u8], value_type: ValueType, cmp: ScanCompare) -> bool {
+    match cmp {
+        ScanCompare::Changed => return new != old,
+        ScanCompare::Unchanged => return new == old,
+        ScanCompare::ChangedByPercent { percent, tolerance } => {
+            let as_float = |bytes| match decode_number(bytes, value_type)? {
+                Number::Int(value) => Some(value as f64),
+                Number::Float(value) => Some(value),
+            };
+            return as_float(new)
+                .zip(as_float(old))
+                .and_then(|(new, old)| percent_change(new, old))
+                .is_some_and(|change| (change - percent).abs() <= tolerance);
+        }
+        _ => {}
+    }
+    match (
+        decode_number(new, value_type),
+        decode_number(old, value_type),
+    ) {
+        (Some(Number::Int(new)), Some(Number::Int(old))) => match cmp {
+            ScanCompare::Increased => new > old,
+            ScanCompare::Decreased => new < old,
+            ScanCompare::IncreasedBy(n) => n.fract() == 0.0 && new - old == n as i128,
+            ScanCompare::DecreasedBy(n) => n.fract() == 0.0 && old - new == n as i128,
+            _ => false,
+        },
+        (Some(Number::Float(new)), Some(Number::Float(old))) => {
+            let scale = new.abs().max(old.abs());
+            match cmp {
+                ScanCompare::Increased => new > old,
+                ScanCompare::Decreased => new < old,
+                ScanCompare::IncreasedBy(n) => float_delta_matches(new - old, n, scale, value_type),
+                ScanCompare::DecreasedBy(n) => float_delta_matches(old - new, n, scale, value_type),
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+fn aligned_offsets(
+    start: u64,
+    len: usize,
+    size: usize,
+    alignment: usize,
+) -> impl ExactSizeIterator<Item = usize> {
+    let alignment = alignment.max(1);
+    let first = (alignment - (start as usize % alignment)) % alignment;
+    (first..len.saturating_sub(size - 1)).step_by(alignment)
+}
+
+pub fn scan_range(
+    pid: i32,
+    regions: &[(u64, u64)],
+    value_type: ValueType,
+    low: f64,
+    high: f64,
+    alignment: usize,
+    control: &ScanControl,
+) -> Result<ScanResult<u64>, String> {
+    if low.is_nan() || high.is_nan() || low > high {
+        return Err(format!(
+            "Invalid range: low bound {} is greater than high bound {}",
+            low, high
+        ));
+    }
+    // Integer bounds are rounded inwards so the comparison itself stays exact
+    let (low_int, high_int) = (low.ceil() as i128, high.floor() as i128);
+    let size = value_type.size();
+
+    Ok(scan_regions(
+        "range",
+        pid,
+        regions,
+        size - 1,
+        control,
+        |start, end| {
+            let mut matches = Vec::new();
+            if let Some(buffer) = read_region(pid, start, end) {
+                for offset in aligned_offsets(start, buffer.len(), size, alignment) {
+                    let in_range = match decode_number(&buffer[offset..], value_type) {
+                        Some(Number::Int(value)) => low_int <= value && value <= high_int,
+                        Some(Number::Float(value)) => low <= value && value <= high,
+                        None => false,
+                    };
+                    if in_range {
+                        matches.push(start + offset as u64);
+                    }
+                }
+            }
+            matches
+        },
+    ))
+}
+
+// Keeps the addresses where (value & mask) == expected. Values are read as their raw
+// unsigned bits, so a signed type is masked the same way as its unsigned twin.
+pub fn scan_bits(
+    pid: i32,
+    regions: &[(u64, u64)],
+    value_type: ValueType,
+    mask: u64,
+    expected: u64,
+    alignment: usize,
+    control: &ScanControl,
+) -> Result<ScanResult<u64>, String> {
+    if matches!(value_type, ValueType::F32 | ValueType::F64) {
+        return Err(format!(
+            "Bit scans need an integer type, not {:?}",
+            value_type
+        ));
+    }
+    let size = value_type.size();
+    let width = u64::MAX >> (64 - 8 * size);
+    if mask & !width != 0 {
+        return Err(format!("Mask {:#x} is wider than {:?}", mask, value_type));
+    }
+    if expected & !mask != 0 {
+        return Err(format!(
+            "Expected bits {:#x} fall outside mask {:#x}, so nothing could match",
+            expected, mask
+        ));
+    }
+
+    Ok(scan_regions(
+        "bits",
+        pid,
+        regions,
+        size - 1,
+        control,
+        |start, end| {
+            let mut matches = Vec::new();
+            if let Some(buffer) = read_region(pid, start, end) {
+                for offset in aligned_offsets(start, buffer.len(), size, alignment) {
+                    let mut bytes = [0u8; 8];
+                    bytes[..size].copy_from_slice(&buffer[offset..offset + size]);
+                    if u64::from_le_bytes(bytes) & mask == expected {
+                        matches.push(start + offset as u64);
+                    }
+                }
+            }
+            matches
+        },
+    ))
+}
+
+pub fn scan_compare(
+    pid: i32,
+    previous: &[(u64, Vec<u8>)],
+    value_type: ValueType,
+    cmp: ScanCompare,
+) -> ScanSnapshot {
+    let started = Instant::now();
+    let requests: Vec<(u64, usize)> = previous
+        .iter()
+        .map(|(address, _)| (*address, value_type.size()))
+        .collect();
+    let matches: ScanSnapshot = util::read_memory_batch(pid, &requests)
+        .into_iter()
+        .zip(previous)
+        .filter_map(|(result, (address, old))| {
+            let new = result.ok()?;
+            if compare_bytes(&new, old, value_type, cmp) {
+                Some((*address, new))
+            } else {
+                None
+            }
+        })
+        .collect();
+    log_refine_summary("compare", pid, previous.len(), matches.len(), started);
+    matches
+}
+
+// xxhash64 of a window of a region's bytes, keyed by the window's bounds. Windows are laid out
+// from the region start, so a region that is unmapped or remapped elsewhere never matches its
+// old hashes.
+pub type RegionHashes = HashMap<(u64, u64), u64>;
+
+// A window holding fewer entries than one per this many bytes is cheaper to read entry by
+// entry than to read whole
+const HASHED_BYTES_PER_ENTRY: u64 = 4096;
+
+#[derive(Debug, PartialEq)]
+enum EntryValue {
+    Unchanged,
+    Read(Vec<u8>),
+    Unreadable,
+}
+
+// A hashed window's hash and the values of the entries it holds
+type WindowValues = (u64, Vec<(usize, EntryValue)>);
+
+// The current value of every entry, reading the regions listed once up front
+fn read_entries_incremental(
+    pid: i32,
+    entries: &ScanSnapshot,
+    size: usize,
+    previous: &RegionHashes,
+) -> (Vec<EntryValue>, RegionHashes, usize) {
+    let regions: Vec<(u64, u64)> = region::enumerate_regions(pid)
+        .unwrap_or_default()
+        .iter()
+        .map(|region| (region.start, region.end))
+        .collect();
+    incremental_values(
+        &regions,
+        entries,
+        size,
+        previous,
+        scan_chunk_size(),
+        |start, end| read_region(pid, start, end),
+        |requests| util::read_memory_batch(pid, requests),
+    )
+}
+
+// Windows dense with entries are read whole and hashed. When the hash still matches the one
+// in previous their entries keep their old values; otherwise the values are taken from the
+// same buffer that was hashed, so a stored value always matches its stored hash. The other
+// entries are read one by one. Returns the hashes taken now for the next refine and how many
+// windows were unchanged.
+fn incremental_values<B, W, M>(
+    regions: &[(u64, u64)],
+    entries: &ScanSnapshot,
+    size: usize,
+    previous: &RegionHashes,
+    chunk: usize,
+    read_window: W,
+    read_many: M,
+) -> (Vec<EntryValue>, RegionHashes, usize)
+where
+    B: Deref<Target = [u8]>,
+    W: Fn(u64, u64) -> Option<B> + Sync,
+    M: FnOnce(&[(u64, usize)]) -> Vec<Result<Vec<u8>, String>>,
+{
+    let mut regions = regions.to_vec();
+    regions.sort_unstable();
+
+    // Each entry goes to the window its address falls in, which runs size - 1 bytes into the
+    // next so it holds the entry whole. Entries running past the end of their region are left
+    // to the plain reads.
+    let mut held: HashMap<(u64, u64), Vec<usize>> = HashMap::new();
+    for (index, (address, _)) in entries.iter().enumerate() {
+        let found = regions
+            .partition_point(|&(start, _)| start <= *address)
+            .checked_sub(1)
+            .map(|region| regions[region])
+            .filter(|&(_, end)| address.saturating_add(size as u64) <= end);
+        if let Some((start, end)) = found {
+            let window_start = start + (address - start) / chunk as u64 * chunk as u64;
+            let window_end = window_start
+                .saturating_add((chunk + size - 1) as u64)
+                .min(end);
+            held.entry((window_start, window_end))
+                .or_default()
+                .push(index);
+        }
+    }
+    let held: Vec<((u64, u64), Vec<usize>)> = held
+        .into_iter()
+        .filter(|((start, end), indices)| {
+            (indices.len() as u64).saturating_mul(HASHED_BYTES_PER_ENTRY) >= end - start
+        })
+        .collect();
+
+    let windows: Vec<Option<WindowValues>> = in_scan_pool(|| {
+        held.par_iter()
+            .map(|&((start, end), ref indices)| {
+                // A window read short falls back to the plain reads
+                let buffer =
+                    read_window(start, end).filter(|buffer| buffer.len() as u64 == end - start)?;
+                let mut hasher = twox_hash::XxHash64::with_seed(0);
+                std::hash::Hasher::write(&mut hasher, &buffer);
+                let hash = std::hash::Hasher::finish(&hasher);
+                let unchanged = previous.get(&(start, end)) == Some(&hash);
+                let values = indices
+                    .iter()
+                    .map(|&index| {
+                        if unchanged {
+                            return (index, EntryValue::Unchanged);
+                        }
+                        let offset = (entries[index].0 - start) as usize;
+                        (
+                            index,
+                            EntryValue::Read(buffer[offset..offset + size].to_vec()),
+                        )
+                    })
+                    .collect();
+                Some((hash, values))
+            })
+            .collect()
+    });
+
+    let mut values: Vec<Option<EntryValue>> = entries.iter().map(|_| None).collect();
+    let mut hashes = RegionHashes::new();
+    let mut skipped = 0;
+    for ((bounds, _), window) in held.iter().zip(windows) {
+        let Some((hash, window_values)) = window else {
+            continue;
+        };
+        if previous.get(bounds) == Some(&hash) {
+            skipped += 1;
+        }
+        hashes.insert(*bounds, hash);
+        for (index, value) in window_values {
+            values[index] = Some(value);
+        }
+    }
+
+    let requests: Vec<(u64, usize)> = entries
+        .iter()
+        .zip(&values)
+        .filter(|(_, value)| value.is_none())
+        .map(|((address, _), _)| (*address, size))
+        .collect();
+    let mut reads = read_many(&requests).into_iter();
+    let values = values
+        .into_iter()
+        .map(|value| {
+            value.unwrap_or_else(|| match reads.next() {
+                Some(Ok(bytes)) => EntryValue::Read(bytes),
+                _ => EntryValue::Unreadable,
+            })
+        })
+        .collect();
+    (values, hashes, skipped)
+}
+
+// Keeps the entries for which keep(new, old) holds, each with its current value
+fn refine_incremental<F>(
+    kind: &str,
+    pid: i32,
+    previous: ScanSnapshot,
+    size: usize,
+    hashes: &RegionHashes,
+    keep: F,
+) -> (ScanSnapshot, RegionHashes)
+where
+    F: Fn(&[u8], &[u8]) -> bool,
+{
+    let started = Instant::now();
+    let count = previous.len();
+    let (values, hashes, skipped) = read_entries_incremental(pid, &previous, size, hashes);
+    let mut reused = 0;
+    let matches: ScanSnapshot = previous
+        .into_iter()
+        .zip(values)
+        .filter_map(|((address, old), value)| match value {
+            EntryValue::Unchanged => {
+                reused += 1;
+                keep(&old, &old).then_some((address, old))
+            }
+            EntryValue::Read(new) => keep(&new, &old).then_some((address, new)),
+            EntryValue::Unreadable => None,
+        })
+        .collect();
+    log::debug!(
+        "{} scan skipped {} unchanged windows: pid={} reused={} read={}",
+        kind,
+        skipped,
+        pid,
+        reused,
+        count - reused
+    );
+    log_refine_summary(kind, pid, count, matches.len(), started);
+    (matches, hashes)
+}
+
+// Like scan_refine and scan_compare, but skipping the regions whose hash has not changed
+// since the hashes were taken. Both return the hashes for the next step.
+pub fn scan_refine_incremental(
+    pid: i32,
+    previous: ScanSnapshot,
+    needle: &[u8],
+    hashes: &RegionHashes,
+) -> (ScanSnapshot, RegionHashes) {
+    refine_incremental("refine", pid, previous, needle.len(), hashes, |new, _| {
+        new == needle
+    })
+}
+
+pub fn scan_compare_incremental(
+    pid: i32,
+    previous: ScanSnapshot,
+    value_type: ValueType,
+    cmp: ScanCompare,
+    hashes: &RegionHashes,
+) -> (ScanSnapshot, RegionHashes) {
+    refine_incremental(
+        "compare",
+        pid,
+        previous,
+        value_type.size(),
+        hashes,
+        |new, old| compare_bytes(new, old, value_type, cmp),
+    )
+}
+
+// Pieces are kept well under the compressed frame limit; each one also holds the first
+// size - 1 bytes of the next so values straddling a piece boundary are still compared
+const SNAPSHOT_PIECE_SIZE: usize = compression::MAX_FRAME_LEN / 2;
+
+// One piece of a memory snapshot, stored as a compress_framed blob
+pub struct RegionSnapshot {
+    pub start: u64,
+    pub len: usize,
+    data: Vec<u8>,
+}
+
+impl RegionSnapshot {
+    fn positions(&self, size: usize, alignment: usize) -> impl ExactSizeIterator<Item = usize> {
+        aligned_offsets(self.start, self.len, size, alignment)
+    }
+}
+
+// The baseline of an unknown initial value scan: every aligned position of the scanned
+// regions, kept as compressed region bytes rather than an entry per address
+pub struct MemorySnapshot {
+    pub pieces: Vec<RegionSnapshot>,
+    pub value_type: ValueType,
+    pub alignment: usize,
+}
+
+impl MemorySnapshot {
+    pub fn position_count(&self) -> usize {
+        self.pieces
+            .iter()
+            .map(|piece| {
+                piece
+                    .positions(self.value_type.size(), self.alignment)
+                    .len()
+            })
+            .sum()
+    }
+
+    // Addresses of the positions offset..offset + limit, skipping whole pieces where possible
+    pub fn positions_page(&self, offset: usize, limit: usize) -> Vec<u64> {
+        let size = self.value_type.size();
+        let mut skip = offset;
+        let mut page = Vec::new();
+        for piece in &self.pieces {
+            if page.len() >= limit {
+                break;
+            }
+            let positions = piece.positions(size, self.alignment);
+            if skip >= positions.len() {
+                skip -= positions.len();
+                continue;
+            }
+            page.extend(
+                positions
+                    .skip(skip)
+                    .take(limit - page.len())
+                    .map(|offset| piece.start + offset as u64),
+            );
+            skip = 0;
+        }
+        page
+    }
+}
+
+pub fn scan_snapshot(
+    pid: i32,
+    regions: &[(u64, u64)],
+    value_type: ValueType,
+    alignment: usize,
+) -> MemorySnapshot {
+    let started = Instant::now();
+    let chunk = scan_chunk_size().min(SNAPSHOT_PIECE_SIZE);
+    let pieces: Vec<(u64, u64)> = regions
+        .iter()
+        .flat_map(|&(start, end)| region_windows(start, end, chunk, value_type.size() - 1))
+        .collect();
+    let pieces: Vec<RegionSnapshot> = in_scan_pool(|| {
+        pieces
+            .par_iter()
+            .filter_map(|&(start, end)| {
+                let region_started = Instant::now();
+                let buffer = read_region(pid, start, end)?;
+                log_region_scanned("snapshot", pid, start, end, 0, region_started);
+                Some(RegionSnapshot {
+                    start,
+                    len: buffer.len(),
+                    data: compression::compress_framed(&buffer).ok()?,
+                })
+            })
+            .collect()
+    });
+    let snapshot = MemorySnapshot {
+        pieces,
+        value_type,
+        alignment: alignment.max(1),
+    };
+    log_scan_summary(
+        "snapshot",
+        pid,
+        regions.len(),
+        snapshot.pieces.iter().map(|piece| piece.len).sum(),
+        snapshot.position_count(),
+        started,
+        "",
+    );
+    snapshot
+}
+
+// Walks every position of the snapshot against the current memory; pieces that can no
+// longer be read drop out
+fn filter_snapshot<F>(kind: &str, pid: i32, snapshot: &MemorySnapshot, keep: F) -> ScanSnapshot
+where
+    F: Fn(&[u8], &[u8]) -> bool + Sync,
+{
+    let started = Instant::now();
+    let size = snapshot.value_type.size();
+    let matches: ScanSnapshot = in_scan_pool(|| {
+        snapshot
+            .pieces
+            .par_iter()
+            .flat_map_iter(|piece| {
+                let baseline = compression::decompress_framed(&piece.data).unwrap_or_default();
+                let current = read_region(pid, piece.start, piece.start + piece.len as u64)
+                    .unwrap_or_default();
+                let len = baseline.len().min(current.len());
+                let region_started = Instant::now();
+                let matches: Vec<_> = aligned_offsets(piece.start, len, size, snapshot.alignment)
+                    .filter(|&offset| {
+                        keep(
+                            &current[offset..offset + size],
+                            &baseline[offset..offset + size],
+                        )
+                    })
+                    .map(|offset| {
+                        (
+                            piece.start + offset as u64,
+                            current[offset..offset + size].to_vec(),
+                        )
+                    })
+                    .collect();
+                let end = piece.start + piece.len as u64;
+                log_region_scanned(kind, pid, piece.start, end, matches.len(), region_started);
+                matches
+            })
+            .collect()
+    });
+    log_scan_summary(
+        kind,
+        pid,
+        snapshot.pieces.len(),
+        snapshot.pieces.iter().map(|piece| piece.len).sum(),
+        matches.len(),
+        started,
+        "",
+    );
+    matches
+}
+
+// The first refine of an unknown initial value scan, comparing against the snapshot
+pub fn scan_compare_snapshot(
+    pid: i32,
+    snapshot: &MemorySnapshot,
+    cmp: ScanCompare,
+) -> ScanSnapshot {
+    filter_snapshot("compare", pid, snapshot, |new, old| {
+        compare_bytes(new, old, snapshot.value_type, cmp)
+    })
+}
+
+pub fn scan_refine_snapshot(pid: i32, snapshot: &MemorySnapshot, needle: &[u8]) -> ScanSnapshot {
+    filter_snapshot("refine", pid, snapshot, |new, _| new == needle)
+}
+
+const DIFF_PAGE_SIZE: usize = 4096;
+// Bytes one diff compares; the baseline sizes the read, so this bounds the allocation
+pub const MAX_DIFF_LENGTH: usize = 1024 * 1024 * 64; // 64MB
+
+// Every byte in start..end that differs from baseline, as (address, old, new). Only the part
+// covered by both the baseline and the current read is compared, so no more than the
+// baseline is read.
+pub fn diff_region(
+    pid: i32,
+    start: u64,
+    end: u64,
+    baseline: &[u8],
+) -> Result<Vec<(u64, u8, u8)>, String> {
+    if baseline.len() > MAX_DIFF_LENGTH {
+        return Err(format!(
+            "Cannot diff more than {} bytes at once; the baseline is {}",
+            MAX_DIFF_LENGTH,
+            baseline.len()
+        ));
+    }
+    let read_end = end.min(start.saturating_add(baseline.len() as u64));
+    let current = read_region(pid, start, read_end)
+        .ok_or_else(|| format!("Failed to read region {:#x}-{:#x}", start, read_end))?;
+    Ok(diff_bytes(start, baseline, &current))
+}
+
+fn diff_bytes(start: u64, baseline: &[u8], current: &[u8]) -> Vec<(u64, u8, u8)> {
+    let len = current.len().min(baseline.len());
+    let mut diffs = Vec::new();
+    for (page_index, (old_page, new_page)) in baseline[..len]
+        .chunks(DIFF_PAGE_SIZE)
+        .zip(current[..len].chunks(DIFF_PAGE_SIZE))
+        .enumerate()
+    {
+        // Most pages are unchanged, and a slice comparison rules them out far faster than
+        // walking the bytes
+        if old_page == new_page {
+            continue;
+        }
+        let page_start = start + (page_index * DIFF_PAGE_SIZE) as u64;
+        diffs.extend(
+            old_page
+                .iter()
+                .zip(new_page)
+                .enumerate()
+                .filter(|(_, (old, new))| old != new)
+                .map(|(offset, (&old, &new))| (page_start + offset as u64, old, new)),
+        );
+    }
+    diffs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn own_pid() -> i32 {
+        unsafe { native_bridge::get_pid_native() }
+    }
+
+    // A region just over one chunk, with bytes written across the first window boundary
+    fn straddling_buffer(needle: &[u8]) -> (Vec<u8>, u64, u64) {
+        let chunk = scan_chunk_size();
+        let mut memory = vec![0u8; chunk + 64];
+        let at = chunk - needle.len() / 2;
+        memory[at..at + needle.len()].copy_from_slice(needle);
+        let start = memory.as_ptr() as u64;
+        (memory, start, start + at as u64)
+    }
+
+    #[test]
+    fn region_reads_reuse_one_allocation_per_thread() {
+        let memory: Vec<u8> = (0..=255).cycle().take(4096).collect();
+        let start = memory.as_ptr() as u64;
+        let full = read_region(own_pid(), start, start + 4096).unwrap();
+        let pooled = full.as_ptr();
+        drop(full);
+
+        let mut allocations = std::collections::HashSet::new();
+        for read in 0..64 {
+            let from = read * 64;
+            let len = 64 - read % 8;
+            let buffer =
+                read_region(own_pid(), start + from as u64, start + (from + len) as u64).unwrap();
+            // Only what was read is exposed, not what the larger reads left behind
+            assert_eq!(&buffer[..], &memory[from..from + len]);
+            allocations.insert(buffer.as_ptr());
+        }
+        assert_eq!(allocations.into_iter().collect::<Vec<_>>(), [pooled]);
+
+        // A read while the pooled buffer is held, as a stolen task would do, gets its own
+        let held = read_region(own_pid(), start, start + 64).unwrap();
+        let nested = read_region(own_pid(), start, start + 64).unwrap();
+        assert_ne!(held.as_ptr(), nested.as_ptr());
+        assert_eq!(held.as_ptr(), pooled);
+    }
+
+    #[test]
+    fn full_window_reads_are_pooled() {
+        let window = scan_chunk_size() + REGEX_OVERLAP;
+        let memory = vec![0x5au8; window];
+        let start = memory.as_ptr() as u64;
+        let full = read_region(own_pid(), start, start + window as u64).unwrap();
+        assert_eq!(full.len(), window);
+        let pooled = full.as_ptr();
+        drop(full);
+
+        let next = read_region(own_pid(), start, start + 4096).unwrap();
+        assert_eq!(next.as_ptr(), pooled);
+    }
+
+    #[test]
+    fn exact_scan_finds_a_known_buffer_at_its_alignment() {
+        let needle = [0x4d, 0x45, 0x4d, 0x53];
+        let mut memory = [0u8; 96];
+        let skew = memory.as_ptr().align_offset(8);
+        let start = memory.as_ptr() as u64 + skew as u64;
+        for at in [3, 16, 44, 64] {
+            memory[skew + at..skew + at + needle.len()].copy_from_slice(&needle);
+        }
+        let regions = [(start, start + 88)];
+        let scan = |needle: &[u8], alignment| {
+            scan_exact(
+                own_pid(),
+                &regions,
+                needle,
+                alignment,
+                &ScanControl::default(),
+            )
+            .matches
+        };
+
+        let at = |offsets: &[u64]| offsets.iter().map(|o| start + o).collect::<Vec<_>>();
+        assert_eq!(scan(&needle, 1), at(&[3, 16, 44, 64]));
+        // Four bytes at an alignment of four take the word scanner
+        assert_eq!(scan(&needle, 4), at(&[16, 44, 64]));
+        assert_eq!(scan(&needle, 8), at(&[16, 64]));
+        assert_eq!(scan(&needle[..3], 8), at(&[16, 64]));
+        assert!(scan(&[], 1).is_empty());
+    }
+
+    #[test]
+    fn pointers_to_a_target_are_found_at_pointer_alignment() {
+        let target = 0x7f12_3456_7890u64;
+        let mut memory = [0u8; 64];
+        let skew = memory.as_ptr().align_offset(8);
+        let start = memory.as_ptr() as u64 + skew as u64;
+        memory[skew + 24..skew + 32].copy_from_slice(&target.to_le_bytes());
+        memory[skew + 37..skew + 45].copy_from_slice(&target.to_le_bytes());
+        // Only the low half of the target, which a 64-bit process does not point with
+        memory[skew + 48..skew + 52].copy_from_slice(&(target as u32).to_le_bytes());
+        let regions = [(start, start + 56)];
+
+        let aligned = find_pointers_to(own_pid(), target, &regions, Alignment::Auto);
+        assert_eq!(aligned, vec![start + 24]);
+        let anywhere = find_pointers_to(own_pid(), target, &regions, Alignment::Fixed(1));
+        assert_eq!(anywhere, vec![start + 24, start + 37]);
+    }
+
+    #[test]
+    fn changing_one_byte_drops_one_refined_address() {
+        let needle = [0x12, 0x34, 0x56, 0x78];
+        let mut memory = [0u8; 64];
+        for at in [0, 16, 32, 48] {
+            memory[at..at + needle.len()].copy_from_slice(&needle);
+        }
+        let start = memory.as_ptr() as u64;
+        let previous: Vec<u64> = [0, 16, 32, 48].iter().map(|o| start + o).collect();
+        assert_eq!(scan_refine(own_pid(), &previous, &needle), previous);
+
+        std::hint::black_box(&mut memory)[33] = 0;
+        let mut expected = previous.clone();
+        expected.remove(2);
+        assert_eq!(scan_refine(own_pid(), &previous, &needle), expected);
+        // An address that is no longer mapped is dropped, not an error
+        let with_unmapped = [expected.clone(), vec![8]].concat();
+        assert_eq!(scan_refine(own_pid(), &with_unmapped, &needle), expected);
+    }
+
+    #[test]
+    fn compares_follow_the_signedness_of_the_type() {
+        // 0x7f -> 0x80 and -1 -> 1: down and up when signed, the other way round when not
+        let mut memory = [0u8; 8];
+        memory[0] = 0x80;
+        memory[4..8].copy_from_slice(&1i32.to_le_bytes());
+        let start = memory.as_ptr() as u64;
+        let byte = [(start, vec![0x7f])];
+        let word = [(start + 4, (-1i32).to_le_bytes().to_vec())];
+        let survivors = |previous: &[(u64, Vec<u8>)], value_type, cmp| {
+            scan_compare(own_pid(), previous, value_type, cmp)
+                .into_iter()
+                .map(|(address, _)| address)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            survivors(&byte, ValueType::I8, ScanCompare::Decreased),
+            [start]
+        );
+        assert!(survivors(&byte, ValueType::I8, ScanCompare::Increased).is_empty());
+        assert_eq!(
+            survivors(&byte, ValueType::U8, ScanCompare::Increased),
+            [start]
+        );
+        assert_eq!(
+            survivors(&byte, ValueType::U8, ScanCompare::IncreasedBy(1.0)),
+            [start]
+        );
+        assert_eq!(
+            survivors(&word, ValueType::I32, ScanCompare::IncreasedBy(2.0)),
+            [start + 4]
+        );
+        assert_eq!(
+            survivors(&word, ValueType::U32, ScanCompare::Decreased),
+            [start + 4]
+        );
+        // Survivors carry the value read now
+        let snapshot = scan_compare(own_pid(), &word, ValueType::I32, ScanCompare::Changed);
+        assert_eq!(snapshot, [(start + 4, 1i32.to_le_bytes().to_vec())]);
+    }
+
+    #[test]
+    fn range_scan_keeps_values_within_inclusive_bounds() {
+        let values: [i32; 8] = [-11, -10, -9, 0, 19, 20, 21, i32::MIN];
+        let mut memory = [0u8; 36];
+        let skew = memory.as_ptr().align_offset(4);
+        for (index, value) in values.iter().enumerate() {
+            let at = skew + index * 4;
+            memory[at..at + 4].copy_from_slice(&value.to_le_bytes());
+        }
+        let start = memory.as_ptr() as u64 + skew as u64;
+        let regions = [(start, start + 32)];
+        let scan = |value_type, low, high| {
+            scan_range(
+                own_pid(),
+                &regions,
+                value_type,
+                low,
+                high,
+                4,
+                &ScanControl::default(),
+            )
+            .map(|result| result.matches)
+        };
+        let at = |indices: &[u64]| indices.iter().map(|i| start + i * 4).collect::<Vec<_>>();
+
+        assert_eq!(
+            scan(ValueType::I32, -10.0, 20.0).unwrap(),
+            at(&[1, 2, 3, 4, 5])
+        );
+        // Fractional bounds round inwards
+        assert_eq!(
+            scan(ValueType::I32, -10.5, 19.5).unwrap(),
+            at(&[1, 2, 3, 4])
+        );
+        // Read unsigned, the negative values are huge
+        assert_eq!(scan(ValueType::U32, 0.0, 20.0).unwrap(), at(&[3, 4, 5]));
+        assert!(scan(ValueType::I32, 1.0, 0.0).is_err());
+        assert!(scan(ValueType::I32, f64::NAN, 0.0).is_err());
+    }
+
+    #[test]
+    fn range_scan_reads_floats() {
+        let values: [f64; 4] = [-0.5, 0.0, 1.0, 1.0000001];
+        let mut memory = [0u8; 40];
+        let skew = memory.as_ptr().align_offset(8);
+        for (index, value) in values.iter().enumerate() {
+            let at = skew + index * 8;
+            memory[at..at + 8].copy_from_slice(&value.to_le_bytes());
+        }
+        let start = memory.as_ptr() as u64 + skew as u64;
+        let result = scan_range(
+            own_pid(),
+            &[(start, start + 32)],
+            ValueType::F64,
+            0.0,
+            1.0,
+            8,
+            &ScanControl::default(),
+        )
+        .unwrap();
+        assert_eq!(result.matches, [start + 8, start + 16]);
+    }
+
+    #[test]
+    fn percent_growth_is_held_to_the_tolerance() {
+        // 1000 -> 1105 is 10.5%: inside 1 point of 10% but not inside 0.1, while the exact
+        // 10% of 2000 -> 2200 passes both. An old value of zero matches no percentage.
+        let old = [1000u32, 2000, 0, 5000];
+        let memory = [1105u32, 2200, 100, 4500];
+        let start = memory.as_ptr() as u64;
+        let previous: ScanSnapshot = old
+            .iter()
+            .enumerate()
+            .map(|(index, value)| (start + index as u64 * 4, value.to_le_bytes().to_vec()))
+            .collect();
+        let survivors = |percent, tolerance| -> Vec<usize> {
+            let cmp = ScanCompare::ChangedByPercent { percent, tolerance };
+            scan_compare(own_pid(), &previous, ValueType::U32, cmp)
+                .iter()
+                .map(|(address, _)| (address - start) as usize / 4)
+                .collect()
+        };
+
+        assert_eq!(survivors(10.0, 1.0), [0, 1]);
+        assert_eq!(survivors(10.0, 0.1), [1]);
+        assert_eq!(survivors(-10.0, 0.1), [3]);
+        assert_eq!(survivors(10.0, f64::INFINITY), [0, 1, 3]);
+
+        let grown = 110.5f32.to_le_bytes();
+        let base = 100.0f32.to_le_bytes();
+        let by_percent = |tolerance| ScanCompare::ChangedByPercent {
+            percent: 10.0,
+            tolerance,
+        };
+        assert!(compare_bytes(
+            &grown,
+            &base,
+            ValueType::F32,
+            by_percent(1.0)
+        ));
+        assert!(!compare_bytes(
+            &grown,
+            &base,
+            ValueType::F32,
+            by_percent(0.1)
+        ));
+    }
+
+    #[test]
+    fn bit_scans_ignore_bits_outside_the_mask() {
+        // 0xac and 0x2e both have bits 2 and 3 set; 0xa8 has only bit 3
+        let mut memory = [0u8; 16];
+        let skew = memory.as_ptr().align_offset(8);
+        let start = memory.as_ptr() as u64 + skew as u64;
+        memory[skew..skew + 4].copy_from_slice(&[0xac, 0x2e, 0xa8, 0x00]);
+        memory[skew + 4..skew + 6].copy_from_slice(&(-0x7ff4i16).to_le_bytes());
+        let regions = [(start, start + 8)];
+        let scan = |value_type, mask, expected, alignment| {
+            scan_bits(
+                own_pid(),
+                &regions,
+                value_type,
+                mask,
+                expected,
+                alignment,
+                &ScanControl::default(),
+            )
+            .map(|result| result.matches)
+        };
+
+        // The low byte of the i16 as well
+        assert_eq!(
+            scan(ValueType::U8, 0x0c, 0x0c, 1),
+            Ok(vec![start, start + 1, start + 4])
+        );
+        assert_eq!(scan(ValueType::U8, 0x0c, 0x08, 1), Ok(vec![start + 2]));
+        // -0x7ff4 is 0x800c: a signed type is masked as its raw bits, at its alignment
+        assert_eq!(scan(ValueType::I16, 0x800c, 0x800c, 2), Ok(vec![start + 4]));
+        assert!(scan(ValueType::U8, 0x100, 0, 1).is_err());
+        assert!(scan(ValueType::U8, 0x0c, 0x10, 1).is_err());
+        assert!(scan(ValueType::F32, 1, 1, 4).is_err());
+    }
+
+    #[test]
+    fn floats_match_within_the_tolerance() {
+        let mut memory = [0u8; 40];
+        let skew = memory.as_ptr().align_offset(8);
+        let start = memory.as_ptr() as u64 + skew as u64;
+        memory[skew + 4..skew + 8].copy_from_slice(&1.0000001f32.to_le_bytes());
+        memory[skew + 8..skew + 16].copy_from_slice(&1.0000001f64.to_le_bytes());
+        memory[skew + 16..skew + 24].copy_from_slice(&f64::NAN.to_le_bytes());
+        let regions = [(start, start + 32)];
+        let scan = |value_type, tolerance| {
+            scan_float(
+                own_pid(),
+                &regions,
+                1.0,
+                value_type,
+                tolerance,
+                &ScanControl::default(),
+            )
+            .matches
+        };
+
+        assert_eq!(scan(FloatType::F32, 1e-4), vec![start + 4]);
+        assert!(scan(FloatType::F32, 1e-9).is_empty());
+        assert_eq!(scan(FloatType::F64, 1e-4), vec![start + 8]);
+        assert!(scan(FloatType::F64, 1e-9).is_empty());
+        // NaN is never within any tolerance, while every other word is
+        assert_eq!(
+            scan(FloatType::F64, f64::INFINITY),
+            vec![start, start + 8, start + 24]
+        );
+    }
+
+    #[test]
+    fn two_thread_pool_finds_what_the_default_pool_does() {
+        let needle = [0xca, 0xfe, 0xba, 0xbe];
+        let mut memory = vec![0u8; 1 << 20];
+        // None of them straddle the region boundaries
+        for at in (8..memory.len()).step_by(4096) {
+            memory[at..at + needle.len()].copy_from_slice(&needle);
+        }
+        let start = memory.as_ptr() as u64;
+        let regions: Vec<(u64, u64)> = (0..16)
+            .map(|index| (start + index * 0x10000, start + (index + 1) * 0x10000))
+            .collect();
+        let scan = || scan_exact(own_pid(), &regions, &needle, 1, &ScanControl::default()).matches;
+
+        let expected = scan();
+        assert_eq!(expected.len(), 256);
+
+        // A pool the caller installed is used as is, whether or not the scan pool is running
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap();
+        assert_eq!(pool.install(|| in_scan_pool(rayon::current_num_threads)), 2);
+        assert_eq!(pool.install(scan), expected);
+    }
+
+    #[test]
+    fn scan_pool_starts_once() {
+        // The only test that starts the global scan pool; the others install their own pools
+        assert_eq!(init_scan_pool(Some(2)), Ok(2));
+        assert!(init_scan_pool(Some(4)).is_err());
+        let on_scan_thread = || {
+            std::thread::current()
+                .name()
+                .is_some_and(|name| name.starts_with("scan-"))
+        };
+        assert!(in_scan_pool(on_scan_thread));
+        assert_eq!(in_scan_pool(rayon::current_num_threads), 2);
+    }
+
+    #[test]
+    fn truncated_is_set_only_past_the_cap() {
+        let needle = [0x5a, 0xa5, 0x5a, 0xa5];
+        let mut memory = vec![0u8; 16 * 4096];
+        for at in (8..memory.len()).step_by(4096) {
+            memory[at..at + needle.len()].copy_from_slice(&needle);
+        }
+        let start = memory.as_ptr() as u64;
+        // One match per region
+        let regions: Vec<(u64, u64)> = (0..16)
+            .map(|index| (start + index * 4096, start + (index + 1) * 4096))
+            .collect();
+        let scan = |max_results| {
+            let control = ScanControl {
+                max_results: Some(max_results),
+                ..Default::default()
+            };
+            scan_exact(own_pid(), &regions, &needle, 1, &control)
+        };
+
+        let exact = scan(16);
+        assert!(!exact.truncated);
+        assert_eq!((exact.matches.len(), exact.total_count), (16, 16));
+        let over = scan(15);
+        assert!(over.truncated);
+        assert_eq!(over.matches.len(), 15);
+        assert!(over.total_count > 15);
+        // Once past the cap the remaining regions are skipped rather than scanned
+        let small = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap()
+            .install(|| scan(1));
+        assert!(small.truncated);
+        assert_eq!((small.matches.len(), small.total_count), (1, 2));
+    }
+
+    #[test]
+    fn nan_compares_by_ieee_rules() {
+        let nan = f64::NAN.to_le_bytes();
+        let one = 1.0f64.to_le_bytes();
+        let compare = |new: &[u8], old: &[u8], cmp| compare_bytes(new, old, ValueType::F64, cmp);
+        assert!(compare(&nan, &one, ScanCompare::Changed));
+        assert!(compare(&nan, &nan, ScanCompare::Unchanged));
+        assert!(!compare(&nan, &one, ScanCompare::Increased));
+        assert!(!compare(&one, &nan, ScanCompare::Decreased));
+        assert!(!compare(&nan, &one, ScanCompare::IncreasedBy(1.0)));
+        assert!(compare(
+            &2.0f64.to_le_bytes(),
+            &one,
+            ScanCompare::IncreasedBy(1.0)
+        ));
+    }
+
+    #[test]
+    fn needle_straddling_a_chunk_boundary_is_found() {
+        let needle = [0xde, 0xad, 0xbe, 0xef, 0x13, 0x37];
+        let (memory, start, at) = straddling_buffer(&needle);
+        let regions = [(start, start + memory.len() as u64)];
+        let result = scan_exact(own_pid(), &regions, &needle, 1, &ScanControl::default());
+        assert_eq!(result.matches, vec![at]);
+    }
+
+    #[test]
+    fn regex_match_straddling_a_chunk_boundary_is_reported_once() {
+        let run = [b'a'; 16];
+        let (memory, start, at) = straddling_buffer(&run);
+        let regions = [(start, start + memory.len() as u64)];
+        let result = scan_regex(own_pid(), &regions, "a+", &ScanControl::default()).unwrap();
+        assert_eq!(result.matches, vec![(at, run.len())]);
+        assert_eq!(result.total_count, 1);
+    }
+
+    #[test]
+    fn word_scan_matches_memmem_at_region_boundaries() {
+        let value = 0xcafe_f00du32;
+        let chunk = scan_chunk_size();
+        let mut memory = vec![0u32; (chunk + 64) / 4];
+        let last = memory.len() - 1;
+        // The first and last words fall outside the region, and the word at the chunk
+        // boundary straddles the first window's end
+        for index in [0, 1, chunk / 4 - 1, chunk / 4, last - 1, last] {
+            memory[index] = value;
+        }
+        let start = memory.as_ptr() as u64;
+        let regions = [(start + 1, start + (memory.len() * 4) as u64 - 1)];
+        let control = ScanControl::default();
+
+        let fast = scan_u32_aligned(own_pid(), &regions, value, &control).matches;
+        let expected: Vec<u64> = [1, chunk / 4 - 1, chunk / 4, last - 1]
+            .iter()
+            .map(|&index| start + (index * 4) as u64)
+            .collect();
+        assert_eq!(fast, expected);
+        let needle = value.to_le_bytes();
+        let memmem = scan_exact(own_pid(), &regions, &needle, 4, &control).matches;
+        assert_eq!(fast, memmem);
+    }
+
+    // cargo test --release scan_speed -- --ignored
+    #[test]
+    #[ignore]
+    fn scan_speed_of_words_against_memmem() {
+        let value = 0x1234_5678u32;
+        let mut memory = vec![0u32; 256 * 1024 * 1024 / 4];
+        // Faulted in up front so neither scan pays for first touching the pages
+        memory.fill(1);
+        memory.fill(0);
+        for index in (0..memory.len()).step_by(4099) {
+            memory[index] = value;
+        }
+        let start = memory.as_ptr() as u64;
+        let regions = [(start, start + (memory.len() * 4) as u64)];
+        let control = ScanControl::default();
+
+        let started = Instant::now();
+        let fast = scan_u32_aligned(own_pid(), &regions, value, &control).matches;
+        let fast_time = started.elapsed();
+        let started = Instant::now();
+        let memmem = scan_exact(own_pid(), &regions, &value.to_le_bytes(), 4, &control).matches;
+        let memmem_time = started.elapsed();
+
+        assert_eq!(fast, memmem);
+        // With a single core the word scan only keeps pace; the margin keeps timer noise out
+        assert!(
+            fast_time < memmem_time * 3 / 2,
+            "256MB: words {:?}, memmem {:?}",
+            fast_time,
+            memmem_time
+        );
+    }
+
+    #[test]
+    fn group_straddling_a_chunk_boundary_is_found() {
+        let needle = [0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88];
+        let (memory, start, at) = straddling_buffer(&needle);
+        let regions = [(start, start + memory.len() as u64)];
+        let element = |offset: u64, value: &[u8]| GroupElement {
+            offset,
+            value_type: ValueType::U32,
+            value: value.to_vec(),
+        };
+        let elements = [element(4, &needle[4..]), element(0, &needle[..4])];
+        let result = scan_group(own_pid(), &regions, &elements, &ScanControl::default()).unwrap();
+        assert_eq!(result.matches, vec![at]);
+    }
+
+    #[test]
+    fn only_the_record_matching_every_element_is_found() {
+        // (i32, f32) records: each decoy satisfies one of the two constraints
+        let records: [(i32, f32); 4] = [(100, 2.0), (99, 1.0), (100, 1.0), (100, -1.0)];
+        let mut memory = Vec::new();
+        for (id, scale) in records {
+            memory.extend_from_slice(&id.to_le_bytes());
+            memory.extend_from_slice(&scale.to_le_bytes());
+        }
+        let start = memory.as_ptr() as u64;
+        let regions = [(start, start + memory.len() as u64)];
+        let elements = [
+            GroupElement {
+                offset: 0,
+                value_type: ValueType::I32,
+                value: 100i32.to_le_bytes().to_vec(),
+            },
+            GroupElement {
+                offset: 4,
+                value_type: ValueType::F32,
+                value: 1.0f32.to_le_bytes().to_vec(),
+            },
+        ];
+        let result = scan_group(own_pid(), &regions, &elements, &ScanControl::default()).unwrap();
+        assert_eq!(result.matches, vec![start + 16]);
+        assert_eq!(result.total_count, 1);
+
+        let wrong_size = [GroupElement {
+            value: vec![0; 2],
+            ..elements[0].clone()
+        }];
+        assert!(scan_group(own_pid(), &regions, &wrong_size, &ScanControl::default()).is_err());
+    }
+
+    #[test]
+    fn group_spans_are_bounded() {
+        let element = |offset: u64| GroupElement {
+            offset,
+            value_type: ValueType::U32,
+            value: vec![0; 4],
+        };
+        let control = ScanControl::default();
+        let too_wide = [element(0), element(scan_chunk_size() as u64)];
+        assert!(scan_group(own_pid(), &[], &too_wide, &control).is_err());
+        let overflowing = [element(0), element(u64::MAX - 1)];
+        assert!(scan_group(own_pid(), &[], &overflowing, &control).is_err());
+    }
+
+    #[test]
+    fn unknown_value_narrows_through_repeated_decreases() {
+        let mut memory: Vec<u32> = vec![10; 16];
+        let start = memory.as_ptr() as u64;
+        let regions = [(start, start + 64)];
+        let snapshot = scan_snapshot(own_pid(), &regions, ValueType::U32, 4);
+        assert_eq!(snapshot.position_count(), 16);
+        let indices = |matches: &ScanSnapshot| -> Vec<u64> {
+            matches
+                .iter()
+                .map(|(address, _)| (address - start) / 4)
+                .collect()
+        };
+
+        for index in [2, 5, 9] {
+            std::hint::black_box(&mut memory)[index] = 7;
+        }
+        let first = scan_compare_snapshot(own_pid(), &snapshot, ScanCompare::Decreased);
+        assert_eq!(indices(&first), [2, 5, 9]);
+        assert_eq!(first[0].1, 7u32.to_le_bytes());
+
+        // Each refine compares against the values the previous one kept, not the snapshot
+        let memory = std::hint::black_box(&mut memory);
+        memory[2] = 8;
+        memory[5] = 6;
+        memory[9] = 3;
+        memory[0] = 1;
+        let second = scan_compare(own_pid(), &first, ValueType::U32, ScanCompare::Decreased);
+        assert_eq!(indices(&second), [5, 9]);
+
+        memory[9] = 2;
+        let third = scan_compare(own_pid(), &second, ValueType::U32, ScanCompare::Decreased);
+        assert_eq!(indices(&third), [9]);
+    }
+
+    // Two dense windows of one region and a sparse entry elsewhere, read from memory
+    // through mocks that count what is read
+    fn incremental_run(
+        memory: &[u8],
+        previous: &RegionHashes,
+        window_reads: &AtomicUsize,
+    ) -> (Vec<EntryValue>, RegionHashes, usize, Vec<(u64, usize)>) {
+        const BASE: u64 = 0x10000;
+        let entries: ScanSnapshot = [0x10, 0x20, 0x1010, 0x1020, 0x8000]
+            .iter()
+            .map(|&offset| (BASE + offset, vec![0; 4]))
+            .collect();
+        let regions = [(BASE, BASE + 0x2000), (BASE + 0x8000, BASE + 0x10000)];
+        let mut requested = Vec::new();
+        let (values, hashes, skipped) = incremental_values(
+            &regions,
+            &entries,
+            4,
+            previous,
+            0x1000,
+            |start, end| {
+                window_reads.fetch_add(1, Ordering::Relaxed);
+                Some(memory[(start - BASE) as usize..(end - BASE) as usize].to_vec())
+            },
+            |requests| {
+                requested = requests.to_vec();
+                requests
+                    .iter()
+                    .map(|&(address, size)| {
+                        let offset = (address - BASE) as usize;
+                        Ok(memory[offset..offset + size].to_vec())
+                    })
+                    .collect()
+            },
+        );
+        (values, hashes, skipped, requested)
+    }
+
+    #[test]
+    fn unchanged_windows_are_not_reread() {
+        let mut memory = vec![0u8; 0x10000];
+        memory[0x1020] = 7;
+        let window_reads = AtomicUsize::new(0);
+
+        // Nothing to compare against yet, so every entry is read
+        let (values, hashes, skipped, requested) =
+            incremental_run(&memory, &RegionHashes::new(), &window_reads);
+        assert_eq!(skipped, 0);
+        assert_eq!(hashes.len(), 2);
+        assert_eq!(values[3], EntryValue::Read(vec![7, 0, 0, 0]));
+        // The sparse entry is read on its own
+        assert_eq!(requested, vec![(0x18000, 4)]);
+
+        // One window changes: only its entries get new values
+        memory[0x1021] = 9;
+        let (values, _, skipped, requested) = incremental_run(&memory, &hashes, &window_reads);
+        assert_eq!(skipped, 1);
+        assert_eq!(values[0], EntryValue::Unchanged);
+        assert_eq!(values[1], EntryValue::Unchanged);
+        assert_eq!(values[3], EntryValue::Read(vec![7, 9, 0, 0]));
+        assert_eq!(requested, vec![(0x18000, 4)]);
+        assert_eq!(window_reads.load(Ordering::Relaxed), 4);
+    }
+
+    #[test]
+    fn short_window_reads_fall_back_to_entry_reads() {
+        let entries: ScanSnapshot = vec![(0x1000, vec![0; 4]), (0x1004, vec![0; 4])];
+        let (values, hashes, _) = incremental_values(
+            &[(0x1000, 0x1008)],
+            &entries,
+            4,
+            &RegionHashes::new(),
+            0x1000,
+            |_, _| Some(vec![0u8; 4]),
+            |requests| {
+                requests
+                    .iter()
+                    .map(|_| Err("unreadable".to_string()))
+                    .collect()
+            },
+        );
+        assert!(hashes.is_empty());
+        assert_eq!(values, vec![EntryValue::Unreadable, EntryValue::Unreadable]);
+    }
+
+    #[test]
+    fn one_changed_byte_gives_one_diff() {
+        let memory = vec![0x5au8; DIFF_PAGE_SIZE * 3];
+        let start = memory.as_ptr() as u64;
+        let mut baseline = memory.clone();
+        baseline[DIFF_PAGE_SIZE + 7] = 0x11;
+
+        let diffs = diff_region(own_pid(), start, start + memory.len() as u64, &baseline).unwrap();
+        assert_eq!(diffs, vec![(start + DIFF_PAGE_SIZE as u64 + 7, 0x11, 0x5a)]);
+    }
+
+    #[test]
+    fn diff_reads_no_more_than_the_baseline() {
+        let memory = [1u8; 64];
+        let start = memory.as_ptr() as u64;
+        // The end is far past the buffer; only the baseline's 16 bytes are compared
+        let diffs = diff_region(own_pid(), start, u64::MAX, &[1u8; 16]).unwrap();
+        assert!(diffs.is_empty());
+        assert!(diff_region(own_pid(), start, u64::MAX, &vec![0u8; MAX_DIFF_LENGTH + 1]).is_err());
+    }
+
+    #[test]
+    fn diff_compares_only_the_overlap() {
+        let diffs = diff_bytes(0x1000, &[1, 2, 3, 4], &[1, 9]);
+        assert_eq!(diffs, vec![(0x1001, 2, 9)]);
+    }
+}
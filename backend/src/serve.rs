@@ -1,17 +1,62 @@
 use include_dir::{include_dir, Dir};
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv4Addr};
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 use warp::http::Response;
 use warp::path::Tail;
 use warp::Filter;
 
 use crate::api;
+use crate::auth;
+use crate::bookmarks;
+use crate::deadline;
+use crate::limit;
 use crate::logger;
 use crate::native_bridge;
 use crate::request;
-
-pub async fn serve(mode: i32, host: IpAddr, port: u16) {
+use crate::scan;
+use crate::settings::Settings;
+use crate::shutdown;
+use crate::util;
+
+pub const BIND_ENV_VAR: &str = "MEMSERVER_BIND";
+// Only the local machine can reach the server unless another address is asked for
+pub const DEFAULT_BIND_ADDRESS: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+pub async fn serve(mode: i32, settings: Settings) {
+    let Settings {
+        host,
+        port,
+        token: auth_token,
+        write_rate,
+        scan_threads,
+        bookmarks_file,
+        file_root,
+        allow_parent_paths,
+        read_retries,
+        scan_chunk_size,
+        read_timeout_ms,
+        suspend_timeout_ms,
+        breakpoint_timeout_ms,
+    } = settings;
+    deadline::set_timeout(deadline::OperationClass::Read, read_timeout_ms);
+    deadline::set_timeout(deadline::OperationClass::Suspend, suspend_timeout_ms);
+    deadline::set_timeout(deadline::OperationClass::Breakpoint, breakpoint_timeout_ms);
+    native_bridge::set_read_retries(read_retries);
+    scan::set_scan_chunk_size(scan_chunk_size);
+    bookmarks::load(bookmarks_file);
+    util::set_file_root(file_root);
+    util::set_allow_parent_paths(allow_parent_paths);
+    match scan::init_scan_pool(scan_threads) {
+        Ok(threads) => log::info!("Scan pool started with {} threads", threads),
+        Err(e) => log::error!("{}; scanning on the shared thread pool", e),
+    }
     let pid_state = Arc::new(Mutex::new(None));
+    // Shared by every endpoint that writes target memory, breakpoint traps included, or files.
+    // Watchpoints only change debug registers and unfreezing writes nothing, so neither is
+    // limited.
+    let write_limit =
+        limit::with_rate_limit(write_rate.map(|rate| Arc::new(limit::RateLimiter::new(rate))));
 
     let cors = warp::cors()
         .allow_any_origin()
@@ -32,6 +77,11 @@ pub async fn serve(mode: i32, host: IpAddr, port: u16) {
         .and(api::with_state(pid_state.clone()))
         .and_then(|pid_state| async move { api::enummodule_handler(pid_state).await });
 
+    let enum_threads = warp::path!("threads")
+        .and(warp::get())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|pid_state| async move { api::enumerate_threads_handler(pid_state).await });
+
     let open_process = warp::path!("process")
         .and(warp::post())
         .and(warp::body::json())
@@ -40,6 +90,30 @@ pub async fn serve(mode: i32, host: IpAddr, port: u16) {
             api::open_process_handler(pid_state, open_process).await
         });
 
+    let open_dump = warp::path!("coredump")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|open_request, pid_state| async move {
+            api::open_dump_handler(pid_state, open_request).await
+        });
+
+    let close_dump = warp::path!("coredump")
+        .and(warp::delete())
+        .and(warp::body::json())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|close_request, pid_state| async move {
+            api::close_dump_handler(pid_state, close_request).await
+        });
+
+    let attach_by_name = warp::path!("attachbyname")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|attach_request, pid_state| async move {
+            api::attach_by_name_handler(pid_state, attach_request).await
+        });
+
     let change_process_state = warp::path!("process")
         .and(warp::put())
         .and(warp::body::json())
@@ -57,14 +131,152 @@ pub async fn serve(mode: i32, host: IpAddr, port: u16) {
             api::read_memory_handler(pid_state, read_memory_request).await
         });
 
+    let view_memory = warp::path!("memoryview")
+        .and(warp::get())
+        .and(warp::query::<request::ViewMemoryRequest>())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|view_request, pid_state| async move {
+            api::view_memory_handler(pid_state, view_request).await
+        });
+
     let write_memory = warp::path!("memory")
         .and(warp::post())
         .and(warp::body::json())
+        // After the body is read, so a rejected request leaves the connection usable
+        .and(write_limit.clone())
         .and(api::with_state(pid_state.clone()))
         .and_then(|write_memory, pid_state| async move {
             api::write_memory_handler(pid_state, write_memory).await
         });
 
+    let fill_memory = warp::path!("fill")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(write_limit.clone())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|fill_request, pid_state| async move {
+            api::fill_memory_handler(pid_state, fill_request).await
+        });
+
+    let copy_memory = warp::path!("copy")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(write_limit.clone())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|copy_request, pid_state| async move {
+            api::copy_memory_handler(pid_state, copy_request).await
+        });
+
+    let read_value = warp::path!("value")
+        .and(warp::get())
+        .and(warp::query::<request::ReadValueRequest>())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|read_value_request, pid_state| async move {
+            api::read_value_handler(pid_state, read_value_request).await
+        });
+
+    let write_value = warp::path!("value")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(write_limit.clone())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|write_value_request, pid_state| async move {
+            api::write_value_handler(pid_state, write_value_request).await
+        });
+
+    let read_string = warp::path!("string")
+        .and(warp::get())
+        .and(warp::query::<request::ReadStringRequest>())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|read_string_request, pid_state| async move {
+            api::read_string_handler(pid_state, read_string_request).await
+        });
+
+    let write_string = warp::path!("string")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(write_limit.clone())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|write_string_request, pid_state| async move {
+            api::write_string_handler(pid_state, write_string_request).await
+        });
+
+    let freeze_add = warp::path!("freeze")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(write_limit.clone())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|freeze_request, pid_state| async move {
+            api::freeze_add_handler(pid_state, freeze_request).await
+        });
+
+    let freeze_remove = warp::path!("unfreeze")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|unfreeze_request, pid_state| async move {
+            api::freeze_remove_handler(pid_state, unfreeze_request).await
+        });
+
+    let freeze_list = warp::path!("freeze")
+        .and(warp::get())
+        .and_then(|| async move { api::freeze_list_handler().await });
+
+    let bookmark_add = warp::path!("bookmark")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and_then(
+            |bookmark_request| async move { api::bookmark_add_handler(bookmark_request).await },
+        );
+
+    let bookmark_remove = warp::path!("bookmark")
+        .and(warp::delete())
+        .and(warp::body::json())
+        .and_then(|bookmark_request| async move {
+            api::bookmark_remove_handler(bookmark_request).await
+        });
+
+    let bookmark_list = warp::path!("bookmarks")
+        .and(warp::get())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|pid_state| async move { api::bookmark_list_handler(pid_state).await });
+
+    let watch_register = warp::path!("watch")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|watch_request, pid_state| async move {
+            api::watch_register_handler(pid_state, watch_request).await
+        });
+
+    let watch_unregister = warp::path!("unwatch")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|unwatch_request, pid_state| async move {
+            api::watch_unregister_handler(pid_state, unwatch_request).await
+        });
+
+    let watch_poll = warp::path!("watch")
+        .and(warp::get())
+        .and_then(|| async move { api::watch_poll_handler().await });
+
+    let guess_type = warp::path!("guesstype")
+        .and(warp::get())
+        .and(warp::query::<request::GuessTypeRequest>())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|guess_request, pid_state| async move {
+            api::guess_type_handler(pid_state, guess_request).await
+        });
+
+    let dissect = warp::path!("dissect")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|dissect_request, pid_state| async move {
+            api::dissect_handler(pid_state, dissect_request).await
+        });
+
     let read_memory_multiple = warp::path!("memories")
         .and(warp::post())
         .and(warp::body::content_length_limit(1024 * 1024 * 10)) // 10MB
@@ -91,12 +303,153 @@ pub async fn serve(mode: i32, host: IpAddr, port: u16) {
             api::memory_filter_handler(pid_state, filter_request).await
         });
 
+    let scan = warp::path!("scan")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|scan_request, pid_state| async move {
+            api::scan_handler(pid_state, scan_request).await
+        });
+
+    let scan_progress = warp::path!("scanprogress")
+        .and(warp::get())
+        .and(warp::query::<request::ScanProgressRequest>())
+        .and_then(
+            |progress_request| async move { api::scan_progress_handler(progress_request).await },
+        );
+
+    let scan_cancel = warp::path!("scancancel")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and_then(|cancel_request| async move { api::scan_cancel_handler(cancel_request).await });
+
+    let scan_refine = warp::path!("scanrefine")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|refine_request, pid_state| async move {
+            api::scan_refine_handler(pid_state, refine_request).await
+        });
+
+    let hash_region = warp::path!("hashregion")
+        .and(warp::get())
+        .and(warp::query::<request::HashRegionRequest>())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|hash_request, pid_state| async move {
+            api::hash_region_handler(pid_state, hash_request).await
+        });
+
+    let diff_region = warp::path!("diff")
+        .and(warp::post())
+        .and(warp::body::content_length_limit(1024 * 1024 * 64)) // 64MB
+        .and(warp::body::json())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|diff_request, pid_state| async move {
+            api::diff_region_handler(pid_state, diff_request).await
+        });
+
+    let scan_stream = warp::path!("scanstream")
+        .and(warp::ws())
+        .and(api::with_state(pid_state.clone()))
+        .map(|ws: warp::ws::Ws, pid_state| {
+            ws.on_upgrade(move |socket| api::scan_stream_handler(pid_state, socket))
+        });
+
+    let first_scan = warp::path!("firstscan")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|first_scan_request, pid_state| async move {
+            api::first_scan_handler(pid_state, first_scan_request).await
+        });
+
+    let group_scan = warp::path!("groupscan")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|group_scan_request, pid_state| async move {
+            api::group_scan_handler(pid_state, group_scan_request).await
+        });
+
+    let session_new = warp::path!("session")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|session_request, pid_state| async move {
+            api::session_new_handler(pid_state, session_request).await
+        });
+
+    let session_refine = warp::path!("sessionrefine")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and_then(
+            |refine_request| async move { api::session_refine_handler(refine_request).await },
+        );
+
+    let session_get = warp::path!("session")
+        .and(warp::get())
+        .and(warp::query::<request::SessionGetRequest>())
+        .and_then(|get_request| async move { api::session_get_handler(get_request).await });
+
+    let session_close = warp::path!("sessionclose")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and_then(|close_request| async move { api::session_close_handler(close_request).await });
+
+    let scan_compare = warp::path!("scancompare")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|compare_request, pid_state| async move {
+            api::scan_compare_handler(pid_state, compare_request).await
+        });
+
+    let pointer_scan = warp::path!("pointerscan")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|pointer_scan_request, pid_state| async move {
+            api::pointer_scan_handler(pid_state, pointer_scan_request).await
+        });
+
+    let find_references = warp::path!("findreferences")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|find_request, pid_state| async move {
+            api::find_references_handler(pid_state, find_request).await
+        });
+
     let enum_regions = warp::path!("regions")
         .and(warp::get())
         .and(api::with_state(pid_state.clone()))
         .and_then(|pid_state| async move { api::enumerate_regions_handler(pid_state).await });
 
     // Debug Routes
+    let read_registers = warp::path!("registers")
+        .and(warp::get())
+        .and(warp::query::<request::ReadRegistersRequest>())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|registers_request, pid_state| async move {
+            api::read_registers_handler(pid_state, registers_request).await
+        });
+
+    let single_step = warp::path!("step")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|step_request, pid_state| async move {
+            api::single_step_handler(pid_state, step_request).await
+        });
+
+    let continue_thread = warp::path!("continue")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|continue_request, pid_state| async move {
+            api::continue_thread_handler(pid_state, continue_request).await
+        });
+
     let set_watchpoint = warp::path!("watchpoint")
         .and(warp::post())
         .and(warp::body::json())
@@ -113,9 +466,28 @@ pub async fn serve(mode: i32, host: IpAddr, port: u16) {
             api::remove_watchpoint_handler(pid_state, remove_watchpoint_request).await
         });
 
+    let access_start = warp::path!("accesswatch")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|access_request, pid_state| async move {
+            api::access_start_handler(pid_state, access_request).await
+        });
+
+    let access_report = warp::path!("accesswatch")
+        .and(warp::get())
+        .and(warp::query::<request::AccessReportRequest>())
+        .and_then(api::access_report_handler);
+
+    let access_stop = warp::path!("accesswatch")
+        .and(warp::delete())
+        .and(warp::body::json())
+        .and_then(api::access_stop_handler);
+
     let set_breakpoint = warp::path!("breakpoint")
         .and(warp::post())
         .and(warp::body::json())
+        .and(write_limit.clone())
         .and(api::with_state(pid_state.clone()))
         .and_then(|set_breakpoint_request, pid_state| async move {
             api::set_breakpoint_handler(pid_state, set_breakpoint_request).await
@@ -124,11 +496,16 @@ pub async fn serve(mode: i32, host: IpAddr, port: u16) {
     let remove_breakpoint = warp::path!("breakpoint")
         .and(warp::delete())
         .and(warp::body::json())
+        .and(write_limit.clone())
         .and(api::with_state(pid_state.clone()))
         .and_then(|remove_breakpoint_request, pid_state| async move {
             api::remove_breakpoint_handler(pid_state, remove_breakpoint_request).await
         });
 
+    let list_breakpoints = warp::path!("breakpoints")
+        .and(warp::get())
+        .and_then(api::list_breakpoints_handler);
+
     // Utility Routes
     let resolve_addr = warp::path!("resolveaddr")
         .and(warp::get())
@@ -138,6 +515,50 @@ pub async fn serve(mode: i32, host: IpAddr, port: u16) {
             api::resolve_addr_handler(pid_state, resolve_addr_request).await
         });
 
+    let symbolicate = warp::path!("symbol")
+        .and(warp::get())
+        .and(warp::query::<request::SymbolicateRequest>())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|symbolicate_request, pid_state| async move {
+            api::symbolicate_handler(pid_state, symbolicate_request).await
+        });
+
+    let module_exports = warp::path!("exports")
+        .and(warp::get())
+        .and(warp::query::<request::ModuleExportsRequest>())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|exports_request, pid_state| async move {
+            api::module_exports_handler(pid_state, exports_request).await
+        });
+
+    let disassemble = warp::path!("disassemble")
+        .and(warp::get())
+        .and(warp::query::<request::DisassembleRequest>())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|disassemble_request, pid_state| async move {
+            api::disassemble_handler(pid_state, disassemble_request).await
+        });
+
+    let disassemble_function = warp::path!("disassemblefunction")
+        .and(warp::get())
+        .and(warp::query::<request::DisassembleFunctionRequest>())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|function_request, pid_state| async move {
+            api::disassemble_function_handler(pid_state, function_request).await
+        });
+
+    let assemble = warp::path!("assemble")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and_then(|assemble_request| async move { api::assemble_handler(assemble_request).await });
+
+    let evaluate_expression = warp::path!("evaluate")
+        .and(warp::get())
+        .and(warp::query::<request::EvaluateExpressionRequest>())
+        .and_then(|evaluate_request| async move {
+            api::evaluate_expression_handler(evaluate_request).await
+        });
+
     let explore_directory = warp::path!("directory")
         .and(warp::get())
         .and(warp::query::<request::ExploreDirectoryRequest>())
@@ -145,6 +566,8 @@ pub async fn serve(mode: i32, host: IpAddr, port: u16) {
             api::explore_directory_handler(explore_directory_request).await
         });
 
+    // Paths are decoded and confined to the configured file root by the handlers, which refuse
+    // ".." unless the settings allow it
     let read_file = warp::path!("file")
         .and(warp::get())
         .and(warp::query::<request::ReadFileRequest>())
@@ -152,6 +575,34 @@ pub async fn serve(mode: i32, host: IpAddr, port: u16) {
             |read_file_request| async move { api::read_file_handler(read_file_request).await },
         );
 
+    let write_file = warp::path!("file")
+        .and(warp::post())
+        .and(warp::query::<request::WriteFileRequest>())
+        .and(warp::body::content_length_limit(
+            util::MAX_FILE_TRANSFER_SIZE,
+        ))
+        .and(warp::body::bytes())
+        // After the body is read, so a rejected upload leaves the connection usable
+        .and(write_limit.clone())
+        .and_then(|write_file_request, data| async move {
+            api::write_file_handler(write_file_request, data).await
+        });
+
+    let dump_regions = warp::path!("dump")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|dump_request, pid_state| async move {
+            api::dump_regions_handler(pid_state, dump_request).await
+        });
+
+    let dump_read = warp::path!("dump")
+        .and(warp::get())
+        .and(warp::query::<request::DumpReadRequest>())
+        .and_then(
+            |dump_read_request| async move { api::dump_read_handler(dump_read_request).await },
+        );
+
     // Info Routes
     let get_app_info = warp::path!("appinfo")
         .and(warp::get())
@@ -162,6 +613,22 @@ pub async fn serve(mode: i32, host: IpAddr, port: u16) {
         .and(warp::get())
         .and_then(api::server_info_handler);
 
+    let capabilities = warp::path!("capabilities")
+        .and(warp::get())
+        .and_then(api::capabilities_handler);
+
+    let started = Instant::now();
+    let health = warp::path!("health")
+        .and(warp::get())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(move |pid_state| async move { api::health_handler(pid_state, started).await });
+
+    let batch = warp::path!("batch")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(api::with_state(pid_state.clone()))
+        .and_then(|calls, pid_state| async move { api::batch_handler(pid_state, calls).await });
+
     let get_exception_info = warp::path!("exceptioninfo")
         .and(warp::get())
         .and_then(api::get_exception_info_handler);
@@ -177,38 +644,129 @@ pub async fn serve(mode: i32, host: IpAddr, port: u16) {
     // Group routes by functionality
     let process_routes = enum_process
         .or(enum_module)
+        .or(enum_threads)
         .or(open_process)
+        .or(attach_by_name)
+        .or(open_dump)
+        .or(close_dump)
         .or(change_process_state);
 
-    let memory_operation_routes = read_memory.or(write_memory).or(read_memory_multiple);
-
-    let memory_analysis_routes = memory_scan.or(memory_filter).or(enum_regions);
+    let memory_operation_routes = read_memory
+        .or(view_memory)
+        .or(write_memory)
+        .or(fill_memory)
+        .or(copy_memory)
+        .or(read_memory_multiple)
+        .or(read_value)
+        .or(write_value)
+        .or(read_string)
+        .or(write_string)
+        .or(dissect)
+        .or(guess_type)
+        .or(freeze_add)
+        .or(freeze_remove)
+        .or(freeze_list)
+        .or(bookmark_add)
+        .or(bookmark_remove)
+        .or(bookmark_list)
+        .or(watch_register)
+        .or(watch_unregister)
+        .or(watch_poll);
+
+    let memory_analysis_routes = memory_scan
+        .or(memory_filter)
+        .or(enum_regions)
+        .or(scan)
+        .or(scan_progress)
+        .or(scan_cancel)
+        .or(scan_refine)
+        .or(scan_compare)
+        .or(first_scan)
+        .or(scan_stream)
+        .or(group_scan)
+        .or(session_new)
+        .or(session_refine)
+        .or(session_get)
+        .or(session_close)
+        .or(diff_region)
+        .or(hash_region)
+        .or(pointer_scan)
+        .or(find_references)
+        .or(dump_regions)
+        .or(dump_read);
 
     let debug_routes = set_watchpoint
         .or(remove_watchpoint)
+        .or(access_start)
+        .or(access_report)
+        .or(access_stop)
         .or(set_breakpoint)
-        .or(remove_breakpoint);
-
-    let utility_routes = resolve_addr.or(explore_directory).or(read_file);
+        .or(remove_breakpoint)
+        .or(list_breakpoints)
+        .or(read_registers)
+        .or(single_step)
+        .or(continue_thread);
+
+    let utility_routes = resolve_addr
+        .or(symbolicate)
+        .or(module_exports)
+        .or(evaluate_expression)
+        .or(assemble)
+        .or(disassemble)
+        .or(disassemble_function)
+        .or(explore_directory)
+        .or(read_file)
+        .or(write_file)
+        .or(batch);
 
     let info_routes = get_app_info
         .or(server_info)
+        .or(capabilities)
+        .or(health)
         .or(get_exception_info)
         .or(pointermap_generate);
 
     // Combine all route groups
-    let routes = process_routes
-        .or(memory_operation_routes)
-        .or(memory_analysis_routes)
-        .or(debug_routes)
-        .or(utility_routes)
-        .or(info_routes)
+    // The bundled frontend stays reachable without a token, everything else needs one
+    auth::warn_if_open(&auth_token);
+    let api_routes = auth::with_auth(auth_token).and(
+        process_routes
+            .or(memory_operation_routes)
+            .or(memory_analysis_routes)
+            .or(debug_routes)
+            .or(utility_routes)
+            .or(info_routes),
+    );
+
+    let routes = api_routes
         .or(static_files)
+        .recover(auth::handle_rejection)
+        .recover(limit::handle_rejection)
         .with(cors)
         .with(warp::log::custom(logger::http_log));
 
-    native_bridge::native_api_init(mode);
-    warp::serve(routes).run((host, port)).await;
+    if host.is_loopback() {
+        log::info!("Binding to {}:{}", host, port);
+    } else {
+        log::warn!(
+            "Binding to {}:{}: the server is reachable from other machines",
+            host,
+            port
+        );
+    }
+    if !native_bridge::native_api_init(mode) {
+        log::error!("Native layer failed to initialize; /health reports the server unavailable");
+    }
+    // The standalone server owns its signals and cleans its targets up on the way out
+    if mode == 0 {
+        tokio::select! {
+            _ = warp::serve(routes).run((host, port)) => {}
+            _ = shutdown::signal() => shutdown::cleanup(),
+        }
+    } else {
+        // Embedded in the target, where SIGTERM and SIGINT belong to the host process
+        warp::serve(routes).run((host, port)).await;
+    }
 }
 
 static STATIC_DIR: Dir = include_dir!("../frontend/out");
@@ -0,0 +1,253 @@
+use crate::scan::{
+    self, Alignment, MemoryProtectionFilter, MemorySnapshot, RegionHashes, ScanCompare,
+    ScanControl, ScanSnapshot,
+};
+use crate::util::{self, ValueType};
+use lazy_static::lazy_static;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+pub type SessionId = u64;
+
+// The result set of a scan kept between requests so refining never ships the addresses
+// back and forth
+pub struct ScanSession {
+    pub pid: i32,
+    pub value_type: ValueType,
+    pub results: SessionResults,
+    // Hashes of the regions holding entries, taken by the last refine. The first refine after
+    // a scan has none to compare against and reads every entry.
+    pub region_hashes: RegionHashes,
+}
+
+// An unknown initial value scan starts from a whole-memory snapshot; the first refine turns
+// it into entries, each holding the bytes last read at its address
+pub enum SessionResults {
+    Snapshot(MemorySnapshot),
+    Entries(ScanSnapshot),
+}
+
+impl SessionResults {
+    fn len(&self) -> usize {
+        match self {
+            SessionResults::Snapshot(snapshot) => snapshot.position_count(),
+            SessionResults::Entries(entries) => entries.len(),
+        }
+    }
+
+    fn addresses(&self, offset: usize, limit: usize) -> Vec<u64> {
+        match self {
+            SessionResults::Snapshot(snapshot) => snapshot.positions_page(offset, limit),
+            SessionResults::Entries(entries) => entries
+                .iter()
+                .skip(offset)
+                .take(limit)
+                .map(|(address, _)| *address)
+                .collect(),
+        }
+    }
+}
+
+// A refine either keeps the addresses still holding an exact value or compares every
+// address with the value it had after the previous step
+pub enum SessionRefine {
+    Exact(Vec<u8>),
+    Compare(ScanCompare),
+}
+
+// Each session has its own lock, so a long refine only blocks requests for that session
+lazy_static! {
+    static ref SCAN_SESSIONS: Arc<Mutex<HashMap<SessionId, Arc<Mutex<ScanSession>>>>> =
+        Arc::new(Mutex::new(HashMap::new()));
+}
+
+static NEXT_SESSION_ID: AtomicU64 = AtomicU64::new(1);
+
+// The table lock is only held to look the session up. A session closed while f runs is
+// dropped once f is done with it.
+fn with_session<T>(
+    id: SessionId,
+    f: impl FnOnce(&mut ScanSession) -> Result<T, String>,
+) -> Result<T, String> {
+    let session = SCAN_SESSIONS
+        .lock()
+        .unwrap()
+        .get(&id)
+        .cloned()
+        .ok_or_else(|| format!("Scan session {} not found", id))?;
+    let mut session = session.lock().unwrap();
+    f(&mut session)
+}
+
+// Runs the first scan and returns the new session with its result count
+pub fn session_new(
+    pid: i32,
+    value_type: ValueType,
+    needle: &[u8],
+    filter: &MemoryProtectionFilter,
+) -> Result<(SessionId, usize), String> {
+    if needle.len() != value_type.size() {
+        return Err(format!(
+            "Value is {} bytes but {:?} needs {}",
+            needle.len(),
+            value_type,
+            value_type.size()
+        ));
+    }
+    let first_scan = scan::scan_first(
+        pid,
+        needle,
+        Some(value_type),
+        filter,
+        &ScanControl::default(),
+    )?;
+    let entries: ScanSnapshot = first_scan
+        .result
+        .matches
+        .into_iter()
+        .map(|address| (address, needle.to_vec()))
+        .collect();
+    Ok(insert_session(
+        pid,
+        value_type,
+        SessionResults::Entries(entries),
+    ))
+}
+
+// Starts an unknown initial value session by snapshotting every aligned position of the
+// regions passing the filter
+pub fn session_new_unknown(
+    pid: i32,
+    value_type: ValueType,
+    filter: &MemoryProtectionFilter,
+) -> Result<(SessionId, usize), String> {
+    let regions = scan::protected_regions(pid, filter)?;
+    let alignment = Alignment::Auto.resolve(value_type.size(), Some(value_type));
+    let snapshot = scan::scan_snapshot(pid, &regions, value_type, alignment);
+    Ok(insert_session(
+        pid,
+        value_type,
+        SessionResults::Snapshot(snapshot),
+    ))
+}
+
+fn insert_session(pid: i32, value_type: ValueType, results: SessionResults) -> (SessionId, usize) {
+    let count = results.len();
+    let id = NEXT_SESSION_ID.fetch_add(1, Ordering::Relaxed);
+    SCAN_SESSIONS.lock().unwrap().insert(
+        id,
+        Arc::new(Mutex::new(ScanSession {
+            pid,
+            value_type,
+            results,
+            region_hashes: RegionHashes::new(),
+        })),
+    );
+    (id, count)
+}
+
+// Narrows the session in place and returns how many addresses are left
+pub fn session_refine(id: SessionId, refine: SessionRefine) -> Result<usize, String> {
+    with_session(id, |session| {
+        let (entries, hashes) = match (&mut session.results, refine) {
+            (SessionResults::Snapshot(snapshot), SessionRefine::Exact(needle)) => (
+                scan::scan_refine_snapshot(session.pid, snapshot, &needle),
+                RegionHashes::new(),
+            ),
+            (SessionResults::Snapshot(snapshot), SessionRefine::Compare(cmp)) => (
+                scan::scan_compare_snapshot(session.pid, snapshot, cmp),
+                RegionHashes::new(),
+            ),
+            (SessionResults::Entries(entries), SessionRefine::Exact(needle)) => {
+                scan::scan_refine_incremental(
+                    session.pid,
+                    std::mem::take(entries),
+                    &needle,
+                    &session.region_hashes,
+                )
+            }
+            (SessionResults::Entries(entries), SessionRefine::Compare(cmp)) => {
+                scan::scan_compare_incremental(
+                    session.pid,
+                    std::mem::take(entries),
+                    session.value_type,
+                    cmp,
+                    &session.region_hashes,
+                )
+            }
+        };
+        session.results = SessionResults::Entries(entries);
+        session.region_hashes = hashes;
+        Ok(session.results.len())
+    })
+}
+
+// One window of the results with their values read now using the session's type, together
+// with the total count; an offset past the end gives an empty page and an address that has
+// become unreadable gets a null value
+pub fn session_get_page(
+    id: SessionId,
+    offset: usize,
+    limit: usize,
+) -> Result<(Vec<(u64, Value)>, usize), String> {
+    // Copy the window out so the reads below never hold the session lock
+    let (pid, value_type, addresses, total) = with_session(id, |session| {
+        Ok((
+            session.pid,
+            session.value_type,
+            session.results.addresses(offset, limit),
+            session.results.len(),
+        ))
+    })?;
+    let page = addresses
+        .into_iter()
+        .map(|address| {
+            let value = util::read_value(pid, address, value_type).unwrap_or(Value::Null);
+            (address, value)
+        })
+        .collect();
+    Ok((page, total))
+}
+
+pub fn session_close(id: SessionId) -> bool {
+    SCAN_SESSIONS.lock().unwrap().remove(&id).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn a_busy_session_does_not_block_the_others() {
+        let (busy, _) = insert_session(42, ValueType::U32, SessionResults::Entries(Vec::new()));
+        let (other, _) = insert_session(
+            42,
+            ValueType::U32,
+            SessionResults::Entries(vec![(0x1000, vec![1, 0, 0, 0])]),
+        );
+
+        // Stands in for a long refine of the busy session
+        let (entered_tx, entered) = mpsc::channel();
+        let (release, release_rx) = mpsc::channel::<()>();
+        let refine = std::thread::spawn(move || {
+            with_session(busy, |_| {
+                entered_tx.send(()).unwrap();
+                release_rx.recv().unwrap();
+                Ok(())
+            })
+        });
+        entered.recv().unwrap();
+
+        let count = with_session(other, |session| Ok(session.results.len())).unwrap();
+        assert_eq!(count, 1);
+        assert!(session_close(other));
+        // Closing the busy session leaves the refine running on its own reference
+        assert!(session_close(busy));
+        release.send(()).unwrap();
+        refine.join().unwrap().unwrap();
+        assert!(with_session(busy, |_| Ok(())).is_err());
+    }
+}
@@ -0,0 +1,375 @@
+use crate::auth;
+use crate::deadline;
+use crate::limit;
+use crate::native_bridge;
+use crate::scan;
+use crate::serve;
+use serde::Deserialize;
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+pub const CONFIG_ENV_VAR: &str = "MEMSERVER_CONFIG";
+pub const PORT_ENV_VAR: &str = "MEMSERVER_PORT";
+pub const SCAN_THREADS_ENV_VAR: &str = "MEMSERVER_SCAN_THREADS";
+pub const BOOKMARKS_ENV_VAR: &str = "MEMSERVER_BOOKMARKS";
+pub const FILE_ROOT_ENV_VAR: &str = "MEMSERVER_FILE_ROOT";
+pub const ALLOW_PARENT_PATHS_ENV_VAR: &str = "MEMSERVER_ALLOW_PARENT_PATHS";
+pub const READ_RETRIES_ENV_VAR: &str = "MEMSERVER_READ_RETRIES";
+pub const SCAN_CHUNK_SIZE_ENV_VAR: &str = "MEMSERVER_SCAN_CHUNK_SIZE";
+pub const READ_TIMEOUT_ENV_VAR: &str = "MEMSERVER_READ_TIMEOUT_MS";
+pub const SUSPEND_TIMEOUT_ENV_VAR: &str = "MEMSERVER_SUSPEND_TIMEOUT_MS";
+pub const BREAKPOINT_TIMEOUT_ENV_VAR: &str = "MEMSERVER_BREAKPOINT_TIMEOUT_MS";
+pub const DEFAULT_PORT: u16 = 3030;
+
+// Everything the server needs at startup. Each source overrides the one before it: the
+// defaults, the JSON config file, the environment, then the command line.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub host: IpAddr,
+    pub port: u16,
+    // Required as a bearer token on API requests; None leaves the API open
+    pub token: Option<String>,
+    // Memory writes per second per client address; None turns the limit off
+    pub write_rate: Option<f64>,
+    // Threads in the scan pool; None gives one per physical core
+    pub scan_threads: Option<usize>,
+    // Where bookmarks are kept between runs
+    pub bookmarks_file: PathBuf,
+    // File requests may only reach paths inside this directory, kept canonical. The default of
+    // / confines nothing by itself, which is why ".." stays refused unless allow_parent_paths.
+    pub file_root: PathBuf,
+    // Whether a file request's path may contain ".."; it still has to stay inside file_root
+    pub allow_parent_paths: bool,
+    // Extra attempts for a target read that fails transiently
+    pub read_retries: u32,
+    // Bytes of a region a scan reads at a time
+    pub scan_chunk_size: usize,
+    // How long a request waits on each class of blocking native call; 0 waits forever
+    pub read_timeout_ms: u64,
+    pub suspend_timeout_ms: u64,
+    pub breakpoint_timeout_ms: u64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            host: serve::DEFAULT_BIND_ADDRESS,
+            port: DEFAULT_PORT,
+            token: None,
+            write_rate: Some(limit::DEFAULT_WRITE_RATE),
+            scan_threads: None,
+            bookmarks_file: default_bookmarks_file(),
+            file_root: PathBuf::from("/"),
+            allow_parent_paths: false,
+            read_retries: native_bridge::DEFAULT_READ_RETRIES,
+            scan_chunk_size: scan::DEFAULT_SCAN_CHUNK_SIZE,
+            read_timeout_ms: deadline::DEFAULT_READ_TIMEOUT_MS,
+            suspend_timeout_ms: deadline::DEFAULT_SUSPEND_TIMEOUT_MS,
+            breakpoint_timeout_ms: deadline::DEFAULT_BREAKPOINT_TIMEOUT_MS,
+        }
+    }
+}
+
+// Bookmarks should survive a reboot, so they live in the user's data directory: %APPDATA% on
+// Windows, otherwise $XDG_DATA_HOME or ~/.local/share. Without any of those, as in a process the
+// library was injected into, they fall back to the temp directory.
+fn default_bookmarks_file() -> PathBuf {
+    let var = |name: &str| std::env::var_os(name).filter(|value| !value.is_empty());
+    let data_dir = if cfg!(windows) {
+        var("APPDATA").map(PathBuf::from)
+    } else {
+        var("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| var("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+    };
+    match data_dir {
+        Some(dir) => dir.join("memory-server").join("bookmarks.json"),
+        None => std::env::temp_dir().join("memory-server-bookmarks.json"),
+    }
+}
+
+// One source of settings, where a missing field keeps the value from the sources before it.
+// A config file is this object as JSON, e.g. {"host": "0.0.0.0", "port": 3030}.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SettingsLayer {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub token: Option<String>,
+    // 0 turns the limit off
+    pub write_rate: Option<f64>,
+    pub scan_threads: Option<usize>,
+    pub bookmarks_file: Option<String>,
+    pub file_root: Option<String>,
+    pub allow_parent_paths: Option<bool>,
+    pub read_retries: Option<u32>,
+    pub scan_chunk_size: Option<usize>,
+    pub read_timeout_ms: Option<u64>,
+    pub suspend_timeout_ms: Option<u64>,
+    pub breakpoint_timeout_ms: Option<u64>,
+}
+
+impl SettingsLayer {
+    // For the environment and the command line, where every value arrives as text. value
+    // looks a setting up by its field name, e.g. "write_rate".
+    pub fn from_strings(
+        value: impl Fn(&str) -> Option<String>,
+        source: &str,
+    ) -> Result<SettingsLayer, String> {
+        fn parse<T: std::str::FromStr>(
+            value: Option<String>,
+            what: &str,
+            source: &str,
+        ) -> Result<Option<T>, String> {
+            value
+                .map(|value| {
+                    value
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("Invalid {} '{}' in {}", what, value, source))
+                })
+                .transpose()
+        }
+        Ok(SettingsLayer {
+            host: value("host"),
+            port: parse(value("port"), "port", source)?,
+            token: value("token"),
+            write_rate: parse(value("write_rate"), "write rate", source)?,
+            scan_threads: parse(value("scan_threads"), "scan thread count", source)?,
+            bookmarks_file: value("bookmarks_file"),
+            file_root: value("file_root"),
+            allow_parent_paths: parse(value("allow_parent_paths"), "parent path setting", source)?,
+            read_retries: parse(value("read_retries"), "read retry count", source)?,
+            scan_chunk_size: parse(value("scan_chunk_size"), "scan chunk size", source)?,
+            read_timeout_ms: parse(value("read_timeout_ms"), "read timeout", source)?,
+            suspend_timeout_ms: parse(value("suspend_timeout_ms"), "suspend timeout", source)?,
+            breakpoint_timeout_ms: parse(
+                value("breakpoint_timeout_ms"),
+                "breakpoint timeout",
+                source,
+            )?,
+        })
+    }
+}
+
+impl Settings {
+    fn apply(&mut self, layer: SettingsLayer, source: &str) -> Result<(), String> {
+        if let Some(host) = layer.host {
+            self.host = host
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid bind address '{}' in {}", host, source))?;
+        }
+        if let Some(port) = layer.port {
+            if port == 0 {
+                return Err(format!("Invalid port 0 in {}", source));
+            }
+            self.port = port;
+        }
+        // An empty token counts as unset rather than as a token nobody can send
+        if let Some(token) = layer.token.filter(|token| !token.is_empty()) {
+            self.token = Some(token);
+        }
+        if let Some(rate) = layer.write_rate {
+            self.write_rate =
+                limit::validate_write_rate(rate).map_err(|e| format!("{} in {}", e, source))?;
+        }
+        if let Some(threads) = layer.scan_threads {
+            if threads == 0 {
+                return Err(format!("Invalid scan thread count 0 in {}", source));
+            }
+            self.scan_threads = Some(threads);
+        }
+        if let Some(path) = layer.bookmarks_file {
+            if path.is_empty() {
+                return Err(format!("Empty bookmarks file path in {}", source));
+            }
+            self.bookmarks_file = PathBuf::from(path);
+        }
+        if let Some(root) = layer.file_root {
+            // Canonical so that resolved request paths can be compared against it
+            self.file_root = std::fs::canonicalize(&root)
+                .map_err(|e| format!("Invalid file root '{}' in {}: {}", root, source, e))?;
+        }
+        if let Some(allow) = layer.allow_parent_paths {
+            self.allow_parent_paths = allow;
+        }
+        if let Some(retries) = layer.read_retries {
+            if retries > native_bridge::MAX_READ_RETRIES {
+                return Err(format!(
+                    "Invalid read retry count {} in {}: at most {} are allowed",
+                    retries,
+                    source,
+                    native_bridge::MAX_READ_RETRIES
+                ));
+            }
+            self.read_retries = retries;
+        }
+        if let Some(size) = layer.scan_chunk_size {
+            if size < scan::MIN_SCAN_CHUNK_SIZE {
+                return Err(format!(
+                    "Invalid scan chunk size {} in {}: at least {} bytes are needed",
+                    size,
+                    source,
+                    scan::MIN_SCAN_CHUNK_SIZE
+                ));
+            }
+            self.scan_chunk_size = size;
+        }
+        if let Some(millis) = layer.read_timeout_ms {
+            self.read_timeout_ms = millis;
+        }
+        if let Some(millis) = layer.suspend_timeout_ms {
+            self.suspend_timeout_ms = millis;
+        }
+        if let Some(millis) = layer.breakpoint_timeout_ms {
+            self.breakpoint_timeout_ms = millis;
+        }
+        Ok(())
+    }
+}
+
+fn file_layer(path: &str) -> Result<SettingsLayer, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read config file {}: {}", path, e))?;
+    serde_json::from_str(&contents).map_err(|e| format!("Invalid config file {}: {}", path, e))
+}
+
+// The environment variable each setting is read from
+fn env_var(name: &str) -> Option<&'static str> {
+    let var = match name {
+        "host" => serve::BIND_ENV_VAR,
+        "port" => PORT_ENV_VAR,
+        "token" => auth::TOKEN_ENV_VAR,
+        "write_rate" => limit::WRITE_RATE_ENV_VAR,
+        "scan_threads" => SCAN_THREADS_ENV_VAR,
+        "bookmarks_file" => BOOKMARKS_ENV_VAR,
+        "file_root" => FILE_ROOT_ENV_VAR,
+        "allow_parent_paths" => ALLOW_PARENT_PATHS_ENV_VAR,
+        "read_retries" => READ_RETRIES_ENV_VAR,
+        "scan_chunk_size" => SCAN_CHUNK_SIZE_ENV_VAR,
+        "read_timeout_ms" => READ_TIMEOUT_ENV_VAR,
+        "suspend_timeout_ms" => SUSPEND_TIMEOUT_ENV_VAR,
+        "breakpoint_timeout_ms" => BREAKPOINT_TIMEOUT_ENV_VAR,
+        _ => return None,
+    };
+    Some(var)
+}
+
+fn env_layer() -> Result<SettingsLayer, String> {
+    SettingsLayer::from_strings(
+        |name| env_var(name).and_then(|var| std::env::var(var).ok()),
+        "the environment",
+    )
+}
+
+// The config file comes from config_path, or MEMSERVER_CONFIG when no path is given; without
+// either only the environment and command line override the defaults
+pub fn load(config_path: Option<&str>, command_line: SettingsLayer) -> Result<Settings, String> {
+    let mut settings = Settings::default();
+    let config_path = config_path
+        .map(str::to_string)
+        .or_else(|| std::env::var(CONFIG_ENV_VAR).ok())
+        .filter(|path| !path.is_empty());
+    if let Some(path) = config_path {
+        settings.apply(file_layer(&path)?, &path)?;
+    }
+    settings.apply(env_layer()?, "the environment")?;
+    settings.apply(command_line, "the command line")?;
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, TcpListener};
+
+    fn command_line(host: &str) -> SettingsLayer {
+        SettingsLayer {
+            host: Some(host.to_string()),
+            ..SettingsLayer::default()
+        }
+    }
+
+    #[test]
+    fn default_listener_is_loopback_only() {
+        let settings = Settings::default();
+        let listener = TcpListener::bind((settings.host, 0)).unwrap();
+        let address = listener.local_addr().unwrap();
+        assert!(address.ip().is_loopback(), "{}", address);
+        assert_eq!(address.ip(), IpAddr::from(Ipv4Addr::LOCALHOST));
+    }
+
+    #[test]
+    fn all_interfaces_only_when_asked_for() {
+        let mut settings = Settings::default();
+        settings
+            .apply(SettingsLayer::default(), "the command line")
+            .unwrap();
+        assert!(settings.host.is_loopback());
+
+        settings
+            .apply(command_line(" 0.0.0.0 "), "the command line")
+            .unwrap();
+        assert_eq!(settings.host, IpAddr::from(Ipv4Addr::UNSPECIFIED));
+
+        let error = settings
+            .apply(command_line("localhost:80"), "the command line")
+            .unwrap_err();
+        assert_eq!(
+            error,
+            "Invalid bind address 'localhost:80' in the command line"
+        );
+    }
+
+    #[test]
+    fn every_variable_shares_the_prefix() {
+        let names = [
+            "host",
+            "port",
+            "token",
+            "write_rate",
+            "scan_threads",
+            "bookmarks_file",
+            "file_root",
+            "allow_parent_paths",
+            "read_retries",
+            "scan_chunk_size",
+            "read_timeout_ms",
+            "suspend_timeout_ms",
+            "breakpoint_timeout_ms",
+        ];
+        for name in names {
+            let var = env_var(name).unwrap();
+            assert!(
+                var.starts_with("MEMSERVER_"),
+                "{} is read from {}",
+                name,
+                var
+            );
+        }
+        assert_eq!(auth::TOKEN_ENV_VAR, "MEMSERVER_TOKEN");
+    }
+
+    #[test]
+    fn parent_paths_are_refused_until_enabled() {
+        let mut settings = Settings::default();
+        assert!(!settings.allow_parent_paths);
+
+        let layer = |value: &str| {
+            let value = value.to_string();
+            SettingsLayer::from_strings(
+                |name| (name == "allow_parent_paths").then(|| value.clone()),
+                "the command line",
+            )
+        };
+        settings
+            .apply(layer("true").unwrap(), "the command line")
+            .unwrap();
+        assert!(settings.allow_parent_paths);
+        assert_eq!(
+            layer("yes").unwrap_err(),
+            "Invalid parent path setting 'yes' in the command line"
+        );
+    }
+}
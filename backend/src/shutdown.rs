@@ -0,0 +1,177 @@
+use crate::breakpoint;
+use crate::native_bridge;
+use lazy_static::lazy_static;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+// What the server has changed in target processes and has to undo before it exits. Software
+// breakpoints are not listed here since the breakpoint table already holds their bytes.
+#[derive(Default)]
+struct TargetState {
+    // Processes suspended on request rather than for the length of one scan
+    suspended: HashSet<i32>,
+    watchpoints: HashSet<usize>,
+    // Registered with the native debugger directly, as the Darwin hardware breakpoints are
+    native_breakpoints: HashSet<usize>,
+}
+
+lazy_static! {
+    static ref TARGET_STATE: Arc<Mutex<TargetState>> = Arc::new(Mutex::new(TargetState::default()));
+}
+
+pub fn track_suspended(pid: i32, suspended: bool) {
+    let mut state = TARGET_STATE.lock().unwrap();
+    if suspended {
+        state.suspended.insert(pid);
+    } else {
+        state.suspended.remove(&pid);
+    }
+}
+
+pub fn track_watchpoint(address: usize, set: bool) {
+    let mut state = TARGET_STATE.lock().unwrap();
+    if set {
+        state.watchpoints.insert(address);
+    } else {
+        state.watchpoints.remove(&address);
+    }
+}
+
+pub fn track_native_breakpoint(address: usize, set: bool) {
+    let mut state = TARGET_STATE.lock().unwrap();
+    if set {
+        state.native_breakpoints.insert(address);
+    } else {
+        state.native_breakpoints.remove(&address);
+    }
+}
+
+// Resolves on the first SIGTERM or SIGINT
+pub async fn signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let (Ok(mut terminate), Ok(mut interrupt)) = (
+            signal(SignalKind::terminate()),
+            signal(SignalKind::interrupt()),
+        ) else {
+            log::warn!("Failed to install signal handlers; targets are not cleaned up on exit");
+            return std::future::pending().await;
+        };
+        tokio::select! {
+            _ = terminate.recv() => log::info!("Received SIGTERM, shutting down"),
+            _ = interrupt.recv() => log::info!("Received SIGINT, shutting down"),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            log::info!("Received Ctrl-C, shutting down");
+        }
+    }
+}
+
+// Leaves every target running as it was before the server touched it
+pub fn cleanup() {
+    let breakpoints = breakpoint::breakpoint_list();
+    let state = std::mem::take(&mut *TARGET_STATE.lock().unwrap());
+    restore_targets(breakpoints, state, native_bridge::detach_debugger);
+}
+
+// The trap bytes go back while the debugger still holds the process, since writes go through
+// it then, and detach runs once nothing needs the debugger. Suspended processes are resumed
+// last so none of them runs into a trap or watchpoint that is about to be removed.
+fn restore_targets(
+    breakpoints: Vec<breakpoint::Breakpoint>,
+    state: TargetState,
+    detach: impl FnOnce(),
+) {
+    for patched in breakpoints {
+        match breakpoint::breakpoint_remove(patched.pid, patched.address) {
+            Ok(()) => log::info!(
+                "Restored breakpoint at {:#x} in pid {}",
+                patched.address,
+                patched.pid
+            ),
+            Err(e) => log::error!(
+                "Failed to restore breakpoint at {:#x} in pid {}: {}",
+                patched.address,
+                patched.pid,
+                e
+            ),
+        }
+    }
+
+    let TargetState {
+        suspended,
+        watchpoints,
+        native_breakpoints,
+    } = state;
+    for address in native_breakpoints {
+        if let Err(e) = native_bridge::remove_breakpoint(address) {
+            log::warn!("Failed to remove breakpoint at {:#x}: {}", address, e);
+        }
+    }
+    for address in watchpoints {
+        if let Err(e) = native_bridge::remove_watchpoint(address) {
+            log::warn!("Failed to remove watchpoint at {:#x}: {}", address, e);
+        }
+    }
+    detach();
+
+    for pid in suspended {
+        if unsafe { native_bridge::resume_process(pid) } {
+            log::info!("Resumed pid {}", pid);
+        } else {
+            log::error!("Failed to resume pid {}", pid);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registered_breakpoints_are_restored() {
+        let pid = unsafe { native_bridge::get_pid_native() };
+        let trap = breakpoint::trap_instruction(crate::util::Arch::default());
+        let mut code = vec![0x90u8; 64];
+        let original = vec![0x55u8; trap.len()];
+        let addresses: Vec<u64> = [8, 32]
+            .iter()
+            .map(|&offset| {
+                code[offset..offset + trap.len()].copy_from_slice(trap);
+                code.as_ptr() as u64 + offset as u64
+            })
+            .collect();
+        let breakpoints: Vec<breakpoint::Breakpoint> = addresses
+            .iter()
+            .map(|&address| breakpoint::Breakpoint {
+                pid,
+                address,
+                original: original.clone(),
+                halt: false,
+            })
+            .collect();
+        for patched in &breakpoints {
+            breakpoint::register_patched(patched.clone());
+        }
+
+        // Only this test's breakpoints and an empty state of its own, so breakpoint and
+        // debugger tests running alongside keep theirs
+        let mut detached = 0;
+        restore_targets(breakpoints, TargetState::default(), || detached += 1);
+        assert_eq!(detached, 1);
+        for &address in &addresses {
+            let offset = (address - code.as_ptr() as u64) as usize;
+            assert_eq!(
+                std::hint::black_box(&code)[offset..offset + trap.len()],
+                original[..]
+            );
+        }
+        assert!(breakpoint::breakpoint_list()
+            .iter()
+            .all(|patched| !addresses.contains(&patched.address)));
+    }
+}
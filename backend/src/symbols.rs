@@ -0,0 +1,607 @@
+use crate::region;
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Export {
+    pub name: String,
+    // Relative to the module base
+    pub offset: u64,
+    pub size: u64,
+}
+
+// Exports sorted by offset, aliases at the same offset ordered with the preferred name first
+pub struct ExportTable {
+    exports: Vec<Export>,
+    by_name: HashMap<String, usize>,
+}
+
+impl ExportTable {
+    fn new(mut exports: Vec<Export>) -> ExportTable {
+        // Aliases share an offset; prefer the public name, e.g. malloc over __libc_malloc
+        exports.sort_by_key(|export| {
+            let underscores = export.name.len() - export.name.trim_start_matches('_').len();
+            (export.offset, underscores, export.name.len())
+        });
+        let by_name = exports
+            .iter()
+            .enumerate()
+            .map(|(index, export)| (export.name.clone(), index))
+            .collect();
+        ExportTable { exports, by_name }
+    }
+
+    pub fn exports(&self) -> &[Export] {
+        &self.exports
+    }
+
+    pub fn find(&self, name: &str) -> Option<&Export> {
+        self.by_name.get(name).map(|&index| &self.exports[index])
+    }
+
+    // The export covering offset, or the nearest one before it when its size is unknown
+    fn covering(&self, offset: u64) -> Option<&Export> {
+        let last = self
+            .exports
+            .partition_point(|export| export.offset <= offset)
+            .checked_sub(1)?;
+        let start = self.exports[last].offset;
+        let export = &self.exports[self.exports.partition_point(|export| export.offset < start)];
+        (export.size == 0 || offset - start < export.size).then_some(export)
+    }
+}
+
+lazy_static! {
+    // Export tables by module path. A module that fails to parse is cached with an empty
+    // table so it is not read again on every lookup.
+    static ref EXPORT_CACHE: Arc<Mutex<HashMap<String, Arc<ExportTable>>>> =
+        Arc::new(Mutex::new(HashMap::new()));
+}
+
+const SHT_DYNSYM: u32 = 11;
+const PT_LOAD: u32 = 1;
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+const STT_GNU_IFUNC: u8 = 10;
+const EM_ARM: u16 = 40;
+// Symbol and string tables run to a few MB even in the largest libraries
+const MAX_ELF_READ: u64 = 64 * 1024 * 1024;
+
+// Names an address as "module!symbol+0xNN", or "module+0xNN" when no export covers it.
+// None when the address is not inside any of the modules.
+pub fn symbolicate(pid: i32, address: u64, modules: &[serde_json::Value]) -> Option<String> {
+    let regions = sorted_regions(pid)?;
+    name_address(pid, &regions, address, modules)
+}
+
+// symbolicate for many addresses, reading the target's mappings once
+pub fn symbolicate_all(
+    pid: i32,
+    addresses: &[u64],
+    modules: &[serde_json::Value],
+) -> Vec<Option<String>> {
+    let regions = sorted_regions(pid).unwrap_or_default();
+    addresses
+        .iter()
+        .map(|&address| name_address(pid, &regions, address, modules))
+        .collect()
+}
+
+fn name_address(
+    pid: i32,
+    regions: &[region::MemoryRegion],
+    address: u64,
+    modules: &[serde_json::Value],
+) -> Option<String> {
+    let (path, base) = containing_module(regions, address, modules)?;
+    let module_name = file_name(&path);
+    let offset = address - base;
+
+    Some(match module_exports(pid, &path).covering(offset) {
+        Some(export) if export.offset == offset => format!("{}!{}", module_name, export.name),
+        Some(export) => format!(
+            "{}!{}+{:#x}",
+            module_name,
+            export.name,
+            offset - export.offset
+        ),
+        None => format!("{}+{:#x}", module_name, offset),
+    })
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string())
+}
+
+// An address as an offset into the module containing it, which stays the same between runs
+// of the target while the module base moves
+#[derive(Debug, Clone, Serialize)]
+pub struct ModuleOffset {
+    pub module: String,
+    pub offset: u64,
+}
+
+impl ModuleOffset {
+    // In the "module+0xNN" form resolve_addr accepts
+    pub fn expression(&self) -> String {
+        format!("{}+{:#x}", self.module, self.offset)
+    }
+}
+
+// The module offset of each address, None for addresses outside every module. The target's
+// mappings are read once for the whole list.
+pub fn module_offsets(
+    pid: i32,
+    addresses: &[u64],
+    modules: &[serde_json::Value],
+) -> Vec<Option<ModuleOffset>> {
+    module_offsets_in(&sorted_regions(pid).unwrap_or_default(), addresses, modules)
+}
+
+fn module_offsets_in(
+    regions: &[region::MemoryRegion],
+    addresses: &[u64],
+    modules: &[serde_json::Value],
+) -> Vec<Option<ModuleOffset>> {
+    addresses
+        .iter()
+        .map(|&address| {
+            let (path, base) = containing_module(regions, address, modules)?;
+            Some(ModuleOffset {
+                module: file_name(&path),
+                offset: address - base,
+            })
+        })
+        .collect()
+}
+
+fn sorted_regions(pid: i32) -> Option<Vec<region::MemoryRegion>> {
+    let mut regions = region::enumerate_regions(pid).ok()?;
+    regions.sort_by_key(|region| region.start);
+    Some(regions)
+}
+
+// Module sizes only cover the mapping holding the ELF header, so the module is the one with
+// the highest base below the address that maps the same file as the address's region
+fn containing_module(
+    regions: &[region::MemoryRegion],
+    address: u64,
+    modules: &[serde_json::Value],
+) -> Option<(String, u64)> {
+    let mapped_path = regions[..regions.partition_point(|region| region.start <= address)]
+        .last()
+        .filter(|region| address < region.end)
+        .and_then(|region| region.path.clone());
+
+    modules
+        .iter()
+        .filter_map(|module| {
+            Some((
+                module["modulename"].as_str()?,
+                module["base"].as_u64()?,
+                module["size"].as_u64().unwrap_or(0),
+            ))
+        })
+        .filter(|&(name, base, size)| {
+            base <= address
+                && match &mapped_path {
+                    Some(path) => path == name,
+                    None => address - base < size,
+                }
+        })
+        .max_by_key(|&(_, base, _)| base)
+        .map(|(name, base, _)| (name.to_string(), base))
+}
+
+// A module's exports as absolute addresses, from its enumerate_modules base and path
+pub fn module_symbols(pid: i32, base: u64, path: &str) -> Vec<(String, u64)> {
+    module_exports(pid, path)
+        .exports()
+        .iter()
+        .map(|export| (export.name.clone(), base.wrapping_add(export.offset)))
+        .collect()
+}
+
+pub fn module_exports(pid: i32, path: &str) -> Arc<ExportTable> {
+    if let Some(table) = EXPORT_CACHE.lock().unwrap().get(path) {
+        return table.clone();
+    }
+    // Read through the target's root so modules in another mount namespace resolve
+    let rooted = format!("/proc/{}/root{}", pid, path);
+    let file_path = if Path::new(&rooted).exists() {
+        rooted
+    } else {
+        path.to_string()
+    };
+    let exports = parse_elf_exports(Path::new(&file_path)).unwrap_or_else(|e| {
+        log::debug!("No exports for {}: {}", path, e);
+        Vec::new()
+    });
+    let table = Arc::new(ExportTable::new(exports));
+    EXPORT_CACHE
+        .lock()
+        .unwrap()
+        .insert(path.to_string(), table.clone());
+    table
+}
+
+// Exports for path as if they had been parsed from its file
+#[cfg(test)]
+pub(crate) fn cache_exports(path: &str, exports: Vec<Export>) {
+    EXPORT_CACHE
+        .lock()
+        .unwrap()
+        .insert(path.to_string(), Arc::new(ExportTable::new(exports)));
+}
+
+// Field access for the four ELF flavours; offsets differ between the 32 and 64-bit layouts
+pub struct ElfReader {
+    pub file: File,
+    pub is_64bit: bool,
+    pub big_endian: bool,
+}
+
+impl ElfReader {
+    // Offsets and sizes come from the file itself, so they are checked against its length
+    // before anything is allocated for them
+    pub fn read_at(&mut self, offset: u64, size: usize) -> Result<Vec<u8>, String> {
+        let file_len = self
+            .file
+            .metadata()
+            .map_err(|e| format!("Failed to read the file size: {}", e))?
+            .len();
+        if size as u64 > MAX_ELF_READ
+            || offset
+                .checked_add(size as u64)
+                .is_none_or(|end| end > file_len)
+        {
+            return Err(format!(
+                "Corrupt ELF: {} bytes at {:#x} do not fit in the {} byte file",
+                size, offset, file_len
+            ));
+        }
+        let mut buffer = vec![0u8; size];
+        self.file
+            .seek(SeekFrom::Start(offset))
+            .and_then(|_| self.file.read_exact(&mut buffer))
+            .map_err(|e| format!("Failed to read {} bytes at {:#x}: {}", size, offset, e))?;
+        Ok(buffer)
+    }
+
+    // Reads count entries at offset, rejecting entries too small for the fields read later
+    pub fn read_table(
+        &mut self,
+        offset: u64,
+        entry_size: usize,
+        count: usize,
+        min_entry_size: usize,
+    ) -> Result<Vec<u8>, String> {
+        if entry_size < min_entry_size {
+            return Err(format!("Corrupt ELF table entry size {}", entry_size));
+        }
+        let size = entry_size
+            .checked_mul(count)
+            .ok_or_else(|| format!("Corrupt ELF table of {} entries", count))?;
+        self.read_at(offset, size)
+    }
+
+    pub fn u16(&self, bytes: &[u8], offset: usize) -> u16 {
+        if self.big_endian {
+            BigEndian::read_u16(&bytes[offset..])
+        } else {
+            LittleEndian::read_u16(&bytes[offset..])
+        }
+    }
+
+    pub fn u32(&self, bytes: &[u8], offset: usize) -> u32 {
+        if self.big_endian {
+            BigEndian::read_u32(&bytes[offset..])
+        } else {
+            LittleEndian::read_u32(&bytes[offset..])
+        }
+    }
+
+    // An address sized field, found at offset64 in the 64-bit layout and offset32 otherwise
+    pub fn word(&self, bytes: &[u8], offset64: usize, offset32: usize) -> u64 {
+        match (self.is_64bit, self.big_endian) {
+            (true, true) => BigEndian::read_u64(&bytes[offset64..]),
+            (true, false) => LittleEndian::read_u64(&bytes[offset64..]),
+            (false, _) => self.u32(bytes, offset32) as u64,
+        }
+    }
+}
+
+// Reads the defined functions and objects of .dynsym, fetching only the headers and the two
+// tables rather than the whole file.
+// Symbol values are link-time addresses: absolute in an ET_EXEC executable, relative to a
+// zero load address in a shared object or PIE. Both become offsets from the module base by
+// subtracting the address file offset 0 is linked at, taken from the first PT_LOAD, since
+// the module base is where offset 0 is mapped.
+fn parse_elf_exports(path: &Path) -> Result<Vec<Export>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut elf = ElfReader {
+        file,
+        is_64bit: true,
+        big_endian: false,
+    };
+    let header = elf.read_at(0, 64)?;
+    if &header[..4] != b"\x7fELF" {
+        return Err(format!("{} is not an ELF file", path.display()));
+    }
+    elf.is_64bit = match header[4] {
+        1 => false,
+        2 => true,
+        class => {
+            return Err(format!(
+                "{} has unknown ELF class {}",
+                path.display(),
+                class
+            ))
+        }
+    };
+    elf.big_endian = header[5] == 2;
+    let machine = elf.u16(&header, 0x12);
+
+    let (program_size, program_count) = if elf.is_64bit {
+        (
+            elf.u16(&header, 0x36) as usize,
+            elf.u16(&header, 0x38) as usize,
+        )
+    } else {
+        (
+            elf.u16(&header, 0x2A) as usize,
+            elf.u16(&header, 0x2C) as usize,
+        )
+    };
+    let programs = elf.read_table(
+        elf.word(&header, 0x20, 0x1C),
+        program_size,
+        program_count,
+        if elf.is_64bit { 0x18 } else { 0x0C },
+    )?;
+    let link_base = programs
+        .chunks_exact(program_size)
+        .find(|program| elf.u32(program, 0) == PT_LOAD)
+        .map(|program| {
+            elf.word(program, 0x10, 0x08)
+                .wrapping_sub(elf.word(program, 0x08, 0x04))
+        })
+        .ok_or_else(|| format!("{} has no loadable segment", path.display()))?;
+
+    let (section_size, section_count) = if elf.is_64bit {
+        (
+            elf.u16(&header, 0x3A) as usize,
+            elf.u16(&header, 0x3C) as usize,
+        )
+    } else {
+        (
+            elf.u16(&header, 0x2E) as usize,
+            elf.u16(&header, 0x30) as usize,
+        )
+    };
+    if section_count == 0 {
+        return Err(format!("{} has no section headers", path.display()));
+    }
+    let sections = elf.read_table(
+        elf.word(&header, 0x28, 0x20),
+        section_size,
+        section_count,
+        if elf.is_64bit { 0x30 } else { 0x1C },
+    )?;
+    let section = |index: usize| &sections[index * section_size..(index + 1) * section_size];
+
+    let dynsym = (0..section_count)
+        .map(section)
+        .find(|s| elf.u32(s, 4) == SHT_DYNSYM)
+        .ok_or_else(|| format!("{} has no .dynsym", path.display()))?;
+    let string_index = elf.u32(dynsym, if elf.is_64bit { 0x28 } else { 0x18 }) as usize;
+    if string_index >= section_count {
+        return Err(format!("{} has a corrupt .dynsym link", path.display()));
+    }
+    let dynstr = section(string_index);
+    // sh_offset and sh_size
+    let table_range = |s: &[u8]| (elf.word(s, 0x18, 0x10), elf.word(s, 0x20, 0x14) as usize);
+    let (symbols_offset, symbols_size) = table_range(dynsym);
+    let (strings_offset, strings_size) = table_range(dynstr);
+    let symbols = elf.read_at(symbols_offset, symbols_size)?;
+    let strings = elf.read_at(strings_offset, strings_size)?;
+
+    let symbol_size = if elf.is_64bit { 24 } else { 16 };
+    Ok(symbols
+        .chunks_exact(symbol_size)
+        .filter_map(|symbol| {
+            let (info, section_index, mut value, size) = if elf.is_64bit {
+                (
+                    symbol[4],
+                    elf.u16(symbol, 6),
+                    elf.word(symbol, 8, 0),
+                    elf.word(symbol, 16, 0),
+                )
+            } else {
+                (
+                    symbol[12],
+                    elf.u16(symbol, 14),
+                    elf.u32(symbol, 4) as u64,
+                    elf.u32(symbol, 8) as u64,
+                )
+            };
+            let symbol_type = info & 0xf;
+            if section_index == 0
+                || value == 0
+                || !matches!(symbol_type, STT_OBJECT | STT_FUNC | STT_GNU_IFUNC)
+            {
+                return None;
+            }
+            // Thumb functions carry the mode in bit 0 of their address
+            if machine == EM_ARM && symbol_type == STT_FUNC {
+                value &= !1;
+            }
+            let name = strings.get(elf.u32(symbol, 0) as usize..)?;
+            let name = &name[..name.iter().position(|&b| b == 0)?];
+            if name.is_empty() {
+                return None;
+            }
+            Some(Export {
+                name: String::from_utf8_lossy(name).into_owned(),
+                offset: value.wrapping_sub(link_base),
+                size,
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHT_STRTAB: u32 = 3;
+
+    // A minimal little-endian ELF64 with one PT_LOAD at link_address and a .dynsym holding
+    // symbols as (name, value, type)
+    fn tiny_elf(
+        link_address: u64,
+        symbols: &[(&str, u64, u8)],
+        dynsym_size: Option<u64>,
+    ) -> Vec<u8> {
+        let mut strings = vec![0u8];
+        let mut table = vec![0u8; 24];
+        for &(name, value, symbol_type) in symbols {
+            let mut symbol = [0u8; 24];
+            LittleEndian::write_u32(&mut symbol[0..], strings.len() as u32);
+            symbol[4] = 0x10 | symbol_type;
+            LittleEndian::write_u16(&mut symbol[6..], 1);
+            LittleEndian::write_u64(&mut symbol[8..], value);
+            LittleEndian::write_u64(&mut symbol[16..], 16);
+            table.extend_from_slice(&symbol);
+            strings.extend_from_slice(name.as_bytes());
+            strings.push(0);
+        }
+        let table_offset = 64 + 56;
+        let strings_offset = table_offset + table.len();
+        let sections_offset = strings_offset + strings.len();
+
+        let mut elf = vec![0u8; 64];
+        elf[..7].copy_from_slice(b"\x7fELF\x02\x01\x01");
+        LittleEndian::write_u16(&mut elf[0x10..], 3);
+        LittleEndian::write_u16(&mut elf[0x12..], 62);
+        LittleEndian::write_u64(&mut elf[0x20..], 64);
+        LittleEndian::write_u64(&mut elf[0x28..], sections_offset as u64);
+        LittleEndian::write_u16(&mut elf[0x36..], 56);
+        LittleEndian::write_u16(&mut elf[0x38..], 1);
+        LittleEndian::write_u16(&mut elf[0x3A..], 64);
+        LittleEndian::write_u16(&mut elf[0x3C..], 3);
+
+        let mut program = [0u8; 56];
+        LittleEndian::write_u32(&mut program[0..], PT_LOAD);
+        LittleEndian::write_u64(&mut program[0x10..], link_address);
+        elf.extend_from_slice(&program);
+        elf.extend_from_slice(&table);
+        elf.extend_from_slice(&strings);
+
+        elf.extend_from_slice(&[0u8; 64]);
+        for (section_type, offset, size, link) in [
+            (
+                SHT_DYNSYM,
+                table_offset,
+                dynsym_size.unwrap_or(table.len() as u64),
+                2,
+            ),
+            (SHT_STRTAB, strings_offset, strings.len() as u64, 0),
+        ] {
+            let mut section = [0u8; 64];
+            LittleEndian::write_u32(&mut section[4..], section_type);
+            LittleEndian::write_u64(&mut section[0x18..], offset as u64);
+            LittleEndian::write_u64(&mut section[0x20..], size);
+            LittleEndian::write_u32(&mut section[0x28..], link);
+            elf.extend_from_slice(&section);
+        }
+        elf
+    }
+
+    fn exports_of(name: &str, elf: &[u8]) -> Result<Vec<Export>, String> {
+        let path =
+            std::env::temp_dir().join(format!("memory-server-{}-{}.so", name, std::process::id()));
+        std::fs::write(&path, elf).unwrap();
+        let exports = parse_elf_exports(&path);
+        std::fs::remove_file(&path).unwrap();
+        exports
+    }
+
+    #[test]
+    fn aob_matches_in_a_module_report_their_offset() {
+        let pid = unsafe { crate::native_bridge::get_pid_native() };
+        let mut memory = vec![0u8; 0x200];
+        memory[0x123..0x127].copy_from_slice(&[0x48, 0x8b, 0x05, 0x99]);
+        let start = memory.as_ptr() as u64;
+        let end = start + memory.len() as u64;
+        let (bytes, mask) = crate::scan::parse_aob_pattern("48 8B 05 ??").unwrap();
+        let control = crate::scan::ScanControl::default();
+        let matches = crate::scan::scan_aob(pid, &[(start, end)], &bytes, &mask, &control).matches;
+        assert_eq!(matches, vec![start + 0x123]);
+
+        // The buffer stands in for the code mapping of a module whose header is mapped a
+        // page below it; the module's size only covers that header
+        let path = "/data/app/lib/arm64/libgame.so";
+        let mapping = |start, end, path: Option<&str>| region::MemoryRegion {
+            start,
+            end,
+            protection: region::Protection::parse("r-xp"),
+            path: path.map(str::to_string),
+            kind: region::RegionKind::AppCode,
+        };
+        let base = start - 0x1000;
+        let regions = [
+            mapping(base, start, Some(path)),
+            mapping(start, end, Some(path)),
+        ];
+        let modules = [serde_json::json!({ "base": base, "size": 0x1000, "modulename": path })];
+        let outside = end + 0x10;
+
+        let offsets = module_offsets_in(&regions, &[matches[0], outside], &modules);
+        let offset = offsets[0].as_ref().unwrap();
+        assert_eq!(
+            (offset.module.as_str(), offset.offset),
+            ("libgame.so", 0x1123)
+        );
+        assert_eq!(offset.expression(), "libgame.so+0x1123");
+        assert!(offsets[1].is_none());
+    }
+
+    #[test]
+    fn shared_object_exports_are_module_relative() {
+        let elf = tiny_elf(
+            0,
+            &[("tick", 0x1234, STT_FUNC), ("score", 0x4000, STT_OBJECT)],
+            None,
+        );
+        let exports = exports_of("dso", &elf).unwrap();
+        let found: Vec<(&str, u64)> = exports
+            .iter()
+            .map(|e| (e.name.as_str(), e.offset))
+            .collect();
+        assert_eq!(found, vec![("tick", 0x1234), ("score", 0x4000)]);
+    }
+
+    #[test]
+    fn executable_exports_drop_the_link_address() {
+        let elf = tiny_elf(0x400000, &[("main", 0x401020, STT_FUNC)], None);
+        let exports = exports_of("exec", &elf).unwrap();
+        assert_eq!(exports[0].offset, 0x1020);
+    }
+
+    #[test]
+    fn table_past_the_end_of_the_file_is_rejected() {
+        let elf = tiny_elf(0, &[("tick", 0x1234, STT_FUNC)], Some(u64::MAX / 2));
+        let error = exports_of("corrupt", &elf).unwrap_err();
+        assert!(error.contains("Corrupt ELF"));
+    }
+}
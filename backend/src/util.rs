@@ -1,13 +1,22 @@
 use crate::native_bridge;
+use crate::region::{self, Protection};
+use crate::symbols;
+use byteorder::{ByteOrder, LittleEndian};
 use capstone::prelude::*;
 use libc::{self};
+use percent_encoding::percent_decode_str;
+use rayon::prelude::*;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
 use std::num::ParseIntError;
-use std::path::Path;
-use std::slice;
+use std::path::{Component, Path, PathBuf};
 use std::str;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct FileItem {
@@ -18,7 +27,18 @@ pub struct FileItem {
     children: Option<Vec<FileItem>>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Endianness {
+    Little,
+    Big,
+}
+
 pub fn read_memory_64(pid: i32, address: u64) -> Result<u64, String> {
+    read_memory_64_endian(pid, address, Endianness::Little)
+}
+
+pub fn read_memory_64_endian(pid: i32, address: u64, endian: Endianness) -> Result<u64, String> {
     let mut buffer = [0u8; 8];
     native_bridge::read_process_memory(pid, address as *mut libc::c_void, 8, &mut buffer).map_err(
         |e| {
@@ -28,10 +48,17 @@ pub fn read_memory_64(pid: i32, address: u64) -> Result<u64, String> {
             )
         },
     )?;
-    Ok(u64::from_le_bytes(buffer))
+    Ok(match endian {
+        Endianness::Little => u64::from_le_bytes(buffer),
+        Endianness::Big => u64::from_be_bytes(buffer),
+    })
 }
 
 pub fn _read_memory_32(pid: i32, address: u32) -> Result<u32, String> {
+    read_memory_32_endian(pid, address as u64, Endianness::Little)
+}
+
+pub fn read_memory_32_endian(pid: i32, address: u64, endian: Endianness) -> Result<u32, String> {
     let mut buffer = [0u8; 4];
     native_bridge::read_process_memory(pid, address as *mut libc::c_void, 4, &mut buffer).map_err(
         |e| {
@@ -41,252 +68,3090 @@ pub fn _read_memory_32(pid: i32, address: u32) -> Result<u32, String> {
             )
         },
     )?;
-    Ok(u32::from_le_bytes(buffer))
-}
-
-pub fn _evaluate_expression(expr: &str) -> Result<isize, String> {
-    let re = Regex::new(r"(\d+)\s*([+\-*/])\s*(\d+)").unwrap();
-    if let Some(caps) = re.captures(expr) {
-        let a: isize = caps[1]
-            .parse()
-            .map_err(|_| "Invalid number in expression".to_string())?;
-        let b: isize = caps[3]
-            .parse()
-            .map_err(|_| "Invalid number in expression".to_string())?;
-        match &caps[2] {
-            "+" => Ok(a + b),
-            "-" => Ok(a - b),
-            "*" => Ok(a * b),
-            "/" => Ok(a / b),
-            _ => Err("Unsupported operation".to_string()),
+    Ok(match endian {
+        Endianness::Little => u32::from_le_bytes(buffer),
+        Endianness::Big => u32::from_be_bytes(buffer),
+    })
+}
+
+fn read_memory_bytes(pid: i32, address: u64, size: usize) -> Result<Vec<u8>, String> {
+    let mut buffer = vec![0u8; size];
+    let nread =
+        native_bridge::read_process_memory(pid, address as *mut libc::c_void, size, &mut buffer)
+            .map_err(|e| {
+                format!(
+                    "Failed to read {} bytes of memory at address {:#x}: {}",
+                    size, address, e
+                )
+            })?;
+    if (nread as usize) < size {
+        return Err(format!(
+            "Partial read at address {:#x}: requested {} bytes, read {}",
+            address, size, nread
+        ));
+    }
+    Ok(buffer)
+}
+
+// Requests whose ranges touch or overlap are served by a single read. Each group is its
+// start, its end and the indices of the requests it covers.
+fn coalesce_reads(requests: &[(u64, usize)]) -> Vec<(u64, u64, Vec<usize>)> {
+    let mut order: Vec<usize> = (0..requests.len()).collect();
+    order.sort_by_key(|&i| requests[i].0);
+
+    let mut groups: Vec<(u64, u64, Vec<usize>)> = Vec::new();
+    for i in order {
+        let (address, size) = requests[i];
+        let end = address.saturating_add(size as u64);
+        match groups.last_mut() {
+            Some((_, group_end, members)) if address <= *group_end => {
+                *group_end = (*group_end).max(end);
+                members.push(i);
+            }
+            _ => groups.push((address, end, vec![i])),
+        }
+    }
+    groups
+}
+
+pub fn read_memory_batch(pid: i32, requests: &[(u64, usize)]) -> Vec<Result<Vec<u8>, String>> {
+    let groups = coalesce_reads(requests);
+    let group_results: Vec<(usize, Result<Vec<u8>, String>)> = groups
+        .par_iter()
+        .flat_map(|(start, end, members)| {
+            let grouped = read_memory_bytes(pid, *start, (end - start) as usize);
+            members
+                .iter()
+                .map(|&i| {
+                    let (address, size) = requests[i];
+                    let offset = (address - start) as usize;
+                    let result = match &grouped {
+                        Ok(buffer) => Ok(buffer[offset..offset + size].to_vec()),
+                        Err(e) if members.len() == 1 => Err(e.clone()),
+                        // Retry on its own so one unreadable address doesn't fail its neighbours
+                        Err(_) => read_memory_bytes(pid, address, size),
+                    };
+                    (i, result)
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    let mut results: Vec<Result<Vec<u8>, String>> = vec![Ok(Vec::new()); requests.len()];
+    for (i, result) in group_results {
+        results[i] = result;
+    }
+    results
+}
+
+pub fn write_memory_bytes(pid: i32, address: u64, bytes: &[u8]) -> Result<(), String> {
+    let nwritten =
+        native_bridge::write_process_memory(pid, address as *mut libc::c_void, bytes.len(), bytes)
+            .map_err(|e| {
+                format!(
+                    "Failed to write {} bytes of memory at address {:#x}: {}",
+                    bytes.len(),
+                    address,
+                    e
+                )
+            })?;
+    if (nwritten as usize) < bytes.len() {
+        return Err(format!(
+            "Partial write at address {:#x}: requested {} bytes, wrote {}",
+            address,
+            bytes.len(),
+            nwritten
+        ));
+    }
+    Ok(())
+}
+
+const WRITE_CHUNK_SIZE: usize = 64 * 1024;
+
+// Code pages count as writable, since writes to another process go through ptrace, which can
+// patch them
+fn writable(protection: Protection) -> bool {
+    protection.contains(Protection::WRITE) || protection.contains(Protection::EXEC)
+}
+
+fn readable(protection: Protection) -> bool {
+    protection.contains(Protection::READ)
+}
+
+// Checks address..address+length against the region list, so an operation over it is never
+// left half applied over an unmapped hole or a page without the access it needs
+fn check_range(
+    regions: &[region::MemoryRegion],
+    address: u64,
+    length: usize,
+    allowed: fn(Protection) -> bool,
+) -> Result<(), String> {
+    let end = address
+        .checked_add(length as u64)
+        .ok_or_else(|| format!("Range at {:#x} overflows the address space", address))?;
+    let mut covered = address;
+    for region in regions
+        .iter()
+        .filter(|region| region.end > address && region.start < end)
+    {
+        if region.start > covered {
+            return Err(format!(
+                "Range {:#x}-{:#x} is not mapped at {:#x}",
+                address, end, covered
+            ));
+        }
+        if !allowed(region.protection) {
+            return Err(format!(
+                "Range {:#x}-{:#x} overlaps the {} region at {:#x}-{:#x}",
+                address, end, region.protection, region.start, region.end
+            ));
+        }
+        covered = covered.max(region.end);
+    }
+    if covered < end {
+        return Err(format!(
+            "Range {:#x}-{:#x} is not mapped at {:#x}",
+            address, end, covered
+        ));
+    }
+    Ok(())
+}
+
+fn sorted_regions(pid: i32) -> Result<Vec<region::MemoryRegion>, String> {
+    let mut regions = region::enumerate_regions(pid)?;
+    regions.sort_by_key(|region| region.start);
+    Ok(regions)
+}
+
+// Writes length copies of byte from address on, after checking the whole range can take it
+pub fn fill_memory(pid: i32, address: u64, byte: u8, length: usize) -> Result<(), String> {
+    if length == 0 {
+        return Err("Fill length must not be zero".to_string());
+    }
+    check_range(&sorted_regions(pid)?, address, length, writable)
+        .map_err(|e| format!("Cannot fill: {}", e))?;
+
+    let chunk = vec![byte; length.min(WRITE_CHUNK_SIZE)];
+    let mut offset = 0;
+    while offset < length {
+        let size = chunk.len().min(length - offset);
+        write_memory_bytes(pid, address + offset as u64, &chunk[..size])?;
+        offset += size;
+    }
+    Ok(())
+}
+
+// Copies length bytes from src to dst like memmove: when dst overlaps the end of src the
+// chunks go from the back, so every chunk is read before a write can overwrite it
+pub fn copy_memory(pid: i32, src: u64, dst: u64, length: usize) -> Result<(), String> {
+    if length == 0 {
+        return Err("Copy length must not be zero".to_string());
+    }
+    let regions = sorted_regions(pid)?;
+    check_range(&regions, src, length, readable)
+        .map_err(|e| format!("Cannot copy from the source: {}", e))?;
+    check_range(&regions, dst, length, writable)
+        .map_err(|e| format!("Cannot copy to the destination: {}", e))?;
+    if src == dst {
+        return Ok(());
+    }
+
+    let chunk_count = length.div_ceil(WRITE_CHUNK_SIZE);
+    let backward = dst > src && dst < src + length as u64;
+    let mut buffer = vec![0u8; length.min(WRITE_CHUNK_SIZE)];
+    for index in 0..chunk_count {
+        let chunk = if backward {
+            chunk_count - 1 - index
+        } else {
+            index
+        };
+        let offset = chunk * WRITE_CHUNK_SIZE;
+        let size = WRITE_CHUNK_SIZE.min(length - offset);
+        let from = src + offset as u64;
+        let nread = native_bridge::read_process_memory(
+            pid,
+            from as *mut libc::c_void,
+            size,
+            &mut buffer[..size],
+        )
+        .map_err(|e| format!("Failed to read {} bytes at {:#x}: {}", size, from, e))?;
+        if (nread as usize) < size {
+            return Err(format!(
+                "Partial read at {:#x}: requested {} bytes, read {}",
+                from, size, nread
+            ));
+        }
+        write_memory_bytes(pid, dst + offset as u64, &buffer[..size])?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgo {
+    Crc32,
+    // Non-cryptographic and several times faster than CRC32 without hardware support
+    Xxhash64,
+}
+
+// Reads per step of hash_region, which bounds its memory use for large regions
+const HASH_CHUNK_SIZE: usize = 1024 * 1024;
+
+// Hex digest of start..end, so a client can tell whether anything in a large range changed
+// without reading it. The whole range must be readable.
+pub fn hash_region(pid: i32, start: u64, end: u64, algo: HashAlgo) -> Result<String, String> {
+    if end <= start {
+        return Err(format!("Empty range {:#x}..{:#x}", start, end));
+    }
+    let length = (end - start) as usize;
+    check_range(&sorted_regions(pid)?, start, length, readable)
+        .map_err(|e| format!("Cannot hash: {}", e))?;
+
+    let mut crc = crc32fast::Hasher::new();
+    let mut xxhash = twox_hash::XxHash64::with_seed(0);
+    let mut offset = 0;
+    while offset < length {
+        let size = HASH_CHUNK_SIZE.min(length - offset);
+        let chunk = read_memory_bytes(pid, start + offset as u64, size)?;
+        match algo {
+            HashAlgo::Crc32 => crc.update(&chunk),
+            HashAlgo::Xxhash64 => std::hash::Hasher::write(&mut xxhash, &chunk),
+        }
+        offset += size;
+    }
+    Ok(match algo {
+        HashAlgo::Crc32 => hex::encode(crc.finalize().to_be_bytes()),
+        HashAlgo::Xxhash64 => hex::encode(std::hash::Hasher::finish(&xxhash).to_be_bytes()),
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueType {
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    U64,
+    I64,
+    F32,
+    F64,
+}
+
+impl ValueType {
+    pub fn parse(type_name: &str) -> Result<ValueType, String> {
+        match type_name {
+            "u8" | "uint8" => Ok(ValueType::U8),
+            "i8" | "int8" => Ok(ValueType::I8),
+            "u16" | "uint16" => Ok(ValueType::U16),
+            "i16" | "int16" => Ok(ValueType::I16),
+            "u32" | "uint32" => Ok(ValueType::U32),
+            "i32" | "int32" => Ok(ValueType::I32),
+            "u64" | "uint64" => Ok(ValueType::U64),
+            "i64" | "int64" => Ok(ValueType::I64),
+            "f32" | "float" => Ok(ValueType::F32),
+            "f64" | "double" => Ok(ValueType::F64),
+            _ => Err(format!(
+                "Unknown value type '{}': expected one of u8, i8, u16, i16, u32, i32, u64, i64, f32, f64",
+                type_name
+            )),
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            ValueType::U8 | ValueType::I8 => 1,
+            ValueType::U16 | ValueType::I16 => 2,
+            ValueType::U32 | ValueType::I32 | ValueType::F32 => 4,
+            ValueType::U64 | ValueType::I64 | ValueType::F64 => 8,
         }
+    }
+
+    // Little-endian bytes of a JSON number; integers must be whole and fit the type
+    pub fn encode(&self, value: &Value) -> Result<Vec<u8>, String> {
+        let out_of_range = || format!("{} does not fit in {:?}", value, self);
+        let int = || {
+            value
+                .as_i64()
+                .map(|n| n as i128)
+                .or_else(|| value.as_u64().map(|n| n as i128))
+                .ok_or_else(|| format!("{} is not an integer", value))
+        };
+        let bytes = match self {
+            ValueType::U8 => u8::try_from(int()?)
+                .map_err(|_| out_of_range())?
+                .to_le_bytes()
+                .to_vec(),
+            ValueType::I8 => i8::try_from(int()?)
+                .map_err(|_| out_of_range())?
+                .to_le_bytes()
+                .to_vec(),
+            ValueType::U16 => u16::try_from(int()?)
+                .map_err(|_| out_of_range())?
+                .to_le_bytes()
+                .to_vec(),
+            ValueType::I16 => i16::try_from(int()?)
+                .map_err(|_| out_of_range())?
+                .to_le_bytes()
+                .to_vec(),
+            ValueType::U32 => u32::try_from(int()?)
+                .map_err(|_| out_of_range())?
+                .to_le_bytes()
+                .to_vec(),
+            ValueType::I32 => i32::try_from(int()?)
+                .map_err(|_| out_of_range())?
+                .to_le_bytes()
+                .to_vec(),
+            ValueType::U64 => u64::try_from(int()?)
+                .map_err(|_| out_of_range())?
+                .to_le_bytes()
+                .to_vec(),
+            ValueType::I64 => i64::try_from(int()?)
+                .map_err(|_| out_of_range())?
+                .to_le_bytes()
+                .to_vec(),
+            ValueType::F32 | ValueType::F64 => {
+                let float = value
+                    .as_f64()
+                    .ok_or_else(|| format!("{} is not a number", value))?;
+                if *self == ValueType::F32 {
+                    // as would turn these into infinity
+                    if float.is_finite() && float.abs() > f32::MAX as f64 {
+                        return Err(out_of_range());
+                    }
+                    (float as f32).to_le_bytes().to_vec()
+                } else {
+                    float.to_le_bytes().to_vec()
+                }
+            }
+        };
+        Ok(bytes)
+    }
+}
+
+pub fn read_typed_value(pid: i32, address: u64, type_name: &str) -> Result<Value, String> {
+    read_value(pid, address, ValueType::parse(type_name)?)
+}
+
+// The write side of read_typed_value. type_name is a value type, or "string" for text,
+// which is written in encoding (UTF-8 when not given) without a terminator.
+pub fn write_typed_value(
+    pid: i32,
+    address: u64,
+    type_name: &str,
+    value: &Value,
+    encoding: Option<StringEncoding>,
+) -> Result<Vec<u8>, String> {
+    let bytes = if type_name == "string" {
+        let text = value
+            .as_str()
+            .ok_or_else(|| format!("{} is not a string", value))?;
+        encoding.unwrap_or(StringEncoding::Utf8).encode(text)?
     } else {
-        expr.parse().map_err(|_| "Invalid expression".to_string())
+        ValueType::parse(type_name)?.encode(value)?
+    };
+    if bytes.is_empty() {
+        return Err("Nothing to write for an empty string".to_string());
     }
+    write_memory_bytes(pid, address, &bytes)?;
+    Ok(bytes)
 }
 
-pub fn resolve_nested_address(
+pub fn read_value(pid: i32, address: u64, value_type: ValueType) -> Result<Value, String> {
+    read_value_endian(pid, address, value_type, Endianness::Little)
+}
+
+pub fn read_value_endian(
     pid: i32,
-    nested_addr: &str,
-    modules: &[serde_json::Value],
-) -> Result<u64, String> {
-    let re = Regex::new(r"(\[)|(\])|([^\[\]]+)").map_err(|e| format!("Regex error: {}", e))?;
-    let mut stack = Vec::new();
-    let mut current_expr = String::new();
+    address: u64,
+    value_type: ValueType,
+    endian: Endianness,
+) -> Result<Value, String> {
+    let bytes = read_memory_bytes(pid, address, value_type.size())?;
+    interpret_bytes(&bytes, value_type, endian)
+}
 
-    for cap in re.captures_iter(nested_addr) {
-        if cap.get(1).is_some() {
-            if !current_expr.is_empty() {
-                stack.push(current_expr);
-                current_expr = String::new();
+// Integers widen to i128 so every 64-bit value compares and subtracts exactly
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Number {
+    Int(i128),
+    Float(f64),
+}
+
+// The plain decoder under interpret_bytes, for scans that compare values and never need
+// them as JSON. None when bytes is shorter than the type.
+pub fn decode_number(bytes: &[u8], value_type: ValueType, endian: Endianness) -> Option<Number> {
+    let size = value_type.size();
+    let mut word = [0u8; 8];
+    word[..size].copy_from_slice(bytes.get(..size)?);
+    let word = &mut word[..size];
+    if endian == Endianness::Big {
+        word.reverse();
+    }
+    let number = match value_type {
+        ValueType::U8 => Number::Int(word[0] as i128),
+        ValueType::I8 => Number::Int(word[0] as i8 as i128),
+        ValueType::U16 => Number::Int(u16::from_le_bytes(word.try_into().unwrap()) as i128),
+        ValueType::I16 => Number::Int(i16::from_le_bytes(word.try_into().unwrap()) as i128),
+        ValueType::U32 => Number::Int(u32::from_le_bytes(word.try_into().unwrap()) as i128),
+        ValueType::I32 => Number::Int(i32::from_le_bytes(word.try_into().unwrap()) as i128),
+        ValueType::U64 => Number::Int(u64::from_le_bytes(word.try_into().unwrap()) as i128),
+        ValueType::I64 => Number::Int(i64::from_le_bytes(word.try_into().unwrap()) as i128),
+        ValueType::F32 => Number::Float(f32::from_le_bytes(word.try_into().unwrap()) as f64),
+        ValueType::F64 => Number::Float(f64::from_le_bytes(word.try_into().unwrap())),
+    };
+    Some(number)
+}
+
+// The one place raw bytes become a typed JSON value, so an address shows the same value in
+// reads, scan results and watch updates. Signed types come out as negative numbers.
+pub fn interpret_bytes(
+    bytes: &[u8],
+    value_type: ValueType,
+    endian: Endianness,
+) -> Result<Value, String> {
+    let number = decode_number(bytes, value_type, endian).ok_or_else(|| {
+        format!(
+            "{:?} needs {} bytes, only {} available",
+            value_type,
+            value_type.size(),
+            bytes.len()
+        )
+    })?;
+    // Every integer type fits one of i64 or u64, and an f32 widened to f64 narrows back exactly
+    let value = match (number, value_type) {
+        (Number::Int(value), _) => match i64::try_from(value) {
+            Ok(value) => json!(value),
+            Err(_) => json!(value as u64),
+        },
+        (Number::Float(value), ValueType::F32) => json!(value as f32),
+        (Number::Float(value), _) => json!(value),
+    };
+    Ok(value)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Radix {
+    #[default]
+    Hex,
+    Dec,
+}
+
+// Puts separator between each group of size characters, counting from the right
+fn group_digits(digits: &str, size: usize, separator: char) -> String {
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / size);
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(size) {
+            grouped.push(separator);
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+// A value as a person reads it, e.g. "0x0000_1234" or "4,660". In hex a value is its raw
+// bits padded to the width of its type, so an i32 of -1 is 0xffff_ffff and a float shows its
+// encoding. Without a type the bytes are shown one at a time, spaced apart when grouped.
+pub fn format_value(
+    bytes: &[u8],
+    value_type: Option<ValueType>,
+    radix: Radix,
+    grouped: bool,
+) -> Result<String, String> {
+    let Some(value_type) = value_type else {
+        return Ok(match (radix, grouped) {
+            (Radix::Hex, false) => hex::encode(bytes),
+            (Radix::Hex, true) => bytes
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<Vec<_>>()
+                .join(" "),
+            // Decimal bytes run together would be unreadable, so they are always spaced
+            (Radix::Dec, _) => bytes
+                .iter()
+                .map(u8::to_string)
+                .collect::<Vec<_>>()
+                .join(" "),
+        });
+    };
+    let size = value_type.size();
+    let word = bytes.get(..size).ok_or_else(|| {
+        format!(
+            "{:?} needs {} bytes, only {} available",
+            value_type,
+            size,
+            bytes.len()
+        )
+    })?;
+    match (radix, value_type) {
+        (Radix::Hex, _) => {
+            let digits: String = word
+                .iter()
+                .rev()
+                .map(|byte| format!("{:02x}", byte))
+                .collect();
+            Ok(if grouped {
+                format!("0x{}", group_digits(&digits, 4, '_'))
+            } else {
+                format!("0x{}", digits)
+            })
+        }
+        // Formatted directly, since JSON has no NaN or infinity to go through. Debug keeps
+        // tiny and huge values in exponent form rather than spelling out every zero.
+        (Radix::Dec, ValueType::F32) => Ok(format!(
+            "{:?}",
+            f32::from_le_bytes(word.try_into().unwrap())
+        )),
+        (Radix::Dec, ValueType::F64) => Ok(format!(
+            "{:?}",
+            f64::from_le_bytes(word.try_into().unwrap())
+        )),
+        (Radix::Dec, _) => {
+            let text = interpret_bytes(word, value_type, Endianness::Little)?.to_string();
+            if !grouped {
+                return Ok(text);
             }
-            current_expr.push('[');
-        } else if cap.get(2).is_some() {
-            if !current_expr.is_empty() {
-                let inner_value = resolve_single_level_address(&current_expr, modules)?;
-                let memory_value = read_memory_64(pid, inner_value)?;
-                if let Some(mut prev_expr) = stack.pop() {
-                    prev_expr.push_str(&format!("0x{:X}", memory_value));
-                    current_expr = prev_expr;
-                } else {
-                    current_expr = format!("0x{:X}", memory_value);
+            Ok(match text.strip_prefix('-') {
+                Some(digits) => format!("-{}", group_digits(digits, 3, ',')),
+                None => group_digits(&text, 3, ','),
+            })
+        }
+    }
+}
+
+// The value at address together with its text in radix, from a single read
+pub fn read_value_formatted(
+    pid: i32,
+    address: u64,
+    value_type: ValueType,
+    endian: Endianness,
+    radix: Radix,
+    grouped: bool,
+) -> Result<(Value, String), String> {
+    let mut bytes = read_memory_bytes(pid, address, value_type.size())?;
+    let value = interpret_bytes(&bytes, value_type, endian)?;
+    if endian == Endianness::Big {
+        bytes.reverse();
+    }
+    Ok((
+        value,
+        format_value(&bytes, Some(value_type), radix, grouped)?,
+    ))
+}
+
+const HEXDUMP_ROW: usize = 16;
+
+fn is_printable(byte: u8) -> bool {
+    byte.is_ascii_graphic() || byte == b' '
+}
+
+// A classic offset | hex | ascii dump for a memory viewer. Rows start on 16 byte boundaries
+// so the same address always sits in the same column; the cells before base_address and past
+// the last byte are left blank. Bytes outside printable ASCII show as '.'.
+pub fn format_hexdump(bytes: &[u8], base_address: u64) -> String {
+    let cells: Vec<Option<u8>> = bytes.iter().copied().map(Some).collect();
+    format_hexdump_cells(&cells, base_address)
+}
+
+// format_hexdump for a range with holes in it: a None cell could not be read and shows as
+// ?? in the hex column and ? in the ascii one
+pub fn format_hexdump_cells(cells: &[Option<u8>], base_address: u64) -> String {
+    let lead = (base_address % HEXDUMP_ROW as u64) as usize;
+    let first_row = base_address - lead as u64;
+    let mut dump = String::new();
+    for row in 0..(lead + cells.len()).div_ceil(HEXDUMP_ROW) {
+        let mut hex = String::with_capacity(HEXDUMP_ROW * 3 + 1);
+        let mut ascii = String::with_capacity(HEXDUMP_ROW);
+        for column in 0..HEXDUMP_ROW {
+            if column == HEXDUMP_ROW / 2 {
+                hex.push(' ');
+            }
+            let cell = (row * HEXDUMP_ROW + column)
+                .checked_sub(lead)
+                .and_then(|index| cells.get(index));
+            match cell {
+                Some(Some(byte)) => {
+                    hex.push_str(&format!("{:02x} ", byte));
+                    ascii.push(if is_printable(*byte) {
+                        *byte as char
+                    } else {
+                        '.'
+                    });
+                }
+                Some(None) => {
+                    hex.push_str("?? ");
+                    ascii.push('?');
+                }
+                None => {
+                    hex.push_str("   ");
+                    ascii.push(' ');
                 }
             }
-            current_expr.push(']');
-        } else if let Some(m) = cap.get(3) {
-            current_expr.push_str(m.as_str());
         }
+        dump.push_str(&format!(
+            "{:016x}  {} |{}|\n",
+            first_row + (row * HEXDUMP_ROW) as u64,
+            hex,
+            ascii
+        ));
     }
+    dump
+}
 
-    resolve_single_level_address(&current_expr, modules)
+// The most one view covers; a scrolling viewer asks for the next window as it goes
+pub const MAX_VIEW_LENGTH: usize = 64 * 1024;
+
+#[derive(Debug, Serialize)]
+pub struct ViewRow {
+    pub address: u64,
+    // Some byte of the row that falls in the view could not be read
+    pub read_failed: bool,
 }
 
-pub fn resolve_single_level_address(
-    addr: &str,
-    modules: &[serde_json::Value],
-) -> Result<u64, String> {
-    let resolved_addr = preemptive_module_resolution(addr, modules)?;
+#[derive(Debug, Serialize)]
+pub struct MemoryView {
+    pub address: u64,
+    pub length: usize,
+    // null for a byte that could not be read
+    pub bytes: Vec<Option<u8>>,
+    // Whether each byte is printable ASCII, so a client can colour the text column
+    pub printable: Vec<bool>,
+    // The rows of the hexdump, each 16 bytes on a 16 byte boundary
+    pub rows: Vec<ViewRow>,
+    pub hexdump: String,
+    // Set when length was cut down to MAX_VIEW_LENGTH
+    pub truncated: bool,
+}
 
-    let re = Regex::new(r"(?:([+\-*])?\s*)(0x[\da-fA-F]+|\d+)")
-        .map_err(|e| format!("Regex error: {}", e))?;
+// A window of memory for a hex editor. Reads go page by page and carry on past a page that
+// cannot be read, so a view straddling an unmapped page still shows the bytes around it.
+pub fn view_memory(pid: i32, address: u64, length: usize) -> Result<MemoryView, String> {
+    if length == 0 {
+        return Err("Nothing to view: length is 0".to_string());
+    }
+    let truncated = length > MAX_VIEW_LENGTH;
+    let length = length.min(MAX_VIEW_LENGTH);
+    let end = address
+        .checked_add(length as u64)
+        .ok_or_else(|| format!("{:#x} + {} runs past the address space", address, length))?;
 
-    let mut current_address: u64 = 0;
-    let mut first_item = true;
-
-    for cap in re.captures_iter(&resolved_addr) {
-        let op = cap.get(1).map(|m| m.as_str());
-        let value_str = cap.get(2).unwrap().as_str();
-        let value = parse_number(value_str)?;
-
-        if first_item {
-            current_address = value;
-            first_item = false;
-        } else if let Some(operator) = op {
-            match operator {
-                "+" => current_address = current_address.wrapping_add(value),
-                "-" => current_address = current_address.wrapping_sub(value),
-                "*" => current_address = current_address.wrapping_mul(value),
-                _ => return Err(format!("Invalid operation: {}", operator)),
-            }
-        } else {
-            return Err("Expected operator, but none found".to_string());
+    let mut bytes: Vec<Option<u8>> = vec![None; length];
+    let mut current = address;
+    while current < end {
+        let page_size = PAGE_SIZE as u64;
+        let page_end = ((current / page_size) + 1)
+            .saturating_mul(page_size)
+            .min(end);
+        let chunk_size = (page_end - current) as usize;
+        let mut buffer = vec![0u8; chunk_size];
+        let nread = native_bridge::read_process_memory(
+            pid,
+            current as *mut libc::c_void,
+            chunk_size,
+            &mut buffer,
+        )
+        .map(|nread| nread.max(0) as usize)
+        .unwrap_or(0);
+        let offset = (current - address) as usize;
+        for (cell, &byte) in bytes[offset..]
+            .iter_mut()
+            .zip(&buffer[..nread.min(chunk_size)])
+        {
+            *cell = Some(byte);
         }
+        current = page_end;
     }
 
-    if first_item {
-        current_address = parse_number(&resolved_addr)?;
+    let lead = (address % HEXDUMP_ROW as u64) as usize;
+    let rows = (0..(lead + length).div_ceil(HEXDUMP_ROW))
+        .map(|row| {
+            let first = (row * HEXDUMP_ROW).saturating_sub(lead);
+            let last = ((row + 1) * HEXDUMP_ROW - lead).min(length);
+            ViewRow {
+                address: address - lead as u64 + (row * HEXDUMP_ROW) as u64,
+                read_failed: bytes[first..last].iter().any(Option::is_none),
+            }
+        })
+        .collect();
+    Ok(MemoryView {
+        address,
+        length,
+        printable: bytes
+            .iter()
+            .map(|byte| byte.is_some_and(is_printable))
+            .collect(),
+        rows,
+        hexdump: format_hexdump_cells(&bytes, address),
+        bytes,
+        truncated,
+    })
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StructField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub field_type: String,
+    pub offset: u64,
+    // Only for "ptr64" and "ptr32": the layout of the struct the pointer refers to
+    pub fields: Option<Vec<StructField>>,
+}
+
+// Reads each field at address+offset; a "ptr64" or "ptr32" field becomes {"address", "fields"}
+// where fields is the dissected sub-struct, or null when the pointer is null or has no layout
+pub fn dissect(pid: i32, address: u64, fields: &[StructField]) -> Result<Value, String> {
+    let mut object = serde_json::Map::new();
+    for field in fields {
+        let field_address = address.wrapping_add(field.offset);
+        let pointer = match field.field_type.as_str() {
+            "ptr64" => Some(read_memory_64(pid, field_address)?),
+            "ptr32" => Some(read_memory_32_endian(pid, field_address, Endianness::Little)? as u64),
+            _ => None,
+        };
+        let value = if let Some(pointer) = pointer {
+            let sub_struct = match &field.fields {
+                Some(sub_fields) if pointer != 0 => dissect(pid, pointer, sub_fields)?,
+                _ => Value::Null,
+            };
+            json!({ "address": pointer, "fields": sub_struct })
+        } else {
+            read_typed_value(pid, field_address, &field.field_type)
+                .map_err(|e| format!("Field '{}': {}", field.name, e))?
+        };
+        object.insert(field.name.clone(), value);
     }
+    Ok(Value::Object(object))
+}
 
-    Ok(current_address)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StringEncoding {
+    Ascii,
+    Utf8,
+    Utf16Le,
 }
 
-fn preemptive_module_resolution(
-    addr: &str,
-    modules: &[serde_json::Value],
-) -> Result<String, String> {
-    let mut resolved = String::from(addr);
-    for module in modules {
-        if let (Some(name), Some(base)) = (module["modulename"].as_str(), module["base"].as_u64()) {
-            let path = Path::new(name);
-            if let Some(file_name) = path.file_name() {
-                let file_name_str = file_name.to_string_lossy();
-                let escaped_name = regex::escape(&file_name_str);
-                let re = Regex::new(&format!(r"\b{}\b", escaped_name))
-                    .map_err(|e| format!("Regex error: {}", e))?;
-
-                resolved = re
-                    .replace_all(&resolved, |caps: &regex::Captures| {
-                        let matched = caps.get(0).unwrap().as_str();
-                        if resolved[caps.get(0).unwrap().end()..].starts_with('.') {
-                            matched.to_string()
-                        } else {
-                            format!("0x{:X}", base)
-                        }
-                    })
-                    .to_string();
+impl StringEncoding {
+    pub fn parse(encoding: &str) -> Result<StringEncoding, String> {
+        match encoding {
+            "ascii" => Ok(StringEncoding::Ascii),
+            "utf8" | "utf-8" => Ok(StringEncoding::Utf8),
+            "utf16" | "utf-16" | "utf16le" | "utf-16le" => Ok(StringEncoding::Utf16Le),
+            _ => Err(format!(
+                "Unknown string encoding '{}': expected one of ascii, utf-8, utf-16le",
+                encoding
+            )),
+        }
+    }
+
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>, String> {
+        match self {
+            StringEncoding::Ascii => {
+                if let Some(c) = text.chars().find(|c| !c.is_ascii()) {
+                    return Err(format!("'{}' is not an ASCII character", c));
+                }
+                Ok(text.as_bytes().to_vec())
+            }
+            StringEncoding::Utf8 => Ok(text.as_bytes().to_vec()),
+            StringEncoding::Utf16Le => {
+                let units: Vec<u16> = text.encode_utf16().collect();
+                let mut bytes = vec![0u8; units.len() * 2];
+                LittleEndian::write_u16_into(&units, &mut bytes);
+                Ok(bytes)
             }
         }
     }
-    Ok(resolved)
-}
 
-fn parse_number(s: &str) -> Result<u64, String> {
-    let s = s.trim();
-    if s.starts_with("0x") {
-        u64::from_str_radix(&s[2..], 16)
-    } else {
-        s.parse::<u64>()
+    // Bytes per code unit, which is also the size of the terminator
+    fn unit_size(&self) -> usize {
+        match self {
+            StringEncoding::Utf16Le => 2,
+            _ => 1,
+        }
     }
-    .map_err(|e: ParseIntError| format!("Invalid number '{}': {}", s, e))
 }
 
-pub fn resolve_symbolic_address(
+// Width of the little-endian length in front of a length-prefixed string. The length counts
+// code units, so UTF-16 characters rather than bytes for utf-16le.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LengthPrefix {
+    U8,
+    U16,
+    U32,
+}
+
+// Writes text at address as prefix, characters, then terminator, and returns how many bytes
+// that was. Nothing is written when the whole string would take more than max_len bytes, so
+// whatever follows the string's buffer is never overwritten.
+pub fn write_string(
     pid: i32,
-    symbolic_addr: &str,
-    modules: &[serde_json::Value],
+    address: u64,
+    text: &str,
+    encoding: StringEncoding,
+    null_terminate: bool,
+    length_prefix: Option<LengthPrefix>,
+    max_len: usize,
 ) -> Result<usize, String> {
-    let resolved = resolve_nested_address(pid, symbolic_addr, modules)?;
-    Ok(resolved as usize)
+    let encoded = encoding.encode(text)?;
+    let units = encoded.len() / encoding.unit_size();
+    let mut bytes = match length_prefix {
+        None => Vec::new(),
+        Some(LengthPrefix::U8) => vec![u8::try_from(units)
+            .map_err(|_| format!("{} characters do not fit a u8 length prefix", units))?],
+        Some(LengthPrefix::U16) => u16::try_from(units)
+            .map_err(|_| format!("{} characters do not fit a u16 length prefix", units))?
+            .to_le_bytes()
+            .to_vec(),
+        Some(LengthPrefix::U32) => u32::try_from(units)
+            .map_err(|_| format!("{} characters do not fit a u32 length prefix", units))?
+            .to_le_bytes()
+            .to_vec(),
+    };
+    bytes.extend_from_slice(&encoded);
+    if null_terminate {
+        bytes.resize(bytes.len() + encoding.unit_size(), 0);
+    }
+    if bytes.is_empty() {
+        return Err("Nothing to write for an empty string".to_string());
+    }
+    if bytes.len() > max_len {
+        return Err(format!(
+            "String needs {} bytes but at most {} may be written",
+            bytes.len(),
+            max_len
+        ));
+    }
+    write_memory_bytes(pid, address, &bytes)?;
+    Ok(bytes.len())
 }
-pub fn parse_directory_structure(raw_data: &str) -> Vec<FileItem> {
-    let mut root_items = Vec::new();
-    let mut stack: Vec<*mut FileItem> = Vec::new();
 
-    for line in raw_data.lines() {
-        let indent = line.chars().take_while(|&c| c == ' ').count() / 2;
-        let content = line.trim_start();
+const STRING_READ_CHUNK: usize = 256;
+const PAGE_SIZE: usize = 0x1000;
 
-        if let Some((item_type, rest)) = content.split_once(':') {
-            let new_item = match item_type {
-                "dir" => FileItem {
-                    item_type: "directory".to_string(),
-                    name: rest.to_string(),
-                    size: None,
-                    last_opened: None,
-                    children: None,
-                },
-                "file" => {
-                    let parts: Vec<&str> = rest.split(',').collect();
-                    if parts.len() == 3 {
-                        FileItem {
-                            item_type: "file".to_string(),
-                            name: parts[0].to_string(),
-                            size: parts[1].parse().ok(),
-                            last_opened: parts[2].parse().ok(),
-                            children: None,
-                        }
-                    } else {
-                        continue;
-                    }
-                }
-                _ => continue,
-            };
+pub fn read_string(
+    pid: i32,
+    address: u64,
+    max_len: usize,
+    encoding: StringEncoding,
+) -> Result<String, String> {
+    let unit = match encoding {
+        StringEncoding::Utf16Le => 2,
+        _ => 1,
+    };
+    let mut bytes: Vec<u8> = Vec::new();
+    let mut chunk = [0u8; STRING_READ_CHUNK];
 
-            while stack.len() > indent {
-                stack.pop();
+    while bytes.len() < max_len {
+        let current = address + bytes.len() as u64;
+        // Never cross a page boundary in one read so a string that ends right before
+        // an unmapped page is still returned
+        let page_remaining = PAGE_SIZE - (current as usize % PAGE_SIZE);
+        let size = STRING_READ_CHUNK
+            .min(max_len - bytes.len())
+            .min(page_remaining);
+        let nread = match native_bridge::read_process_memory(
+            pid,
+            current as *mut libc::c_void,
+            size,
+            &mut chunk[..size],
+        ) {
+            Ok(nread) => nread.max(0) as usize,
+            Err(e) => {
+                if bytes.is_empty() {
+                    return Err(format!(
+                        "Failed to read string at address {:#x}: {}",
+                        address, e
+                    ));
+                }
+                break;
             }
+        };
+        if nread == 0 {
+            break;
+        }
 
-            if stack.is_empty() {
-                root_items.push(new_item);
+        let scanned = bytes.len() / unit * unit;
+        bytes.extend_from_slice(&chunk[..nread]);
+        if let Some(position) = bytes[scanned..]
+            .chunks_exact(unit)
+            .position(|code_unit| code_unit.iter().all(|&b| b == 0))
+        {
+            bytes.truncate(scanned + position * unit);
+            break;
+        }
+    }
 
-                if item_type == "dir" {
-                    let last_ptr = root_items.last_mut().unwrap() as *mut FileItem;
-                    stack.push(last_ptr);
+    let text = match encoding {
+        StringEncoding::Ascii => bytes
+            .iter()
+            .map(|&b| {
+                if b.is_ascii() {
+                    b as char
+                } else {
+                    char::REPLACEMENT_CHARACTER
                 }
-            } else {
-                unsafe {
-                    let parent = stack.last_mut().unwrap();
-                    if let Some(children) = &mut (**parent).children {
-                        children.push(new_item);
-                    } else {
-                        (**parent).children = Some(vec![new_item]);
-                    }
+            })
+            .collect(),
+        StringEncoding::Utf8 => String::from_utf8_lossy(&bytes).into_owned(),
+        StringEncoding::Utf16Le => {
+            let units: Vec<u16> = bytes
+                .chunks_exact(2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+    };
+    Ok(text)
+}
 
-                    if item_type == "dir" {
-                        let last_ptr = (**parent).children.as_mut().unwrap().last_mut().unwrap()
-                            as *mut FileItem;
-                        stack.push(last_ptr);
-                    }
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ExprToken {
+    Number(isize),
+    Op(&'static str),
+    LParen,
+    RParen,
+}
+
+fn tokenize_expression(expr: &str) -> Result<Vec<ExprToken>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = expr.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '0'..='9' => {
+                let mut digits = String::new();
+                while let Some(&d) = chars.peek().filter(|d| d.is_ascii_alphanumeric()) {
+                    digits.push(d);
+                    chars.next();
+                }
+                // Hex literals cover the full 64-bit range and wrap into isize like a pointer cast
+                let value = match digits
+                    .strip_prefix("0x")
+                    .or_else(|| digits.strip_prefix("0X"))
+                {
+                    Some(hex_digits) => u64::from_str_radix(hex_digits, 16).map(|v| v as isize),
+                    None => digits.parse(),
+                }
+                .map_err(|_| format!("Invalid number '{}' in expression", digits))?;
+                tokens.push(ExprToken::Number(value));
+            }
+            '<' | '>' => {
+                chars.next();
+                if chars.next() != Some(c) {
+                    return Err(format!("Unexpected character '{}' in expression", c));
                 }
+                tokens.push(ExprToken::Op(if c == '<' { "<<" } else { ">>" }));
             }
+            '+' | '-' | '*' | '/' | '&' | '|' | '^' => {
+                let op = match c {
+                    '+' => "+",
+                    '-' => "-",
+                    '*' => "*",
+                    '/' => "/",
+                    '&' => "&",
+                    '|' => "|",
+                    _ => "^",
+                };
+                tokens.push(ExprToken::Op(op));
+                chars.next();
+            }
+            '(' => {
+                tokens.push(ExprToken::LParen);
+                chars.next();
+            }
+            ')' => {
+                tokens.push(ExprToken::RParen);
+                chars.next();
+            }
+            _ => return Err(format!("Unexpected character '{}' in expression", c)),
         }
     }
+    Ok(tokens)
+}
 
-    root_items
+// Same relative precedence as C: | < ^ < & < shifts < additive < multiplicative
+fn binary_precedence(op: &str) -> Option<u8> {
+    match op {
+        "|" => Some(1),
+        "^" => Some(2),
+        "&" => Some(3),
+        "<<" | ">>" => Some(4),
+        "+" | "-" => Some(5),
+        "*" | "/" => Some(6),
+        _ => None,
+    }
 }
 
-pub fn get_cache_directory(pid: i32) -> String {
-    let result = native_bridge::get_application_info(pid);
-    let parsed_result: Value = serde_json::from_str(&result.unwrap()).unwrap();
-    let target_os = env!("TARGET_OS");
-    if target_os == "ios" {
-        parsed_result["CachesDirectory"]
-            .to_string()
-            .replace("\"", "")
-    } else {
-        "".to_string()
+fn apply_binary(op: &str, a: isize, b: isize) -> Result<isize, String> {
+    match op {
+        "+" => Ok(a.wrapping_add(b)),
+        "-" => Ok(a.wrapping_sub(b)),
+        "*" => Ok(a.wrapping_mul(b)),
+        "/" if b == 0 => Err("Division by zero in expression".to_string()),
+        "/" => Ok(a.wrapping_div(b)),
+        "&" => Ok(a & b),
+        "|" => Ok(a | b),
+        "^" => Ok(a ^ b),
+        // Bits shifted past the top are dropped, so oversized shifts give 0 (or the sign for >>)
+        "<<" | ">>" if b < 0 => Err(format!("Negative shift amount {} in expression", b)),
+        "<<" => Ok(u32::try_from(b)
+            .ok()
+            .and_then(|b| a.checked_shl(b))
+            .unwrap_or(0)),
+        ">>" => Ok(u32::try_from(b)
+            .ok()
+            .and_then(|b| a.checked_shr(b))
+            .unwrap_or(if a < 0 { -1 } else { 0 })),
+        _ => Err(format!("Unsupported operation '{}'", op)),
     }
 }
 
-pub fn disassemble(bytecode: *const u8, length: usize, address: u64) -> String {
-    let bytes = unsafe { slice::from_raw_parts(bytecode, length) };
-    let cs = Capstone::new()
-        .arm64()
-        .mode(arch::arm64::ArchMode::Arm)
-        .detail(true)
-        .build()
-        .expect("Failed to create Capstone object");
+// Deepest nesting of parentheses and unary signs, so a client cannot overflow the stack
+pub const MAX_EXPRESSION_DEPTH: usize = 256;
+
+struct ExpressionParser {
+    tokens: Vec<ExprToken>,
+    pos: usize,
+    depth: usize,
+}
+
+impl ExpressionParser {
+    fn next(&mut self) -> Option<ExprToken> {
+        let token = self.tokens.get(self.pos).copied();
+        self.pos += 1;
+        token
+    }
+
+    // Precedence climbing: each call folds every operator binding at least as tightly as
+    // min_precedence
+    fn parse_binary(&mut self, min_precedence: u8) -> Result<isize, String> {
+        let mut lhs = self.parse_unary()?;
+        while let Some(&ExprToken::Op(op)) = self.tokens.get(self.pos) {
+            let precedence = match binary_precedence(op) {
+                Some(precedence) if precedence >= min_precedence => precedence,
+                _ => break,
+            };
+            self.pos += 1;
+            let rhs = self.parse_binary(precedence + 1)?;
+            lhs = apply_binary(op, lhs, rhs)?;
+        }
+        Ok(lhs)
+    }
+
+    fn parse_unary(&mut self) -> Result<isize, String> {
+        if self.depth >= MAX_EXPRESSION_DEPTH {
+            return Err(format!(
+                "Expression nests deeper than {} levels",
+                MAX_EXPRESSION_DEPTH
+            ));
+        }
+        self.depth += 1;
+        let value = self.parse_operand();
+        self.depth -= 1;
+        value
+    }
 
-    let instructions = cs
-        .disasm_all(bytes, address)
-        .expect("Failed to disassemble");
-    let mut result = String::new();
+    fn parse_operand(&mut self) -> Result<isize, String> {
+        match self.next() {
+            Some(ExprToken::Number(value)) => Ok(value),
+            Some(ExprToken::Op("-")) => Ok(self.parse_unary()?.wrapping_neg()),
+            Some(ExprToken::Op("+")) => self.parse_unary(),
+            Some(ExprToken::LParen) => {
+                let value = self.parse_binary(0)?;
+                match self.next() {
+                    Some(ExprToken::RParen) => Ok(value),
+                    _ => Err("Missing closing parenthesis in expression".to_string()),
+                }
+            }
+            Some(token) => Err(format!("Unexpected token {:?} in expression", token)),
+            None => Err("Unexpected end of expression".to_string()),
+        }
+    }
+}
 
-    for i in instructions.iter() {
-        let mnemonic = i.mnemonic().unwrap_or("");
-        let op_str = i.op_str().unwrap_or("");
-        result.push_str(&format!("{:#x}: {} {}\n", i.address(), mnemonic, op_str));
+pub fn evaluate_expression(expr: &str) -> Result<isize, String> {
+    let mut parser = ExpressionParser {
+        tokens: tokenize_expression(expr)?,
+        pos: 0,
+        depth: 0,
+    };
+    if parser.tokens.is_empty() {
+        return Err("Invalid expression".to_string());
     }
+    let value = parser.parse_binary(0)?;
+    match parser.tokens.get(parser.pos) {
+        None => Ok(value),
+        Some(token) => Err(format!("Unexpected token {:?} in expression", token)),
+    }
+}
+
+// Module names are matched by file name, case-insensitively, and only where not followed by '.'
+// so "libc" never matches inside "libc.so". A name followed by "!symbol" stands for that
+// export instead of the module base. The lookup table and pattern are built once per module
+// list and can then be reused for any number of addresses.
+pub struct ModuleMap {
+    pid: i32,
+    // Base and path of each module by lowercase file name
+    bases: HashMap<String, (u64, String)>,
+    pattern: Option<Regex>,
+}
 
-    result
+impl ModuleMap {
+    pub fn new(pid: i32, modules: &[serde_json::Value]) -> Result<ModuleMap, String> {
+        let mut bases = HashMap::new();
+        for module in modules {
+            if let (Some(name), Some(base)) =
+                (module["modulename"].as_str(), module["base"].as_u64())
+            {
+                if let Some(file_name) = Path::new(name).file_name() {
+                    bases
+                        .entry(file_name.to_string_lossy().to_lowercase())
+                        .or_insert((base, name.to_string()));
+                }
+            }
+        }
+
+        // Longest names first so "libfoo.so.1" wins over "libfoo.so" at the same position
+        let mut names: Vec<&String> = bases.keys().collect();
+        names.sort_by_key(|name| std::cmp::Reverse(name.len()));
+        let pattern = if names.is_empty() {
+            None
+        } else {
+            let alternatives: Vec<String> = names.iter().map(|name| regex::escape(name)).collect();
+            Some(
+                Regex::new(&format!(
+                    r"(?i)\b({})\b(?:!([A-Za-z_$][\w$.@]*))?",
+                    alternatives.join("|")
+                ))
+                .map_err(|e| format!("Regex error: {}", e))?,
+            )
+        };
+        Ok(ModuleMap {
+            pid,
+            bases,
+            pattern,
+        })
+    }
+
+    fn resolve_names(&self, addr: &str) -> Result<String, String> {
+        let pattern = match &self.pattern {
+            Some(pattern) => pattern,
+            None => return Ok(addr.to_string()),
+        };
+        let mut resolved = String::with_capacity(addr.len());
+        let mut last = 0;
+        for caps in pattern.captures_iter(addr) {
+            let matched = caps.get(0).unwrap();
+            let name = caps.get(1).unwrap().as_str();
+            resolved.push_str(&addr[last..matched.start()]);
+            last = matched.end();
+            let (base, path) = match self.bases.get(&name.to_lowercase()) {
+                Some(module) if !addr[matched.end()..].starts_with('.') => module,
+                _ => {
+                    resolved.push_str(matched.as_str());
+                    continue;
+                }
+            };
+            let address = match caps.get(2) {
+                Some(symbol) => symbols::module_exports(self.pid, path)
+                    .find(symbol.as_str())
+                    .map(|export| base.wrapping_add(export.offset))
+                    .ok_or_else(|| format!("Symbol {} not found in {}", symbol.as_str(), name))?,
+                None => *base,
+            };
+            resolved.push_str(&format!("0x{:X}", address));
+        }
+        resolved.push_str(&addr[last..]);
+        Ok(resolved)
+    }
+}
+
+pub fn resolve_nested_address(
+    pid: i32,
+    nested_addr: &str,
+    modules: &[serde_json::Value],
+) -> Result<u64, String> {
+    resolve_nested_address_with_map(pid, nested_addr, &ModuleMap::new(pid, modules)?)
+}
+
+pub fn resolve_nested_address_with_map(
+    pid: i32,
+    nested_addr: &str,
+    module_map: &ModuleMap,
+) -> Result<u64, String> {
+    if !nested_addr.contains(['[', ']']) {
+        return resolve_single_level_address(nested_addr, module_map);
+    }
+
+    let re = Regex::new(r"(\[)|(\])|([^\[\]]+)").map_err(|e| format!("Regex error: {}", e))?;
+    // One term list per open bracket; a closed bracket is dereferenced and becomes a single
+    // value in its parent, so pointer values are never formatted and re-parsed as text
+    let mut stack: Vec<Vec<AddressTerm>> = Vec::new();
+    let mut current: Vec<AddressTerm> = Vec::new();
+
+    for cap in re.captures_iter(nested_addr) {
+        if cap.get(1).is_some() {
+            stack.push(std::mem::take(&mut current));
+        } else if cap.get(2).is_some() {
+            let mut parent = stack.pop().ok_or_else(unbalanced_brackets)?;
+            let inner_value = evaluate_address_terms(&current)?;
+            let memory_value = read_memory_64(pid, inner_value)?;
+            parent.push(AddressTerm::Value(memory_value));
+            current = parent;
+        } else if let Some(m) = cap.get(3) {
+            current.extend(tokenize_address(m.as_str(), module_map)?);
+        }
+    }
+
+    if !stack.is_empty() {
+        return Err(unbalanced_brackets());
+    }
+    evaluate_address_terms(&current)
+}
+
+fn unbalanced_brackets() -> String {
+    "Unbalanced brackets in address expression".to_string()
+}
+
+pub fn resolve_single_level_address(addr: &str, module_map: &ModuleMap) -> Result<u64, String> {
+    evaluate_address_terms(&tokenize_address(addr, module_map)?)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AddressTerm {
+    Value(u64),
+    Op(char),
+}
+
+fn tokenize_address(addr: &str, module_map: &ModuleMap) -> Result<Vec<AddressTerm>, String> {
+    let resolved_addr = module_map.resolve_names(addr)?;
+    let re =
+        Regex::new(r"([+\-*/])|(0x[\da-fA-F]+|\d+)").map_err(|e| format!("Regex error: {}", e))?;
+
+    re.captures_iter(&resolved_addr)
+        .map(|cap| match (cap.get(1), cap.get(2)) {
+            (Some(op), _) => Ok(AddressTerm::Op(op.as_str().chars().next().unwrap())),
+            (_, Some(value)) => parse_number(value.as_str()).map(AddressTerm::Value),
+            _ => unreachable!(),
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .and_then(|terms| {
+            if terms.is_empty() && !resolved_addr.trim().is_empty() {
+                parse_number(&resolved_addr).map(|value| vec![AddressTerm::Value(value)])
+            } else {
+                Ok(terms)
+            }
+        })
+}
+
+// * and / fold into the current term, + and - start a new one, giving the usual precedence.
+// A leading sign applies to the first term, so "-0x10" wraps like 0 - 0x10.
+fn evaluate_address_terms(terms: &[AddressTerm]) -> Result<u64, String> {
+    let mut current_address: u64 = 0;
+    let mut pending_op = '+';
+    let mut term: Option<u64> = None;
+    let mut operator: Option<char> = None;
+
+    for &item in terms {
+        match (item, term, operator) {
+            (AddressTerm::Op(op), None, None) if op == '+' || op == '-' => {
+                pending_op = op;
+                operator = Some('+');
+            }
+            (AddressTerm::Op(op), Some(_), None) => operator = Some(op),
+            (AddressTerm::Value(value), None, _) => {
+                term = Some(value);
+                operator = None;
+            }
+            (AddressTerm::Value(value), Some(current), Some(op)) => {
+                term = Some(match op {
+                    '*' => current.wrapping_mul(value),
+                    '/' => current
+                        .checked_div(value)
+                        .ok_or_else(|| "Division by zero in address expression".to_string())?,
+                    _ => {
+                        current_address = apply_term(current_address, pending_op, current);
+                        pending_op = op;
+                        value
+                    }
+                });
+                operator = None;
+            }
+            (AddressTerm::Value(_), Some(_), None) => {
+                return Err("Expected operator, but none found".to_string())
+            }
+            (AddressTerm::Op(op), _, _) => return Err(format!("Invalid operation: {}", op)),
+        }
+    }
+
+    match (term, operator) {
+        (Some(term), None) => Ok(apply_term(current_address, pending_op, term)),
+        (None, None) => Err("Empty address expression".to_string()),
+        (_, Some(op)) => Err(format!("Missing operand after '{}'", op)),
+    }
+}
+
+fn apply_term(current_address: u64, op: char, term: u64) -> u64 {
+    if op == '-' {
+        current_address.wrapping_sub(term)
+    } else {
+        current_address.wrapping_add(term)
+    }
+}
+
+fn parse_number(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    if s.starts_with("0x") {
+        u64::from_str_radix(&s[2..], 16)
+    } else {
+        s.parse::<u64>()
+    }
+    .map_err(|e: ParseIntError| format!("Invalid number '{}': {}", s, e))
+}
+
+pub fn resolve_symbolic_address(
+    pid: i32,
+    symbolic_addr: &str,
+    modules: &[serde_json::Value],
+) -> Result<usize, String> {
+    let resolved = resolve_nested_address(pid, symbolic_addr, modules)?;
+    Ok(resolved as usize)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolvedAddress {
+    pub address: u64,
+    pub module_name: Option<String>,
+    pub module_offset: Option<u64>,
+}
+
+// Module info is only filled in when the final address lies within a module's base..base+size
+pub fn resolve_with_module_context(
+    pid: i32,
+    symbolic_addr: &str,
+    modules: &[serde_json::Value],
+) -> Result<ResolvedAddress, String> {
+    let address = resolve_symbolic_address(pid, symbolic_addr, modules)? as u64;
+    let module = modules.iter().find_map(|module| {
+        let base = module["base"].as_u64()?;
+        let size = module["size"].as_u64()?;
+        if address < base || address >= base.saturating_add(size) {
+            return None;
+        }
+        let name = module["modulename"].as_str()?;
+        let file_name = Path::new(name).file_name()?.to_string_lossy().into_owned();
+        Some((file_name, address - base))
+    });
+
+    Ok(ResolvedAddress {
+        address,
+        module_name: module.as_ref().map(|(name, _)| name.clone()),
+        module_offset: module.map(|(_, offset)| offset),
+    })
+}
+pub const DEFAULT_INDENT_WIDTH: usize = 2;
+
+// One tab is one level; spaces count as a level per indent_width of them
+fn indent_level(line: &str, indent_width: usize) -> usize {
+    let mut tabs = 0;
+    let mut spaces = 0;
+    for c in line.chars() {
+        match c {
+            '\t' => tabs += 1,
+            ' ' => spaces += 1,
+            _ => break,
+        }
+    }
+    tabs + spaces / indent_width.max(1)
+}
+
+pub fn parse_directory_structure(raw_data: &str, indent_width: usize) -> Vec<FileItem> {
+    let mut root_items = Vec::new();
+    // Directories that are still open, outermost first; each owns its children until it is
+    // closed and moved into its parent
+    let mut stack: Vec<FileItem> = Vec::new();
+
+    for line in raw_data.lines() {
+        // lines() only drops the \r of a \r\n pair, so stray carriage returns are trimmed here
+        let line = line.trim_end_matches('\r');
+        let indent = indent_level(line, indent_width);
+        let content = line.trim_start();
+
+        if let Some((item_type, rest)) = content.split_once(':') {
+            let new_item = match item_type {
+                "dir" => FileItem {
+                    item_type: "directory".to_string(),
+                    name: rest.to_string(),
+                    size: None,
+                    last_opened: None,
+                    children: None,
+                },
+                "file" => {
+                    let parts: Vec<&str> = rest.split(',').collect();
+                    if parts.len() == 3 {
+                        FileItem {
+                            item_type: "file".to_string(),
+                            name: parts[0].to_string(),
+                            size: parts[1].parse().ok(),
+                            // A negative mtime is not a real timestamp
+                            last_opened: parts[2].parse().ok().filter(|&time: &i64| time >= 0),
+                            children: None,
+                        }
+                    } else {
+                        continue;
+                    }
+                }
+                _ => continue,
+            };
+
+            while stack.len() > indent {
+                close_directory(&mut stack, &mut root_items);
+            }
+
+            if item_type == "dir" {
+                stack.push(new_item);
+            } else {
+                attach_item(stack.last_mut(), &mut root_items, new_item);
+            }
+        }
+    }
+
+    while !stack.is_empty() {
+        close_directory(&mut stack, &mut root_items);
+    }
+
+    root_items
+}
+
+fn close_directory(stack: &mut Vec<FileItem>, root_items: &mut Vec<FileItem>) {
+    if let Some(directory) = stack.pop() {
+        attach_item(stack.last_mut(), root_items, directory);
+    }
+}
+
+fn attach_item(parent: Option<&mut FileItem>, root_items: &mut Vec<FileItem>, item: FileItem) {
+    match parent {
+        Some(parent) => parent.children.get_or_insert_with(Vec::new).push(item),
+        None => root_items.push(item),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileSortKey {
+    Name,
+    Size,
+    LastOpened,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+// Sorts every level of the tree; entries without the key (e.g. directory sizes) sort first
+// when ascending, and ties fall back to the name so the order is stable across requests
+pub fn sort_file_items(items: &mut [FileItem], key: FileSortKey, order: SortOrder) {
+    items.sort_by(|a, b| {
+        let ordering = match key {
+            FileSortKey::Name => a.name.cmp(&b.name),
+            FileSortKey::Size => a.size.cmp(&b.size).then_with(|| a.name.cmp(&b.name)),
+            FileSortKey::LastOpened => a
+                .last_opened
+                .cmp(&b.last_opened)
+                .then_with(|| a.name.cmp(&b.name)),
+        };
+        match order {
+            SortOrder::Asc => ordering,
+            SortOrder::Desc => ordering.reverse(),
+        }
+    });
+    for item in items.iter_mut() {
+        if let Some(children) = item.children.as_mut() {
+            sort_file_items(children, key, order);
+        }
+    }
+}
+
+// Largest file moved in a single request; bigger files go through read_file_chunk and
+// appending uploads
+pub const MAX_FILE_TRANSFER_SIZE: u64 = 256 * 1024 * 1024;
+
+// Path query parameters arrive percent-encoded; decoding happens before check_file_path so an
+// encoded "%2e%2e%2f" is refused as "../" by its ".." check
+pub fn decode_path_param(raw: &str) -> Result<String, String> {
+    let decoded = percent_decode_str(raw)
+        .decode_utf8()
+        .map_err(|_| format!("Path {} is not valid UTF-8 once decoded", raw))?;
+    if decoded.contains('\0') {
+        return Err("Invalid path: contains null byte".to_string());
+    }
+    Ok(decoded.into_owned())
+}
+
+lazy_static::lazy_static! {
+    // Every path a request names must resolve inside this directory; set from the settings
+    static ref FILE_ROOT: RwLock<PathBuf> = RwLock::new(PathBuf::from("/"));
+}
+
+// A ".." in a request's path is refused outright unless the settings allow it
+static ALLOW_PARENT_PATHS: AtomicBool = AtomicBool::new(false);
+
+// root must already be canonical, as Settings keeps it
+pub fn set_file_root(root: PathBuf) {
+    *FILE_ROOT.write().unwrap() = root;
+}
+
+pub fn set_allow_parent_paths(allow: bool) {
+    ALLOW_PARENT_PATHS.store(allow, Ordering::Relaxed);
+}
+
+// The canonical form of a request's path, which is what the file operation should use.
+// Relative paths are taken from the file root, and symlinks and any allowed ".." are followed
+// before the result is checked against the root.
+pub fn check_file_path(path: &str) -> Result<String, String> {
+    let allow_parent = ALLOW_PARENT_PATHS.load(Ordering::Relaxed);
+    resolve_under_root(&FILE_ROOT.read().unwrap(), path, allow_parent)?
+        .into_os_string()
+        .into_string()
+        .map_err(|_| format!("Path {} does not resolve to valid UTF-8", path))
+}
+
+fn resolve_under_root(root: &Path, path: &str, allow_parent: bool) -> Result<PathBuf, String> {
+    if path.is_empty() {
+        return Err("Path must not be empty".to_string());
+    }
+    // Checked before anything is resolved, since with a root of / every ".." stays inside it
+    if !allow_parent
+        && Path::new(path)
+            .components()
+            .any(|c| c == Component::ParentDir)
+    {
+        return Err(format!("Path {} must not contain \"..\"", path));
+    }
+    let joined = root.join(path);
+    let resolved = match fs::canonicalize(&joined) {
+        Ok(resolved) => resolved,
+        // A file about to be written need not exist yet, but its directory must
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let name = match joined.components().next_back() {
+                Some(Component::Normal(name)) => name.to_owned(),
+                _ => return Err(format!("Path {} does not name a file", path)),
+            };
+            let parent = joined
+                .parent()
+                .ok_or_else(|| format!("Path {} has no parent directory", path))?;
+            fs::canonicalize(parent)
+                .map_err(|e| format!("Failed to resolve {}: {}", path, e))?
+                .join(name)
+        }
+        Err(e) => return Err(format!("Failed to resolve {}: {}", path, e)),
+    };
+    if !resolved.starts_with(root) {
+        return Err(format!(
+            "Path {} is outside the file root {}",
+            path,
+            root.display()
+        ));
+    }
+    Ok(resolved)
+}
+
+fn check_transfer_size(path: &str, size: u64) -> Result<(), String> {
+    if size > MAX_FILE_TRANSFER_SIZE {
+        return Err(format!(
+            "File {} is {} bytes, above the {} byte transfer limit; transfer it in chunks",
+            path, size, MAX_FILE_TRANSFER_SIZE
+        ));
+    }
+    Ok(())
+}
+
+pub fn read_file(path: &str) -> Result<Vec<u8>, String> {
+    // The native reader loads the whole file, so refuse oversized files before it does
+    if let Ok(metadata) = fs::metadata(path) {
+        check_transfer_size(path, metadata.len())?;
+    }
+    native_bridge::read_file_data(path)
+}
+
+pub fn read_file_chunk(path: &str, offset: u64, length: usize) -> Result<Vec<u8>, String> {
+    check_transfer_size(path, length as u64)?;
+    let mut file = File::open(path).map_err(|e| format!("Failed to open file {}: {}", path, e))?;
+    file.seek(SeekFrom::Start(offset))
+        .map_err(|e| format!("Failed to seek to {} in {}: {}", offset, path, e))?;
+    // length comes from the client, so only what the file still holds past offset is reserved
+    let remaining = file
+        .metadata()
+        .map(|metadata| metadata.len().saturating_sub(offset))
+        .unwrap_or(0);
+    let mut data = Vec::with_capacity(length.min(remaining as usize));
+    file.take(length as u64)
+        .read_to_end(&mut data)
+        .map_err(|e| format!("Failed to read file {}: {}", path, e))?;
+    Ok(data)
+}
+
+// With append set the data is added to the end of the file, which is how large uploads are
+// sent chunk by chunk
+pub fn write_file(path: &str, data: &[u8], append: bool) -> Result<(), String> {
+    check_transfer_size(path, data.len() as u64)?;
+    native_bridge::write_file_data(path, data, append)
+}
+
+pub fn suspend_process(pid: i32) -> Result<(), String> {
+    if unsafe { native_bridge::suspend_process(pid) } {
+        Ok(())
+    } else {
+        Err(format!("Failed to suspend process {}", pid))
+    }
+}
+
+pub fn resume_process(pid: i32) -> Result<(), String> {
+    if unsafe { native_bridge::resume_process(pid) } {
+        Ok(())
+    } else {
+        Err(format!("Failed to resume process {}", pid))
+    }
+}
+
+// Keeps the target stopped for as long as the guard lives; it is resumed on drop, including
+// while unwinding from a panic, so a failed scan can't leave the process frozen
+pub struct SuspendGuard {
+    pid: i32,
+}
+
+impl SuspendGuard {
+    pub fn new(pid: i32) -> Result<SuspendGuard, String> {
+        suspend_process(pid)?;
+        Ok(SuspendGuard { pid })
+    }
+}
+
+impl Drop for SuspendGuard {
+    fn drop(&mut self) {
+        if let Err(e) = resume_process(self.pid) {
+            log::error!("{}", e);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessInfo {
+    pub pid: i32,
+    #[serde(rename = "processname")]
+    pub name: String,
+    pub path: Option<String>,
+}
+
+// On Linux and Android a process whose /proc/<pid>/exe can't be read is one we can't attach to
+// either (kernel threads, other users' processes), so it is left out of the list
+pub fn list_processes() -> Vec<ProcessInfo> {
+    let own_pid = std::process::id() as i32;
+    let target_os = env!("TARGET_OS");
+    let checks_access = target_os == "linux" || target_os == "android";
+
+    native_bridge::enumerate_processes()
+        .into_iter()
+        .filter(|(pid, _)| *pid != own_pid)
+        .filter_map(|(pid, name)| {
+            let path = std::fs::read_link(format!("/proc/{}/exe", pid))
+                .ok()
+                .map(|path| path.to_string_lossy().into_owned());
+            if checks_access && path.is_none() {
+                return None;
+            }
+            Some(ProcessInfo { pid, name, path })
+        })
+        .collect()
+}
+
+// How attach_by_name compares a process against the name asked for
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum NameMatch {
+    // The process name or the file name of its executable, exactly
+    #[default]
+    Exact,
+    // Any part of either, ignoring case
+    Substring,
+}
+
+#[derive(Debug)]
+pub enum ProcessLookupError {
+    NotFound(String),
+    // Every process that matched, for the caller to choose from
+    Ambiguous(String, Vec<ProcessInfo>),
+}
+
+impl std::fmt::Display for ProcessLookupError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProcessLookupError::NotFound(name) => write!(f, "No process matches '{}'", name),
+            ProcessLookupError::Ambiguous(name, candidates) => write!(
+                f,
+                "{} processes match '{}'; attach by pid instead",
+                candidates.len(),
+                name
+            ),
+        }
+    }
+}
+
+// Finds the one process called name so a client can attach without looking the pid up
+// first. Only resolves the pid; attaching is still up to the caller.
+pub fn attach_by_name(name: &str, matching: NameMatch) -> Result<ProcessInfo, ProcessLookupError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(ProcessLookupError::NotFound(name.to_string()));
+    }
+    let needle = name.to_lowercase();
+    let matches = |candidate: &str| match matching {
+        NameMatch::Exact => candidate == name,
+        NameMatch::Substring => candidate.to_lowercase().contains(&needle),
+    };
+    let mut candidates: Vec<ProcessInfo> = list_processes()
+        .into_iter()
+        .filter(|process| {
+            let file_name = process
+                .path
+                .as_deref()
+                .and_then(|path| Path::new(path).file_name())
+                .and_then(|file_name| file_name.to_str());
+            matches(&process.name) || file_name.is_some_and(matches)
+        })
+        .collect();
+    match candidates.len() {
+        0 => Err(ProcessLookupError::NotFound(name.to_string())),
+        1 => Ok(candidates.remove(0)),
+        _ => {
+            candidates.sort_by_key(|process| process.pid);
+            Err(ProcessLookupError::Ambiguous(name.to_string(), candidates))
+        }
+    }
+}
+
+// Serializes to the same "modulename"/"base"/"size" shape the address resolver reads,
+// with the short file name alongside so the UI doesn't have to split paths itself
+#[derive(Debug, Clone, Serialize)]
+pub struct ModuleInfo {
+    pub name: String,
+    pub base: u64,
+    pub size: u64,
+    #[serde(rename = "modulename")]
+    pub path: String,
+    pub is_64bit: bool,
+}
+
+pub fn enumerate_modules(pid: i32) -> Vec<ModuleInfo> {
+    let modules = native_bridge::enum_modules(pid).unwrap_or_default();
+    modules
+        .iter()
+        .filter_map(|module| {
+            let path = module["modulename"].as_str()?.to_string();
+            let name = Path::new(&path)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.clone());
+            Some(ModuleInfo {
+                name,
+                base: module["base"].as_u64()?,
+                size: module["size"].as_u64().unwrap_or(0),
+                path,
+                is_64bit: module["is_64bit"].as_bool().unwrap_or(true),
+            })
+        })
+        .collect()
+}
+
+// Pointer width of the target in bytes, taken from its main executable; 8 when that is unknown
+pub fn pointer_size(pid: i32) -> usize {
+    match enumerate_modules(pid).first() {
+        Some(module) if !module.is_64bit => 4,
+        _ => 8,
+    }
+}
+
+pub fn get_cache_directory(pid: i32) -> String {
+    let result = native_bridge::get_application_info(pid);
+    let parsed_result: Value = serde_json::from_str(&result.unwrap()).unwrap();
+    let target_os = env!("TARGET_OS");
+    if target_os == "ios" {
+        parsed_result["CachesDirectory"]
+            .to_string()
+            .replace("\"", "")
+    } else {
+        "".to_string()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Arch {
+    Arm64,
+    X86,
+    #[serde(rename = "x86_64")]
+    X86_64,
+}
+
+// The architecture the server was built for; anything else defaults to arm64, the usual
+// Android and iOS target
+impl Default for Arch {
+    fn default() -> Self {
+        if cfg!(target_arch = "x86_64") {
+            Arch::X86_64
+        } else if cfg!(target_arch = "x86") {
+            Arch::X86
+        } else {
+            Arch::Arm64
+        }
+    }
+}
+
+impl Arch {
+    pub const ALL: [Arch; 3] = [Arch::Arm64, Arch::X86, Arch::X86_64];
+}
+
+// Only meaningful for x86; arm64 has a single syntax and ignores it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AsmSyntax {
+    #[default]
+    Intel,
+    Att,
+}
+
+fn build_capstone(arch: Arch, syntax: AsmSyntax) -> Result<Capstone, String> {
+    let cs = match arch {
+        Arch::Arm64 => Capstone::new()
+            .arm64()
+            .mode(arch::arm64::ArchMode::Arm)
+            .detail(true)
+            .build(),
+        Arch::X86 => Capstone::new()
+            .x86()
+            .mode(arch::x86::ArchMode::Mode32)
+            .detail(true)
+            .build(),
+        Arch::X86_64 => Capstone::new()
+            .x86()
+            .mode(arch::x86::ArchMode::Mode64)
+            .detail(true)
+            .build(),
+    };
+    let mut cs = cs.map_err(|e| format!("Failed to create Capstone object: {}", e))?;
+    if arch != Arch::Arm64 {
+        let syntax = match syntax {
+            AsmSyntax::Intel => capstone::Syntax::Intel,
+            AsmSyntax::Att => capstone::Syntax::Att,
+        };
+        cs.set_syntax(syntax)
+            .map_err(|e| format!("Failed to set disassembly syntax: {}", e))?;
+    }
+    Ok(cs)
+}
+
+pub fn disassembler_available(arch: Arch) -> bool {
+    build_capstone(arch, AsmSyntax::default()).is_ok()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Instruction {
+    pub address: u64,
+    pub bytes: Vec<u8>,
+    pub mnemonic: String,
+    pub op_str: String,
+    pub size: usize,
+    pub branch_target: Option<u64>,
+    // The address an operand names outright, when the instruction alone fixes it
+    pub reference: Option<u64>,
+    // Where reference lands, e.g. "-> libc.so.6!malloc" or "-> [heap]"; set by
+    // annotate_instructions
+    pub annotation: Option<String>,
+}
+
+// Only direct jumps and calls carry their destination as an immediate operand;
+// register and memory operands can't be resolved statically and yield None
+fn branch_target(cs: &Capstone, insn: &capstone::Insn) -> Option<u64> {
+    let detail = cs.insn_detail(insn).ok()?;
+    let is_branch = detail.groups().iter().any(|group| {
+        let group = group.0 as u32;
+        group == capstone::InsnGroupType::CS_GRP_JUMP
+            || group == capstone::InsnGroupType::CS_GRP_CALL
+    });
+    if !is_branch {
+        return None;
+    }
+
+    // arm64 cbz/tbz also take a register or bit number, the target is always the last operand
+    match detail.arch_detail().operands().last()? {
+        arch::ArchOperand::Arm64Operand(op) => match op.op_type {
+            arch::arm64::Arm64OperandType::Imm(target) => Some(target as u64),
+            _ => None,
+        },
+        arch::ArchOperand::X86Operand(op) => match op.op_type {
+            arch::x86::X86OperandType::Imm(target) => Some(target as u64),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// A branch target, an rip-relative or absolute memory operand, or failing those the first
+// immediate. Operands relative to any other register depend on run time state and yield None.
+fn operand_reference(
+    cs: &Capstone,
+    insn: &capstone::Insn,
+    branch_target: Option<u64>,
+) -> Option<u64> {
+    if branch_target.is_some() {
+        return branch_target;
+    }
+    let detail = cs.insn_detail(insn).ok()?;
+    let operands = detail.arch_detail().operands();
+    let next = insn.address() + insn.len() as u64;
+    let memory = operands.iter().find_map(|operand| match operand {
+        arch::ArchOperand::X86Operand(op) => match &op.op_type {
+            arch::x86::X86OperandType::Mem(mem) if mem.index().0 == 0 => match mem.base().0 {
+                0 => Some(mem.disp() as u64),
+                base if base == arch::x86::X86Reg::X86_REG_RIP as u16 => {
+                    Some(next.wrapping_add(mem.disp() as u64))
+                }
+                _ => None,
+            },
+            _ => None,
+        },
+        _ => None,
+    });
+    memory.or_else(|| {
+        operands.iter().find_map(|operand| match operand {
+            arch::ArchOperand::X86Operand(op) => match op.op_type {
+                arch::x86::X86OperandType::Imm(value) => Some(value as u64),
+                _ => None,
+            },
+            arch::ArchOperand::Arm64Operand(op) => match op.op_type {
+                arch::arm64::Arm64OperandType::Imm(value) => Some(value as u64),
+                _ => None,
+            },
+            _ => None,
+        })
+    })
+}
+
+// Labels each instruction whose reference lands in a mapping of pid: a module address by
+// symbol, anything else by the mapping's name or protection. Unmapped references, which
+// include most small immediates, are left without one.
+pub fn annotate_instructions(pid: i32, instructions: &mut [Instruction]) {
+    let references: Vec<u64> = instructions.iter().filter_map(|i| i.reference).collect();
+    if references.is_empty() {
+        return;
+    }
+    let modules = native_bridge::enum_modules(pid).unwrap_or_default();
+    let mut regions = region::enumerate_regions(pid).unwrap_or_default();
+    regions.sort_by_key(|region| region.start);
+    let mut names = symbols::symbolicate_all(pid, &references, &modules).into_iter();
+    for instruction in instructions.iter_mut() {
+        let Some(reference) = instruction.reference else {
+            continue;
+        };
+        let symbol = names.next().flatten();
+        instruction.annotation = symbol
+            .or_else(|| {
+                let region = regions[..regions.partition_point(|r| r.start <= reference)]
+                    .last()
+                    .filter(|region| reference < region.end)?;
+                Some(match &region.path {
+                    Some(path) => path.clone(),
+                    None => format!("anonymous {}", region.protection),
+                })
+            })
+            .map(|name| format!("-> {}", name));
+    }
+}
+
+// Decodes bytes already in hand; the handlers read target code through disassemble_at
+#[cfg(test)]
+pub(crate) fn disassemble_structured(
+    bytecode: &[u8],
+    address: u64,
+    arch: Arch,
+    syntax: AsmSyntax,
+) -> Result<Vec<Instruction>, String> {
+    if bytecode.is_empty() {
+        return Err(format!("No bytes to disassemble at {:#x}", address));
+    }
+    disassemble_bytes(bytecode, address, arch, syntax, None)
+}
+
+// Decodes up to count instructions, or all of them when count is None
+fn disassemble_bytes(
+    bytes: &[u8],
+    address: u64,
+    arch: Arch,
+    syntax: AsmSyntax,
+    count: Option<usize>,
+) -> Result<Vec<Instruction>, String> {
+    let cs = build_capstone(arch, syntax)?;
+
+    let instructions = match count {
+        Some(count) => cs.disasm_count(bytes, address, count),
+        None => cs.disasm_all(bytes, address),
+    }
+    .map_err(|e| format!("Failed to disassemble at {:#x}: {}", address, e))?;
+
+    Ok(instructions
+        .iter()
+        .map(|i| {
+            let branch_target = branch_target(&cs, i);
+            Instruction {
+                address: i.address(),
+                bytes: i.bytes().to_vec(),
+                mnemonic: i.mnemonic().unwrap_or("").to_string(),
+                op_str: i.op_str().unwrap_or("").to_string(),
+                size: i.len(),
+                branch_target,
+                reference: operand_reference(&cs, i, branch_target),
+                annotation: None,
+            }
+        })
+        .collect())
+}
+
+fn max_instruction_size(arch: Arch) -> usize {
+    match arch {
+        Arch::Arm64 => 4,
+        Arch::X86 | Arch::X86_64 => 15,
+    }
+}
+
+// Reads page by page so that a range running into an unmapped page still yields the
+// bytes in front of it
+fn read_until_unmapped(pid: i32, address: u64, size: usize) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(size);
+    let end = address.saturating_add(size as u64);
+    let mut current = address;
+    while current < end {
+        let page_size = PAGE_SIZE as u64;
+        let page_end = ((current / page_size) + 1)
+            .saturating_mul(page_size)
+            .min(end);
+        let chunk_size = (page_end - current) as usize;
+        let mut buffer = vec![0u8; chunk_size];
+        let nread = native_bridge::read_process_memory(
+            pid,
+            current as *mut libc::c_void,
+            chunk_size,
+            &mut buffer,
+        )
+        .map(|nread| nread.max(0) as usize)
+        .unwrap_or(0);
+        bytes.extend_from_slice(&buffer[..nread.min(chunk_size)]);
+        if nread < chunk_size {
+            break;
+        }
+        current = page_end;
+    }
+    bytes
+}
+
+// Instructions decoded per request; the bytes read for them are sized from this up front
+pub const MAX_DISASSEMBLE_COUNT: usize = 4096;
+
+// Fewer than instruction_count instructions come back when the code runs into an unmapped
+// page or bytes that do not decode
+pub fn disassemble_at(
+    pid: i32,
+    address: u64,
+    instruction_count: usize,
+    arch: Arch,
+    syntax: AsmSyntax,
+) -> Result<Vec<Instruction>, String> {
+    if instruction_count == 0 {
+        return Ok(Vec::new());
+    }
+    if instruction_count > MAX_DISASSEMBLE_COUNT {
+        return Err(format!(
+            "Cannot disassemble more than {} instructions at once",
+            MAX_DISASSEMBLE_COUNT
+        ));
+    }
+    let size = instruction_count * max_instruction_size(arch);
+    let mut bytes = read_until_unmapped(pid, address, size);
+    if bytes.is_empty() {
+        return Err(format!("Failed to read memory at {:#x}", address));
+    }
+    // Show the instructions under breakpoints rather than their traps
+    crate::breakpoint::unpatch(pid, address, &mut bytes);
+    disassemble_bytes(&bytes, address, arch, syntax, Some(instruction_count))
+}
+
+// The instruction that ends at address, which is where an x86 data watchpoint leaves pc after
+// the access. Variable-length code can decode several ways backwards, so one with a memory
+// operand is preferred, then the longest.
+pub fn instruction_before(
+    pid: i32,
+    address: u64,
+    arch: Arch,
+    syntax: AsmSyntax,
+) -> Option<Instruction> {
+    let window = max_instruction_size(arch).min(address as usize);
+    let start = address - window as u64;
+    let mut bytes = read_until_unmapped(pid, start, window);
+    if bytes.len() < window {
+        return None;
+    }
+    crate::breakpoint::unpatch(pid, start, &mut bytes);
+    (1..=window)
+        .filter_map(|size| {
+            let from = window - size;
+            let decoded =
+                disassemble_bytes(&bytes[from..], start + from as u64, arch, syntax, Some(1));
+            decoded.ok()?.into_iter().next().filter(|i| i.size == size)
+        })
+        .max_by_key(|i| (i.op_str.contains('['), i.size))
+}
+
+// Why disassemble_function stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FunctionEnd {
+    Return,
+    InstructionLimit,
+    Unmapped,
+    Undecodable,
+}
+
+// ret and retq on x86, ret and the pointer authenticating retaa/retab on arm64
+fn is_return(instruction: &Instruction) -> bool {
+    matches!(
+        instruction.mnemonic.as_str(),
+        "ret" | "retq" | "retaa" | "retab"
+    )
+}
+
+// Bytes read per step of a function walk; most functions end well inside the first one
+const FUNCTION_READ_SIZE: usize = PAGE_SIZE;
+
+// Linear sweep from start up to and including the first return, or max_instructions
+// instructions. Branches are not followed, so code after a jump over the return and
+// tail calls are outside the function as far as this is concerned.
+pub fn disassemble_function(
+    pid: i32,
+    start: u64,
+    arch: Arch,
+    max_instructions: usize,
+    syntax: AsmSyntax,
+) -> Result<(Vec<Instruction>, FunctionEnd), String> {
+    let mut instructions: Vec<Instruction> = Vec::new();
+    let mut current = start;
+    while instructions.len() < max_instructions {
+        let mut bytes = read_until_unmapped(pid, current, FUNCTION_READ_SIZE);
+        if bytes.is_empty() {
+            if instructions.is_empty() {
+                return Err(format!("Failed to read memory at {:#x}", start));
+            }
+            return Ok((instructions, FunctionEnd::Unmapped));
+        }
+        let read_all = bytes.len() == FUNCTION_READ_SIZE;
+        crate::breakpoint::unpatch(pid, current, &mut bytes);
+        let decoded = disassemble_bytes(
+            &bytes,
+            current,
+            arch,
+            syntax,
+            Some(max_instructions - instructions.len()),
+        )?;
+        let Some(last) = decoded.last() else {
+            return Ok((instructions, FunctionEnd::Undecodable));
+        };
+        let next = last.address + last.size as u64;
+        let consumed = (next - current) as usize;
+        for instruction in decoded {
+            let returned = is_return(&instruction);
+            instructions.push(instruction);
+            if returned {
+                return Ok((instructions, FunctionEnd::Return));
+            }
+        }
+        if instructions.len() == max_instructions {
+            break;
+        }
+        // Decoding stops early at bytes that are not an instruction, or at one cut off by
+        // the end of the read; only the second is worth another read
+        if consumed + max_instruction_size(arch) < bytes.len() {
+            return Ok((instructions, FunctionEnd::Undecodable));
+        }
+        if !read_all {
+            return Ok((instructions, FunctionEnd::Unmapped));
+        }
+        current = next;
+    }
+    Ok((instructions, FunctionEnd::InstructionLimit))
+}
+
+// Distinct (package, core) pairs in the CPU topology, so hyperthread siblings count once.
+// Falls back to the logical CPU count where the topology cannot be read.
+pub fn physical_core_count() -> usize {
+    let logical = std::thread::available_parallelism()
+        .map(|count| count.get())
+        .unwrap_or(1);
+    let read_id = |cpu: &Path, name: &str| {
+        fs::read_to_string(cpu.join("topology").join(name))
+            .ok()
+            .and_then(|id| id.trim().parse::<i64>().ok())
+    };
+    let cores: std::collections::HashSet<(i64, i64)> = fs::read_dir("/sys/devices/system/cpu")
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|cpu| {
+                    cpu.file_name()
+                        .and_then(|name| name.to_str())
+                        .and_then(|name| name.strip_prefix("cpu"))
+                        .is_some_and(|index| {
+                            !index.is_empty() && index.bytes().all(|b| b.is_ascii_digit())
+                        })
+                })
+                .filter_map(|cpu| {
+                    Some((
+                        read_id(&cpu, "physical_package_id")?,
+                        read_id(&cpu, "core_id")?,
+                    ))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    if cores.is_empty() {
+        logical
+    } else {
+        cores.len().min(logical)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn big_endian_reads_reverse_the_bytes() {
+        let pid = unsafe { native_bridge::get_pid_native() };
+        let memory = 0x0102030405060708u64.to_le_bytes();
+        let address = memory.as_ptr() as u64;
+        let little = read_memory_64_endian(pid, address, Endianness::Little).unwrap();
+        let big = read_memory_64_endian(pid, address, Endianness::Big).unwrap();
+        assert_eq!(little, 0x0102030405060708);
+        assert_eq!(big, 0x0807060504030201);
+        assert_eq!(read_memory_64(pid, address).unwrap(), little);
+    }
+
+    #[test]
+    fn adjacent_batch_reads_share_one_read() {
+        let pid = unsafe { native_bridge::get_pid_native() };
+        let memory: Vec<u8> = (0..24).collect();
+        let start = memory.as_ptr() as u64;
+        // Out of order, with an unreadable address in the middle of the batch
+        let requests = [(start + 16, 8), (0, 8), (start, 8), (start + 8, 8)];
+
+        let groups = coalesce_reads(&requests);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[1], (start, start + 24, vec![2, 3, 0]));
+
+        let results = read_memory_batch(pid, &requests);
+        assert_eq!(results[0].as_deref(), Ok(&memory[16..24]));
+        assert!(results[1].is_err());
+        assert_eq!(results[2].as_deref(), Ok(&memory[..8]));
+        assert_eq!(results[3].as_deref(), Ok(&memory[8..16]));
+    }
+
+    #[test]
+    fn one_module_map_serves_a_whole_batch() {
+        let modules: Vec<Value> = (0..500u64)
+            .map(|index| {
+                json!({
+                    "modulename": format!("/system/lib64/libmod{}.so", index),
+                    "base": 0x7000_0000 + index * 0x10000,
+                })
+            })
+            .collect();
+        let addresses: Vec<String> = (0..1000)
+            .map(|index| format!("libmod{}.so + 0x{:x}", index % 500, index))
+            .collect();
+
+        let map = ModuleMap::new(0, &modules).unwrap();
+        for (index, address) in addresses.iter().enumerate() {
+            let expected = 0x7000_0000 + (index as u64 % 500) * 0x10000 + index as u64;
+            assert_eq!(
+                resolve_single_level_address(address, &map).unwrap(),
+                expected
+            );
+        }
+        // The table is only read while resolving, so a map keeps serving after a batch
+        assert_eq!(map.bases.len(), 500);
+    }
+
+    #[test]
+    fn module_symbol_terms_resolve_to_the_export() {
+        let path = "/fixture/lib64/libgame.so";
+        symbols::cache_exports(
+            path,
+            vec![symbols::Export {
+                name: "update_score".to_string(),
+                offset: 0x1230,
+                size: 0x40,
+            }],
+        );
+        let modules = [json!({ "base": 0x7000_0000u64, "size": 0x10000, "modulename": path })];
+        let module_map = ModuleMap::new(0, &modules).unwrap();
+        let resolve = |addr| resolve_single_level_address(addr, &module_map);
+
+        assert_eq!(resolve("libgame.so!update_score + 0x10"), Ok(0x7000_1240));
+        assert_eq!(resolve("LIBGAME.SO!update_score"), Ok(0x7000_1230));
+        assert_eq!(resolve("libgame.so + 0x10"), Ok(0x7000_0010));
+        assert_eq!(
+            resolve("libgame.so!missing_fn + 0x10"),
+            Err("Symbol missing_fn not found in libgame.so".to_string())
+        );
+    }
+
+    #[test]
+    fn brackets_must_balance() {
+        let pid = unsafe { native_bridge::get_pid_native() };
+        let target = [0x1122u64, 0x3344];
+        let pointer = target.as_ptr() as u64;
+        let chain = [pointer];
+        let root = chain.as_ptr() as u64;
+        let resolve = |expression: &str| resolve_nested_address(pid, expression, &[]);
+
+        assert_eq!(resolve(&format!("[0x{:x}]", pointer)).unwrap(), 0x1122);
+        assert_eq!(
+            resolve(&format!("[0x{:x}] + 8", root)).unwrap(),
+            pointer + 8
+        );
+        assert_eq!(resolve(&format!("[[0x{:x}]+8]", root)).unwrap(), 0x3344);
+        for unbalanced in [
+            format!("[[0x{:x}]", root),
+            format!("[0x{:x}]]", root),
+            format!("]0x{:x}[", root),
+        ] {
+            assert_eq!(
+                resolve(&unbalanced).unwrap_err(),
+                "Unbalanced brackets in address expression"
+            );
+        }
+    }
+
+    #[test]
+    fn deeply_nested_directories_keep_their_shape() {
+        // Siblings at every level make each children Vec grow well past its first capacity
+        let mut raw = String::new();
+        for depth in 0..100 {
+            let indent = "  ".repeat(depth);
+            for index in 0..10 {
+                raw.push_str(&format!(
+                    "{}file:f{}_{},{},{}\n",
+                    indent, depth, index, index, 0
+                ));
+            }
+            raw.push_str(&format!("{}dir:d{}\n", indent, depth));
+        }
+        raw.push_str("file:last,1,-1\n");
+
+        let tree = parse_directory_structure(&raw, DEFAULT_INDENT_WIDTH);
+        assert_eq!(tree.len(), 12);
+        assert_eq!(tree[11].name, "last");
+        assert_eq!(tree[11].last_opened, None);
+        let mut level = &tree;
+        for depth in 0..100 {
+            let directory = &level[10];
+            assert_eq!(directory.name, format!("d{}", depth));
+            assert_eq!(level[3].name, format!("f{}_3", depth));
+            assert_eq!(level[3].size, Some(3));
+            match &directory.children {
+                Some(children) => level = children,
+                None => assert_eq!(depth, 99),
+            }
+        }
+    }
+
+    #[test]
+    fn packed_struct_fields_dissect_at_their_offsets() {
+        #[repr(C, packed)]
+        struct Record {
+            health: u8,
+            speed: f32,
+        }
+        #[repr(C, packed)]
+        struct Owner {
+            id: i16,
+            record: *const Record,
+        }
+
+        let pid = unsafe { native_bridge::get_pid_native() };
+        let record = Record {
+            health: 200,
+            speed: 1.5,
+        };
+        let owner = Owner {
+            id: -7,
+            record: &record,
+        };
+        let field = |name: &str, field_type: &str, offset, fields| StructField {
+            name: name.to_string(),
+            field_type: field_type.to_string(),
+            offset,
+            fields,
+        };
+        let record_fields = vec![
+            field("health", "u8", 0, None),
+            field("speed", "f32", 1, None),
+        ];
+
+        let value = dissect(pid, &record as *const Record as u64, &record_fields).unwrap();
+        assert_eq!(value, json!({ "health": 200, "speed": 1.5 }));
+
+        let owner_fields = [
+            field("id", "i16", 0, None),
+            field("record", "ptr64", 2, Some(record_fields)),
+        ];
+        let value = dissect(pid, &owner as *const Owner as u64, &owner_fields).unwrap();
+        assert_eq!(value["id"], -7);
+        assert_eq!(value["record"]["address"], &record as *const Record as u64);
+        assert_eq!(value["record"]["fields"]["speed"], 1.5);
+
+        // A 32-bit target's pointer is read as four bytes; without a layout it is not followed
+        let narrow_pointer: u32 = 0x1234_5678;
+        let narrow = [field("next", "ptr32", 0, None)];
+        let value = dissect(pid, &narrow_pointer as *const u32 as u64, &narrow).unwrap();
+        assert_eq!(
+            value,
+            json!({ "next": { "address": 0x1234_5678, "fields": null } })
+        );
+
+        let unknown = [field("id", "i24", 0, None)];
+        let error = dissect(pid, &owner as *const Owner as u64, &unknown).unwrap_err();
+        assert!(error.starts_with("Field 'id'"), "{}", error);
+    }
+
+    #[test]
+    fn tabs_and_crlf_parse_like_spaces_and_lf() {
+        let spaces = "dir:a\n  file:x,1,2\n  dir:b\n    file:y,3,4\n  junk\nfile:z,5,6\n";
+        let expected = serde_json::to_value(parse_directory_structure(spaces, 2)).unwrap();
+        let parsed = |raw: &str, indent_width| {
+            serde_json::to_value(parse_directory_structure(raw, indent_width)).unwrap()
+        };
+
+        assert_eq!(expected[0]["children"][1]["children"][0]["name"], "y");
+        assert_eq!(parsed(&spaces.replace("  ", "\t"), 2), expected);
+        assert_eq!(parsed(&spaces.replace('\n', "\r\n"), 2), expected);
+        assert_eq!(
+            parsed(&spaces.replace("  ", "\t").replace('\n', "\r\n"), 2),
+            expected
+        );
+        assert_eq!(parsed(&spaces.replace("  ", "    "), 4), expected);
+        // A lone carriage return at the end of a line is trimmed as well
+        assert_eq!(parsed(&spaces.replace('\n', "\r\r\n"), 2), expected);
+    }
+
+    #[test]
+    fn disassemble_count_is_bounded() {
+        // Rejected before anything is read or allocated, so no target is needed
+        let error = disassemble_at(
+            0,
+            0x1000,
+            MAX_DISASSEMBLE_COUNT + 1,
+            Arch::X86_64,
+            AsmSyntax::default(),
+        )
+        .unwrap_err();
+        assert!(error.contains("more than"));
+        assert!(disassemble_at(0, 0x1000, usize::MAX, Arch::X86_64, AsmSyntax::default()).is_err());
+    }
+
+    #[test]
+    fn function_disassembly_stops_at_the_return() {
+        let pid = unsafe { native_bridge::get_pid_native() };
+        // push rbp; mov rbp, rsp; mov eax, 1; pop rbp; ret; then code of the next function
+        let code: [u8; 14] = [
+            0x55, 0x48, 0x89, 0xe5, 0xb8, 0x01, 0x00, 0x00, 0x00, 0x5d, 0xc3, 0x90, 0x90, 0xc3,
+        ];
+        let start = code.as_ptr() as u64;
+        let disassemble = |max_instructions| {
+            disassemble_function(pid, start, Arch::X86_64, max_instructions, AsmSyntax::Intel)
+                .unwrap()
+        };
+
+        let (instructions, end) = disassemble(64);
+        let mnemonics: Vec<&str> = instructions.iter().map(|i| i.mnemonic.as_str()).collect();
+        assert_eq!(mnemonics, ["push", "mov", "mov", "pop", "ret"]);
+        assert_eq!(end, FunctionEnd::Return);
+        assert_eq!(instructions[4].address, start + 10);
+
+        let (instructions, end) = disassemble(2);
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(end, FunctionEnd::InstructionLimit);
+    }
+
+    #[test]
+    fn x86_syntax_changes_the_rendering() {
+        // mov rax, rbx
+        let code = [0x48, 0x89, 0xd8];
+        let render = |syntax| {
+            let instruction = disassemble_structured(&code, 0x1000, Arch::X86_64, syntax)
+                .unwrap()
+                .remove(0);
+            format!("{} {}", instruction.mnemonic, instruction.op_str)
+        };
+        assert_eq!(render(AsmSyntax::Intel), "mov rax, rbx");
+        assert_eq!(render(AsmSyntax::Att), "movq %rbx, %rax");
+        assert_eq!(render(AsmSyntax::default()), render(AsmSyntax::Intel));
+    }
+
+    #[test]
+    fn direct_arm64_branches_carry_their_target() {
+        // b #0x100, bl #0x100, br x0, ret
+        let code = [
+            0x40, 0x00, 0x00, 0x14, 0x40, 0x00, 0x00, 0x94, 0x00, 0x00, 0x1f, 0xd6, 0xc0, 0x03,
+            0x5f, 0xd6,
+        ];
+        let instructions =
+            disassemble_structured(&code, 0x1000, Arch::Arm64, AsmSyntax::default()).unwrap();
+        let mnemonics: Vec<&str> = instructions.iter().map(|i| i.mnemonic.as_str()).collect();
+        assert_eq!(mnemonics, ["b", "bl", "br", "ret"]);
+        let targets: Vec<Option<u64>> = instructions.iter().map(|i| i.branch_target).collect();
+        assert_eq!(targets, [Some(0x1100), Some(0x1104), None, None]);
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn branch_to_a_known_symbol_is_annotated() {
+        let pid = unsafe { native_bridge::get_pid_native() };
+        let malloc: unsafe extern "C" fn(libc::size_t) -> *mut libc::c_void = libc::malloc;
+        let malloc = malloc as usize as u64;
+        // call malloc from 0x100 bytes in front of it, then a mov of an unmapped immediate
+        let code = [0xe8, 0xfb, 0x00, 0x00, 0x00, 0xb8, 0x10, 0x00, 0x00, 0x00];
+        let mut instructions =
+            disassemble_structured(&code, malloc - 0x100, Arch::X86_64, AsmSyntax::default())
+                .unwrap();
+        assert_eq!(instructions[0].branch_target, Some(malloc));
+        assert_eq!(instructions[1].reference, Some(0x10));
+
+        annotate_instructions(pid, &mut instructions);
+        let annotation = instructions[0].annotation.as_deref().unwrap();
+        assert!(
+            annotation.starts_with("-> libc") && annotation.contains("malloc"),
+            "{}",
+            annotation
+        );
+        assert_eq!(instructions[1].annotation, None);
+    }
+
+    fn region(start: u64, end: u64, flags: &str) -> region::MemoryRegion {
+        region::MemoryRegion {
+            start,
+            end,
+            protection: Protection::parse(flags),
+            path: None,
+            kind: region::RegionKind::Anonymous,
+        }
+    }
+
+    #[test]
+    fn ranges_must_be_mapped_with_the_access_they_need() {
+        let regions = [
+            region(0x1000, 0x2000, "rw-p"),
+            region(0x2000, 0x3000, "r-xp"),
+            region(0x3000, 0x4000, "r--p"),
+            region(0x5000, 0x6000, "rw-p"),
+        ];
+        let check = |address, length, allowed| check_range(&regions, address, length, allowed);
+
+        // Contiguous regions are one range, and code counts as writable
+        assert!(check(0x1800, 0x1000, writable).is_ok());
+        assert!(check(0x1000, 0x3000, readable).is_ok());
+        assert!(check(0x2800, 0x1000, writable)
+            .unwrap_err()
+            .contains("r-- region"));
+        assert!(check(0x3800, 0x1000, readable)
+            .unwrap_err()
+            .contains("not mapped at 0x4000"));
+        assert!(check(0x800, 0x1000, readable)
+            .unwrap_err()
+            .contains("not mapped at 0x800"));
+        assert!(check(0x5fff, 2, readable).is_err());
+        assert!(check(0x5fff, 1, readable).is_ok());
+        assert!(check(u64::MAX, 2, readable)
+            .unwrap_err()
+            .contains("overflows"));
+    }
+
+    #[test]
+    fn changing_one_byte_changes_the_hash() {
+        let pid = unsafe { native_bridge::get_pid_native() };
+        let mut memory = b"123456789".to_vec();
+        let start = memory.as_ptr() as u64;
+        let end = start + memory.len() as u64;
+        let hash = |algo| hash_region(pid, start, end, algo).unwrap();
+
+        // The standard CRC-32 check value
+        let crc = hash(HashAlgo::Crc32);
+        assert_eq!(crc, "cbf43926");
+        let xxhash = hash(HashAlgo::Xxhash64);
+        assert_eq!(xxhash.len(), 16);
+
+        std::hint::black_box(&mut memory)[4] ^= 1;
+        assert_ne!(hash(HashAlgo::Crc32), crc);
+        assert_ne!(hash(HashAlgo::Xxhash64), xxhash);
+        std::hint::black_box(&mut memory)[4] ^= 1;
+        assert_eq!(hash(HashAlgo::Crc32), crc);
+        assert_eq!(hash(HashAlgo::Xxhash64), xxhash);
+
+        assert!(hash_region(pid, start, start, HashAlgo::Crc32).is_err());
+        // Hashing chunk by chunk gives the digest of the whole range
+        let large: Vec<u8> = (0..HASH_CHUNK_SIZE * 2 + 7).map(|i| i as u8).collect();
+        let start = large.as_ptr() as u64;
+        let crc = hash_region(pid, start, start + large.len() as u64, HashAlgo::Crc32).unwrap();
+        assert_eq!(crc, hex::encode(crc32fast::hash(&large).to_be_bytes()));
+    }
+
+    #[test]
+    fn typed_writes_round_trip_through_the_reader() {
+        let pid = unsafe { native_bridge::get_pid_native() };
+        let memory = [0u8; 16];
+        let address = memory.as_ptr() as u64;
+        let values = [
+            ("u8", json!(255)),
+            ("i8", json!(-128)),
+            ("u16", json!(65535)),
+            ("i16", json!(-2)),
+            ("u32", json!(4_000_000_000u32)),
+            ("i32", json!(-100_000)),
+            ("u64", json!(u64::MAX)),
+            ("i64", json!(i64::MIN)),
+            ("f32", json!(1.5)),
+            ("f64", json!(-2.25)),
+        ];
+        for (type_name, value) in values {
+            let written = write_typed_value(pid, address, type_name, &value, None).unwrap();
+            assert_eq!(written.len(), ValueType::parse(type_name).unwrap().size());
+            assert_eq!(
+                read_typed_value(pid, address, type_name).unwrap(),
+                value,
+                "{}",
+                type_name
+            );
+        }
+
+        let before = read_memory_bytes(pid, address, 16).unwrap();
+        for (type_name, value) in [
+            ("u8", json!(300)),
+            ("u32", json!(-1)),
+            ("i32", json!(1.5)),
+            ("f32", json!(1e39)),
+            ("i16", json!("7")),
+        ] {
+            assert!(write_typed_value(pid, address, type_name, &value, None).is_err());
+        }
+        // A rejected value leaves memory as it was rather than writing a truncated one
+        assert_eq!(read_memory_bytes(pid, address, 16).unwrap(), before);
+
+        let written = write_typed_value(
+            pid,
+            address,
+            "string",
+            &json!("hi"),
+            Some(StringEncoding::Utf16Le),
+        )
+        .unwrap();
+        assert_eq!(written, [b'h', 0, b'i', 0]);
+    }
+
+    #[test]
+    fn strings_write_in_each_encoding() {
+        let pid = unsafe { native_bridge::get_pid_native() };
+        let memory = [0xffu8; 32];
+        let address = memory.as_ptr() as u64;
+        let write = |text, encoding, null_terminate, length_prefix, max_len| {
+            write_string(
+                pid,
+                address,
+                text,
+                encoding,
+                null_terminate,
+                length_prefix,
+                max_len,
+            )
+        };
+        let bytes = |len| read_memory_bytes(pid, address, len).unwrap();
+
+        assert_eq!(write("name", StringEncoding::Ascii, true, None, 32), Ok(5));
+        assert_eq!(bytes(6), b"name\0\xff");
+        assert_eq!(
+            read_string(pid, address, 32, StringEncoding::Ascii).unwrap(),
+            "name"
+        );
+        assert!(write("näme", StringEncoding::Ascii, true, None, 32).is_err());
+
+        assert_eq!(write("näme", StringEncoding::Utf8, true, None, 32), Ok(6));
+        assert_eq!(
+            read_string(pid, address, 32, StringEncoding::Utf8).unwrap(),
+            "näme"
+        );
+
+        assert_eq!(
+            write(
+                "hé",
+                StringEncoding::Utf16Le,
+                false,
+                Some(LengthPrefix::U16),
+                32
+            ),
+            Ok(6)
+        );
+        assert_eq!(bytes(6), [2, 0, b'h', 0, 0xe9, 0]);
+        assert_eq!(write("hé", StringEncoding::Utf16Le, true, None, 32), Ok(6));
+        assert_eq!(
+            read_string(pid, address, 32, StringEncoding::Utf16Le).unwrap(),
+            "hé"
+        );
+
+        // Too long for max_len, so nothing at all is written
+        let before = bytes(32);
+        assert!(write("overflowing", StringEncoding::Ascii, true, None, 11).is_err());
+        assert_eq!(bytes(32), before);
+    }
+
+    #[test]
+    fn filled_bytes_read_back() {
+        let pid = unsafe { native_bridge::get_pid_native() };
+        let memory = vec![0x11u8; 256];
+        let start = memory.as_ptr() as u64;
+
+        fill_memory(pid, start + 16, 0xab, 100).unwrap();
+        let filled = read_memory_bytes(pid, start, memory.len()).unwrap();
+        assert!(filled[..16].iter().all(|&byte| byte == 0x11));
+        assert!(filled[16..116].iter().all(|&byte| byte == 0xab));
+        assert!(filled[116..].iter().all(|&byte| byte == 0x11));
+        assert!(fill_memory(pid, start, 0xab, 0).is_err());
+    }
+
+    #[test]
+    fn overlapping_copies_behave_like_memmove() {
+        let pid = unsafe { native_bridge::get_pid_native() };
+        let pattern: Vec<u8> = (0..WRITE_CHUNK_SIZE * 4)
+            .map(|index| (index % 251) as u8)
+            .collect();
+        // Several chunks long, so the order the chunks are copied in matters
+        let length = WRITE_CHUNK_SIZE * 2 + 1000;
+        for (src, dst) in [(0, 4096), (4096, 0), (0, 16), (WRITE_CHUNK_SIZE, 0)] {
+            let memory = pattern.clone();
+            let start = memory.as_ptr() as u64;
+            copy_memory(pid, start + src as u64, start + dst as u64, length).unwrap();
+
+            let mut expected = pattern.clone();
+            expected.copy_within(src..src + length, dst);
+            let copied = read_memory_bytes(pid, start, memory.len()).unwrap();
+            assert!(copied == expected, "copy from {} to {}", src, dst);
+        }
+    }
+
+    #[test]
+    fn hexdump_rows_stay_aligned_to_16_bytes() {
+        let dump = format_hexdump(b"Hello, world!\x00\x01", 0x1004);
+        let rows: Vec<&str> = dump.lines().collect();
+        assert_eq!(
+            rows,
+            [
+                "0000000000001000              48 65 6c 6c  6f 2c 20 77 6f 72 6c 64  |    Hello, world|",
+                "0000000000001010  21 00 01                                          |!..             |",
+            ]
+        );
+        let holes = format_hexdump_cells(&[Some(b'A'), None, Some(0x7f)], 0x2000);
+        assert_eq!(
+            holes,
+            "0000000000002000  41 ?? 7f                                          |A?.             |\n"
+        );
+        assert_eq!(format_hexdump(&[0; 32], 0x3000).lines().count(), 2);
+        assert_eq!(format_hexdump(&[0; 32], 0x3001).lines().count(), 3);
+        assert!(format_hexdump(&[], 0x3000).is_empty());
+    }
+
+    #[test]
+    fn views_mark_bytes_past_an_unreadable_page() {
+        let pid = unsafe { native_bridge::get_pid_native() };
+        let page = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                PAGE_SIZE * 2,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        assert_ne!(page, libc::MAP_FAILED);
+        let page_end = page as u64 + PAGE_SIZE as u64;
+        // Inaccessible rather than unmapped, so nothing else can be mapped there meanwhile
+        unsafe {
+            std::ptr::write_bytes(page as *mut u8, b'A', PAGE_SIZE);
+            libc::mprotect(page_end as *mut libc::c_void, PAGE_SIZE, libc::PROT_NONE);
+        }
+
+        // 24 readable bytes, then 24 that are not, starting half way into a row
+        let view = view_memory(pid, page_end - 24, 48);
+        unsafe { libc::munmap(page, PAGE_SIZE * 2) };
+        let view = view.unwrap();
+        assert_eq!(view.length, 48);
+        assert!(view.bytes[..24].iter().all(|&byte| byte == Some(b'A')));
+        assert!(view.bytes[24..].iter().all(Option::is_none));
+        assert_eq!(view.printable[..24], [true; 24]);
+        assert_eq!(view.printable[24..], [false; 24]);
+        let rows: Vec<(u64, bool)> = view
+            .rows
+            .iter()
+            .map(|row| (row.address, row.read_failed))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                (page_end - 32, false),
+                (page_end - 16, false),
+                (page_end, true),
+                (page_end + 16, true),
+            ]
+        );
+        let dump: Vec<&str> = view.hexdump.lines().collect();
+        assert_eq!(dump.len(), 4);
+        assert!(dump[1].ends_with("|AAAAAAAAAAAAAAAA|"));
+        assert!(dump[2].ends_with("|????????????????|"));
+
+        assert!(view_memory(pid, page_end - 24, 0).is_err());
+        let buffer = vec![0u8; MAX_VIEW_LENGTH + 1];
+        let view = view_memory(pid, buffer.as_ptr() as u64, buffer.len()).unwrap();
+        assert!(view.truncated);
+        assert_eq!(view.bytes.len(), MAX_VIEW_LENGTH);
+    }
+
+    #[test]
+    fn values_format_in_either_radix() {
+        let format = |bytes: &[u8], value_type, radix, grouped| {
+            format_value(bytes, value_type, radix, grouped).unwrap()
+        };
+        let value = 0x1234u32.to_le_bytes();
+        assert_eq!(
+            format(&value, Some(ValueType::U32), Radix::Hex, true),
+            "0x0000_1234"
+        );
+        assert_eq!(
+            format(&value, Some(ValueType::U32), Radix::Hex, false),
+            "0x00001234"
+        );
+        assert_eq!(
+            format(&value, Some(ValueType::U32), Radix::Dec, false),
+            "4660"
+        );
+        assert_eq!(
+            format(&value, Some(ValueType::U32), Radix::Dec, true),
+            "4,660"
+        );
+
+        let negative = (-1234567i32).to_le_bytes();
+        assert_eq!(
+            format(&negative, Some(ValueType::I32), Radix::Dec, true),
+            "-1,234,567"
+        );
+        assert_eq!(
+            format(
+                &(-1i32).to_le_bytes(),
+                Some(ValueType::I32),
+                Radix::Hex,
+                true
+            ),
+            "0xffff_ffff"
+        );
+        assert_eq!(
+            format(
+                &(-100i64).to_le_bytes(),
+                Some(ValueType::I64),
+                Radix::Dec,
+                true
+            ),
+            "-100"
+        );
+        assert_eq!(
+            format(
+                &1.5f32.to_le_bytes(),
+                Some(ValueType::F32),
+                Radix::Dec,
+                true
+            ),
+            "1.5"
+        );
+        // Only the first value's bytes are formatted
+        assert_eq!(
+            format(&[1, 2, 3], Some(ValueType::U8), Radix::Dec, false),
+            "1"
+        );
+
+        let raw = [0xde, 0xad, 0x07];
+        assert_eq!(format(&raw, None, Radix::Hex, false), "dead07");
+        assert_eq!(format(&raw, None, Radix::Hex, true), "de ad 07");
+        assert_eq!(format(&raw, None, Radix::Dec, false), "222 173 7");
+        assert!(format_value(&raw, Some(ValueType::U32), Radix::Hex, false).is_err());
+    }
+
+    #[test]
+    fn paths_must_resolve_inside_the_file_root() {
+        let base = std::env::temp_dir().join(format!("memory-server-root-{}", std::process::id()));
+        let root = base.join("root");
+        let _ = fs::remove_dir_all(&base);
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("sub/file"), b"data").unwrap();
+        fs::write(base.join("outside"), b"data").unwrap();
+        let root = fs::canonicalize(&root).unwrap();
+
+        assert_eq!(
+            resolve_under_root(&root, "sub/file", false).unwrap(),
+            root.join("sub/file")
+        );
+        // Once allowed, ".." is fine while it stays inside, and a file being created need not
+        // exist
+        assert_eq!(
+            resolve_under_root(&root, "sub/../sub/new", true).unwrap(),
+            root.join("sub/new")
+        );
+        assert!(resolve_under_root(&root, "sub/../sub/new", false).is_err());
+        assert!(resolve_under_root(&root, "../outside", true).is_err());
+        assert!(resolve_under_root(&root, base.join("outside").to_str().unwrap(), false).is_err());
+        assert!(resolve_under_root(&root, "sub/..", true).is_ok());
+        assert!(resolve_under_root(&root, "sub/missing/..", true).is_err());
+        assert!(resolve_under_root(&root, "", false).is_err());
+
+        // A symlink out of the root is followed before the check
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(base.join("outside"), root.join("link")).unwrap();
+            assert!(resolve_under_root(&root, "link", false).is_err());
+        }
+
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn chunk_reads_reserve_only_what_the_file_holds() {
+        let path = std::env::temp_dir().join(format!("memory-server-chunk-{}", std::process::id()));
+        fs::write(&path, b"0123456789").unwrap();
+        let path_str = path.to_str().unwrap();
+
+        let chunk = read_file_chunk(path_str, 6, MAX_FILE_TRANSFER_SIZE as usize).unwrap();
+        assert_eq!(chunk, b"6789");
+        assert!(chunk.capacity() < 4096, "{}", chunk.capacity());
+        assert!(read_file_chunk(path_str, 20, 1024).unwrap().is_empty());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn encoded_parent_directories_are_refused_by_default() {
+        let settings = crate::settings::Settings::default();
+        let (root, allow_parent) = (&settings.file_root, settings.allow_parent_paths);
+        let decoded = decode_path_param("%2e%2e%2fetc%2fpasswd").unwrap();
+        assert_eq!(decoded, "../etc/passwd");
+        let error = resolve_under_root(root, &decoded, allow_parent).unwrap_err();
+        assert!(error.contains("\"..\""), "{}", error);
+        assert!(resolve_under_root(
+            root,
+            &decode_path_param("tmp%2f%2e%2e").unwrap(),
+            allow_parent
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn expressions_follow_precedence_and_parentheses() {
+        assert_eq!(evaluate_expression("2+3*4").unwrap(), 14);
+        assert_eq!(evaluate_expression("(1+2)*3").unwrap(), 9);
+        assert_eq!(evaluate_expression("((2+3)*(4-1))/5").unwrap(), 3);
+        assert_eq!(evaluate_expression("10-4-3").unwrap(), 3);
+        assert_eq!(evaluate_expression("-(2+3)*2").unwrap(), -10);
+        assert!(evaluate_expression("1/0")
+            .unwrap_err()
+            .contains("Division by zero"));
+        assert!(evaluate_expression("(1+2").is_err());
+        assert!(evaluate_expression("1+").is_err());
+        assert!(evaluate_expression("").is_err());
+    }
+
+    #[test]
+    fn expressions_take_hex_and_bit_operators() {
+        assert_eq!(evaluate_expression("0x1000 + (0x20 << 4)").unwrap(), 0x1200);
+        assert_eq!(evaluate_expression("0xFF & 0x0F | 0x100").unwrap(), 0x10F);
+        assert_eq!(evaluate_expression("0x10 ^ 0x11 >> 1").unwrap(), 0x18);
+        assert_eq!(evaluate_expression("1 + 2 << 3").unwrap(), 24);
+        // Bits shifted out of the top are dropped rather than panicking
+        assert_eq!(evaluate_expression("0x4000000000000001 << 2").unwrap(), 4);
+        assert_eq!(evaluate_expression("1 << 200").unwrap(), 0);
+        assert!(evaluate_expression("1 << -1").is_err());
+    }
+
+    #[test]
+    fn deeply_nested_expressions_are_rejected() {
+        let depth = MAX_EXPRESSION_DEPTH - 1;
+        let nested = format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+        assert_eq!(evaluate_expression(&nested).unwrap(), 1);
+
+        let too_deep = format!("{}1{}", "(".repeat(100_000), ")".repeat(100_000));
+        assert!(evaluate_expression(&too_deep)
+            .unwrap_err()
+            .contains("deeper"));
+        assert!(evaluate_expression(&"-".repeat(100_000)).is_err());
+    }
+
+    #[test]
+    fn negative_integers_interpret_as_negative_json_numbers() {
+        let interpret =
+            |bytes: &[u8], value_type, endian| interpret_bytes(bytes, value_type, endian).unwrap();
+        assert_eq!(
+            interpret(&(-5i32).to_le_bytes(), ValueType::I32, Endianness::Little),
+            json!(-5)
+        );
+        assert_eq!(
+            interpret(&(-5i32).to_be_bytes(), ValueType::I32, Endianness::Big),
+            json!(-5)
+        );
+        assert_eq!(
+            interpret(&i64::MIN.to_le_bytes(), ValueType::I64, Endianness::Little),
+            json!(i64::MIN)
+        );
+        assert_eq!(
+            interpret(&(-1i64).to_be_bytes(), ValueType::I64, Endianness::Big),
+            json!(-1)
+        );
+        // The same bits unsigned stay positive
+        assert_eq!(
+            interpret(&(-1i64).to_le_bytes(), ValueType::U64, Endianness::Little),
+            json!(u64::MAX)
+        );
+        assert!(interpret_bytes(&[0; 4], ValueType::I64, Endianness::Little).is_err());
+    }
+
+    #[test]
+    fn nan_decodes_as_a_float() {
+        let decoded = decode_number(&f64::NAN.to_le_bytes(), ValueType::F64, Endianness::Little);
+        assert!(matches!(decoded, Some(Number::Float(value)) if value.is_nan()));
+        let decoded = decode_number(&f32::NAN.to_le_bytes(), ValueType::F32, Endianness::Little);
+        assert!(matches!(decoded, Some(Number::Float(value)) if value.is_nan()));
+        assert_eq!(
+            decode_number(&1.5f32.to_le_bytes(), ValueType::F32, Endianness::Little),
+            Some(Number::Float(1.5))
+        );
+    }
 }
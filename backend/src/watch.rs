@@ -0,0 +1,130 @@
+use crate::util::{self, ValueType};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Once};
+use std::thread;
+use std::time::Duration;
+
+const WATCH_INTERVAL: Duration = Duration::from_millis(200);
+
+struct WatchEntry {
+    value_type: String,
+    last: Option<Value>,
+    changed: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchUpdate {
+    pub pid: i32,
+    pub address: u64,
+    pub value_type: String,
+    pub value: Value,
+}
+
+lazy_static! {
+    // Keyed by (pid, address) so the same address watched in two processes stays two entries
+    static ref WATCH_TABLE: Arc<Mutex<HashMap<(i32, u64), WatchEntry>>> =
+        Arc::new(Mutex::new(HashMap::new()));
+}
+
+static WATCH_WORKER: Once = Once::new();
+
+// Registering an address that is already watched in pid replaces its type and reports it again
+// on the next poll
+pub fn watch_register(pid: i32, address: u64, value_type: &str) -> Result<(), String> {
+    ValueType::parse(value_type)?;
+    WATCH_TABLE.lock().unwrap().insert(
+        (pid, address),
+        WatchEntry {
+            value_type: value_type.to_string(),
+            last: None,
+            changed: false,
+        },
+    );
+    WATCH_WORKER.call_once(|| {
+        thread::spawn(watch_worker);
+    });
+    Ok(())
+}
+
+pub fn watch_unregister(pid: i32, address: u64) -> bool {
+    WATCH_TABLE
+        .lock()
+        .unwrap()
+        .remove(&(pid, address))
+        .is_some()
+}
+
+// Entries whose value changed since the previous poll; the first successful read counts as a change
+pub fn watch_poll() -> Vec<WatchUpdate> {
+    let mut table = WATCH_TABLE.lock().unwrap();
+    let mut updates: Vec<WatchUpdate> = table
+        .iter_mut()
+        .filter(|(_, entry)| entry.changed)
+        .filter_map(|(&(pid, address), entry)| {
+            entry.changed = false;
+            Some(WatchUpdate {
+                pid,
+                address,
+                value_type: entry.value_type.clone(),
+                value: entry.last.clone()?,
+            })
+        })
+        .collect();
+    updates.sort_by_key(|update| (update.pid, update.address));
+    updates
+}
+
+fn watch_worker() {
+    loop {
+        thread::sleep(WATCH_INTERVAL);
+        let watched: Vec<((i32, u64), String)> = WATCH_TABLE
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(&key, entry)| (key, entry.value_type.clone()))
+            .collect();
+
+        // Read without holding the lock, then record the results; an address that has become
+        // unreadable keeps its last value and is simply not reported this round
+        let values: Vec<((i32, u64), String, Value)> = watched
+            .into_iter()
+            .filter_map(|((pid, address), value_type)| {
+                util::read_typed_value(pid, address, &value_type)
+                    .ok()
+                    .map(|value| ((pid, address), value_type, value))
+            })
+            .collect();
+
+        let mut table = WATCH_TABLE.lock().unwrap();
+        for (key, value_type, value) in values {
+            if let Some(entry) = table.get_mut(&key) {
+                // Re-registered with another type while this read was in flight
+                if entry.value_type != value_type {
+                    continue;
+                }
+                if entry.last.as_ref() != Some(&value) {
+                    entry.last = Some(value);
+                    entry.changed = true;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_address_in_two_processes_is_watched_separately() {
+        // Pids no process can have, so the worker's reads just fail
+        watch_register(-2, 0x1000, "u32").unwrap();
+        watch_register(-3, 0x1000, "f32").unwrap();
+        assert!(watch_unregister(-2, 0x1000));
+        assert!(!watch_unregister(-2, 0x1000));
+        assert!(watch_unregister(-3, 0x1000));
+    }
+}